@@ -0,0 +1,45 @@
+//! Looking up every game's icon in a librarycache the size of a large library, the way a sync
+//! does: one index built on the first lookup, then a map lookup per game.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use steamer::icons::IconIndex;
+use steamer::report::{LogFormat, Reporter};
+
+const GAMES: u32 = 2000;
+
+/// A librarycache with the icon, logo and hero art Steam keeps for each game.
+fn generate_cache(dir: &Path) {
+    const JPEG: &[u8] = &[0xff, 0xd8, 0xff, 0xe0, 0x42, 0x42, 0xff, 0xd9];
+    for appid in 0..GAMES {
+        let game = dir.join((100_000 + appid * 10).to_string());
+        fs::create_dir_all(&game).unwrap();
+        fs::write(game.join(format!("{:040x}.jpg", appid)), JPEG).unwrap();
+        fs::write(game.join("logo.png"), JPEG).unwrap();
+        fs::write(game.join("library_hero.jpg"), JPEG).unwrap();
+    }
+}
+
+fn icon_index(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("steamer-bench-icons-{}", std::process::id()));
+    generate_cache(&dir);
+    let reporter = Reporter::new(LogFormat::Human, true);
+    let appids: Vec<String> = (0..GAMES)
+        .map(|appid| (100_000 + appid * 10).to_string())
+        .collect();
+
+    c.bench_function("icon index, every game", |b| {
+        b.iter(|| {
+            let index = IconIndex::new(&dir);
+            for appid in &appids {
+                black_box(index.find(appid, &reporter));
+            }
+        })
+    });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, icon_index);
+criterion_main!(benches);
@@ -0,0 +1,155 @@
+//! The phases of a sync over a made-up library the size of a large one: parsing the VDF files,
+//! scanning the libraries and writing the entries. Everything happens in a temporary
+//! directory, the real Steam install and applications directory aren't touched.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use steamer::appinfo::AppInfoSource;
+use steamer::desktop::{self, EntryOptions};
+use steamer::paths::Paths;
+use steamer::report::{LogFormat, Reporter};
+use steamer::steam::{self, GameInfo};
+use steamer::vdf;
+
+const GAMES: usize = 2000;
+const LIBRARIES: usize = 3;
+
+/// Makes a Steam root with [`LIBRARIES`] libraries holding [`GAMES`] games between them,
+/// spread round robin, the first library being the root itself as in a real install. The
+/// manifests have the keys and depot blocks real ones have, so parsing them costs about the
+/// same. Returns the manifest paths.
+fn generate_fixture(steam_root: &Path) -> Vec<PathBuf> {
+    let library_paths: Vec<PathBuf> = (0..LIBRARIES)
+        .map(|i| match i {
+            0 => steam_root.to_path_buf(),
+            _ => steam_root.with_file_name(format!("library{}", i)),
+        })
+        .collect();
+    let mut apps: Vec<Vec<u32>> = vec![Vec::new(); LIBRARIES];
+    let mut manifests = Vec::with_capacity(GAMES);
+    for i in 0..GAMES {
+        let appid = 100_000 + i as u32 * 10;
+        let library = &library_paths[i % LIBRARIES];
+        let installdir = format!("Synthetic Game {}", i);
+        fs::create_dir_all(library.join("steamapps/common").join(&installdir)).unwrap();
+        let path = library.join(format!("steamapps/appmanifest_{}.acf", appid));
+        fs::write(&path, manifest(appid, &installdir)).unwrap();
+        manifests.push(path);
+        apps[i % LIBRARIES].push(appid);
+    }
+
+    let mut vdf = String::from("\"libraryfolders\"\n{\n");
+    for (i, (path, apps)) in library_paths.iter().zip(&apps).enumerate() {
+        vdf.push_str(&format!(
+            "\t\"{}\"\n\t{{\n\t\t\"path\"\t\t\"{}\"\n\t\t\"label\"\t\t\"\"\n\t\t\"apps\"\n\t\t{{\n",
+            i,
+            path.display()
+        ));
+        for appid in apps {
+            vdf.push_str(&format!("\t\t\t\"{}\"\t\t\"{}\"\n", appid, 1_000_000_000));
+        }
+        vdf.push_str("\t\t}\n\t}\n");
+    }
+    vdf.push_str("}\n");
+    fs::write(steam_root.join("steamapps/libraryfolders.vdf"), vdf).unwrap();
+    manifests
+}
+
+fn manifest(appid: u32, installdir: &str) -> String {
+    format!(
+        r#""AppState"
+{{
+	"appid"		"{appid}"
+	"universe"		"1"
+	"name"		"{installdir}"
+	"StateFlags"		"4"
+	"installdir"		"{installdir}"
+	"LastUpdated"		"1700000000"
+	"SizeOnDisk"		"1000000000"
+	"StagingSize"		"0"
+	"buildid"		"12345678"
+	"LastOwner"		"76561197960287930"
+	"UpdateResult"		"0"
+	"BytesToDownload"		"0"
+	"BytesDownloaded"		"0"
+	"AutoUpdateBehavior"		"0"
+	"AllowOtherDownloadsWhileRunning"		"0"
+	"ScheduledAutoUpdate"		"0"
+	"InstalledDepots"
+	{{
+		"{depot}"
+		{{
+			"manifest"		"1234567890123456789"
+			"size"		"1000000000"
+		}}
+	}}
+	"UserConfig"
+	{{
+		"language"		"english"
+	}}
+	"MountedConfig"
+	{{
+		"language"		"english"
+	}}
+}}
+"#,
+        depot = appid + 1
+    )
+}
+
+fn sync_phases(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("steamer-bench-sync-{}", std::process::id()));
+    let steam_root = dir.join("steam");
+    let manifests = generate_fixture(&steam_root);
+    let paths = Paths {
+        library_vdf: steam_root.join("steamapps/libraryfolders.vdf"),
+        library_cache: dir.join("cache/libraryfolders.vdf"),
+        cache_dir: dir.join("cache"),
+        icon_cache: dir.join("cache/icons"),
+        app_dir: dir.join("applications"),
+        index_dir: dir.join("applications"),
+        extra_libraries: Vec::new(),
+        ignore_files: Vec::new(),
+        steam_root,
+    };
+    let reporter = Reporter::new(LogFormat::Human, true);
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(false);
+    let options = EntryOptions::default();
+
+    let texts: Vec<String> = manifests
+        .iter()
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect();
+    let library_vdf = fs::read_to_string(&paths.library_vdf).unwrap();
+    c.bench_function("parse vdf", |b| {
+        b.iter(|| {
+            black_box(vdf::parse_text(&library_vdf).unwrap());
+            for text in &texts {
+                black_box(steam::parse_manifest_text(text).unwrap());
+            }
+        })
+    });
+
+    c.bench_function("scan", |b| {
+        b.iter(|| black_box(steam::scan_libraries(&paths, &appinfo, None, &reporter).unwrap()))
+    });
+
+    let (libraries, _) = steam::scan_libraries(&paths, &appinfo, None, &reporter).unwrap();
+    let games: Vec<GameInfo> = libraries.into_iter().flat_map(|l| l.games).collect();
+    assert_eq!(games.len(), GAMES);
+    c.bench_function("write", |b| {
+        b.iter(|| {
+            for game in &games {
+                let path = options.entry_path(&paths.app_dir, game);
+                let content = desktop::render_entry(game, "steam", &options);
+                desktop::create_desktop_file(&path, &content).unwrap();
+            }
+        })
+    });
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+criterion_group!(benches, sync_phases);
+criterion_main!(benches);
@@ -0,0 +1,335 @@
+//! Reader for `appcache/appinfo.vdf`, Steam's binary cache of store metadata for every app
+//! the client knows about. Only the entry index is built on load; an app's KeyValues are
+//! parsed the first time somebody asks for them.
+
+use crate::report::Reporter;
+use crate::vdf::{self, Node};
+use anyhow::{Context, Result, bail};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAGIC_V27: u32 = 0x07564427;
+const MAGIC_V28: u32 = 0x07564428;
+const MAGIC_V29: u32 = 0x07564429;
+
+pub struct AppInfo {
+    data: Vec<u8>,
+    keys: Option<Vec<String>>,
+    /// appid -> (start, end) of the app's KeyValues in `data`
+    entries: HashMap<u32, (usize, usize)>,
+}
+
+/// What appinfo knows about an app, for games that have no readable manifest.
+pub struct AppSummary {
+    pub name: Option<String>,
+    /// `game`, `application`, `tool`, `dlc`, ... lowercased
+    pub app_type: Option<String>,
+    pub installdir: Option<String>,
+    /// The app this one belongs to, set for DLC, demos and playtests.
+    pub parent: Option<String>,
+    pub free_to_download: bool,
+}
+
+impl AppInfo {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("Could not read {:?}", path))?;
+        Self::parse(data).with_context(|| format!("Could not parse {:?}", path))
+    }
+
+    fn parse(data: Vec<u8>) -> Result<Self> {
+        let magic = read_u32(&data, 0)?;
+        let (entries_start, header_len, keys) = match magic {
+            // info_state, last_updated, pics_token, sha1, change_number
+            MAGIC_V27 => (8, 40, None),
+            // ...then the sha1 of the binary KeyValues
+            MAGIC_V28 => (8, 60, None),
+            // same entries, but keys live in a string table at the end of the file
+            MAGIC_V29 => {
+                let table_offset = read_u64(&data, 8)? as usize;
+                (16, 60, Some(read_string_table(&data, table_offset)?))
+            }
+            other => bail!("Unsupported appinfo.vdf version 0x{:08x}", other),
+        };
+
+        let mut entries = HashMap::new();
+        let mut pos = entries_start;
+        loop {
+            let appid = read_u32(&data, pos)?;
+            if appid == 0 {
+                break;
+            }
+            let size = read_u32(&data, pos + 4)? as usize;
+            let body = pos + 8;
+            let end = body + size;
+            if end > data.len() || size < header_len {
+                bail!("Truncated entry for app {} at offset {}", appid, pos);
+            }
+            entries.insert(appid, (body + header_len, end));
+            pos = end;
+        }
+
+        Ok(Self {
+            data,
+            keys,
+            entries,
+        })
+    }
+
+    /// The `appinfo` section for an app (`common`, `extended`, `config`, ...).
+    pub fn app(&self, appid: &str) -> Option<Node> {
+        let &(start, end) = self.entries.get(&appid.parse().ok()?)?;
+        let (node, _) = vdf::parse_binary(&self.data[start..end], self.keys.as_deref()).ok()?;
+        match node.get("appinfo") {
+            Some(inner) => Some(inner.clone()),
+            None => Some(node),
+        }
+    }
+
+    pub fn summary(&self, appid: &str) -> Option<AppSummary> {
+        let app = self.app(appid)?;
+        Some(AppSummary {
+            name: app.path("common/name").and_then(Node::to_text),
+            app_type: app
+                .path("common/type")
+                .and_then(Node::to_text)
+                .map(|t| t.to_lowercase()),
+            installdir: app.path("config/installdir").and_then(Node::to_text),
+            parent: app
+                .path("common/parent")
+                .and_then(Node::to_text)
+                .filter(|p| p != "0"),
+            free_to_download: app
+                .path("common/freetodownload")
+                .and_then(Node::to_text)
+                .is_some_and(|v| v == "1"),
+        })
+    }
+
+    /// Store names per locale from `common/name_localized`, keyed by Desktop Entry locale.
+    /// Languages Steam has no obvious locale for are dropped.
+    pub fn localized_names(&self, appid: &str) -> Vec<(String, String)> {
+        let Some(app) = self.app(appid) else {
+            return Vec::new();
+        };
+        let Some(names) = app.path("common/name_localized") else {
+            return Vec::new();
+        };
+        names
+            .entries()
+            .iter()
+            .filter_map(|(language, name)| {
+                let locale = steam_language_locale(language)?;
+                let name = name.to_text().filter(|n| !n.trim().is_empty())?;
+                Some((locale.to_string(), name))
+            })
+            .collect()
+    }
+}
+
+/// Maps Steam's language names to the `lang_COUNTRY` codes Desktop Entry keys use.
+fn steam_language_locale(language: &str) -> Option<&'static str> {
+    let locale = match language.to_lowercase().as_str() {
+        "arabic" => "ar",
+        "brazilian" => "pt_BR",
+        "bulgarian" => "bg",
+        "czech" => "cs",
+        "danish" => "da",
+        "dutch" => "nl",
+        "english" => "en",
+        "finnish" => "fi",
+        "french" => "fr",
+        "german" => "de",
+        "greek" => "el",
+        "hungarian" => "hu",
+        "indonesian" => "id",
+        "italian" => "it",
+        "japanese" => "ja",
+        "koreana" | "korean" => "ko",
+        "latam" => "es_419",
+        "norwegian" => "nb",
+        "polish" => "pl",
+        "portuguese" => "pt",
+        "romanian" => "ro",
+        "russian" => "ru",
+        "schinese" => "zh_CN",
+        "spanish" => "es",
+        "swedish" => "sv",
+        "tchinese" => "zh_TW",
+        "thai" => "th",
+        "turkish" => "tr",
+        "ukrainian" => "uk",
+        "vietnamese" => "vi",
+        _ => return None,
+    };
+    Some(locale)
+}
+
+/// Loads appinfo.vdf on first use, so runs that never need it don't pay for reading it.
+pub struct AppInfoSource {
+    path: PathBuf,
+    /// Off with `--no-appinfo`, every caller then does without.
+    enabled: bool,
+    loaded: OnceCell<Option<AppInfo>>,
+}
+
+impl AppInfoSource {
+    pub fn new(steam_root: &Path) -> Self {
+        Self {
+            path: steam_root.join("appcache/appinfo.vdf"),
+            enabled: true,
+            loaded: OnceCell::new(),
+        }
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Whether there's a file to read, for lookups that have a fallback and shouldn't warn about
+    /// it missing.
+    pub fn exists(&self) -> bool {
+        self.enabled && self.path.is_file()
+    }
+
+    /// Returns the parsed file, warning once if it's missing or unreadable. Always `None` when
+    /// disabled, without reading anything.
+    pub fn get(&self, reporter: &Reporter) -> Option<&AppInfo> {
+        if !self.enabled {
+            return None;
+        }
+        self.loaded
+            .get_or_init(|| match AppInfo::load(&self.path) {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    reporter.warn(format!("{:#}", e));
+                    None
+                }
+            })
+            .as_ref()
+    }
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    match data.get(pos..pos + 4) {
+        Some(bytes) => Ok(u32::from_le_bytes(bytes.try_into().unwrap())),
+        None => bail!("Unexpected end of file at offset {}", pos),
+    }
+}
+
+fn read_u64(data: &[u8], pos: usize) -> Result<u64> {
+    match data.get(pos..pos + 8) {
+        Some(bytes) => Ok(u64::from_le_bytes(bytes.try_into().unwrap())),
+        None => bail!("Unexpected end of file at offset {}", pos),
+    }
+}
+
+fn read_string_table(data: &[u8], offset: usize) -> Result<Vec<String>> {
+    let count = read_u32(data, offset)? as usize;
+    let mut strings = Vec::with_capacity(count);
+    let mut pos = offset + 4;
+    for _ in 0..count {
+        let rest = data.get(pos..).context("String table out of bounds")?;
+        let len = rest
+            .iter()
+            .position(|&b| b == 0)
+            .context("Unterminated string in string table")?;
+        strings.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        pos += len + 1;
+    }
+    Ok(strings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+
+    /// Binary KeyValues for a section holding `strings` and the sections in `nested`.
+    fn section(name: &str, strings: &[(&str, &str)], nested: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = vec![0x00];
+        data.extend_from_slice(name.as_bytes());
+        data.push(0);
+        for (key, value) in strings {
+            data.push(0x01);
+            data.extend_from_slice(key.as_bytes());
+            data.push(0);
+            data.extend_from_slice(value.as_bytes());
+            data.push(0);
+        }
+        for section in nested {
+            data.extend_from_slice(section);
+        }
+        data.push(0x08);
+        data
+    }
+
+    /// An appinfo.vdf in the oldest format, with an `appinfo` section of `sections` per app.
+    fn appinfo(apps: &[(u32, Vec<Vec<u8>>)]) -> AppInfo {
+        AppInfo::parse(appinfo_data(apps)).unwrap()
+    }
+
+    /// The bytes of the file [`appinfo`] parses.
+    fn appinfo_data(apps: &[(u32, Vec<Vec<u8>>)]) -> Vec<u8> {
+        let mut data = MAGIC_V27.to_le_bytes().to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for (appid, sections) in apps {
+            let mut body = vec![0u8; 40];
+            body.extend(section("appinfo", &[], sections));
+            body.push(0x08);
+            data.extend_from_slice(&appid.to_le_bytes());
+            data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            data.extend_from_slice(&body);
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn localized_names_use_desktop_entry_locales() {
+        let names = section(
+            "name_localized",
+            &[
+                ("german", "Portal 2 (de)"),
+                ("schinese", "传送门2"),
+                ("klingon", "Portal 2 (tlh)"),
+                ("french", " "),
+            ],
+            &[],
+        );
+        let common = section("common", &[("name", "Portal 2")], &[names]);
+        let info = appinfo(&[(620, vec![common]), (400, vec![])]);
+
+        assert_eq!(
+            info.localized_names("620"),
+            [
+                ("de".to_string(), "Portal 2 (de)".to_string()),
+                ("zh_CN".to_string(), "传送门2".to_string()),
+            ]
+        );
+        assert!(info.localized_names("400").is_empty());
+        assert!(info.localized_names("70").is_empty());
+    }
+
+    #[test]
+    fn a_disabled_source_never_reads_the_file() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let steam_root =
+            std::env::temp_dir().join(format!("steamer-appinfo-test-{}", std::process::id()));
+        fs::create_dir_all(steam_root.join("appcache")).unwrap();
+        let common = section("common", &[("name", "Portal 2")], &[]);
+        fs::write(
+            steam_root.join("appcache/appinfo.vdf"),
+            appinfo_data(&[(620, vec![common])]),
+        )
+        .unwrap();
+
+        assert!(AppInfoSource::new(&steam_root).get(&reporter).is_some());
+        let disabled = AppInfoSource::new(&steam_root).enabled(false);
+        assert!(disabled.get(&reporter).is_none());
+        assert!(disabled.loaded.get().is_none());
+        fs::remove_dir_all(&steam_root).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+//! Reading `appmanifest_*.acf` files straight out of a library backup, without extracting it.
+//! Handles tar (plain or gzipped) and zip; only the manifests are ever read into memory.
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tar::{Archive, Entries, EntryType};
+use zip::ZipArchive;
+
+/// Manifests bigger than this aren't manifests, they're never more than a few KiB.
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// A manifest found in an archive, with the path it has inside it.
+pub struct ArchivedManifest {
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+pub fn read_manifests(path: &Path) -> Result<Vec<ArchivedManifest>> {
+    let mut file = File::open(path).with_context(|| format!("Could not open {:?}", path))?;
+    let mut magic = [0u8; 4];
+    let read = file.read(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let manifests = match &magic[..read] {
+        [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => read_zip(file),
+        [0x1f, 0x8b, ..] => read_tar(Archive::new(GzDecoder::new(BufReader::new(file))).entries()),
+        // a plain tar can seek past the files it doesn't need
+        _ => read_tar(Archive::new(BufReader::new(file)).entries_with_seek()),
+    };
+    manifests.with_context(|| format!("Could not read archive {:?}", path))
+}
+
+fn is_manifest(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    file_name.starts_with("appmanifest_") && file_name.ends_with(".acf")
+}
+
+/// The manifests among the entries of a tar. GNU long names and pax paths are resolved by the
+/// `tar` crate, so `name` is always the whole path.
+fn read_tar<R: Read>(entries: io::Result<Entries<'_, R>>) -> Result<Vec<ArchivedManifest>> {
+    let mut manifests = Vec::new();
+    for entry in entries? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        if entry.header().entry_type() != EntryType::Regular
+            || !is_manifest(&name)
+            || entry.size() > MAX_MANIFEST_SIZE
+        {
+            continue;
+        }
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut content)?;
+        manifests.push(ArchivedManifest { name, content });
+    }
+    Ok(manifests)
+}
+
+fn read_zip(file: File) -> Result<Vec<ArchivedManifest>> {
+    let mut zip = ZipArchive::new(BufReader::new(file))?;
+    let mut manifests = Vec::new();
+    for index in 0..zip.len() {
+        // only the central directory is read for the entries that are skipped
+        let name = zip
+            .name_for_index(index)
+            .context("Corrupt zip central directory")??
+            .into_owned();
+        if !is_manifest(&name) {
+            continue;
+        }
+        let mut file = zip
+            .by_index(index)
+            .with_context(|| format!("Could not read {} from the zip", name))?;
+        if !file.is_file() || file.size() > MAX_MANIFEST_SIZE {
+            continue;
+        }
+        let mut content = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut content)?;
+        manifests.push(ArchivedManifest { name, content });
+    }
+    Ok(manifests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Compression, Crc, GzBuilder};
+    use std::fs;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process;
+
+    const MANIFEST: &[u8] = b"\"AppState\"\n{\n\t\"appid\"\t\t\"620\"\n}\n";
+
+    /// `"appid" "620"` as raw DEFLATE with the fixed Huffman codes.
+    const DEFLATED: &[u8] = &[
+        0x53, 0x4a, 0x2c, 0x28, 0xc8, 0x4c, 0x51, 0x52, 0x50, 0x32, 0x33, 0x32, 0x50, 0x02, 0x00,
+    ];
+
+    fn write_archive(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("steamer-archive-{}-{}", process::id(), name));
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    fn read(name: &str, data: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+        let path = write_archive(name, data);
+        let manifests = read_manifests(&path);
+        fs::remove_file(path).unwrap();
+        Ok(manifests?
+            .into_iter()
+            .map(|manifest| (manifest.name, manifest.content))
+            .collect())
+    }
+
+    /// A ustar header for an entry of `size` bytes, with a correct checksum.
+    fn tar_header(name: &str, size: usize, kind: u8) -> [u8; 512] {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[100..107].copy_from_slice(b"0000644");
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|&b| b as u32).sum();
+        header[148..155].copy_from_slice(format!("{:06o}\0", sum).as_bytes());
+        header
+    }
+
+    fn tar_entry(tar: &mut Vec<u8>, name: &str, content: &[u8], kind: u8) {
+        tar.extend_from_slice(&tar_header(name, content.len(), kind));
+        tar.extend_from_slice(content);
+        tar.resize(tar.len().div_ceil(512) * 512, 0);
+    }
+
+    fn tar_end(tar: &mut Vec<u8>) {
+        tar.extend_from_slice(&[0u8; 1024]);
+    }
+
+    #[test]
+    fn reads_manifests_from_a_tar() {
+        let mut tar = Vec::new();
+        tar_entry(
+            &mut tar,
+            "steamapps/common/readme.txt",
+            b"not a manifest",
+            b'0',
+        );
+        tar_entry(&mut tar, "steamapps/appmanifest_620.acf", MANIFEST, b'0');
+        tar_end(&mut tar);
+        let manifests = read("plain.tar", &tar).unwrap();
+        assert_eq!(
+            manifests,
+            [(
+                "steamapps/appmanifest_620.acf".to_string(),
+                MANIFEST.to_vec()
+            )]
+        );
+    }
+
+    #[test]
+    fn reads_gnu_long_names() {
+        let long = format!("{}/steamapps/appmanifest_620.acf", "library".repeat(20));
+        assert!(long.len() > 100);
+        let mut tar = Vec::new();
+        tar_entry(
+            &mut tar,
+            "././@LongLink",
+            format!("{}\0", long).as_bytes(),
+            b'L',
+        );
+        tar_entry(&mut tar, &long[..100], MANIFEST, b'0');
+        tar_end(&mut tar);
+        assert_eq!(read("long.tar", &tar).unwrap(), [(long, MANIFEST.to_vec())]);
+    }
+
+    #[test]
+    fn reads_a_gzipped_tar() {
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "appmanifest_620.acf", MANIFEST, b'0');
+        // some tools leave out the zero blocks at the end
+
+        let mut gzip = GzBuilder::new()
+            .filename("backup.tar")
+            .write(Vec::new(), Compression::default());
+        gzip.write_all(&tar).unwrap();
+        let gzip = gzip.finish().unwrap();
+        assert_eq!(
+            read("backup.tar.gz", &gzip).unwrap(),
+            [("appmanifest_620.acf".to_string(), MANIFEST.to_vec())]
+        );
+    }
+
+    #[test]
+    fn rejects_a_corrupt_tar_header() {
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "appmanifest_620.acf", MANIFEST, b'0');
+        tar[0] = b'x';
+        assert!(read("corrupt.tar", &tar).is_err());
+    }
+
+    /// A zip whose entries are written the way streaming tools do: sizes left out of the local
+    /// header and given in a data descriptor after the data instead. Each entry is its name, the
+    /// compression method, the stored data and what that data unpacks to.
+    fn streamed_zip(entries: &[(&str, u16, &[u8], &[u8])]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut central = Vec::new();
+        for &(name, method, data, content) in entries {
+            let offset = zip.len() as u32;
+            let size = content.len();
+            let mut crc = Crc::new();
+            crc.update(content);
+            let crc = crc.sum();
+            zip.extend_from_slice(b"PK\x03\x04");
+            zip.extend_from_slice(&20u16.to_le_bytes());
+            zip.extend_from_slice(&0x08u16.to_le_bytes());
+            zip.extend_from_slice(&method.to_le_bytes());
+            zip.extend_from_slice(&[0; 16]);
+            zip.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            zip.extend_from_slice(&0u16.to_le_bytes());
+            zip.extend_from_slice(name.as_bytes());
+            zip.extend_from_slice(data);
+            zip.extend_from_slice(b"PK\x07\x08");
+            zip.extend_from_slice(&crc.to_le_bytes());
+            zip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            zip.extend_from_slice(&(size as u32).to_le_bytes());
+
+            central.extend_from_slice(b"PK\x01\x02");
+            central.extend_from_slice(&[20, 0, 20, 0, 0x08, 0]);
+            central.extend_from_slice(&method.to_le_bytes());
+            central.extend_from_slice(&[0; 4]);
+            central.extend_from_slice(&crc.to_le_bytes());
+            central.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            central.extend_from_slice(&(size as u32).to_le_bytes());
+            central.extend_from_slice(&(name.len() as u16).to_le_bytes());
+            central.extend_from_slice(&[0; 12]);
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+        }
+        let offset = zip.len() as u32;
+        zip.extend_from_slice(&central);
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 4]);
+        zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        zip.extend_from_slice(&(central.len() as u32).to_le_bytes());
+        zip.extend_from_slice(&offset.to_le_bytes());
+        zip.extend_from_slice(&0u16.to_le_bytes());
+        zip
+    }
+
+    #[test]
+    fn reads_a_zip_with_data_descriptors() {
+        let zip = streamed_zip(&[
+            (
+                "steamapps/common/readme.txt",
+                0,
+                b"not a manifest",
+                b"not a manifest",
+            ),
+            ("steamapps/appmanifest_620.acf", 0, MANIFEST, MANIFEST),
+            (
+                "steamapps/appmanifest_400.acf",
+                8,
+                DEFLATED,
+                b"\"appid\" \"620\"",
+            ),
+        ]);
+        assert_eq!(
+            read("streamed.zip", &zip).unwrap(),
+            [
+                (
+                    "steamapps/appmanifest_620.acf".to_string(),
+                    MANIFEST.to_vec()
+                ),
+                (
+                    "steamapps/appmanifest_400.acf".to_string(),
+                    b"\"appid\" \"620\"".to_vec()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_zip_compression() {
+        let zip = streamed_zip(&[("appmanifest_620.acf", 12, b"BZh9", MANIFEST)]);
+        assert!(read("bzip2.zip", &zip).is_err());
+    }
+}
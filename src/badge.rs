@@ -0,0 +1,249 @@
+//! `--badge-proton`: a copy of the icon of each game that runs in Proton, with a small badge in
+//! its lower right corner. There's no image library to draw with, so the copy is an SVG showing
+//! the original icon with the badge on top, which every launcher reading `Icon=` paths renders.
+//! Copies live in `~/.cache/steamer/badged`, the icons in Steam's cache are never changed.
+
+use crate::icons::FALLBACK_ICON;
+use crate::report::Reporter;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The badge, drawn over the lower right quarter of the icon.
+const BADGE: &[u8] = include_bytes!("../assets/proton-badge.png");
+
+/// Whether `appid` runs in Proton: its library has a prefix for it in `steamapps/compatdata`.
+/// Native games never get one, and neither do games that were never started.
+pub fn runs_in_proton(library: &Path, appid: &str) -> bool {
+    library.join("steamapps/compatdata").join(appid).is_dir()
+}
+
+/// The badged copies in steamer's cache directory.
+pub struct ProtonBadges {
+    dir: PathBuf,
+    dry_run: bool,
+}
+
+impl ProtonBadges {
+    pub fn new(cache_dir: &Path, dry_run: bool) -> Self {
+        Self {
+            dir: cache_dir.join("badged"),
+            dry_run,
+        }
+    }
+
+    /// The `Icon=` of a game: a badged copy of `icon` when the game runs in Proton, `icon` as
+    /// it is otherwise. A copy left from when the game ran in Proton is removed, and one whose
+    /// icon changed since is drawn again. Icons other than PNG and JPEG files, like SVG, ICO or
+    /// the fallback icon name, stay unbadged with a note.
+    pub fn apply(
+        &self,
+        appid: &str,
+        name: &str,
+        library: &Path,
+        icon: &str,
+        reporter: &Reporter,
+    ) -> String {
+        let badged = self.dir.join(format!("{}.svg", appid));
+        if !runs_in_proton(library, appid) {
+            self.remove(&badged, reporter);
+            return icon.to_string();
+        }
+
+        let image = match icon {
+            FALLBACK_ICON => None,
+            path => fs::read(path).ok(),
+        };
+        let Some((media_type, image)) = image.and_then(|image| Some((media_type(&image)?, image)))
+        else {
+            reporter.warn(format!(
+                "Not badging the icon of {}, only PNG and JPEG icons can be: {}",
+                name, icon
+            ));
+            self.remove(&badged, reporter);
+            return icon.to_string();
+        };
+        let svg = badged_svg(media_type, &image);
+
+        if !self.dry_run && fs::read_to_string(&badged).ok().as_deref() != Some(svg.as_str()) {
+            let written = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&badged, svg));
+            if let Err(e) = written {
+                reporter.warn(format!(
+                    "Could not write the badged icon of {} to {:?}: {}",
+                    name, badged, e
+                ));
+                return icon.to_string();
+            }
+        }
+        badged.to_string_lossy().into_owned()
+    }
+
+    fn remove(&self, badged: &Path, reporter: &Reporter) {
+        if self.dry_run || !badged.exists() {
+            return;
+        }
+        if let Err(e) = fs::remove_file(badged) {
+            reporter.warn(format!(
+                "Could not remove the badged icon {:?}: {}",
+                badged, e
+            ));
+        }
+    }
+}
+
+/// The MIME type of a PNG or JPEG, from its signature.
+fn media_type(image: &[u8]) -> Option<&'static str> {
+    if image.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if image.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else {
+        None
+    }
+}
+
+/// An SVG of `image` scaled to fill it, with [`BADGE`] over the lower right corner. Both are
+/// embedded, so the copy keeps working when Steam replaces its cached file.
+fn badged_svg(media_type: &str, image: &[u8]) -> String {
+    format!(
+        concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" ",
+            "xmlns:xlink=\"http://www.w3.org/1999/xlink\" ",
+            "width=\"256\" height=\"256\" viewBox=\"0 0 256 256\">\n",
+            "<image width=\"256\" height=\"256\" xlink:href=\"data:{};base64,{}\"/>\n",
+            "<image x=\"152\" y=\"152\" width=\"104\" height=\"104\" ",
+            "xlink:href=\"data:image/png;base64,{}\"/>\n",
+            "</svg>\n"
+        ),
+        media_type,
+        base64(image),
+        base64(BADGE)
+    )
+}
+
+/// Standard base64 with padding, for the `data:` URIs.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-badge-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd]), "//79");
+    }
+
+    #[test]
+    fn only_badges_png_and_jpeg() {
+        assert_eq!(media_type(BADGE), Some("image/png"));
+        assert_eq!(media_type(&[0xff, 0xd8, 0xff, 0xe0]), Some("image/jpeg"));
+        assert_eq!(
+            media_type(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"),
+            None
+        );
+        assert_eq!(media_type(&[0, 0, 1, 0]), None);
+    }
+
+    #[test]
+    fn follows_the_game_in_and_out_of_proton() {
+        let reporter = Reporter::new(LogFormat::Human, false);
+        let dir = temp_dir();
+        let library = dir.join("library");
+        let prefix = library.join("steamapps/compatdata/620");
+        let icon = dir.join("620.png");
+        fs::write(&icon, BADGE).unwrap();
+        let icon = icon.to_str().unwrap();
+        let badges = ProtonBadges::new(&dir.join("cache"), false);
+        let badged = dir.join("cache/badged/620.svg");
+
+        // native until it gets a prefix
+        assert_eq!(
+            badges.apply("620", "Portal 2", &library, icon, &reporter),
+            icon
+        );
+        assert!(!badged.exists());
+
+        fs::create_dir_all(&prefix).unwrap();
+        assert_eq!(
+            badges.apply("620", "Portal 2", &library, icon, &reporter),
+            badged.to_str().unwrap()
+        );
+        let svg = fs::read_to_string(&badged).unwrap();
+        assert!(svg.contains(&format!("data:image/png;base64,{}", base64(BADGE))));
+
+        // a new icon draws the copy again
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0];
+        jpeg.extend([0x42; 16]);
+        fs::write(icon, &jpeg).unwrap();
+        badges.apply("620", "Portal 2", &library, icon, &reporter);
+        let svg = fs::read_to_string(&badged).unwrap();
+        assert!(svg.contains(&format!("data:image/jpeg;base64,{}", base64(&jpeg))));
+
+        fs::remove_dir_all(&prefix).unwrap();
+        assert_eq!(
+            badges.apply("620", "Portal 2", &library, icon, &reporter),
+            icon
+        );
+        assert!(!badged.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn leaves_svg_and_ico_icons_unbadged() {
+        let reporter = Reporter::new(LogFormat::Human, false);
+        let dir = temp_dir();
+        let library = dir.join("library");
+        fs::create_dir_all(library.join("steamapps/compatdata/620")).unwrap();
+        let badges = ProtonBadges::new(&dir.join("cache"), false);
+
+        let svg = dir.join("620.svg");
+        fs::write(&svg, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+        let ico = dir.join("620.ico");
+        fs::write(&ico, [0, 0, 1, 0, 1, 0, 16, 16]).unwrap();
+        for icon in [svg.to_str().unwrap(), ico.to_str().unwrap(), FALLBACK_ICON] {
+            assert_eq!(
+                badges.apply("620", "Portal 2", &library, icon, &reporter),
+                icon
+            );
+        }
+        assert!(!dir.join("cache/badged/620.svg").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
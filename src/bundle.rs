@@ -0,0 +1,413 @@
+//! `steamer debug-bundle`: what a "no games found" report needs, in one tar.gz. Every file
+//! goes through `Sanitizer` before it's added, and the command prints each one. Nothing is read
+//! from where Steam keeps logins and tokens: `config/config.vdf`, `config/loginusers.vdf`, the
+//! `ssfn*` files, and the files in `userdata`, of which only the folder names are listed.
+
+use crate::cli::{Args, DebugBundleArgs};
+use crate::config::{self, Config};
+use crate::configure;
+use crate::disk;
+use crate::inspect::{self, Checks};
+use crate::paths::Paths;
+use crate::report::{self, Reporter};
+use crate::steam;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory everything in the bundle sits in.
+const BUNDLE_DIR: &str = "steamer-debug";
+
+/// Directory levels of `userdata` listed: accounts, their appids, and the folders in those.
+const USERDATA_DEPTH: usize = 3;
+
+/// Fewest digits of an account ID that's redacted. Shorter numbers, like the `userdata/0` Steam
+/// keeps settings from before a login in, would take every size, appid and build number that
+/// happens to contain them as a word.
+const MIN_ACCOUNT_DIGITS: usize = 6;
+
+struct BundleFile {
+    name: String,
+    content: String,
+    /// Where it came from, printed with the name.
+    source: String,
+}
+
+pub fn debug_bundle(args: &Args, bundle: &DebugBundleArgs) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, true).human_to_stderr(true);
+    let config_path = config::default_path();
+    let config = Config::load_default(args.profile.as_deref())?;
+    let paths = Paths::resolve(args, true, &reporter);
+    let sanitizer = Sanitizer::new(args, &config, paths.as_ref().ok(), bundle.redact_usernames);
+
+    let mut files = Vec::new();
+    let mut missing = Vec::new();
+    let mut add = |name: &str, content: String, source: String| {
+        files.push(BundleFile {
+            name: name.to_string(),
+            content: sanitizer.clean(&content),
+            source,
+        });
+    };
+
+    let mut about = format!("steamer {}\n", env!("CARGO_PKG_VERSION"));
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    about.push_str(&format!("arguments: {:?}\n", arguments));
+    about.push_str(&format!(
+        "desktop: {}\n",
+        std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default()
+    ));
+    match paths {
+        Ok(ref paths) => {
+            about.push_str(&format!("steam root: {:?}\n", paths.steam_root));
+            about.push_str(&format!("applications: {:?}\n", paths.app_dir));
+            about.push_str(&format!("icon cache: {:?}\n", paths.icon_cache));
+            about.push_str(&format!("extra libraries: {:?}\n", paths.extra_libraries));
+        }
+        Err(ref e) => about.push_str(&format!("paths: could not be resolved: {:#}\n", e)),
+    }
+    add("steamer.txt", about, "version, arguments and paths".into());
+
+    if let Some(ref path) = config_path {
+        add(
+            "config.txt",
+            configure::effective(args, path)?,
+            "the settings in effect".into(),
+        );
+    }
+
+    let mut checks = Checks::collect();
+    if let Err(e) = inspect::run_checks(args, &mut checks) {
+        checks.lines.push(format!("doctor stopped: {:#}", e));
+    }
+    add(
+        "doctor.txt",
+        checks.lines.join("\n") + "\n",
+        "steamer doctor".into(),
+    );
+
+    if let Ok(ref paths) = paths {
+        match fs::read_to_string(&paths.library_vdf) {
+            Ok(text) => add(
+                "libraryfolders.vdf",
+                text,
+                format!("{:?}", paths.library_vdf),
+            ),
+            Err(e) => missing.push(format!("libraryfolders.vdf: {}", e)),
+        }
+
+        let mut manifests = Vec::new();
+        if let Ok((folders, _)) = steam::read_library_folders(paths, &reporter) {
+            for folder in folders {
+                let Ok(entries) = fs::read_dir(folder.path.join("steamapps")) else {
+                    continue;
+                };
+                let mut found: Vec<_> = entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.file_name()
+                            .and_then(|n| n.to_str())
+                            .is_some_and(|n| n.starts_with("appmanifest_") && n.ends_with(".acf"))
+                    })
+                    .collect();
+                found.sort();
+                manifests.extend(found);
+            }
+        }
+        if manifests.is_empty() {
+            missing.push("appmanifests: none found in any library".to_string());
+        }
+        for path in manifests.iter().take(bundle.manifests) {
+            if let Ok(text) = fs::read_to_string(path) {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                add(&format!("manifests/{}", name), text, format!("{:?}", path));
+            }
+        }
+
+        if paths.icon_cache.is_dir() {
+            add(
+                "librarycache.txt",
+                listing(&paths.icon_cache, 2, true),
+                format!("names and sizes in {:?}", paths.icon_cache),
+            );
+        } else {
+            missing.push(format!(
+                "librarycache: {:?} doesn't exist",
+                paths.icon_cache
+            ));
+        }
+
+        let userdata = paths.steam_root.join("userdata");
+        if userdata.is_dir() {
+            add(
+                "userdata.txt",
+                listing(&userdata, USERDATA_DEPTH, false),
+                format!("folder names in {:?}", userdata),
+            );
+        }
+
+        let last_run = report::last_run_log(&paths.cache_dir);
+        match fs::read_to_string(&last_run) {
+            Ok(text) => add("last-run.jsonl", text, format!("{:?}", last_run)),
+            Err(_) => missing.push("last-run.jsonl: no sync has saved one yet".to_string()),
+        }
+    }
+
+    let archive: Vec<(String, &[u8])> = files
+        .iter()
+        .map(|file| {
+            (
+                format!("{}/{}", BUNDLE_DIR, file.name),
+                file.content.as_bytes(),
+            )
+        })
+        .collect();
+    fs::write(&bundle.output, gzip(&tar(&archive)))
+        .with_context(|| format!("Could not write {:?}", bundle.output))?;
+
+    println!("Wrote {:?} with:", bundle.output);
+    let width = files.iter().map(|f| f.name.len()).max().unwrap_or(0);
+    for file in &files {
+        println!(
+            "  {:<width$}  {:>9}  {}",
+            file.name,
+            disk::format_size(file.content.len() as u64),
+            file.source,
+            width = width
+        );
+    }
+    for note in &missing {
+        println!("Not included: {}", note);
+    }
+    if bundle.redact_usernames {
+        println!("User names and account IDs are replaced with <user> and <account>.");
+    }
+    Ok(())
+}
+
+/// Indented names under `dir`, down to `depth` levels, with sizes when `sizes` is set and
+/// folder names only otherwise.
+fn listing(dir: &Path, depth: usize, sizes: bool) -> String {
+    let mut out = String::new();
+    list_into(&mut out, dir, depth, sizes, 0);
+    out
+}
+
+fn list_into(out: &mut String, dir: &Path, depth: usize, sizes: bool, level: usize) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if !is_dir && !sizes {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let indent = "  ".repeat(level);
+        if is_dir {
+            out.push_str(&format!("{}{}/\n", indent, name));
+            if level + 1 < depth {
+                list_into(out, &entry.path(), depth, sizes, level + 1);
+            }
+        } else {
+            let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+            out.push_str(&format!("{}{}  {}\n", indent, name, size));
+        }
+    }
+}
+
+/// Takes out what shouldn't leave the machine. The API key is always replaced; with
+/// `--redact-usernames` so are the user's name where it's a path component, the SteamID and
+/// account IDs, which appear in manifests, the config and `userdata`.
+struct Sanitizer {
+    secrets: Vec<String>,
+    patterns: Vec<(Regex, &'static str)>,
+}
+
+impl Sanitizer {
+    fn new(args: &Args, config: &Config, paths: Option<&Paths>, redact: bool) -> Self {
+        let mut secrets: Vec<String> = args.api_key.iter().cloned().collect();
+        secrets.extend(config.api_key().ok().flatten());
+        secrets.retain(|s| !s.is_empty());
+
+        let mut patterns = Vec::new();
+        if redact {
+            let mut users: Vec<String> = dirs::home_dir()
+                .and_then(|home| home.file_name().map(|n| n.to_string_lossy().into_owned()))
+                .into_iter()
+                .chain(std::env::var("USER").ok())
+                .filter(|user| !user.is_empty())
+                .collect();
+            users.dedup();
+            for user in users {
+                let pattern = format!(r"/{}\b", regex::escape(&user));
+                patterns.push((Regex::new(&pattern).unwrap(), "/<user>"));
+            }
+
+            let mut accounts: Vec<String> = args.steam_id.iter().cloned().collect();
+            accounts.extend(config.steam_id().ok().flatten());
+            if let Some(paths) = paths
+                && let Ok(entries) = fs::read_dir(paths.steam_root.join("userdata"))
+            {
+                accounts.extend(
+                    entries
+                        .filter_map(Result::ok)
+                        .map(|entry| entry.file_name().to_string_lossy().into_owned()),
+                );
+            }
+            accounts.retain(|id| {
+                id.len() >= MIN_ACCOUNT_DIGITS && id.bytes().all(|b| b.is_ascii_digit())
+            });
+            accounts.sort();
+            accounts.dedup();
+            for id in accounts {
+                let pattern = format!(r"\b{}\b", id);
+                patterns.push((Regex::new(&pattern).unwrap(), "<account>"));
+            }
+            // the SteamID64 of whoever installed the game
+            patterns.push((
+                Regex::new(r#"(?i)("LastOwner"\s+")\d+"#).unwrap(),
+                "$1<account>",
+            ));
+        }
+        Self { secrets, patterns }
+    }
+
+    fn clean(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for secret in &self.secrets {
+            out = out.replace(secret.as_str(), "<redacted>");
+        }
+        for (pattern, replacement) in &self.patterns {
+            out = pattern.replace_all(&out, *replacement).into_owned();
+        }
+        out
+    }
+}
+
+/// A ustar archive of regular files.
+fn tar(files: &[(String, &[u8])]) -> Vec<u8> {
+    let mtime = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut out = Vec::new();
+    for (name, content) in files {
+        let mut header = [0u8; 512];
+        let name = name.as_bytes();
+        header[..name.len().min(100)].copy_from_slice(&name[..name.len().min(100)]);
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+        header[136..148].copy_from_slice(format!("{:011o}\0", mtime).as_bytes());
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        let checksum: u32 = header.iter().map(|&b| u32::from(b)).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+
+        out.extend_from_slice(&header);
+        out.extend_from_slice(content);
+        out.resize(out.len().next_multiple_of(512), 0);
+    }
+    // two empty blocks end the archive
+    out.resize(out.len() + 1024, 0);
+    out
+}
+
+/// Gzip with stored deflate blocks. A bundle is a few dozen KiB of text, so skipping
+/// compression costs little and needs no compressor.
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 3];
+    let mut chunks = data.chunks(0xffff).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let len = chunk.len() as u16;
+        out.push(u8::from(chunks.peek().is_none()));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    #[test]
+    fn redacts_keys_and_accounts_but_not_short_ids() {
+        let dir = std::env::temp_dir().join(format!("steamer-bundle-test-{}", std::process::id()));
+        for account in ["0", "42", "123456789"] {
+            fs::create_dir_all(dir.join("Steam/userdata").join(account)).unwrap();
+        }
+        let paths = Paths {
+            steam_root: dir.join("Steam"),
+            library_vdf: dir.join("Steam/steamapps/libraryfolders.vdf"),
+            library_cache: dir.join("cache/libraryfolders.vdf"),
+            cache_dir: dir.join("cache"),
+            icon_cache: dir.join("Steam/appcache/librarycache"),
+            app_dir: dir.join("applications"),
+            index_dir: dir.join("index"),
+            extra_libraries: Vec::new(),
+            ignore_files: Vec::new(),
+        };
+        let cli = Cli::try_parse_from([
+            "steamer",
+            "--api-key",
+            "0123456789ABCDEF",
+            "--steam-id",
+            "76561197960287930",
+        ])
+        .unwrap();
+        let config = Config::from_text("api_key = \"FEDCBA9876543210\"", Path::new("c")).unwrap();
+        let manifest = "\"appid\"\t\"620\"\n\"SizeOnDisk\"\t\"4200\"\n\"buildid\"\t\"1042\"\n\"UpdateResult\"\t\"42\"\n\
+                        \"LastOwner\"\t\"76561197960287930\"\n\
+                        \"userdata/123456789/620\"\n\"userdata/0/config\"\n\
+                        \"key\"\t\"0123456789ABCDEF\" \"FEDCBA9876543210\"\n";
+
+        let sanitizer = Sanitizer::new(&cli.args, &config, Some(&paths), true);
+        assert_eq!(
+            sanitizer.clean(manifest),
+            "\"appid\"\t\"620\"\n\"SizeOnDisk\"\t\"4200\"\n\"buildid\"\t\"1042\"\n\"UpdateResult\"\t\"42\"\n\
+             \"LastOwner\"\t\"<account>\"\n\
+             \"userdata/<account>/620\"\n\"userdata/0/config\"\n\
+             \"key\"\t\"<redacted>\" \"<redacted>\"\n"
+        );
+
+        // without --redact-usernames only the keys go
+        let sanitizer = Sanitizer::new(&cli.args, &config, Some(&paths), false);
+        let cleaned = sanitizer.clean(manifest);
+        assert!(cleaned.contains("\"LastOwner\"\t\"76561197960287930\""));
+        assert!(cleaned.contains("userdata/123456789/620"));
+        assert!(!cleaned.contains("0123456789ABCDEF") && !cleaned.contains("FEDCBA9876543210"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
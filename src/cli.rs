@@ -0,0 +1,734 @@
+use crate::config::Config;
+use crate::desktop::{self, DesktopEnvironment, EntryOptions, ExecMode};
+use crate::family::FamilyViewMode;
+use crate::filter::{KeywordMatch, SizeRange, SkipRules};
+use crate::icons::IconSource;
+use crate::names::{NameRules, NameSuffix};
+use crate::net;
+use crate::output::{EmitKind, ExportFormat, MarkdownSort};
+use crate::report::{self, LogFormat};
+use crate::runs;
+use crate::template::Template;
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+const DEFAULT_SKIP_KEYWORDS: &[&str] = &[
+    "Proton",
+    "Steam Linux Runtime",
+    "Steamworks",
+    "Common Redistributables",
+    "SteamVR",
+    "Dedicated Server",
+    "Soundtrack",
+];
+
+const DEFAULT_IGNORED_APP_IDS: &[&str] = &["480"];
+
+const MIN_NAME_LENGTH: usize = 4;
+
+#[derive(Parser, Debug)]
+#[command(version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub args: Args,
+}
+
+/// Without a subcommand steamer syncs the desktop entries, as it always has.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// List installed games and leftovers without writing anything
+    List(ListArgs),
+    /// Check the Steam installation for problems
+    Doctor,
+    /// Show per-library statistics
+    Stats,
+    /// Remove every entry steamer generated
+    Clean,
+    /// Pick the games that get entries from a list, saving the rest to .steamerignore
+    Select,
+    /// Remove everything steamer wrote: entries, caches, indexes and launch history
+    Purge,
+    /// Show what steamer makes of one appmanifest_*.acf, without writing anything
+    Render(RenderArgs),
+    /// Print a text or binary VDF file as JSON
+    ParseVdf(ParseVdfArgs),
+    /// Print the entries in another format instead of writing desktop files
+    Export(ExportArgs),
+    /// Show when games were launched from entries made with --track-launches
+    History(HistoryArgs),
+    /// Read and change the settings in config.toml
+    Config(ConfigArgs),
+    /// Collect what a bug report needs into a tar.gz, without logins or tokens
+    DebugBundle(DebugBundleArgs),
+    /// Watch the applications directory and restore entries other tools delete or change
+    Watch(WatchArgs),
+    /// Record a launch, then run the command after --; used by --track-launches entries
+    #[command(hide = true)]
+    LaunchWrapper(LaunchWrapperArgs),
+    /// Start Steam if needed and wait for a login, then run the command after --; used by --wait-for-steam entries
+    #[command(hide = true)]
+    WaitForSteam(WaitForSteamArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ListArgs {
+    /// List directories in steamapps/common that no manifest refers to
+    #[arg(long)]
+    pub orphaned: bool,
+    /// List Proton prefixes in steamapps/compatdata for games that are no longer installed
+    #[arg(long)]
+    pub stale_prefixes: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct RenderArgs {
+    /// The manifest to parse; it doesn't have to be inside a Steam library
+    pub manifest: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    #[arg(long, value_enum)]
+    pub format: ExportFormat,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct HistoryArgs {
+    /// Count the launches of each week instead
+    #[arg(long)]
+    pub weekly: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigAction {
+    /// Print a setting, or every setting in a table like limits or game.620
+    Get { key: String },
+    /// Change a setting, leaving the rest of the file as it is
+    Set {
+        key: String,
+        /// Written as in the file; quotes around a single word can be left out
+        #[arg(allow_hyphen_values = true)]
+        value: String,
+    },
+    /// Remove a setting, or a whole table
+    Unset { key: String },
+    /// Print the settings in effect, with where each one comes from
+    List,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DebugBundleArgs {
+    /// Where to write the bundle
+    #[arg(short, long, default_value = "steamer-debug.tar.gz")]
+    pub output: PathBuf,
+    /// How many appmanifest files to include
+    #[arg(long, default_value_t = 5, value_name = "N")]
+    pub manifests: usize,
+    /// Replace the user name in paths, the SteamID and account IDs
+    #[arg(long)]
+    pub redact_usernames: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct WatchArgs {
+    /// How long the applications directory has to stay quiet after a change before the damaged entries are written again
+    #[arg(long, default_value_t = 2, value_name = "SECONDS")]
+    pub settle: u64,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct LaunchWrapperArgs {
+    pub appid: String,
+    /// The launch command, written after --
+    #[arg(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct WaitForSteamArgs {
+    /// The launcher that starts Steam, given steam://open/main
+    #[arg(long, default_value = "steam")]
+    pub start: String,
+    /// The launch command, written after --
+    #[arg(last = true, required = true)]
+    pub command: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ParseVdfArgs {
+    /// libraryfolders.vdf, an appmanifest, shortcuts.vdf, loginusers.vdf, ...
+    pub file: PathBuf,
+    /// Read the file as binary VDF; by default a leading 0x00 byte means binary
+    #[arg(long)]
+    pub binary: bool,
+    /// Print only the value at this /-separated key path, e.g. libraryfolders/0/path
+    #[arg(long)]
+    pub path: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Run without writing files to disk, only discovering applications
+    #[arg(short, long, global = true)]
+    pub dry_run: bool,
+    /// Dry run that prints every entry it would write to stdout, one stream sorted by filename
+    #[arg(long, global = true)]
+    pub dry_run_write: bool,
+    /// Dry run that prints a unified diff between the entries on disk and the ones it would write
+    #[arg(long, global = true)]
+    pub diff_output: bool,
+    /// Settings from ~/.config/steamer/profiles/<NAME>.toml, on top of the ones in config.toml
+    #[arg(long, value_name = "NAME", global = true)]
+    pub profile: Option<String>,
+    /// Path to Steam installation (defaults to ~/.local/share/Steam, then ~/.steam/steam or ~/.steam/root)
+    #[arg(short, long, global = true)]
+    pub steam_path: Option<String>,
+    /// Path to applications directory (defaults to the session desktop's app_dirs entry in the config, else $XDG_DATA_HOME/applications)
+    #[arg(short, long, global = true)]
+    pub app_dir: Option<String>,
+    /// Keywords to skip, repeatable or comma separated (defaults to Proton,Steam Linux Runtime,Steamworks,Common Redistributables,SteamVR,Dedicated Server,Soundtrack)
+    #[arg(short = 'k', long, value_delimiter = ',', global = true)]
+    pub skip_keywords: Option<Vec<String>>,
+    /// Keywords to skip in addition to the defaults, repeatable or comma separated
+    #[arg(long, value_delimiter = ',', global = true)]
+    pub add_skip_keywords: Vec<String>,
+    /// App IDs to skip, repeatable or comma separated; ranges like 2000-2100 and prefixes like 12* work too (defaults to 480)
+    #[arg(short, long, value_delimiter = ',', global = true)]
+    pub ignored_app_ids: Option<Vec<String>>,
+    /// App IDs, ranges or prefixes to skip in addition to the defaults, repeatable or comma separated
+    #[arg(long, value_delimiter = ',', global = true)]
+    pub add_ignored_app_ids: Vec<String>,
+    /// Leave out the default skip keywords and app IDs, only skipping what is asked for
+    #[arg(long, global = true)]
+    pub no_default_skips: bool,
+    /// Skip demos, by appinfo type or a name ending in the word "Demo"
+    #[arg(long, global = true)]
+    pub skip_demos: bool,
+    /// Skip games with emoji in their name; symbols like ™ or ♥ don't count unless shown as emoji
+    #[arg(long, global = true)]
+    pub skip_emoji: bool,
+    /// Keep playtests, which are skipped by default
+    #[arg(long, global = true)]
+    pub include_playtests: bool,
+    /// Write entries for the Steam tools people start themselves, SteamVR and its performance test, which the default skip keywords leave out
+    #[arg(long, global = true)]
+    pub tools: bool,
+    /// Skip games Steam never finished installing, like a first download that stopped part way
+    #[arg(long, global = true)]
+    pub skip_partial: bool,
+    /// Skip games whose size on disk is outside MIN..MAX, with sizes like 50MB or 1.5GiB; MIN.. and ..MAX leave an end open, ..= includes MAX
+    #[arg(long, value_name = "MIN..MAX", value_parser = parse_size_range, global = true)]
+    pub size_range: Option<SizeRange>,
+    /// Only write entries for games that appeared in the last N syncs, for a menu of recently added games
+    #[arg(long, value_name = "N", value_parser = parse_new_since, global = true)]
+    pub new_since: Option<usize>,
+    /// Steam account the entries are for; games its Family View doesn't allow are left out
+    #[arg(long, value_name = "ACCOUNT", global = true)]
+    pub user: Option<String>,
+    /// What to do with games Family View doesn't allow for --user
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub family_view: FamilyViewMode,
+    /// How skip keywords are matched against game names
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub keyword_match: KeywordMatch,
+    /// What to generate, comma separated; json and html write an index to --index-dir
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "desktop",
+        global = true
+    )]
+    pub emit: Vec<EmitKind>,
+    /// Directory for the json and html indexes (defaults to ~/.local/share/steamer)
+    #[arg(long, global = true)]
+    pub index_dir: Option<String>,
+    /// Also write a shell script with a steam_<name> function launching each game, fish syntax for a .fish path
+    #[arg(long, value_name = "PATH", global = true)]
+    pub emit_aliases: Option<PathBuf>,
+    /// Also write a Markdown table of the games with their app ID, size, last played time and library
+    #[arg(long, value_name = "PATH", global = true)]
+    pub emit_markdown: Option<PathBuf>,
+    /// Order of the --emit-markdown rows
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub sort: MarkdownSort,
+    /// Template for the Comment= line, using {name} and {appid} placeholders
+    #[arg(long, value_parser = parse_comment_template, global = true)]
+    pub comment_template: Option<Template>,
+    /// Include the Steam app ID in the Comment= line, after the template if one is given
+    #[arg(long, global = true)]
+    pub comment_appid: bool,
+    /// Add the ProtonDB rating of games that run in Proton to the Comment= line, fetched from protondb.com
+    #[arg(long, global = true)]
+    pub protondb: bool,
+    /// GenericName= of the entries, like "Video Game"; repeat as LOCALE=TEXT (e.g. de=Videospiel) for GenericName[xx]= translations
+    #[arg(long, value_name = "TEXT", value_parser = parse_generic_name, global = true)]
+    pub generic_name: Vec<(Option<String>, String)>,
+    /// Locales to add translated Name[xx]= lines for from appinfo, comma separated (e.g. de,fr,pt_BR)
+    #[arg(long, value_delimiter = ',', global = true)]
+    pub locales: Vec<String>,
+    /// File of extra keywords per game, lines like "730: CS2, counter strike"
+    #[arg(long, value_name = "PATH", global = true)]
+    pub keywords_file: Option<PathBuf>,
+    /// Skip the built-in name cleanup, keeping ™, ® and © and doubled spaces (config: raw_names)
+    #[arg(long, global = true)]
+    pub raw_names: bool,
+    /// Keep names in the Unicode form Steam has them in, instead of composing accents as NFC does
+    #[arg(long, global = true)]
+    pub no_nfc: bool,
+    /// Remove emoji from names; symbols like ™ or ♥ stay unless shown as emoji
+    #[arg(long, global = true)]
+    pub strip_emoji: bool,
+    /// Suffixes to drop from names, comma separated
+    #[arg(long, value_enum, value_delimiter = ',', global = true)]
+    pub strip_suffixes: Vec<NameSuffix>,
+    /// Cut names longer than this many characters, ending them with an ellipsis (at least 4)
+    #[arg(long, value_parser = parse_max_name_length, global = true)]
+    pub max_name_length: Option<usize>,
+    /// How the Exec= line launches the game; xdg-open lets the registered steam:// handler decide
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub exec_mode: ExecMode,
+    /// Launch games with steam -bigpicture -applaunch, for controller-only setups
+    #[arg(long, global = true)]
+    pub bigpicture: bool,
+    /// Write the full path of the steam executable into Exec= and TryExec=, for sessions without it on PATH
+    #[arg(long, global = true)]
+    pub absolute_exec: bool,
+    /// Write entries into a subdirectory per first letter of the game's name, like A/ and 0-9/
+    #[arg(long, global = true)]
+    pub bucket_by_letter: bool,
+    /// Refer to the home directory as $HOME in Exec= lines, for an applications directory synced between machines
+    #[arg(long, global = true)]
+    pub portable_paths: bool,
+    /// Launch games in a systemd user scope, with the [limits] from the config as its properties
+    #[arg(long, global = true)]
+    pub systemd_scope: bool,
+    /// Also write steam-client.desktop, launching the Steam client from the resolved executable
+    #[arg(long, global = true)]
+    pub with_steam_entry: bool,
+    /// Launch games through steamer launch-wrapper, recording each launch for steamer history
+    #[arg(long, global = true)]
+    pub track_launches: bool,
+    /// Launch games through steamer wait-for-steam, which starts Steam and waits for a login first, for shared machines
+    #[arg(long, global = true)]
+    pub wait_for_steam: bool,
+    /// Add an "Open save folder" action to entries of games whose save folder can be found
+    #[arg(long, global = true)]
+    pub save_dir_action: bool,
+    /// Launcher to tune the entries for (defaults to pantheon in Pantheon sessions, otherwise generic)
+    #[arg(long, value_enum, global = true)]
+    pub desktop_environment: Option<DesktopEnvironment>,
+    /// Rename entries an older tool or naming scheme left, like <PREFIX>620.desktop, to steam-620.desktop instead of writing new ones next to them
+    #[arg(long, value_name = "PREFIX", global = true)]
+    pub migrate_from: Option<String>,
+    /// Where icons are looked for, comma separated, the first found wins; sources left out aren't used
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "custom,steam,grid",
+        global = true
+    )]
+    pub icon_sources: Vec<IconSource>,
+    /// Give games that run in Proton a copy of their icon with a Proton badge, kept in steamer's cache
+    #[arg(long, global = true)]
+    pub badge_proton: bool,
+    /// Leave the Categories= line out of generated entries
+    #[arg(long, global = true)]
+    pub no_categories: bool,
+    /// Add the app ID and store URL to the Keywords= line, to find games by either
+    #[arg(long, global = true)]
+    pub keywords_appid: bool,
+    /// Mark each entry with X-Steamer-Source=steam, or =shortcut for non-Steam games, so menus and scripts can tell them from each other and from other entries
+    #[arg(long, global = true)]
+    pub tag_source: bool,
+    /// Also add " (Steam)", or " (non-Steam)" for non-Steam games, to the name of each entry
+    #[arg(long, requires = "tag_source", global = true)]
+    pub tag_source_name: bool,
+    /// Keep a "Steam" folder in the GNOME app grid holding the generated entries
+    #[arg(long, global = true)]
+    pub gnome_folder: bool,
+    /// Format of progress output; json emits one event per line on stderr
+    #[arg(long, value_enum, default_value_t, global = true)]
+    pub log_format: LogFormat,
+    /// Template for the last line of a sync, using {created}, {skipped}, {removed} and {elapsed} (milliseconds) placeholders
+    #[arg(long, value_name = "TEMPLATE", value_parser = parse_summary_format, global = true)]
+    pub summary_format: Option<Template>,
+    /// Proxy for network features, overriding HTTP_PROXY/HTTPS_PROXY
+    #[arg(long, global = true)]
+    pub proxy: Option<String>,
+    /// Disable every network request; network features fall back to local data
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Move removed entries to the trash instead of deleting them (config: cleanup_mode = "trash")
+    #[arg(long, global = true)]
+    pub trash: bool,
+    /// Another library folder to scan, not registered with Steam; can be repeated (config: extra_libraries)
+    #[arg(long, value_name = "PATH", global = true)]
+    pub extra_library: Vec<String>,
+    /// Also write entries that install owned games that aren't installed, from the Steam Web API
+    #[arg(long, global = true)]
+    pub include_uninstalled: bool,
+    /// Steam Web API key for --include-uninstalled (config: api_key)
+    #[arg(long, value_name = "KEY", global = true)]
+    pub api_key: Option<String>,
+    /// SteamID64 of the account whose games --include-uninstalled lists (config: steam_id)
+    #[arg(long, value_name = "STEAMID", global = true)]
+    pub steam_id: Option<String>,
+    /// Keep the --include-uninstalled entries out of menus, only found by search (config: hide_uninstalled)
+    #[arg(long, global = true)]
+    pub hide_uninstalled: bool,
+    /// Also write entries for the non-Steam games added to Steam, from every account's shortcuts.vdf
+    #[arg(long, global = true)]
+    pub include_shortcuts: bool,
+    /// Skip games whose steamapps/common folder isn't in their library or any other one
+    #[arg(long, global = true)]
+    pub verify_installdir: bool,
+    /// Read every manifest and appinfo again instead of reusing what the last run resolved
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+    /// Never read appinfo.vdf, for huge ones that are slow to parse; demos are then only told by name and playtests aren't skipped
+    #[arg(long, conflicts_with = "locales", global = true)]
+    pub no_appinfo: bool,
+    /// Stop at the first manifest that can't be parsed or entry that can't be written
+    #[arg(long, global = true)]
+    pub fail_fast: bool,
+    /// Read every entry back after writing it and write it again if it differs, for storage that loses writes
+    #[arg(long, global = true)]
+    pub verify_writes: bool,
+    /// If another steamer run is updating entries, wait for it instead of exiting
+    #[arg(long, global = true)]
+    pub wait: bool,
+    /// Only rewrite the entries of games whose icon in Steam's cache changed since the last run, leaving the others and removed games' entries as they are
+    #[arg(long, conflicts_with_all = ["no_cache", "include_uninstalled"], global = true)]
+    pub icon_changes_only: bool,
+    /// Only add and update entries, never remove existing ones (config: cleanup = "never")
+    #[arg(long, global = true)]
+    pub additive: bool,
+    /// Only write again the entries deleted or changed since steamer wrote them, and those of games it never wrote one for, leaving the rest and removed games' entries as they are
+    #[arg(long, conflicts_with = "icon_changes_only", global = true)]
+    pub repair: bool,
+    /// Ask before a cleanup removes more than N entries beyond the ones the run writes
+    #[arg(long, value_name = "N", default_value_t = 20, global = true)]
+    pub removal_margin: usize,
+    /// Stop instead of asking when a cleanup would remove more entries than --removal-margin allows
+    #[arg(long, global = true)]
+    pub safe: bool,
+    /// Experimental: list the games in a tar, tar.gz or zip library backup, without writing anything
+    #[arg(long, value_name = "PATH")]
+    pub from_archive: Option<String>,
+    /// Only report games whose manifest appears in more than one library, without writing anything
+    #[arg(long)]
+    pub report_duplicates: bool,
+    /// Only count the games per library that would get a launcher, skipping icons and appinfo
+    #[arg(long, conflicts_with_all = ["from_archive", "report_duplicates"])]
+    pub count_only: bool,
+}
+
+impl Args {
+    /// How entries are rendered, from the flags and the config. `steam_binary` is the resolved
+    /// steam executable, only used with `--absolute-exec`.
+    pub fn entry_options(
+        &self,
+        config: &Config,
+        steam_binary: Option<PathBuf>,
+    ) -> anyhow::Result<EntryOptions> {
+        if self.bigpicture && self.exec_mode != ExecMode::Steam {
+            anyhow::bail!("--bigpicture passes options to steam, it needs --exec-mode steam");
+        }
+        Ok(EntryOptions {
+            comment_template: self.comment_template.clone(),
+            comment_appid: self.comment_appid,
+            omit_categories: self.no_categories,
+            keywords_appid: self.keywords_appid,
+            locales: self.locales(),
+            generic_names: self.generic_names()?,
+            name_rules: NameRules::new(
+                !(self.raw_names || config.raw_names()?),
+                &self.strip_suffixes,
+                &config.name_rules()?,
+            )?
+            .nfc(!self.no_nfc)
+            .strip_emoji(self.strip_emoji),
+            max_name_length: self.max_name_length,
+            exec_mode: self.exec_mode,
+            steam_binary: steam_binary.filter(|_| self.absolute_exec),
+            big_picture: self.bigpicture,
+            systemd_scope: self.systemd_scope.then(|| config.limits()).transpose()?,
+            launch_wrapper: self
+                .track_launches
+                .then(|| std::env::current_exe().context("Could not find the steamer executable"))
+                .transpose()?,
+            wait_for_steam: self
+                .wait_for_steam
+                .then(|| std::env::current_exe().context("Could not find the steamer executable"))
+                .transpose()?,
+            environment: config.environment()?,
+            desktop_environment: self
+                .desktop_environment
+                .unwrap_or_else(DesktopEnvironment::detect),
+            extra_keywords: match self.keywords_file {
+                Some(ref path) => desktop::read_keywords_file(path)?,
+                None => Default::default(),
+            },
+            bucket_by_letter: self.bucket_by_letter,
+            tag_source: self.tag_source,
+            tag_source_name: self.tag_source_name,
+            portable_home: self
+                .portable_paths
+                .then(|| dirs::home_dir().context("Could not find home directory"))
+                .transpose()?,
+        })
+    }
+
+    /// The API key and SteamID for `--include-uninstalled`, flags first, then the config.
+    pub fn owned_games_account(&self, config: &Config) -> anyhow::Result<(String, String)> {
+        net::require_network("--include-uninstalled")?;
+        let api_key = match self.api_key {
+            Some(ref key) => key.clone(),
+            None => config
+                .api_key()?
+                .context("--include-uninstalled needs a Steam Web API key, pass --api-key or set api_key in the config")?,
+        };
+        let steam_id = match self.steam_id {
+            Some(ref id) => id.clone(),
+            None => config
+                .steam_id()?
+                .context("--include-uninstalled needs a SteamID64, pass --steam-id or set steam_id in the config")?,
+        };
+        // both end up in a URL, so anything else is refused instead of escaped
+        if api_key.is_empty() || !api_key.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            anyhow::bail!("The Steam Web API key should only contain letters and digits");
+        }
+        if steam_id.is_empty() || !steam_id.bytes().all(|b| b.is_ascii_digit()) {
+            anyhow::bail!("The SteamID should be a SteamID64, a number like 76561197960287930");
+        }
+        Ok((api_key, steam_id))
+    }
+
+    /// The skip rules every command filters games with.
+    pub fn skip_rules(&self) -> anyhow::Result<SkipRules> {
+        Ok(SkipRules::new(
+            &self.ignored_app_ids(),
+            &self.skip_keywords(),
+            self.keyword_match,
+        )?
+        .skip_demos(self.skip_demos)
+        .skip_playtests(!self.include_playtests)
+        .skip_partial(self.skip_partial)
+        .skip_emoji(self.skip_emoji)
+        .keep_tools(self.tools)
+        .size_range(self.size_range.clone()))
+    }
+
+    pub fn skip_keywords(&self) -> Vec<String> {
+        let mut keywords = match self.skip_keywords {
+            Some(ref values) => clean_list(values),
+            None => self.defaults(DEFAULT_SKIP_KEYWORDS),
+        };
+        keywords.extend(clean_list(&self.add_skip_keywords));
+        keywords
+    }
+
+    pub fn locales(&self) -> Vec<String> {
+        clean_list(&self.locales)
+    }
+
+    /// The `--generic-name` values, the untranslated one first.
+    fn generic_names(&self) -> anyhow::Result<Vec<(Option<String>, String)>> {
+        let mut names = self.generic_name.clone();
+        names.sort_by(|a, b| a.0.cmp(&b.0));
+        if let [(Some(_), _), ..] = names[..] {
+            anyhow::bail!(
+                "--generic-name translations need an untranslated --generic-name to go with them"
+            );
+        }
+        if let Some(pair) = names.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            match pair[0].0 {
+                Some(ref locale) => anyhow::bail!("--generic-name gives {} more than once", locale),
+                None => anyhow::bail!("--generic-name without a locale is given more than once"),
+            }
+        }
+        Ok(names)
+    }
+
+    pub fn ignored_app_ids(&self) -> Vec<String> {
+        let mut ids = match self.ignored_app_ids {
+            Some(ref values) => clean_list(values),
+            None => self.defaults(DEFAULT_IGNORED_APP_IDS),
+        };
+        ids.extend(clean_list(&self.add_ignored_app_ids));
+        ids
+    }
+
+    /// A built-in list, or nothing with `--no-default-skips`.
+    fn defaults(&self, list: &[&str]) -> Vec<String> {
+        if self.no_default_skips {
+            Vec::new()
+        } else {
+            to_owned_list(list)
+        }
+    }
+}
+
+fn parse_comment_template(source: &str) -> Result<Template, String> {
+    Template::parse(source, desktop::COMMENT_PLACEHOLDERS).map_err(|e| e.to_string())
+}
+
+/// `TEXT`, or `LOCALE=TEXT` for a translation when what's before the `=` looks like a locale
+/// such as `de`, `pt_BR` or `sr@latin`.
+fn parse_generic_name(value: &str) -> Result<(Option<String>, String), String> {
+    let is_locale = |locale: &str| {
+        let (language, rest) = locale.split_at(locale.find(['_', '@']).unwrap_or(locale.len()));
+        (2..=3).contains(&language.len())
+            && language.bytes().all(|b| b.is_ascii_lowercase())
+            && rest
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'@')
+    };
+    let (locale, text) = match value.split_once('=') {
+        Some((locale, text)) if is_locale(locale) => (Some(locale.to_string()), text),
+        _ => (None, value),
+    };
+    if text.trim().is_empty() {
+        return Err("the generic name is empty".to_string());
+    }
+    Ok((locale, text.trim().to_string()))
+}
+
+fn parse_size_range(text: &str) -> Result<SizeRange, String> {
+    SizeRange::parse(text).map_err(|e| e.to_string())
+}
+
+fn parse_summary_format(source: &str) -> Result<Template, String> {
+    Template::parse(source, report::SUMMARY_PLACEHOLDERS).map_err(|e| e.to_string())
+}
+
+fn parse_max_name_length(value: &str) -> Result<usize, String> {
+    let length: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if length < MIN_NAME_LENGTH {
+        return Err(format!(
+            "must be at least {}, shorter names can't be told apart",
+            MIN_NAME_LENGTH
+        ));
+    }
+    Ok(length)
+}
+
+fn parse_new_since(value: &str) -> Result<usize, String> {
+    let runs: usize = value.parse().map_err(|e| format!("{}", e))?;
+    if !(1..=runs::MAX_RUNS).contains(&runs) {
+        return Err(format!(
+            "must be from 1 to {}, the number of syncs steamer remembers",
+            runs::MAX_RUNS
+        ));
+    }
+    Ok(runs)
+}
+
+/// Trims list values and drops the empty ones left behind by stray commas.
+fn clean_list(values: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn to_owned_list(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(flags: &[&str]) -> Args {
+        Cli::try_parse_from(["steamer"].iter().chain(flags))
+            .unwrap()
+            .args
+    }
+
+    #[test]
+    fn list_flags_repeat_and_split_on_commas() {
+        let args = parse(&[
+            "-k",
+            "Demo, Soundtrack",
+            "-k",
+            "Beta,,",
+            "--skip-keywords=Tool",
+        ]);
+        assert_eq!(args.skip_keywords(), ["Demo", "Soundtrack", "Beta", "Tool"]);
+
+        let args = parse(&["--locales", "de,fr", "--locales", " ja "]);
+        assert_eq!(args.locales(), ["de", "fr", "ja"]);
+    }
+
+    #[test]
+    fn added_keywords_go_on_top_of_the_defaults() {
+        let keywords =
+            parse(&["--add-skip-keywords", "Demo", "--add-skip-keywords=Beta"]).skip_keywords();
+        assert_eq!(keywords.len(), DEFAULT_SKIP_KEYWORDS.len() + 2);
+        assert_eq!(
+            keywords[..DEFAULT_SKIP_KEYWORDS.len()],
+            *DEFAULT_SKIP_KEYWORDS
+        );
+        assert_eq!(keywords[DEFAULT_SKIP_KEYWORDS.len()..], ["Demo", "Beta"]);
+
+        let keywords = parse(&["-k", "Demo", "--add-skip-keywords", "Beta"]).skip_keywords();
+        assert_eq!(keywords, ["Demo", "Beta"]);
+    }
+
+    #[test]
+    fn generic_names_need_the_untranslated_one() {
+        let args = parse(&[
+            "--generic-name",
+            "pt_BR=Jogo",
+            "--generic-name",
+            "Game=Show",
+        ]);
+        assert_eq!(
+            args.generic_names().unwrap(),
+            [
+                (None, "Game=Show".to_string()),
+                (Some("pt_BR".to_string()), "Jogo".to_string()),
+            ]
+        );
+
+        let error = parse(&["--generic-name", "de=Spiel"]).generic_names();
+        assert!(error.unwrap_err().to_string().contains("untranslated"));
+        let error = parse(&["--generic-name", "Game", "--generic-name", "Spiel"]).generic_names();
+        assert!(error.unwrap_err().to_string().contains("more than once"));
+        assert!(Cli::try_parse_from(["steamer", "--generic-name", "de= "]).is_err());
+    }
+
+    #[test]
+    fn default_skips_can_be_turned_off_or_added_to() {
+        assert_eq!(parse(&[]).ignored_app_ids(), ["480"]);
+        assert_eq!(
+            parse(&["--add-ignored-app-ids", "228980,1070560"]).ignored_app_ids(),
+            ["480", "228980", "1070560"]
+        );
+        assert_eq!(parse(&["-i", "228980"]).ignored_app_ids(), ["228980"]);
+
+        let args = parse(&["--no-default-skips", "--add-ignored-app-ids", "228980"]);
+        assert_eq!(args.ignored_app_ids(), ["228980"]);
+        assert!(args.skip_keywords().is_empty());
+        let args = parse(&["--no-default-skips", "--add-skip-keywords", "Demo"]);
+        assert_eq!(args.skip_keywords(), ["Demo"]);
+        assert!(args.ignored_app_ids().is_empty());
+    }
+}
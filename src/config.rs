@@ -0,0 +1,1562 @@
+//! `~/.config/steamer/config.toml`, for settings people want on every run. It only understands
+//! the part of TOML steamer needs: `[tables]`, `[[arrays of tables]]`, `key = value` with
+//! strings, booleans, integers and single-line arrays, and `#` comments. Flags on the command
+//! line win over the file.
+
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+/// Every key the file may contain, with dotted names for keys inside a table. Resource limits
+/// are checked separately, they can sit in `[limits]` or in any `[game.<appid>.limits]`, and so
+/// are the variables of a `[game.<appid>.env]` and the desktops of `[app_dirs]`, which can
+/// have any name.
+const KNOWN_KEYS: &[&str] = &[
+    "cleanup",
+    "cleanup_mode",
+    "extra_libraries",
+    "api_key",
+    "steam_id",
+    "hide_uninstalled",
+    "raw_names",
+    "steam_roots",
+    "merge_steam_roots",
+];
+
+/// Keys of a `[game.<appid>]` table, besides its limits.
+const GAME_KEYS: &[&str] = &["icon", "save_dir"];
+
+/// Arrays of tables, each `[[name]]` an entry, with the keys an entry may have.
+const ARRAY_TABLES: &[(&str, &[&str])] = &[
+    ("name_rules", &["pattern", "replace"]),
+    ("servers", &["name", "exec", "appid", "terminal"]),
+];
+
+/// Keys allowed in a limits table, with the systemd property each one sets.
+const LIMIT_PROPERTIES: &[(&str, &str)] = &[
+    ("memory_max", "MemoryMax"),
+    ("memory_high", "MemoryHigh"),
+    ("memory_swap_max", "MemorySwapMax"),
+    ("cpu_weight", "CPUWeight"),
+    ("cpu_quota", "CPUQuota"),
+    ("allowed_cpus", "AllowedCPUs"),
+    ("io_weight", "IOWeight"),
+    ("tasks_max", "TasksMax"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Bool(bool),
+    Int(i64),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Str(_) => "a string",
+            Value::Bool(_) => "a boolean",
+            Value::Int(_) => "an integer",
+            Value::Array(_) => "an array",
+        }
+    }
+
+    /// A value given on the command line, read the way the file is. Shells eat quotes, so a
+    /// bare word that isn't a boolean or a number is taken as a string.
+    pub fn from_arg(text: &str) -> Result<Self> {
+        let text = text.trim();
+        match parse_value(text) {
+            Ok(value) => Ok(value),
+            Err(_) if !text.starts_with(['"', '\'', '[']) => Ok(Value::Str(text.to_string())),
+            Err(message) => bail!("{}", message),
+        }
+    }
+}
+
+/// The value as it's written in the file.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => {
+                f.write_str("\"")?;
+                for c in s.chars() {
+                    match c {
+                        '"' => f.write_str("\\\"")?,
+                        '\\' => f.write_str("\\\\")?,
+                        '\n' => f.write_str("\\n")?,
+                        '\t' => f.write_str("\\t")?,
+                        c => write!(f, "{}", c)?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    path: Option<PathBuf>,
+    /// Every value with its dotted key and the line it's on.
+    values: Vec<(String, Value, usize)>,
+    /// The `--profile` file layered over this one, with the keys taken from it.
+    profile: Option<(PathBuf, Vec<String>)>,
+}
+
+/// What happens to existing `steam-*.desktop` files before new ones are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Cleanup {
+    /// Remove them all, so entries for uninstalled games go away
+    #[default]
+    Always,
+    /// Leave them, only adding and updating entries
+    Never,
+}
+
+/// A `[[servers]]` entry: a launcher for a dedicated server, or a script managing one.
+#[derive(Debug, Clone, Default)]
+pub struct Server {
+    pub name: String,
+    /// Written to `Exec=` as given.
+    pub exec: String,
+    /// The server's app, whose icon the launcher gets.
+    pub appid: Option<String>,
+    pub terminal: bool,
+}
+
+/// systemd properties for `--systemd-scope`, from `[limits]` and `[game.<appid>.limits]`.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    global: Vec<(&'static str, String)>,
+    games: Vec<(String, Vec<(&'static str, String)>)>,
+}
+
+impl Limits {
+    /// The properties for one game, its own values replacing the global ones, sorted by name so
+    /// moving lines around in the config doesn't change the entries.
+    pub fn for_game(&self, appid: &str) -> Vec<(&'static str, &str)> {
+        let own = self
+            .games
+            .iter()
+            .find(|(id, _)| id == appid)
+            .map_or(&[][..], |(_, props)| props);
+        let mut merged: Vec<(&'static str, &str)> = self
+            .global
+            .iter()
+            .filter(|(name, _)| !own.iter().any(|(n, _)| n == name))
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        merged.extend(own.iter().map(|(name, value)| (*name, value.as_str())));
+        merged.sort_by_key(|(name, _)| *name);
+        merged
+    }
+}
+
+/// How an entry is got rid of when it's removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Removal {
+    #[default]
+    Delete,
+    /// Move it to the freedesktop trash, so it can be restored
+    Trash,
+}
+
+impl Config {
+    /// Loads the file from the user's config directory, or an empty config if there is none,
+    /// with the `--profile` named `profile` layered over it.
+    pub fn load_default(profile: Option<&str>) -> Result<Self> {
+        let config = match default_path() {
+            Some(path) if path.exists() => Self::load(&path)?,
+            _ => Self::default(),
+        };
+        config.with_profile(profile)
+    }
+
+    /// This config with the profile named `profile` layered over it, if one is given. A profile
+    /// without a file is an error listing the ones there are.
+    pub fn with_profile(self, profile: Option<&str>) -> Result<Self> {
+        let Some(name) = profile else {
+            return Ok(self);
+        };
+        let path = profile_path(name)?;
+        if !path.exists() {
+            let profiles = profile_names();
+            if profiles.is_empty() {
+                bail!("There is no profile {:?}, {:?} doesn't exist", name, path);
+            }
+            bail!(
+                "There is no profile {:?}, the profiles are: {}",
+                name,
+                profiles.join(", ")
+            );
+        }
+        Ok(self.layer(Self::load(&path)?))
+    }
+
+    /// This config with the values of `profile` replacing its own. A `[[table]]` the profile
+    /// has replaces every entry of it here, since entries are matched up by position.
+    fn layer(mut self, profile: Config) -> Self {
+        let replaced_tables: Vec<&str> = profile
+            .values
+            .iter()
+            .filter_map(|(key, _, _)| array_key(key).map(|(name, _, _)| name))
+            .collect();
+        self.values.retain(|(key, _, _)| {
+            !profile.values.iter().any(|(k, _, _)| k == key)
+                && !array_key(key).is_some_and(|(name, _, _)| replaced_tables.contains(&name))
+        });
+        let keys = profile.values.iter().map(|(k, _, _)| k.clone()).collect();
+        self.values.extend(profile.values);
+        self.profile = profile.path.map(|path| (path, keys));
+        self
+    }
+
+    /// The file `key` was read from.
+    fn file(&self, key: &str) -> Option<&Path> {
+        match self.profile {
+            Some((ref path, ref keys)) if keys.iter().any(|k| k == key) => Some(path),
+            _ => self.path.as_deref(),
+        }
+    }
+
+    /// Where `key` is set, for `steamer config list`: its line, with the file when that's
+    /// the profile.
+    pub fn source(&self, key: &str) -> Option<String> {
+        let line = self.line(key)?;
+        match self.profile {
+            Some((ref path, ref keys)) if keys.iter().any(|k| k == key) => {
+                Some(format!("{} line {}", path.display(), line))
+            }
+            _ => Some(format!("line {}", line)),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text =
+            fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+        Self::from_text(&text, path)
+    }
+
+    /// The config in `text`, reporting errors as if it were read from `path`.
+    pub fn from_text(text: &str, path: &Path) -> Result<Self> {
+        let values = parse(text).with_context(|| format!("Could not parse {:?}", path))?;
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            values,
+            profile: None,
+        })
+    }
+
+    /// Every value in file order, with its dotted key and line number.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &Value, usize)> {
+        self.values
+            .iter()
+            .map(|(key, value, line)| (key.as_str(), value, *line))
+    }
+
+    /// Keys steamer doesn't know, most likely typos, for the caller to warn about.
+    pub fn unknown_keys(&self) -> Vec<&str> {
+        self.values
+            .iter()
+            .map(|(k, _, _)| k.as_str())
+            .filter(|k| !is_known_key(k))
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, v, _)| v)
+    }
+
+    /// The line `key` is set on.
+    pub fn line(&self, key: &str) -> Option<usize> {
+        self.values
+            .iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, _, l)| *l)
+    }
+
+    fn get_str(&self, key: &str) -> Result<Option<&str>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Str(s)) => Ok(Some(s)),
+            Some(other) => Err(self.type_error(key, "a string", other)),
+        }
+    }
+
+    fn get_bool(&self, key: &str) -> Result<Option<bool>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Bool(b)) => Ok(Some(*b)),
+            Some(other) => Err(self.type_error(key, "a boolean", other)),
+        }
+    }
+
+    fn type_error(&self, key: &str, expected: &str, found: &Value) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{} in {:?} must be {}, found {}",
+            key,
+            self.file(key).unwrap_or(Path::new("config")),
+            expected,
+            found.type_name()
+        )
+    }
+
+    pub fn cleanup(&self) -> Result<Option<Cleanup>> {
+        match self.get_str("cleanup")? {
+            None => Ok(None),
+            Some("always") => Ok(Some(Cleanup::Always)),
+            Some("never") => Ok(Some(Cleanup::Never)),
+            Some(other) => bail!("cleanup must be \"always\" or \"never\", found {:?}", other),
+        }
+    }
+
+    pub fn cleanup_mode(&self) -> Result<Option<Removal>> {
+        match self.get_str("cleanup_mode")? {
+            None => Ok(None),
+            Some("delete") => Ok(Some(Removal::Delete)),
+            Some("trash") => Ok(Some(Removal::Trash)),
+            Some(other) => bail!(
+                "cleanup_mode must be \"delete\" or \"trash\", found {:?}",
+                other
+            ),
+        }
+    }
+
+    /// Library folders to scan on top of the ones Steam knows about.
+    pub fn extra_libraries(&self) -> Result<Vec<PathBuf>> {
+        let items = match self.get("extra_libraries") {
+            None => return Ok(Vec::new()),
+            Some(Value::Array(items)) => items,
+            Some(other) => return Err(self.type_error("extra_libraries", "an array", other)),
+        };
+        items
+            .iter()
+            .map(|item| match item {
+                Value::Str(path) => Ok(PathBuf::from(path)),
+                other => Err(self.type_error("extra_libraries", "an array of strings", other)),
+            })
+            .collect()
+    }
+
+    /// Steam installs to try in order when `--steam-path` isn't given, in place of the usual
+    /// places.
+    pub fn steam_roots(&self) -> Result<Vec<PathBuf>> {
+        let items = match self.get("steam_roots") {
+            None => return Ok(Vec::new()),
+            Some(Value::Array(items)) => items,
+            Some(other) => return Err(self.type_error("steam_roots", "an array", other)),
+        };
+        items
+            .iter()
+            .map(|item| match item {
+                Value::Str(path) => self.expand_path("steam_roots", path),
+                other => Err(self.type_error("steam_roots", "an array of strings", other)),
+            })
+            .collect()
+    }
+
+    /// Scan the libraries of every one of `steam_roots` that exists, not just the first.
+    pub fn merge_steam_roots(&self) -> Result<bool> {
+        Ok(self.get_bool("merge_steam_roots")?.unwrap_or(false))
+    }
+
+    /// Steam Web API key for `--include-uninstalled`.
+    pub fn api_key(&self) -> Result<Option<String>> {
+        Ok(self.get_str("api_key")?.map(str::to_string))
+    }
+
+    /// SteamID64 whose owned games `--include-uninstalled` lists. Being a 17 digit number, it
+    /// may be written with or without quotes.
+    pub fn steam_id(&self) -> Result<Option<String>> {
+        match self.get("steam_id") {
+            None => Ok(None),
+            Some(Value::Str(s)) => Ok(Some(s.clone())),
+            Some(Value::Int(n)) => Ok(Some(n.to_string())),
+            Some(other) => Err(self.type_error("steam_id", "a string or an integer", other)),
+        }
+    }
+
+    /// Keep the entries of games that aren't installed out of menus, only found by search.
+    pub fn hide_uninstalled(&self) -> Result<bool> {
+        Ok(self.get_bool("hide_uninstalled")?.unwrap_or(false))
+    }
+
+    /// Leave names as Steam has them, without the built-in cleanup.
+    pub fn raw_names(&self) -> Result<bool> {
+        Ok(self.get_bool("raw_names")?.unwrap_or(false))
+    }
+
+    /// The `(pattern, replace)` pairs of the `[[name_rules]]` entries, in file order. The
+    /// patterns are compiled by the caller, which reports bad ones with their entry number.
+    pub fn name_rules(&self) -> Result<Vec<(String, String)>> {
+        let mut rules: Vec<(Option<String>, Option<String>)> = Vec::new();
+        for (key, value, _) in &self.values {
+            let Some(("name_rules", index, field)) = array_key(key) else {
+                continue;
+            };
+            let Value::Str(value) = value else {
+                return Err(self.type_error(key, "a string", value));
+            };
+            if rules.len() <= index {
+                rules.resize(index + 1, (None, None));
+            }
+            match field {
+                "pattern" => rules[index].0 = Some(value.clone()),
+                _ => rules[index].1 = Some(value.clone()),
+            }
+        }
+        rules
+            .into_iter()
+            .enumerate()
+            .map(|(index, rule)| match rule {
+                (Some(pattern), replace) => Ok((pattern, replace.unwrap_or_default())),
+                (None, _) => bail!("[[name_rules]] entry {} has no pattern", index + 1),
+            })
+            .collect()
+    }
+
+    /// The `[[servers]]` entries, in file order. Empty names and commands are refused, so an
+    /// empty one below is one the entry doesn't have.
+    pub fn servers(&self) -> Result<Vec<Server>> {
+        let mut servers: Vec<Server> = Vec::new();
+        for (key, value, _) in &self.values {
+            let Some(("servers", index, field)) = array_key(key) else {
+                continue;
+            };
+            if servers.len() <= index {
+                servers.resize(index + 1, Server::default());
+            }
+            let server = &mut servers[index];
+            match (field, value) {
+                ("name", Value::Str(s)) if !s.trim().is_empty() => server.name = s.clone(),
+                ("exec", Value::Str(s)) if !s.trim().is_empty() => server.exec = s.clone(),
+                ("name" | "exec", Value::Str(_)) => bail!("{} can't be empty", key),
+                ("name" | "exec", other) => return Err(self.type_error(key, "a string", other)),
+                ("appid", Value::Int(id)) if *id > 0 => server.appid = Some(id.to_string()),
+                ("appid", Value::Str(id))
+                    if !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()) =>
+                {
+                    server.appid = Some(id.clone())
+                }
+                ("appid", other) => return Err(self.type_error(key, "an app ID", other)),
+                ("terminal", Value::Bool(b)) => server.terminal = *b,
+                (_, other) => return Err(self.type_error(key, "a boolean", other)),
+            }
+        }
+        for (index, server) in servers.iter().enumerate() {
+            if server.name.is_empty() {
+                bail!("[[servers]] entry {} has no name", index + 1);
+            }
+            if server.exec.is_empty() {
+                bail!("[[servers]] entry {} has no exec", index + 1);
+            }
+        }
+        Ok(servers)
+    }
+
+    /// The `icon` of each `[game.<appid>]` table.
+    pub fn custom_icons(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.game_paths("icon")
+    }
+
+    /// The `save_dir` of each `[game.<appid>]` table.
+    pub fn save_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.game_paths("save_dir")
+    }
+
+    /// The applications directory of each desktop in the `[app_dirs]` table, keyed by its name
+    /// as `XDG_CURRENT_DESKTOP` has it, in table order.
+    pub fn app_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut dirs = Vec::new();
+        for (key, value, _) in &self.values {
+            let Some(desktop) = app_dir_key(key) else {
+                continue;
+            };
+            let Value::Str(path) = value else {
+                return Err(self.type_error(key, "a string", value));
+            };
+            dirs.push((desktop.to_string(), self.expand_path(key, path)?));
+        }
+        Ok(dirs)
+    }
+
+    /// A path set per game.
+    fn game_paths(&self, field: &str) -> Result<Vec<(String, PathBuf)>> {
+        let mut paths = Vec::new();
+        for (key, value, _) in &self.values {
+            let Some((appid, name)) = game_key(key) else {
+                continue;
+            };
+            if name != field {
+                continue;
+            }
+            let Value::Str(path) = value else {
+                return Err(self.type_error(key, "a string", value));
+            };
+            paths.push((appid.to_string(), self.expand_path(key, path)?));
+        }
+        Ok(paths)
+    }
+
+    /// A leading `~/` is the home directory, and other relative paths are taken from the
+    /// directory of the file `key` is in.
+    fn expand_path(&self, key: &str, path: &str) -> Result<PathBuf> {
+        let base = self.file(key).and_then(Path::parent);
+        Ok(match path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(rest),
+            None => base.map_or_else(|| PathBuf::from(path), |base| base.join(path)),
+        })
+    }
+
+    /// The variables of each `[game.<appid>.env]` table, sorted by name.
+    pub fn environment(&self) -> Result<HashMap<String, Vec<(String, String)>>> {
+        let mut environment: HashMap<String, Vec<(String, String)>> = HashMap::new();
+        for (key, value, _) in &self.values {
+            let Some((appid, name)) = env_key(key) else {
+                continue;
+            };
+            let mut chars = name.chars();
+            let valid = chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                bail!(
+                    "{} isn't a valid environment variable name, use letters, digits and _",
+                    name
+                );
+            }
+            let value = match value {
+                Value::Str(s) if s.chars().any(char::is_control) => {
+                    bail!("{} can't contain control characters", key)
+                }
+                Value::Str(s) => s.clone(),
+                Value::Int(n) => n.to_string(),
+                other => return Err(self.type_error(key, "a string or an integer", other)),
+            };
+            environment
+                .entry(appid.to_string())
+                .or_default()
+                .push((name.to_string(), value));
+        }
+        // sorted like the limits, the order they're set in doesn't matter to the game
+        for vars in environment.values_mut() {
+            vars.sort();
+        }
+        Ok(environment)
+    }
+
+    pub fn limits(&self) -> Result<Limits> {
+        let mut limits = Limits::default();
+        for (key, value, _) in &self.values {
+            let Some((appid, name)) = limit_key(key) else {
+                continue;
+            };
+            let value = match value {
+                Value::Str(s) if s.chars().any(char::is_control) => {
+                    bail!("{} can't contain control characters", key)
+                }
+                Value::Str(s) => s.clone(),
+                Value::Int(n) => n.to_string(),
+                other => return Err(self.type_error(key, "a string or an integer", other)),
+            };
+            let props = match appid {
+                None => &mut limits.global,
+                Some(appid) => match limits.games.iter().position(|(id, _)| id == appid) {
+                    Some(i) => &mut limits.games[i].1,
+                    None => {
+                        limits.games.push((appid.to_string(), Vec::new()));
+                        &mut limits.games.last_mut().unwrap().1
+                    }
+                },
+            };
+            props.push((name, value));
+        }
+        Ok(limits)
+    }
+}
+
+fn is_known_key(key: &str) -> bool {
+    KNOWN_KEYS.contains(&key)
+        || limit_key(key).is_some()
+        || array_key(key).is_some()
+        || game_key(key).is_some()
+        || env_key(key).is_some()
+        || app_dir_key(key).is_some()
+}
+
+/// Tables that hold settings: `limits`, `app_dirs`, `game.<appid>`, `game.<appid>.limits`,
+/// `game.<appid>.env`, an array of
+/// tables like `name_rules` and a single entry of one, `name_rules.<index>`.
+fn is_table_key(key: &str) -> bool {
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if key == "limits" || key == "app_dirs" {
+        return true;
+    }
+    for (name, _) in ARRAY_TABLES {
+        if key == *name {
+            return true;
+        }
+        if let Some(index) = key
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('.'))
+        {
+            return is_number(index);
+        }
+    }
+    match key.strip_prefix("game.") {
+        Some(rest) => is_number(
+            rest.strip_suffix(".limits")
+                .or_else(|| rest.strip_suffix(".env"))
+                .unwrap_or(rest),
+        ),
+        None => false,
+    }
+}
+
+/// Checks that `key` is a setting steamer knows, or with `tables` also a table of settings,
+/// suggesting the closest known key for a typo.
+pub fn check_key(key: &str, tables: bool) -> Result<()> {
+    if is_known_key(key) || (tables && is_table_key(key)) {
+        return Ok(());
+    }
+    // keys naming a game or an entry are suggested with the same appid or index
+    let segment = |prefix: &str| {
+        key.strip_prefix(prefix)
+            .and_then(|rest| rest.split('.').next())
+            .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
+            .unwrap_or("0")
+            .to_string()
+    };
+    let appid = segment("game.");
+    let mut candidates: Vec<String> = KNOWN_KEYS.iter().map(|k| k.to_string()).collect();
+    for (name, _) in LIMIT_PROPERTIES {
+        candidates.push(format!("limits.{}", name));
+        candidates.push(format!("game.{}.limits.{}", appid, name));
+    }
+    for name in GAME_KEYS {
+        candidates.push(format!("game.{}.{}", appid, name));
+    }
+    for (table, keys) in ARRAY_TABLES {
+        let index = segment(&format!("{}.", table));
+        for name in *keys {
+            candidates.push(format!("{}.{}.{}", table, index, name));
+        }
+        if tables {
+            candidates.push(table.to_string());
+        }
+    }
+    if tables {
+        candidates.push("limits".to_string());
+        candidates.push("app_dirs".to_string());
+        candidates.push(format!("game.{}.limits", appid));
+        candidates.push(format!("game.{}.env", appid));
+    }
+    let closest = candidates
+        .iter()
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|(distance, _)| *distance <= (key.len() / 3).max(1))
+        .min_by_key(|(distance, _)| *distance);
+    match closest {
+        Some((_, candidate)) => bail!(
+            "Unknown config key {:?}, did you mean {:?}?",
+            key,
+            candidate
+        ),
+        None => bail!("Unknown config key {:?}", key),
+    }
+}
+
+/// Levenshtein distance, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let next = (row[j + 1] + 1)
+                .min(row[j] + 1)
+                .min(diagonal + usize::from(ca != *cb));
+            diagonal = row[j + 1];
+            row[j + 1] = next;
+        }
+    }
+    row[b.len()]
+}
+
+/// Splits `limits.<key>` and `game.<appid>.limits.<key>` into the appid, if any, and the
+/// systemd property name.
+fn limit_key(key: &str) -> Option<(Option<&str>, &'static str)> {
+    let (appid, name) = match key.strip_prefix("limits.") {
+        Some(name) => (None, name),
+        None => {
+            let (appid, name) = key.strip_prefix("game.")?.split_once(".limits.")?;
+            if appid.is_empty() || !appid.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            (Some(appid), name)
+        }
+    };
+    LIMIT_PROPERTIES
+        .iter()
+        .find(|(k, _)| *k == name)
+        .map(|(_, property)| (appid, *property))
+}
+
+/// Splits `game.<appid>.<key>` into the appid and the key.
+fn game_key(key: &str) -> Option<(&str, &str)> {
+    let (appid, field) = key.strip_prefix("game.")?.split_once('.')?;
+    if appid.is_empty() || !appid.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = GAME_KEYS.iter().find(|k| **k == field)?;
+    Some((appid, field))
+}
+
+/// Splits `game.<appid>.env.<name>` into the appid and the variable name.
+fn env_key(key: &str) -> Option<(&str, &str)> {
+    let (appid, name) = key.strip_prefix("game.")?.split_once(".env.")?;
+    if appid.is_empty() || !appid.bytes().all(|b| b.is_ascii_digit()) || name.is_empty() {
+        return None;
+    }
+    Some((appid, name))
+}
+
+/// The desktop name of `app_dirs.<desktop>`.
+fn app_dir_key(key: &str) -> Option<&str> {
+    key.strip_prefix("app_dirs.")
+        .filter(|desktop| !desktop.is_empty())
+}
+
+/// Splits `<name>.<index>.<key>`, as `[[name]]` entries are stored, into the name, the index
+/// and the key.
+fn array_key(key: &str) -> Option<(&'static str, usize, &'static str)> {
+    let (name, rest) = key.split_once('.')?;
+    let (name, keys) = ARRAY_TABLES.iter().find(|(n, _)| *n == name)?;
+    let (index, field) = rest.split_once('.')?;
+    let field = keys.iter().find(|k| **k == field)?;
+    Some((name, index.parse().ok()?, field))
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("steamer/config.toml"))
+}
+
+fn profiles_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("steamer/profiles"))
+}
+
+/// `~/.config/steamer/profiles/<name>.toml`, whether or not it exists.
+pub fn profile_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        bail!(
+            "{:?} isn't a profile name, it can't be empty or a path",
+            name
+        );
+    }
+    Ok(profiles_dir()
+        .context("Could not find the config directory")?
+        .join(format!("{}.toml", name)))
+}
+
+/// The profiles there are files for, sorted.
+fn profile_names() -> Vec<String> {
+    let mut names: Vec<String> = profiles_dir()
+        .and_then(|dir| fs::read_dir(dir).ok())
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            Some(name.strip_suffix(".toml")?.to_string())
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[derive(Debug)]
+struct ParseError {
+    message: String,
+    line: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on line {}", self.message, self.line)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// One line of the file as the parser sees it.
+enum Line {
+    /// Blank, or only a comment.
+    Empty,
+    /// `[name]` or `[[name]]`.
+    Table,
+    /// A value with its full dotted key.
+    Pair(String, Value),
+}
+
+fn parse(text: &str) -> Result<Vec<(String, Value, usize)>, ParseError> {
+    let mut values: Vec<(String, Value, usize)> = Vec::new();
+    for (index, line) in scan(text)?.into_iter().enumerate() {
+        let Line::Pair(key, value) = line else {
+            continue;
+        };
+        if values.iter().any(|(k, _, _)| *k == key) {
+            return Err(ParseError {
+                message: format!("{} is set twice", key),
+                line: index + 1,
+            });
+        }
+        values.push((key, value, index + 1));
+    }
+    Ok(values)
+}
+
+/// Reads every line of `text`, one `Line` for each.
+fn scan(text: &str) -> Result<Vec<Line>, ParseError> {
+    let mut lines = Vec::new();
+    let mut table = String::new();
+    let mut array_tables: Vec<(String, usize)> = Vec::new();
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let error = |message: String| ParseError { message, line };
+        let content = strip_comment(raw).trim();
+        if content.is_empty() {
+            lines.push(Line::Empty);
+            continue;
+        }
+
+        // each `[[name]]` starts a new entry, stored as the table `name.<index>`
+        if let Some(name) = content.strip_prefix("[[") {
+            let Some(name) = name.strip_suffix("]]") else {
+                return Err(error("Expected ']]' after the table name".to_string()));
+            };
+            let name = parse_key(name.trim()).map_err(error)?;
+            let count = array_tables.iter_mut().find(|(n, _)| *n == name);
+            let index = match count {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count - 1
+                }
+                None => {
+                    array_tables.push((name.clone(), 1));
+                    0
+                }
+            };
+            table = format!("{}.{}", name, index);
+            lines.push(Line::Table);
+            continue;
+        }
+
+        if let Some(name) = content.strip_prefix('[') {
+            let Some(name) = name.strip_suffix(']') else {
+                return Err(error("Expected ']' after the table name".to_string()));
+            };
+            table = parse_key(name.trim()).map_err(error)?;
+            lines.push(Line::Table);
+            continue;
+        }
+
+        let Some((key, value)) = content.split_once('=') else {
+            return Err(error(format!(
+                "Expected 'key = value', found {:?}",
+                content
+            )));
+        };
+        let key = parse_key(key.trim()).map_err(error)?;
+        let key = if table.is_empty() {
+            key
+        } else {
+            format!("{}.{}", table, key)
+        };
+        let value = parse_value(value.trim()).map_err(error)?;
+        lines.push(Line::Pair(key, value));
+    }
+    Ok(lines)
+}
+
+/// `text` with `key` set to `value`, edited with toml_edit so comments, spacing and the order
+/// of everything else stay as they are. A key that's already there keeps its place and
+/// comment; a new one goes after the last key of its table, and a missing table is added at
+/// the end. Entries of an array of tables like `[[name_rules]]` can be added one past the
+/// last.
+pub fn set_in(text: &str, key: &str, value: &Value) -> Result<String> {
+    let mut doc: DocumentMut = text.parse().context("Could not parse the config")?;
+    // written the way this file's reader expects, not in toml_edit's choice of quotes
+    let mut value: toml_edit::Value = value
+        .to_string()
+        .parse()
+        .context("Could not write the value")?;
+    value.decor_mut().clear();
+    let end = Some(last_position(doc.as_table()) + 1);
+    let had_values = !doc.as_table().get_values().is_empty();
+
+    let (table, leaf) = match array_key(key) {
+        Some((name, index, field)) => {
+            let entries = doc
+                .entry(name)
+                .or_insert_with(|| Item::ArrayOfTables(ArrayOfTables::new()))
+                .as_array_of_tables_mut()
+                .with_context(|| format!("{} is not an array of tables", name))?;
+            if index > entries.len() {
+                bail!(
+                    "There are {} [[{}]] entries, a new one has to be {}.{}",
+                    entries.len(),
+                    name,
+                    name,
+                    entries.len()
+                );
+            }
+            if index == entries.len() {
+                let mut entry = Table::new();
+                entry.set_position(end);
+                entries.push(entry);
+            }
+            (entries.get_mut(index).unwrap(), field)
+        }
+        None => {
+            let (path, leaf) = match key.rsplit_once('.') {
+                Some((path, leaf)) => (path.split('.').collect(), leaf),
+                None => (Vec::new(), key),
+            };
+            let mut table = doc.as_table_mut();
+            for (i, name) in path.iter().enumerate() {
+                table = table
+                    .entry(name)
+                    .or_insert_with(|| {
+                        let mut new = Table::new();
+                        new.set_implicit(true);
+                        new.set_position(end);
+                        Item::Table(new)
+                    })
+                    .as_table_mut()
+                    .with_context(|| format!("{} is not a table", path[..=i].join(".")))?;
+            }
+            (table, leaf)
+        }
+    };
+    // a table only there for the tables under it gets its header after the last table
+    if !has_header(table) {
+        table.set_position(end);
+        table.decor_mut().clear();
+    }
+    match table.get_mut(leaf) {
+        Some(Item::Value(old)) => {
+            let decor = old.decor().clone();
+            *old = value;
+            *old.decor_mut() = decor;
+        }
+        Some(_) => bail!("{} is a table, not a value", key),
+        None => {
+            table.insert(leaf, Item::Value(value));
+        }
+    }
+
+    // a first top-level key is kept apart from the table below it by a blank line
+    if !had_values && !key.contains('.') {
+        let first = first_position(doc.as_table());
+        for_each_table(doc.as_table_mut(), &mut |table| {
+            if table.position() == first && has_header(table) {
+                let prefix = table
+                    .decor()
+                    .prefix()
+                    .and_then(|p| p.as_str())
+                    .unwrap_or("");
+                if !prefix.starts_with('\n') {
+                    let prefix = format!("\n{}", prefix);
+                    table.decor_mut().set_prefix(prefix);
+                }
+            }
+        });
+    }
+    Ok(doc.to_string())
+}
+
+/// `text` without `key`, or without every key and table under it when it names a table or an
+/// entry of an array of tables. `None` if nothing matched. Comments right above a table's header
+/// or a key are taken to be about it, so they go with it.
+pub fn unset_in(text: &str, key: &str) -> Result<Option<String>> {
+    let mut doc: DocumentMut = text.parse().context("Could not parse the config")?;
+    let path: Vec<&str> = key.split('.').collect();
+    if !remove_path(doc.as_table_mut(), &path) {
+        return Ok(None);
+    }
+    Ok(Some(doc.to_string()))
+}
+
+fn remove_path(table: &mut Table, path: &[&str]) -> bool {
+    match path {
+        [] => false,
+        [leaf] => table.remove(leaf).is_some(),
+        [name, index, rest @ ..] if table.get(name).is_some_and(Item::is_array_of_tables) => {
+            let entries = table[*name].as_array_of_tables_mut().unwrap();
+            let Some(index) = index.parse().ok().filter(|&i| i < entries.len()) else {
+                return false;
+            };
+            if !rest.is_empty() {
+                return remove_path(entries.get_mut(index).unwrap(), rest);
+            }
+            entries.remove(index);
+            if entries.is_empty() {
+                table.remove(name);
+            }
+            true
+        }
+        [name, rest @ ..] => table
+            .get_mut(name)
+            .and_then(Item::as_table_mut)
+            .is_some_and(|inner| remove_path(inner, rest)),
+    }
+}
+
+/// Calls `f` on every table under `table`, entries of arrays of tables included.
+fn for_each_table(table: &mut Table, f: &mut impl FnMut(&mut Table)) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            Item::Table(inner) => {
+                f(inner);
+                for_each_table(inner, f);
+            }
+            Item::ArrayOfTables(entries) => {
+                for entry in entries.iter_mut() {
+                    f(entry);
+                    for_each_table(entry, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Where the last table of the document is, so a new one can go after it.
+fn last_position(table: &Table) -> isize {
+    let mut last = 0;
+    for_each_table_ref(table, &mut |t| last = last.max(t.position().unwrap_or(0)));
+    last
+}
+
+/// Where the first table with a header in the file is.
+fn first_position(table: &Table) -> Option<isize> {
+    let mut first = None;
+    for_each_table_ref(table, &mut |t| {
+        if let Some(position) = t.position()
+            && has_header(t)
+        {
+            first = Some(first.map_or(position, |first: isize| first.min(position)));
+        }
+    });
+    first
+}
+
+/// Implicit tables, like `game` above `[game.620]`, only get a header with keys of their own.
+fn has_header(table: &Table) -> bool {
+    !table.is_implicit() || !table.get_values().is_empty()
+}
+
+fn for_each_table_ref(table: &Table, f: &mut impl FnMut(&Table)) {
+    for (_, item) in table.iter() {
+        match item {
+            Item::Table(inner) => {
+                f(inner);
+                for_each_table_ref(inner, f);
+            }
+            Item::ArrayOfTables(entries) => {
+                for entry in entries.iter() {
+                    f(entry);
+                    for_each_table_ref(entry, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_key(key: &str) -> Result<String, String> {
+    if let Some(quoted) = key.strip_prefix('"').and_then(|k| k.strip_suffix('"')) {
+        return Ok(quoted.to_string());
+    }
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.';
+    if key.is_empty() || !key.chars().all(valid) {
+        return Err(format!("Invalid key {:?}", key));
+    }
+    Ok(key.to_string())
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let (value, rest) = parse_inline(text)?;
+    if !rest.trim().is_empty() {
+        return Err(format!("Unexpected {:?} after the value", rest.trim()));
+    }
+    Ok(value)
+}
+
+/// Parses one value from the start of `text`, returning it and whatever follows.
+fn parse_inline(text: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::Str(value), &rest[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, other)) => return Err(format!("Unknown escape '\\{}'", other)),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("Unterminated string".to_string());
+    }
+
+    if let Some(rest) = text.strip_prefix('\'') {
+        return match rest.find('\'') {
+            Some(end) => Ok((Value::Str(rest[..end].to_string()), &rest[end + 1..])),
+            None => Err("Unterminated string".to_string()),
+        };
+    }
+
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_inline(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("Expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+
+    let end = text
+        .find(|c: char| c == ',' || c == ']' || c.is_whitespace())
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    match word {
+        "true" => Ok((Value::Bool(true), rest)),
+        "false" => Ok((Value::Bool(false), rest)),
+        _ => match word.replace('_', "").parse() {
+            Ok(n) => Ok((Value::Int(n), rest)),
+            Err(_) => Err(format!("Invalid value {:?}, strings need quotes", word)),
+        },
+    }
+}
+
+/// Drops a trailing `# comment`, leaving `#` inside strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "\
+# steamer config
+exec_mode = \"xdg-open\" # launch through the desktop
+
+# per game
+[game.620]
+name = \"Portal 2\"
+
+# tidy up names
+[[name_rules]]
+pattern = \" - Deluxe Edition$\"
+replace = \"\"
+
+[environment]
+MANGOHUD = \"1\"
+";
+
+    fn config(text: &str) -> Config {
+        Config::from_text(text, Path::new("config.toml")).unwrap()
+    }
+
+    #[test]
+    fn reads_the_cleanup_setting() {
+        assert_eq!(config("").cleanup().unwrap(), None);
+        assert_eq!(
+            config("cleanup = \"never\"").cleanup().unwrap(),
+            Some(Cleanup::Never)
+        );
+        assert_eq!(
+            config("cleanup = \"always\"").cleanup().unwrap(),
+            Some(Cleanup::Always)
+        );
+        let error = config("cleanup = \"sometimes\"").cleanup().unwrap_err();
+        assert!(error.to_string().contains("\"sometimes\""), "{}", error);
+        let error = config("cleanup = false").cleanup().unwrap_err();
+        assert!(error.to_string().contains("must be a string"), "{}", error);
+    }
+
+    #[test]
+    fn profiles_are_layered_over_the_config() {
+        let base = config(concat!(
+            "exec_mode = \"xdg-open\"\n",
+            "hide_uninstalled = true\n",
+            "[game.620]\nicon = \"icons/portal.png\"\n",
+            "[[servers]]\nname = \"Valheim\"\nexec = \"valheim\"\n",
+            "[[servers]]\nname = \"Palworld\"\nexec = \"palserver\"\n",
+        ));
+        let profile = Config::from_text(
+            concat!(
+                "hide_uninstalled = false\n",
+                "[game.400]\nicon = \"portal.png\"\n",
+                "[[servers]]\nname = \"Factorio\"\nexec = \"factorio\"\n",
+            ),
+            Path::new("/home/user/.config/steamer/profiles/couch.toml"),
+        )
+        .unwrap();
+        let layered = base.layer(profile);
+        assert_eq!(layered.get_str("exec_mode").unwrap(), Some("xdg-open"));
+        assert!(!layered.hide_uninstalled().unwrap());
+        // a profile's [[servers]] replaces them all rather than the first one
+        let servers = layered.servers().unwrap();
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].name, "Factorio");
+        // relative paths are taken from the file they're in
+        assert_eq!(
+            layered.custom_icons().unwrap(),
+            [
+                ("620".to_string(), PathBuf::from("icons/portal.png")),
+                (
+                    "400".to_string(),
+                    PathBuf::from("/home/user/.config/steamer/profiles/portal.png")
+                ),
+            ]
+        );
+        assert_eq!(layered.source("exec_mode").as_deref(), Some("line 1"));
+        assert_eq!(
+            layered.source("hide_uninstalled").as_deref(),
+            Some("/home/user/.config/steamer/profiles/couch.toml line 1")
+        );
+    }
+
+    #[test]
+    fn profile_names_are_not_paths() {
+        for name in ["", ".hidden", "../config", "a/b"] {
+            let error = profile_path(name).unwrap_err();
+            assert!(
+                error.to_string().contains("isn't a profile name"),
+                "{}",
+                error
+            );
+        }
+        assert!(
+            profile_path("couch")
+                .unwrap()
+                .ends_with("steamer/profiles/couch.toml")
+        );
+    }
+
+    #[test]
+    fn reads_game_environments() {
+        let environment = config(concat!(
+            "[game.620.env]\n",
+            "PROTON_LOG = 1\n",
+            "DXVK_HUD = \"fps,frametimes\"\n",
+            "[game.400.env]\n",
+            "MANGOHUD = \"1\"\n",
+        ))
+        .environment()
+        .unwrap();
+        assert_eq!(
+            environment["620"],
+            [
+                ("DXVK_HUD".to_string(), "fps,frametimes".to_string()),
+                ("PROTON_LOG".to_string(), "1".to_string()),
+            ]
+        );
+        assert_eq!(
+            environment["400"],
+            [("MANGOHUD".to_string(), "1".to_string())]
+        );
+        assert!(config("[game.620.env]\nA = 1\n").unknown_keys().is_empty());
+
+        let error = |text: &str| config(text).environment().unwrap_err().to_string();
+        assert_eq!(
+            error("[game.620.env]\n\"1ST\" = \"x\"\n"),
+            "1ST isn't a valid environment variable name, use letters, digits and _"
+        );
+        assert_eq!(
+            error("[game.620.env]\nA = \"x\\ny\"\n"),
+            "game.620.env.A can't contain control characters"
+        );
+        assert!(error("[game.620.env]\nA = true\n").contains("a string or an integer"));
+    }
+
+    #[test]
+    fn game_limits_replace_the_global_ones() {
+        let limits = config(
+            "[limits]\nmemory_max = \"8G\"\ncpu_weight = 50\n\n\
+             [game.620.limits]\nmemory_max = \"4G\"\ntasks_max = 512\n",
+        )
+        .limits()
+        .unwrap();
+        assert_eq!(
+            limits.for_game("620"),
+            [
+                ("CPUWeight", "50"),
+                ("MemoryMax", "4G"),
+                ("TasksMax", "512")
+            ]
+        );
+        assert_eq!(
+            limits.for_game("400"),
+            [("CPUWeight", "50"), ("MemoryMax", "8G")]
+        );
+
+        let error = config("[limits]\nmemory_max = true").limits().unwrap_err();
+        assert!(
+            error.to_string().contains("a string or an integer"),
+            "{}",
+            error
+        );
+        assert_eq!(
+            config("[limits]\nmemroy_max = \"8G\"").unknown_keys(),
+            ["limits.memroy_max"]
+        );
+    }
+
+    #[test]
+    fn reads_servers() {
+        let servers = config(concat!(
+            "[[servers]]\n",
+            "name = \"Valheim Server\"\n",
+            "exec = \"/srv/valheim/start.sh\"\n",
+            "appid = 896660\n",
+            "terminal = true\n",
+            "\n",
+            "[[servers]]\n",
+            "name = \"Palworld\"\n",
+            "exec = \"palserver\"\n",
+            "appid = \"2394010\"\n",
+        ))
+        .servers()
+        .unwrap();
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].name, "Valheim Server");
+        assert_eq!(servers[0].exec, "/srv/valheim/start.sh");
+        assert_eq!(servers[0].appid.as_deref(), Some("896660"));
+        assert!(servers[0].terminal);
+        assert_eq!(servers[1].appid.as_deref(), Some("2394010"));
+        assert!(!servers[1].terminal);
+
+        let error = |text: &str| config(text).servers().unwrap_err().to_string();
+        assert_eq!(
+            error("[[servers]]\nname = \"Valheim\"\n"),
+            "[[servers]] entry 1 has no exec"
+        );
+        assert_eq!(
+            error("[[servers]]\nname = \" \"\nexec = \"x\"\n"),
+            "servers.0.name can't be empty"
+        );
+        assert!(
+            error("[[servers]]\nname = \"V\"\nexec = \"x\"\nappid = \"v\"\n").contains("an app ID")
+        );
+    }
+
+    #[test]
+    fn reads_steam_roots() {
+        let roots = Config::from_text(
+            "steam_roots = [\"~/.var/app/com.valvesoftware.Steam/.steam/steam\", \"steam\"]\nmerge_steam_roots = true\n",
+            Path::new("/etc/steamer/config.toml"),
+        )
+        .unwrap();
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            roots.steam_roots().unwrap(),
+            [
+                home.join(".var/app/com.valvesoftware.Steam/.steam/steam"),
+                PathBuf::from("/etc/steamer/steam"),
+            ]
+        );
+        assert!(roots.merge_steam_roots().unwrap());
+
+        assert!(Config::default().steam_roots().unwrap().is_empty());
+        assert!(!Config::default().merge_steam_roots().unwrap());
+        let error = config("steam_roots = \"~/.steam\"\n")
+            .steam_roots()
+            .unwrap_err();
+        assert!(error.to_string().contains("an array"), "{}", error);
+    }
+
+    #[test]
+    fn set_replaces_a_value_in_place() {
+        let text = set_in(CONFIG, "exec_mode", &Value::Str("steam".to_string())).unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace(
+                "exec_mode = \"xdg-open\" # launch",
+                "exec_mode = \"steam\" # launch"
+            )
+        );
+        let text = set_in(CONFIG, "name_rules.0.replace", &Value::Str("!".to_string())).unwrap();
+        assert_eq!(text, CONFIG.replace("replace = \"\"", "replace = \"!\""));
+    }
+
+    #[test]
+    fn set_adds_keys_to_their_table() {
+        let text = set_in(CONFIG, "game.620.hidden", &Value::Bool(true)).unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace(
+                "name = \"Portal 2\"\n",
+                "name = \"Portal 2\"\nhidden = true\n"
+            )
+        );
+        // top-level keys go after the last one, above the first table
+        let text = set_in(CONFIG, "raw_names", &Value::Bool(true)).unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace("the desktop\n", "the desktop\nraw_names = true\n")
+        );
+        let text = set_in("[environment]\n", "raw_names", &Value::Bool(true)).unwrap();
+        assert_eq!(text, "raw_names = true\n\n[environment]\n");
+    }
+
+    #[test]
+    fn set_adds_missing_tables_at_the_end() {
+        let text = set_in(CONFIG, "game.400.name", &Value::Str("Portal".to_string())).unwrap();
+        assert_eq!(text, format!("{}\n[game.400]\nname = \"Portal\"\n", CONFIG));
+        let text = set_in(CONFIG, "name_rules.1.pattern", &Value::Str("x".to_string())).unwrap();
+        assert_eq!(
+            text,
+            format!("{}\n[[name_rules]]\npattern = \"x\"\n", CONFIG)
+        );
+        assert!(set_in(CONFIG, "name_rules.5.pattern", &Value::Str("x".to_string())).is_err());
+    }
+
+    #[test]
+    fn unset_removes_only_the_key() {
+        let text = unset_in(CONFIG, "environment.MANGOHUD").unwrap().unwrap();
+        assert_eq!(text, CONFIG.replace("MANGOHUD = \"1\"\n", ""));
+        assert_eq!(unset_in(CONFIG, "environment.DXVK_HUD").unwrap(), None);
+    }
+
+    #[test]
+    fn unset_removes_a_table_with_its_comments() {
+        let text = unset_in(CONFIG, "game.620").unwrap().unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace("# per game\n[game.620]\nname = \"Portal 2\"\n\n", "")
+        );
+        let text = unset_in(CONFIG, "name_rules").unwrap().unwrap();
+        assert_eq!(
+            text,
+            CONFIG.replace(
+                "# tidy up names\n[[name_rules]]\npattern = \" - Deluxe Edition$\"\nreplace = \"\"\n\n",
+                ""
+            )
+        );
+    }
+
+    #[test]
+    fn set_then_unset_gives_the_file_back() {
+        for (key, value) in [
+            ("raw_names", Value::Bool(true)),
+            ("game.620.hidden", Value::Bool(true)),
+            ("game.400.name", Value::Str("Portal".to_string())),
+            ("environment.DXVK_HUD", Value::Int(1)),
+            (
+                "name_rules.1.pattern",
+                Value::Array(vec![Value::Str("x".to_string())]),
+            ),
+        ] {
+            let set = set_in(CONFIG, key, &value).unwrap();
+            let table = match key.strip_suffix(".pattern") {
+                Some(entry) => entry,
+                None if key == "game.400.name" => "game.400",
+                None => key,
+            };
+            let unset = unset_in(&set, table).unwrap().unwrap();
+            assert_eq!(unset, CONFIG, "{}", key);
+        }
+    }
+
+    #[test]
+    fn edits_read_back_as_they_were_set() {
+        let value = Value::Str("C:\\Games \"Steam\"\t".to_string());
+        let text = set_in("", "game.620.icon", &value).unwrap();
+        assert_eq!(
+            text,
+            "[game.620]\nicon = \"C:\\\\Games \\\"Steam\\\"\\t\"\n"
+        );
+        assert_eq!(config(&text).get("game.620.icon"), Some(&value));
+
+        // a game table after [game.620.limits] still gets its own header
+        let text = set_in(
+            "[game.620.limits]\nmemory_max = \"4G\"\n",
+            "game.620.icon",
+            &Value::Str("/icons/620.png".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            text,
+            "[game.620.limits]\nmemory_max = \"4G\"\n\n[game.620]\nicon = \"/icons/620.png\"\n"
+        );
+        assert!(config(&text).unknown_keys().is_empty());
+    }
+
+    #[test]
+    fn unset_takes_the_comment_above_a_key() {
+        let text = "cleanup = \"never\"\n# my own names\nraw_names = true\n";
+        assert_eq!(
+            unset_in(text, "raw_names").unwrap().unwrap(),
+            "cleanup = \"never\"\n"
+        );
+        let text = set_in(CONFIG, "name_rules.1.pattern", &Value::Str("x".to_string())).unwrap();
+        assert_eq!(unset_in(&text, "name_rules.1").unwrap().unwrap(), CONFIG);
+        assert_eq!(unset_in(CONFIG, "name_rules.1").unwrap(), None);
+    }
+}
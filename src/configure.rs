@@ -0,0 +1,294 @@
+//! `steamer config`: reads and changes `config.toml` from the command line. Changes are made
+//! with toml_edit, so comments and formatting outside the changed lines stay as they are, and
+//! the result is checked with the same rules a run uses before it replaces the file.
+
+use crate::cli::{Args, ConfigAction, ConfigArgs};
+use crate::config::{self, Config, Value};
+use crate::names::NameRules;
+use anyhow::{Context, Result, bail};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+/// With `--profile`, get, set and unset work on the profile's file instead of config.toml.
+pub fn run(args: &Args, config_args: &ConfigArgs) -> Result<()> {
+    let path = config::default_path().context("Could not find the config directory")?;
+    let file = match args.profile {
+        Some(ref name) => config::profile_path(name)?,
+        None => path.clone(),
+    };
+    match config_args.action {
+        ConfigAction::Get { ref key } => get(&file, key),
+        ConfigAction::Set { ref key, ref value } => set(&file, key, value),
+        ConfigAction::Unset { ref key } => unset(&file, key),
+        ConfigAction::List => {
+            print!("{}", effective(args, &path)?);
+            Ok(())
+        }
+    }
+}
+
+/// The file's text, empty when there is no file yet.
+fn read(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e).with_context(|| format!("Could not read {:?}", path)),
+    }
+}
+
+/// Strings are printed without quotes, so scripts can use them as they are.
+fn get(path: &Path, key: &str) -> Result<()> {
+    let config = Config::from_text(&read(path)?, path)?;
+    if config.get(key).is_none() {
+        config::check_key(key, true)?;
+    }
+    if let Some(value) = config.get(key) {
+        match value {
+            Value::Str(s) => println!("{}", s),
+            other => println!("{}", other),
+        }
+        return Ok(());
+    }
+    let prefix = format!("{}.", key);
+    let mut found = false;
+    for (k, value, _) in config.entries() {
+        if let Some(rest) = k.strip_prefix(&prefix) {
+            println!("{} = {}", rest, value);
+            found = true;
+        }
+    }
+    if !found {
+        bail!("{} is not set in {:?}", key, path);
+    }
+    Ok(())
+}
+
+fn set(path: &Path, key: &str, value: &str) -> Result<()> {
+    config::check_key(key, false)?;
+    let value = Value::from_arg(value).with_context(|| format!("Invalid value for {}", key))?;
+    let text = read(path)?;
+    Config::from_text(&text, path)?;
+    let text = config::set_in(&text, key, &value)?;
+    validate(&text, path)?;
+    write_atomic(path, &text)
+}
+
+/// Unknown keys can be removed too, that's how a typo the runs warn about is got rid of.
+fn unset(path: &Path, key: &str) -> Result<()> {
+    let text = read(path)?;
+    Config::from_text(&text, path)?;
+    let known = config::check_key(key, true);
+    let Some(text) = config::unset_in(&text, key)? else {
+        known?;
+        bail!("{} is not set in {:?}", key, path);
+    };
+    validate(&text, path)?;
+    write_atomic(path, &text)
+}
+
+/// Reads every setting the way a run would, so a change that would make runs fail is refused.
+fn validate(text: &str, path: &Path) -> Result<()> {
+    let config = Config::from_text(text, path)?;
+    config.cleanup()?;
+    config.cleanup_mode()?;
+    config.extra_libraries()?;
+    config.steam_roots()?;
+    config.merge_steam_roots()?;
+    config.api_key()?;
+    config.steam_id()?;
+    config.hide_uninstalled()?;
+    config.raw_names()?;
+    NameRules::new(true, &[], &config.name_rules()?)?;
+    config.custom_icons()?;
+    config.save_dirs()?;
+    config.app_dirs()?;
+    config.servers()?;
+    config.limits()?;
+    config.environment()?;
+    Ok(())
+}
+
+/// Writes through a temporary file next to `path`, so a crash leaves the old file or the new
+/// one and never half of each. A symlinked config, as dotfile managers make, is written where
+/// the link points, with the permissions it had.
+fn write_atomic(path: &Path, text: &str) -> Result<()> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = path
+        .parent()
+        .context("The config path has no parent directory")?;
+    fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))?;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = dir.join(format!(".{}.{}.tmp", name, process::id()));
+
+    let result = (|| -> Result<()> {
+        let mut file = File::create(&temp)?;
+        file.write_all(text.as_bytes())?;
+        if let Ok(meta) = fs::metadata(&path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.with_context(|| format!("Could not write {:?}", path))
+}
+
+/// The settings in effect, each with the flag, the config or profile line or the default it
+/// comes from. Settings without a default that aren't set are left out, and the API key is
+/// never shown.
+pub fn effective(args: &Args, path: &Path) -> Result<String> {
+    let exists = path.exists();
+    let config = Config::from_text(&read(path)?, path)?.with_profile(args.profile.as_deref())?;
+    let line = |key: &str| config.source(key);
+    let mut rows: Vec<(String, Value, String)> = Vec::new();
+    let mut row = |key: &str, value: Value, source: String| {
+        rows.push((key.to_string(), value, source));
+    };
+
+    let from_file = |key: &str, default: Value| match (config.get(key), line(key)) {
+        (Some(value), Some(line)) => (value.clone(), line),
+        _ => (default, "default".to_string()),
+    };
+    let flag_or_file = |set: bool, flag: &str, key: &str, flag_value: Value, default: Value| {
+        if set {
+            (flag_value, flag.to_string())
+        } else {
+            from_file(key, default)
+        }
+    };
+
+    let (value, source) = flag_or_file(
+        args.additive,
+        "--additive",
+        "cleanup",
+        Value::Str("never".into()),
+        Value::Str("always".into()),
+    );
+    row("cleanup", value, source);
+    let (value, source) = flag_or_file(
+        args.trash,
+        "--trash",
+        "cleanup_mode",
+        Value::Str("trash".into()),
+        Value::Str("delete".into()),
+    );
+    row("cleanup_mode", value, source);
+
+    let mut libraries: Vec<Value> = args
+        .extra_library
+        .iter()
+        .map(|l| Value::Str(l.clone()))
+        .collect();
+    let mut sources = Vec::new();
+    if !libraries.is_empty() {
+        sources.push("--extra-library".to_string());
+    }
+    if let Some(Value::Array(items)) = config.get("extra_libraries") {
+        libraries.extend(items.iter().cloned());
+        sources.extend(line("extra_libraries"));
+    }
+    if sources.is_empty() {
+        sources.push("default".to_string());
+    }
+    row(
+        "extra_libraries",
+        Value::Array(libraries),
+        sources.join(", "),
+    );
+    if let (Some(value), Some(line)) = (config.get("steam_roots"), line("steam_roots")) {
+        let source = match args.steam_path {
+            Some(_) => format!("{}, not used with --steam-path", line),
+            None => line,
+        };
+        row("steam_roots", value.clone(), source);
+    }
+    let (value, source) = from_file("merge_steam_roots", Value::Bool(false));
+    row("merge_steam_roots", value, source);
+
+    let hidden = Value::Str("(hidden)".into());
+    if args.api_key.is_some() {
+        row("api_key", hidden, "--api-key".into());
+    } else if let Some(line) = line("api_key") {
+        row(
+            "api_key",
+            hidden,
+            format!("{}, shown by `config get api_key`", line),
+        );
+    }
+    match args.steam_id {
+        Some(ref id) => row("steam_id", Value::Str(id.clone()), "--steam-id".into()),
+        None => {
+            if let (Some(value), Some(line)) = (config.get("steam_id"), line("steam_id")) {
+                row("steam_id", value.clone(), line);
+            }
+        }
+    }
+    let (value, source) = flag_or_file(
+        args.hide_uninstalled,
+        "--hide-uninstalled",
+        "hide_uninstalled",
+        Value::Bool(true),
+        Value::Bool(false),
+    );
+    row("hide_uninstalled", value, source);
+    let (value, source) = flag_or_file(
+        args.raw_names,
+        "--raw-names",
+        "raw_names",
+        Value::Bool(true),
+        Value::Bool(false),
+    );
+    row("raw_names", value, source);
+
+    // tables, and keys steamer doesn't know, only come from the file
+    let unknown = config.unknown_keys();
+    for (key, value, _) in config.entries() {
+        if !key.contains('.') && !unknown.contains(&key) {
+            continue;
+        }
+        let line = config.source(key).unwrap_or_default();
+        let source = if unknown.contains(&key) {
+            format!("{}, unknown key, ignored", line)
+        } else {
+            line
+        };
+        row(key, value.clone(), source);
+    }
+
+    let mut out = if exists {
+        format!("# {}\n", path.display())
+    } else {
+        format!("# no config file at {}\n", path.display())
+    };
+    if let Some(ref name) = args.profile {
+        out.push_str(&format!(
+            "# profile {} from {}\n",
+            name,
+            config::profile_path(name)?.display()
+        ));
+    }
+    let settings: Vec<(String, &str)> = rows
+        .iter()
+        .map(|(key, value, source)| (format!("{} = {}", key, value), source.as_str()))
+        .collect();
+    let width = settings
+        .iter()
+        .map(|(setting, _)| setting.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(48);
+    for (setting, source) in settings {
+        out.push_str(&format!(
+            "{:<width$}  # {}\n",
+            setting,
+            source,
+            width = width
+        ));
+    }
+    Ok(out)
+}
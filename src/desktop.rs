@@ -0,0 +1,1533 @@
+use crate::config::{Limits, Removal, Server};
+use crate::names::NameRules;
+use crate::report::Reporter;
+use crate::steam::GameInfo;
+use crate::template::Template;
+use crate::trash;
+use crate::unicode;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Placeholders available to `--comment-template`.
+pub const COMMENT_PLACEHOLDERS: &[&str] = &["name", "appid"];
+
+const STORE_URL: &str = "https://store.steampowered.com/app/";
+
+/// Added to the name of entries for games that aren't installed, since launching them installs.
+pub const INSTALL_SUFFIX: &str = " — install";
+
+/// Added to the names with `--tag-source-name`, telling Steam's games apart in a mixed menu.
+const SOURCE_ANNOTATION: &str = " (Steam)";
+
+/// [`SOURCE_ANNOTATION`] for the non-Steam games from `--include-shortcuts`.
+const SHORTCUT_ANNOTATION: &str = " (non-Steam)";
+
+/// How the `Exec=` line hands the `steam://` URL over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExecMode {
+    /// Pass it to the `steam` binary on the PATH
+    #[default]
+    Steam,
+    /// Open it with `xdg-open`, for Flatpak or several installs where the URL handler picks Steam
+    XdgOpen,
+}
+
+/// Launcher the entries are tuned for. Keys only change where a launcher handles the generic
+/// entry badly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DesktopEnvironment {
+    /// Plain Desktop Entry spec output
+    #[default]
+    Generic,
+    /// elementary OS: no store URL in Keywords=, X-GNOME-FullName= for shortened names
+    Pantheon,
+}
+
+impl DesktopEnvironment {
+    /// Picks the tuning from `XDG_CURRENT_DESKTOP`, a colon separated list like `Pantheon:GNOME`.
+    pub fn detect() -> Self {
+        let desktops = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        if desktops
+            .split(':')
+            .any(|d| d.eq_ignore_ascii_case("pantheon"))
+        {
+            DesktopEnvironment::Pantheon
+        } else {
+            DesktopEnvironment::Generic
+        }
+    }
+}
+
+/// Settings that shape every generated entry, independent of the game.
+#[derive(Debug, Default)]
+pub struct EntryOptions {
+    pub comment_template: Option<Template>,
+    pub comment_appid: bool,
+    /// Leave out `Categories=`, for launchers that only show a flat list.
+    pub omit_categories: bool,
+    /// Add the appid and store URL to `Keywords=`, so pasting either into search finds the game.
+    pub keywords_appid: bool,
+    /// Locales to write `Name[xx]=` lines for when appinfo has a name for them.
+    pub locales: Vec<String>,
+    /// `--generic-name`: the `GenericName=` text, then its translations by locale.
+    pub generic_names: Vec<(Option<String>, String)>,
+    /// Cleanup applied to `Name=` before it's cut to `max_name_length`.
+    pub name_rules: NameRules,
+    /// Longest `Name=` in graphemes, longer names are cut with an ellipsis.
+    pub max_name_length: Option<usize>,
+    pub exec_mode: ExecMode,
+    /// `--absolute-exec`: the resolved steam executable, used in `Exec=` and `TryExec=`.
+    pub steam_binary: Option<PathBuf>,
+    /// `--bigpicture`: have Steam open Big Picture and launch the game with `-applaunch`.
+    pub big_picture: bool,
+    /// Run the launch inside a transient systemd user scope with these limits.
+    pub systemd_scope: Option<Limits>,
+    /// `--track-launches`: the steamer executable, whose `launch-wrapper` records each launch.
+    pub launch_wrapper: Option<PathBuf>,
+    /// The steamer executable, for `--wait-for-steam`.
+    pub wait_for_steam: Option<PathBuf>,
+    /// Variables from `[game.<appid>.env]`, set with `env` in front of the launcher, by appid.
+    pub environment: HashMap<String, Vec<(String, String)>>,
+    pub desktop_environment: DesktopEnvironment,
+    /// Nicknames from `--keywords-file`, by appid.
+    pub extra_keywords: HashMap<String, Vec<String>>,
+    /// `--portable-paths`: the home directory, which `Exec=` commands refer to as `$HOME`.
+    pub portable_home: Option<PathBuf>,
+    /// `--bucket-by-letter`: write entries into a subdirectory per first letter of the name.
+    pub bucket_by_letter: bool,
+    /// `--tag-source`: mark each entry with `X-Steamer-Source=steam`, or `shortcut` for the
+    /// non-Steam games.
+    pub tag_source: bool,
+    /// `--tag-source-name`: add [`SOURCE_ANNOTATION`] or [`SHORTCUT_ANNOTATION`] to the names
+    /// as well.
+    pub tag_source_name: bool,
+}
+
+impl EntryOptions {
+    /// Each keyword is its own list item, so searching for an appid matches that keyword as a
+    /// whole and not the digits of some other field.
+    fn keywords(&self, game: &GameInfo) -> Vec<String> {
+        let mut keywords = Vec::new();
+        if self.keywords_appid {
+            keywords.push(game.appid.clone());
+            // Pantheon's launcher matches search terms anywhere in a keyword, so the URL would
+            // put every game in the results for "steam" or "app". Shortcuts have no store page.
+            if self.desktop_environment != DesktopEnvironment::Pantheon && !game.shortcut {
+                keywords.push(format!("{}{}/", STORE_URL, game.appid));
+            }
+        }
+        // a cut or cleaned up name still finds the game when searching for the original
+        let original = self.name_rules.normalize(&game.name);
+        if self.display_name(&game.name) != original {
+            keywords.push(original);
+        }
+        let first_extra = keywords.len();
+        for extra in self.extra_keywords.get(&game.appid).into_iter().flatten() {
+            let known = |k: &String| k.eq_ignore_ascii_case(extra);
+            if !keywords.iter().any(known) && !extra.eq_ignore_ascii_case(&game.name) {
+                keywords.push(extra.clone());
+            }
+        }
+        keywords[first_extra..].sort_by_cached_key(|k| (k.to_lowercase(), k.clone()));
+        keywords
+    }
+
+    /// Where the entry of `game` goes in `app_dir`.
+    pub fn entry_path(&self, app_dir: &Path, game: &GameInfo) -> PathBuf {
+        let filename = format!("steam-{}.desktop", game.appid);
+        if self.bucket_by_letter {
+            app_dir
+                .join(letter_bucket(&self.name_rules.apply(&game.name)))
+                .join(filename)
+        } else {
+            app_dir.join(filename)
+        }
+    }
+
+    /// The desktop file ID of the entry of `game`, which names it in menus and app folders.
+    pub fn desktop_id(&self, game: &GameInfo) -> String {
+        desktop_id(Path::new(""), &self.entry_path(Path::new(""), game))
+    }
+
+    fn shorten(&self, name: &str) -> Option<String> {
+        unicode::truncate(name, self.max_name_length?)
+    }
+
+    fn display_name(&self, name: &str) -> String {
+        let cleaned = self.name_rules.apply(name);
+        self.shorten(&cleaned).unwrap_or(cleaned)
+    }
+
+    /// The `Name=` of an entry, with the source annotation after the cut so it always shows.
+    fn entry_name(&self, game: &GameInfo, name: &str) -> String {
+        let mut name = self.display_name(name);
+        if self.tag_source_name {
+            name.push_str(if game.shortcut {
+                SHORTCUT_ANNOTATION
+            } else {
+                SOURCE_ANNOTATION
+            });
+        }
+        name
+    }
+
+    fn comment(&self, game: &GameInfo) -> Option<String> {
+        let templated = self
+            .comment_template
+            .as_ref()
+            .map(|t| t.render(&[("name", &game.name), ("appid", &game.appid)]))
+            .filter(|c| !c.is_empty());
+
+        let comment = match (templated, self.comment_appid) {
+            (Some(comment), true) => Some(format!("{} (Steam AppID {})", comment, game.appid)),
+            (None, true) => Some(format!("Steam AppID {}", game.appid)),
+            (comment, false) => comment,
+        };
+        match (comment, &game.protondb_tier) {
+            (Some(comment), Some(tier)) => Some(format!("{}, ProtonDB: {}", comment, tier)),
+            (None, Some(tier)) => Some(format!("ProtonDB: {}", tier)),
+            (comment, None) => comment,
+        }
+    }
+
+    fn exec(&self, game: &GameInfo) -> String {
+        self.command_line(&self.launch_args(game))
+    }
+
+    /// The launch command, outermost wrapper first: the launch-wrapper recording it, the wait
+    /// for a logged in Steam, the systemd scope, then the launcher and what it's asked to open.
+    /// Installs aren't recorded.
+    pub fn launch_args(&self, game: &GameInfo) -> Vec<String> {
+        let launcher = match (self.exec_mode, &self.steam_binary) {
+            (ExecMode::Steam, Some(binary)) => binary.to_string_lossy().into_owned(),
+            (ExecMode::Steam, None) => "steam".to_string(),
+            (ExecMode::XdgOpen, _) => "xdg-open".to_string(),
+        };
+        let mut args = Vec::new();
+        if let Some(steamer) = self.launch_wrapper.as_ref().filter(|_| !game.not_installed) {
+            args.push(steamer.to_string_lossy().into_owned());
+            args.push("launch-wrapper".to_string());
+            args.push(game.appid.clone());
+            args.push("--".to_string());
+        }
+        if let Some(steamer) = &self.wait_for_steam {
+            args.push(steamer.to_string_lossy().into_owned());
+            args.push("wait-for-steam".to_string());
+            args.push(format!("--start={}", launcher));
+            args.push("--".to_string());
+        }
+        if let Some(limits) = &self.systemd_scope {
+            args.extend(["systemd-run", "--user", "--scope"].map(String::from));
+            args.push(format!("--unit=steam-app-{}", game.appid));
+            for (property, value) in limits.for_game(&game.appid) {
+                args.push("-p".to_string());
+                args.push(format!("{}={}", property, value));
+            }
+        }
+        if let Some(vars) = self
+            .environment
+            .get(&game.appid)
+            .filter(|_| !game.not_installed)
+        {
+            args.push("env".to_string());
+            args.extend(
+                vars.iter()
+                    .map(|(name, value)| format!("{}={}", name, value)),
+            );
+        }
+        args.push(launcher);
+        if game.not_installed {
+            args.push(format!("steam://install/{}", game.appid));
+        } else if self.big_picture && !game.shortcut {
+            // -applaunch only takes the appids of Steam's own games
+            args.extend(["-bigpicture", "-applaunch", game.appid.as_str()].map(String::from));
+        } else {
+            args.push(format!("steam://rungameid/{}", game.run_id()));
+        }
+        args
+    }
+
+    /// The `Exec=` command running `args`. With `--portable-paths`, one with a path under the
+    /// home directory is run through `sh`, which has the only `$HOME` an `Exec=` line can use.
+    fn command_line(&self, args: &[String]) -> String {
+        let home = self.portable_home.as_deref();
+        let relative = |arg: &str| Some(Path::new(arg).strip_prefix(home?).ok()?.to_path_buf());
+        if !args.iter().any(|arg| relative(arg).is_some()) {
+            return args
+                .iter()
+                .map(|arg| quote_exec_arg(arg))
+                .collect::<Vec<_>>()
+                .join(" ");
+        }
+        let script: Vec<String> = args
+            .iter()
+            .map(|arg| match relative(arg) {
+                Some(rest) => format!("\"$HOME\"/{}", quote_shell_arg(&rest.to_string_lossy())),
+                None => quote_shell_arg(arg),
+            })
+            .collect();
+        format!(
+            "sh -c {}",
+            quote_exec_arg(&format!("exec {}", script.join(" ")))
+        )
+    }
+
+    /// `Icon=` can't refer to the home directory, so with `--portable-paths` an icon under it
+    /// is swapped for the icon Steam installs into the hicolor theme for its own shortcuts,
+    /// when there is one. Other icons are written as they are.
+    fn icon(&self, game: &GameInfo, icon_path: &str) -> String {
+        let Some(home) = self.portable_home.as_deref() else {
+            return icon_path.to_string();
+        };
+        if !Path::new(icon_path).starts_with(home) {
+            return icon_path.to_string();
+        }
+        let name = format!("steam_icon_{}", game.appid);
+        let hicolor = dirs::data_dir().map(|dir| dir.join("icons/hicolor"));
+        let themed = hicolor
+            .and_then(|dir| fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .any(|size| size.path().join(format!("apps/{}.png", name)).is_file());
+        if themed { name } else { icon_path.to_string() }
+    }
+}
+
+pub fn render_entry(game: &GameInfo, icon_path: &str, options: &EntryOptions) -> String {
+    let mut content = String::from("[Desktop Entry]\n");
+    for (key, value) in entry_fields(game, icon_path, options) {
+        content.push_str(&format!("{}={}\n", key, value));
+    }
+    content
+}
+
+/// Id of the action that opens a game's save folder, from `--save-dir-action`.
+pub const SAVE_DIR_ACTION: &str = "open-save-folder";
+
+/// The `Exec=` value of the save folder action, already escaped like [`entry_fields`] values.
+pub fn save_dir_exec(dir: &Path, options: &EntryOptions) -> String {
+    escape_value(
+        &options.command_line(&["xdg-open".to_string(), dir.to_string_lossy().into_owned()]),
+    )
+}
+
+/// The `Actions=` key and the action group opening `dir`. Goes after every other key of the
+/// entry, since the keys that follow a group header belong to that group.
+pub fn save_dir_action(dir: &Path, options: &EntryOptions) -> String {
+    format!(
+        "Actions={};\n\n[Desktop Action {}]\nName=Open save folder\nExec={}\n",
+        SAVE_DIR_ACTION,
+        SAVE_DIR_ACTION,
+        save_dir_exec(dir, options)
+    )
+}
+
+/// The keys of an entry in file order, with their values as they appear after the `=`. The order
+/// is fixed: `Name`, its translations, `GenericName` and its translations, `Comment`, `TryExec`,
+/// `Exec`, `Icon`, `Terminal`, `Type`, `Categories`, `Keywords`, then the `X-` keys, with
+/// `NoDisplay` and the save folder action added after them by the output.
+/// Translations are sorted by locale and extra keywords by name, and nothing depends on the time,
+/// so the same games and options always give the same bytes, as dotfile repos need.
+pub fn entry_fields(
+    game: &GameInfo,
+    icon_path: &str,
+    options: &EntryOptions,
+) -> Vec<(String, String)> {
+    let mut name = options.entry_name(game, &game.name);
+    if game.not_installed {
+        name.push_str(INSTALL_SUFFIX);
+    }
+    let mut fields = vec![("Name".to_string(), escape_value(&name))];
+    let mut locales: Vec<&String> = options.locales.iter().collect();
+    locales.sort();
+    locales.dedup();
+    for locale in locales {
+        if let Some((_, name)) = game.localized_names.iter().find(|(l, _)| l == locale)
+            && *name != game.name
+        {
+            fields.push((
+                format!("Name[{}]", locale),
+                escape_value(&options.entry_name(game, name)),
+            ));
+        }
+    }
+    for (locale, generic_name) in &options.generic_names {
+        let key = match locale {
+            Some(locale) => format!("GenericName[{}]", locale),
+            None => "GenericName".to_string(),
+        };
+        fields.push((key, escape_value(generic_name)));
+    }
+    if let Some(comment) = options.comment(game) {
+        fields.push(("Comment".to_string(), escape_value(&comment)));
+    }
+    // a TryExec= that doesn't exist hides the entry, so one that only exists on this machine
+    // is left out
+    if let (ExecMode::Steam, Some(binary)) = (options.exec_mode, &options.steam_binary)
+        && !options
+            .portable_home
+            .as_ref()
+            .is_some_and(|home| binary.starts_with(home))
+    {
+        fields.push((
+            "TryExec".to_string(),
+            escape_value(&binary.to_string_lossy()),
+        ));
+    }
+    fields.push(("Exec".to_string(), escape_value(&options.exec(game))));
+    fields.push((
+        "Icon".to_string(),
+        escape_value(&options.icon(game, icon_path)),
+    ));
+    fields.push(("Terminal".to_string(), "false".to_string()));
+    fields.push(("Type".to_string(), "Application".to_string()));
+    if !options.omit_categories {
+        fields.push(("Categories".to_string(), "Game;".to_string()));
+    }
+    let keywords = options.keywords(game);
+    if !keywords.is_empty() {
+        fields.push(("Keywords".to_string(), format_list(&keywords)));
+    }
+    // GLib based launchers like Pantheon's show this in place of Name= where there's room
+    let full_name = options.name_rules.apply(&game.name);
+    if options.desktop_environment == DesktopEnvironment::Pantheon
+        && options.shorten(&full_name).is_some()
+    {
+        fields.push(("X-GNOME-FullName".to_string(), escape_value(&full_name)));
+    }
+    if game.incomplete {
+        fields.push(("X-Steamer-Incomplete".to_string(), "true".to_string()));
+    }
+    // so menus and scripts can tell the games from appmanifests and shortcuts.vdf apart
+    if options.tag_source {
+        let source = if game.shortcut { "shortcut" } else { "steam" };
+        fields.push(("X-Steamer-Source".to_string(), source.to_string()));
+    }
+    fields
+}
+
+/// Filename of the `--with-steam-entry` launcher. It starts with `steam-` like the game entries,
+/// so cleanup and the backup treat it the same way.
+pub const STEAM_CLIENT_ENTRY: &str = "steam-client.desktop";
+
+/// The `--with-steam-entry` launcher for the client itself, running `binary` if it was found.
+pub fn render_client_entry(binary: Option<&Path>) -> String {
+    let mut content = String::from("[Desktop Entry]\nName=Steam\n");
+    content.push_str("Comment=Application for managing and playing games on Steam\n");
+    let exec = match binary {
+        Some(binary) => {
+            let binary = binary.to_string_lossy();
+            content.push_str(&format!("TryExec={}\n", escape_value(&binary)));
+            quote_exec_arg(&binary)
+        }
+        None => "steam".to_string(),
+    };
+    content.push_str(&format!(
+        "Exec={}\n\
+        Icon=steam\n\
+        Terminal=false\n\
+        Type=Application\n\
+        Categories=Network;FileTransfer;Game;\n",
+        escape_value(&exec)
+    ));
+    content
+}
+
+/// Filenames for the `[[servers]]` launchers, `steam-server-` and the name's words in lowercase
+/// ASCII joined by `-`. Servers whose names give the same filename get their position added.
+pub fn server_filenames(servers: &[Server]) -> Vec<String> {
+    let slugs: Vec<String> = servers
+        .iter()
+        .enumerate()
+        .map(|(index, server)| {
+            let words: Vec<String> = server
+                .name
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_ascii_lowercase)
+                .collect();
+            if words.is_empty() {
+                (index + 1).to_string()
+            } else {
+                words.join("-")
+            }
+        })
+        .collect();
+    slugs
+        .iter()
+        .enumerate()
+        .map(|(index, slug)| {
+            if slugs.iter().filter(|s| *s == slug).count() > 1 {
+                format!("steam-server-{}-{}.desktop", slug, index + 1)
+            } else {
+                format!("steam-server-{}.desktop", slug)
+            }
+        })
+        .collect()
+}
+
+/// The launcher for a `[[servers]]` entry, with `icon` being its app's icon if it has one.
+pub fn render_server_entry(server: &Server, icon: &str) -> String {
+    format!(
+        "[Desktop Entry]\n\
+        Name={}\n\
+        Comment=Dedicated server\n\
+        Exec={}\n\
+        Icon={}\n\
+        Terminal={}\n\
+        Type=Application\n\
+        Categories=Game;\n",
+        escape_value(&server.name),
+        escape_value(&server.exec),
+        escape_value(icon),
+        server.terminal
+    )
+}
+
+pub fn create_desktop_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::File::create(path)?;
+    file.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// How many times `--verify-writes` writes an entry that doesn't read back right.
+pub const VERIFY_ATTEMPTS: u32 = 3;
+
+/// `--verify-writes`: writes the file, flushes it to the disk and reads it back, writing it again
+/// while what comes back differs. Returns whether it matched in the end. A full disk or a
+/// flaky network mount can leave an empty or cut off file without the write reporting an error.
+pub fn create_verified(path: &Path, content: &str, reporter: &Reporter) -> Result<bool> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    for attempt in 1..=VERIFY_ATTEMPTS {
+        let mut file = fs::File::create(path)?;
+        file.write_all(content.as_bytes())?;
+        match file.sync_all() {
+            // some FUSE filesystems and special files can't be flushed, the read back still tells
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => {}
+            result => result?,
+        }
+        drop(file);
+        let written = fs::read(path)?;
+        if written == content.as_bytes() {
+            return Ok(true);
+        }
+        reporter.warn(format!(
+            "{:?} read back as {} bytes that differ from the {} written{}",
+            path,
+            written.len(),
+            content.len(),
+            if attempt < VERIFY_ATTEMPTS {
+                ", writing it again"
+            } else {
+                ""
+            }
+        ));
+    }
+    Ok(false)
+}
+
+/// The subdirectory `--bucket-by-letter` puts a game named `name` in: the first letter of the
+/// name, with accents dropped from Latin ones, "0-9" for a name starting with a digit and "#"
+/// for one with neither.
+pub fn letter_bucket(name: &str) -> String {
+    match name.chars().find(|c| c.is_alphanumeric()) {
+        Some(c) if c.is_ascii_digit() => "0-9".to_string(),
+        Some(c) => match unicode::base_letter(c) {
+            Some(letter) => letter.to_string(),
+            None if c.is_alphabetic() => c.to_uppercase().collect(),
+            None => "#".to_string(),
+        },
+        None => "#".to_string(),
+    }
+}
+
+/// Names [`letter_bucket`] gives, told apart from other directories by looking at them.
+pub fn is_bucket(name: &str) -> bool {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        _ if name == "0-9" || name == "#" => true,
+        (Some(c), None) => c.is_alphabetic() && !c.is_lowercase(),
+        _ => false,
+    }
+}
+
+/// Every `steam-*.desktop` file in `app_dir` and in its letter buckets, sorted. The buckets are
+/// looked in whether or not `--bucket-by-letter` is on, so turning it off cleans them up.
+pub fn generated_entries(app_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(app_dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if is_generated_entry(&name) {
+            paths.push(entry.path());
+        } else if is_bucket(&name) && entry.file_type()?.is_dir() {
+            for entry in fs::read_dir(entry.path())? {
+                let entry = entry?;
+                if is_generated_entry(&entry.file_name().to_string_lossy()) {
+                    paths.push(entry.path());
+                }
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Entries in `app_dir` named `<prefix>...desktop` by an older scheme, with the app ID each
+/// launches: the rest of the name when that's a number, otherwise the `steam://rungameid/`
+/// URL in the file. Files with neither, and steamer's own entries, aren't included.
+pub fn entries_with_prefix(app_dir: &Path, prefix: &str) -> Result<Vec<(PathBuf, String)>> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(app_dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(rest) = name
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(".desktop"))
+        else {
+            continue;
+        };
+        if is_generated_entry(name) || !path.is_file() {
+            continue;
+        }
+        let appid = if !rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit()) {
+            Some(rest.to_string())
+        } else {
+            fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| rungameid(&content))
+        };
+        if let Some(appid) = appid {
+            found.push((path, appid));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// The app ID in the first `steam://rungameid/` URL of an entry's `Exec=` line.
+fn rungameid(content: &str) -> Option<String> {
+    let exec = content
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Exec="))?;
+    let (_, rest) = exec.split_once("steam://rungameid/")?;
+    let appid: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    (!appid.is_empty()).then_some(appid)
+}
+
+/// The desktop file ID of the entry at `path`: its path under `app_dir`, with `-` for `/`.
+pub fn desktop_id(app_dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(app_dir).unwrap_or(path);
+    relative
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Removes every generated entry in `app_dir` and its buckets, returning how many there were.
+/// Buckets left empty are removed too.
+pub fn remove_generated_entries(
+    app_dir: &Path,
+    removal: Removal,
+    reporter: &Reporter,
+) -> Result<usize> {
+    let paths = generated_entries(app_dir)?;
+    for path in &paths {
+        remove_entry(path, removal, reporter)?;
+        if let Some(parent) = path.parent().filter(|parent| *parent != app_dir) {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+    Ok(paths.len())
+}
+
+/// The generated entries as they were before the run, kept in memory so an interrupted run can
+/// put them back. They're a few hundred bytes each, even large libraries stay small.
+pub struct Backup {
+    files: Vec<(PathBuf, Vec<u8>)>,
+}
+
+impl Backup {
+    pub fn take(app_dir: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        for path in generated_entries(app_dir)? {
+            let content = fs::read(&path)?;
+            files.push((path, content));
+        }
+        Ok(Self { files })
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Replaces whatever the run wrote with the saved entries, returning how many came back.
+    pub fn restore(&self, app_dir: &Path, reporter: &Reporter) -> Result<usize> {
+        remove_generated_entries(app_dir, Removal::Delete, reporter)?;
+        for (path, content) in &self.files {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        Ok(self.files.len())
+    }
+}
+
+/// Trashing falls back to deleting, the cleanup shouldn't stall on a filesystem without a trash.
+fn remove_entry(path: &Path, removal: Removal, reporter: &Reporter) -> Result<()> {
+    if removal == Removal::Trash {
+        match trash::move_to_trash(path) {
+            Ok(()) => return Ok(()),
+            Err(e) => reporter.warn(format!(
+                "Could not move {:?} to the trash, deleting it instead: {:#}",
+                path, e
+            )),
+        }
+    }
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+pub fn is_generated_entry(filename: &str) -> bool {
+    filename.starts_with("steam-") && filename.ends_with(".desktop")
+}
+
+/// Formats a `;` separated list value, escaping separators inside the items.
+pub fn format_list(items: &[String]) -> String {
+    items
+        .iter()
+        .map(|item| format!("{};", escape_value(item).replace(';', "\\;")))
+        .collect()
+}
+
+/// Reads a `--keywords-file`: lines of an appid, a `:` and comma separated keywords, with `#`
+/// starting a comment. Lines for the same appid add up, and whitespace inside a keyword is
+/// collapsed to single spaces.
+pub fn read_keywords_file(path: &Path) -> Result<HashMap<String, Vec<String>>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Could not read {:?}", path))?;
+    parse_keywords(&text).with_context(|| format!("Could not parse {:?}", path))
+}
+
+fn parse_keywords(text: &str) -> Result<HashMap<String, Vec<String>>> {
+    let mut keywords: HashMap<String, Vec<String>> = HashMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((appid, words)) = line.split_once(':') else {
+            bail!(
+                "Expected an app ID, a colon and keywords on line {}",
+                index + 1
+            );
+        };
+        let appid = appid.trim();
+        if appid.is_empty() || !appid.bytes().all(|b| b.is_ascii_digit()) {
+            bail!("{:?} isn't an app ID on line {}", appid, index + 1);
+        }
+        let list = keywords.entry(appid.to_string()).or_default();
+        for word in words.split(',') {
+            let word = word
+                .split(|c: char| c.is_whitespace() || c.is_control())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !word.is_empty() && !list.iter().any(|k| k.eq_ignore_ascii_case(&word)) {
+                list.push(word);
+            }
+        }
+    }
+    Ok(keywords)
+}
+
+/// Quotes one `Exec=` argument if it holds anything the spec reserves, and doubles `%` so it
+/// isn't read as a field code. The result still goes through [`escape_value`].
+fn quote_exec_arg(arg: &str) -> String {
+    let arg = arg.replace('%', "%%");
+    let reserved = |c: char| " \t\n\"'\\><~|&;$*?#()`".contains(c);
+    if !arg.contains(reserved) {
+        return arg;
+    }
+    let mut out = String::from('"');
+    for c in arg.chars() {
+        if matches!(c, '"' | '`' | '$' | '\\') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
+
+/// Quotes an argument for `sh`, leaving plain words as they are.
+pub fn quote_shell_arg(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "/._-:=,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Escapes a string value per the Desktop Entry spec, so stray newlines can't inject keys.
+pub fn escape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(appid: &str, name: &str) -> GameInfo {
+        GameInfo {
+            appid: appid.to_string(),
+            name: name.to_string(),
+            ..GameInfo::default()
+        }
+    }
+
+    fn field<'a>(fields: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn tags_the_source_only_when_asked() {
+        let portal = game("620", "Portal 2");
+        let fields = entry_fields(&portal, "steam", &EntryOptions::default());
+        assert_eq!(field(&fields, "X-Steamer-Source"), None);
+        assert_eq!(field(&fields, "Name"), Some("Portal 2"));
+
+        let options = EntryOptions {
+            tag_source: true,
+            ..EntryOptions::default()
+        };
+        let fields = entry_fields(&portal, "steam", &options);
+        assert_eq!(field(&fields, "X-Steamer-Source"), Some("steam"));
+        assert_eq!(field(&fields, "Name"), Some("Portal 2"));
+    }
+
+    #[test]
+    fn annotates_every_name() {
+        let options = EntryOptions {
+            tag_source: true,
+            tag_source_name: true,
+            locales: vec!["de".to_string()],
+            max_name_length: Some(8),
+            ..EntryOptions::default()
+        };
+        let mut portal = game("620", "Portal 2");
+        portal.localized_names = vec![("de".to_string(), "Portal Zwei".to_string())];
+        let fields = entry_fields(&portal, "steam", &options);
+        assert_eq!(field(&fields, "Name"), Some("Portal 2 (Steam)"));
+        assert_eq!(field(&fields, "Name[de]"), Some("Portal… (Steam)"));
+
+        let mut uninstalled = game("400", "Portal");
+        uninstalled.not_installed = true;
+        let fields = entry_fields(&uninstalled, "steam", &options);
+        assert_eq!(field(&fields, "Name"), Some("Portal (Steam) — install"));
+        assert_eq!(field(&fields, "X-Steamer-Source"), Some("steam"));
+    }
+
+    #[test]
+    fn mixed_sources_are_tagged_each_their_own() {
+        let options = EntryOptions {
+            tag_source: true,
+            keywords_appid: true,
+            big_picture: true,
+            ..EntryOptions::default()
+        };
+        let portal = game("620", "Portal 2");
+        let mut heroic = game("3456789012", "Heroic");
+        heroic.shortcut = true;
+        let sources: Vec<_> = [&portal, &heroic]
+            .iter()
+            .map(|game| {
+                let fields = entry_fields(game, "steam", &options);
+                (
+                    field(&fields, "Name").unwrap().to_string(),
+                    field(&fields, "X-Steamer-Source").unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                ("Portal 2".to_string(), "steam".to_string()),
+                ("Heroic".to_string(), "shortcut".to_string()),
+            ]
+        );
+
+        let fields = entry_fields(&heroic, "steam", &options);
+        assert_eq!(
+            field(&fields, "Exec"),
+            Some("steam steam://rungameid/14846795755745705984")
+        );
+        assert_eq!(field(&fields, "Keywords"), Some("3456789012;"));
+
+        let annotated = EntryOptions {
+            tag_source_name: true,
+            ..options
+        };
+        let fields = entry_fields(&heroic, "steam", &annotated);
+        assert_eq!(field(&fields, "Name"), Some("Heroic (non-Steam)"));
+    }
+
+    #[test]
+    fn comments_carry_the_appid() {
+        let portal = game("620", "Portal 2");
+        let options = EntryOptions {
+            comment_appid: true,
+            ..EntryOptions::default()
+        };
+        let fields = entry_fields(&portal, "steam", &options);
+        assert_eq!(field(&fields, "Comment"), Some("Steam AppID 620"));
+
+        let template = Template::parse("Play {name}", COMMENT_PLACEHOLDERS).unwrap();
+        let options = EntryOptions {
+            comment_template: Some(template),
+            ..options
+        };
+        let fields = entry_fields(&portal, "steam", &options);
+        assert_eq!(
+            field(&fields, "Comment"),
+            Some("Play Portal 2 (Steam AppID 620)")
+        );
+
+        let fields = entry_fields(&portal, "steam", &EntryOptions::default());
+        assert_eq!(field(&fields, "Comment"), None);
+    }
+
+    #[test]
+    fn server_launchers_get_filenames_of_their_own() {
+        let server = |name: &str| Server {
+            name: name.to_string(),
+            exec: "start.sh".to_string(),
+            ..Server::default()
+        };
+        assert_eq!(
+            server_filenames(&[
+                server("Valheim Server"),
+                server("ARK: Survival"),
+                server("ark survival"),
+                server("???"),
+            ]),
+            [
+                "steam-server-valheim-server.desktop",
+                "steam-server-ark-survival-2.desktop",
+                "steam-server-ark-survival-3.desktop",
+                "steam-server-4.desktop",
+            ]
+        );
+
+        let content = render_server_entry(
+            &Server {
+                terminal: true,
+                ..server("Valheim Server")
+            },
+            "/icons/896660.jpg",
+        );
+        assert!(
+            content.contains("\nName=Valheim Server\nComment=Dedicated server\nExec=start.sh\n")
+        );
+        assert!(content.contains("\nIcon=/icons/896660.jpg\nTerminal=true\n"));
+    }
+
+    #[test]
+    fn icon_paths_cant_inject_keys() {
+        let icon = "/icons/620.jpg\nExec=rm -rf ~";
+        let fields = entry_fields(&game("620", "Portal 2"), icon, &EntryOptions::default());
+        assert_eq!(
+            field(&fields, "Icon"),
+            Some("/icons/620.jpg\\nExec=rm -rf ~")
+        );
+
+        let server = Server {
+            name: "Valheim Server".to_string(),
+            exec: "/srv/valheim/start.sh".to_string(),
+            ..Server::default()
+        };
+        let content = render_server_entry(&server, icon);
+        assert!(content.contains("Icon=/icons/620.jpg\\nExec=rm -rf ~\n"));
+        assert_eq!(content.matches("\nExec=").count(), 1);
+    }
+
+    /// The entry of `game` with the options `flags` give, as a run would write it.
+    fn rendered(game: &GameInfo, flags: &[&str]) -> String {
+        use crate::cli::Cli;
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["steamer"].iter().chain(flags)).unwrap();
+        let options = cli
+            .args
+            .entry_options(&crate::config::Config::default(), None)
+            .unwrap();
+        render_entry(game, "/icons/620.jpg", &options)
+    }
+
+    #[test]
+    fn generic_names_come_after_the_names() {
+        let portal = game("620", "Portal 2");
+        let content = rendered(
+            &portal,
+            &[
+                "--generic-name",
+                "de=Videospiel",
+                "--generic-name",
+                "Video Game; Puzzle",
+            ],
+        );
+        assert!(
+            content.contains(
+                "\nName=Portal 2\nGenericName=Video Game; Puzzle\nGenericName[de]=Videospiel\n"
+            ),
+            "{}",
+            content
+        );
+        assert!(!rendered(&portal, &[]).contains("GenericName"));
+    }
+
+    #[test]
+    fn exec_mode_picks_the_launcher() {
+        let portal = game("620", "Portal 2");
+        assert!(rendered(&portal, &[]).contains("\nExec=steam steam://rungameid/620\n"));
+        assert!(
+            rendered(&portal, &["--exec-mode", "xdg-open"])
+                .contains("\nExec=xdg-open steam://rungameid/620\n")
+        );
+    }
+
+    #[test]
+    fn systemd_scopes_wrap_the_launcher() {
+        let limits = crate::config::Config::from_text(
+            "[limits]\nmemory_max = \"8G\"\n[game.620.limits]\ncpu_quota = \"200%\"\n",
+            Path::new("config.toml"),
+        )
+        .unwrap()
+        .limits()
+        .unwrap();
+        let options = EntryOptions {
+            systemd_scope: Some(limits),
+            ..EntryOptions::default()
+        };
+        let content = render_entry(&game("620", "Portal 2"), "steam", &options);
+        assert!(
+            content.contains(
+                "\nExec=systemd-run --user --scope --unit=steam-app-620 -p CPUQuota=200%% \
+                 -p MemoryMax=8G steam steam://rungameid/620\n"
+            ),
+            "{}",
+            content
+        );
+    }
+
+    #[test]
+    fn waiting_for_steam_wraps_the_launch() {
+        let options = EntryOptions {
+            launch_wrapper: Some(PathBuf::from("/usr/bin/steamer")),
+            wait_for_steam: Some(PathBuf::from("/usr/bin/steamer")),
+            exec_mode: ExecMode::XdgOpen,
+            ..EntryOptions::default()
+        };
+        let content = render_entry(&game("620", "Portal 2"), "steam", &options);
+        assert!(
+            content.contains(
+                "\nExec=/usr/bin/steamer launch-wrapper 620 -- /usr/bin/steamer wait-for-steam \
+                 --start=xdg-open -- xdg-open steam://rungameid/620\n"
+            ),
+            "{}",
+            content
+        );
+
+        // an install has no launch to record, but still needs a logged in Steam
+        let mut uninstalled = game("400", "Portal");
+        uninstalled.not_installed = true;
+        let content = render_entry(&uninstalled, "steam", &options);
+        assert!(content.contains(
+            "\nExec=/usr/bin/steamer wait-for-steam --start=xdg-open -- xdg-open steam://install/400\n"
+        ));
+    }
+
+    #[test]
+    fn game_environments_go_in_front_of_the_launcher() {
+        let options = EntryOptions {
+            environment: HashMap::from([(
+                "620".to_string(),
+                vec![
+                    ("PROTON_LOG".to_string(), "1".to_string()),
+                    ("DXVK_HUD".to_string(), "fps frametimes".to_string()),
+                ],
+            )]),
+            ..EntryOptions::default()
+        };
+        let content = render_entry(&game("620", "Portal 2"), "steam", &options);
+        assert!(
+            content.contains(
+                "\nExec=env PROTON_LOG=1 \"DXVK_HUD=fps frametimes\" steam steam://rungameid/620\n"
+            ),
+            "{}",
+            content
+        );
+        // other games, and installs, start as they are
+        let content = render_entry(&game("400", "Portal"), "steam", &options);
+        assert!(content.contains("\nExec=steam steam://rungameid/400\n"));
+        let mut uninstalled = game("620", "Portal 2");
+        uninstalled.not_installed = true;
+        let content = render_entry(&uninstalled, "steam", &options);
+        assert!(content.contains("\nExec=steam steam://install/620\n"));
+    }
+
+    #[test]
+    fn absolute_exec_names_the_resolved_binary() {
+        let portal = game("620", "Portal 2");
+        let mut options = EntryOptions {
+            steam_binary: Some(PathBuf::from("/usr/games/steam")),
+            ..EntryOptions::default()
+        };
+        let content = render_entry(&portal, "steam", &options);
+        assert!(
+            content.contains("\nTryExec=/usr/games/steam\n"),
+            "{}",
+            content
+        );
+        assert!(content.contains("\nExec=/usr/games/steam steam://rungameid/620\n"));
+
+        // xdg-open doesn't run steam itself, so there's nothing to try
+        options.exec_mode = ExecMode::XdgOpen;
+        let content = render_entry(&portal, "steam", &options);
+        assert!(!content.contains("TryExec="), "{}", content);
+        assert!(content.contains("\nExec=xdg-open steam://rungameid/620\n"));
+    }
+
+    #[test]
+    fn big_picture_launches_through_steam_options() {
+        let portal = game("620", "Portal 2");
+        assert!(
+            rendered(&portal, &["--bigpicture"])
+                .contains("\nExec=steam -bigpicture -applaunch 620\n")
+        );
+
+        use crate::cli::Cli;
+        use clap::Parser;
+        let cli =
+            Cli::try_parse_from(["steamer", "--bigpicture", "--exec-mode", "xdg-open"]).unwrap();
+        let error = cli
+            .args
+            .entry_options(&crate::config::Config::default(), None)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("needs --exec-mode steam"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn exec_arguments_are_quoted_and_keep_percent_signs() {
+        assert_eq!(
+            quote_exec_arg("steam://rungameid/620"),
+            "steam://rungameid/620"
+        );
+        assert_eq!(quote_exec_arg("100%"), "100%%");
+        assert_eq!(
+            quote_exec_arg("/opt/Steam Runtime/steam"),
+            "\"/opt/Steam Runtime/steam\""
+        );
+        assert_eq!(
+            quote_exec_arg("$HOME/\"a\"`b`\\"),
+            "\"\\$HOME/\\\"a\\\"\\`b\\`\\\\\""
+        );
+    }
+
+    #[test]
+    fn keywords_carry_the_appid_and_store_url() {
+        let portal = game("620", "Portal 2");
+        assert!(!rendered(&portal, &[]).contains("Keywords="));
+        assert!(
+            rendered(&portal, &["--keywords-appid"])
+                .contains("\nKeywords=620;https://store.steampowered.com/app/620/;\n")
+        );
+    }
+
+    #[test]
+    fn reads_keywords_files() {
+        let keywords = parse_keywords(concat!(
+            "# nicknames\n",
+            "730: CS2, counter  strike\n",
+            "\n",
+            "730: cs2, CSGO # the old name\n",
+            "620:\n",
+        ))
+        .unwrap();
+        assert_eq!(keywords["730"], ["CS2", "counter strike", "CSGO"]);
+        assert!(keywords["620"].is_empty());
+
+        let error = parse_keywords("730 CS2\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected an app ID, a colon and keywords on line 1"
+        );
+        let error = parse_keywords("# comment\ncs: CS2\n").unwrap_err();
+        assert_eq!(error.to_string(), "\"cs\" isn't an app ID on line 2");
+    }
+
+    #[test]
+    fn keywords_files_add_to_the_keywords() {
+        let path =
+            std::env::temp_dir().join(format!("steamer-keywords-test-{}", std::process::id()));
+        fs::write(&path, "620: portal two, Portal 2\n400: portal one\n").unwrap();
+        let portal = game("620", "Portal 2");
+        // the name itself adds nothing to search for
+        assert!(
+            rendered(&portal, &["--keywords-file", path.to_str().unwrap()])
+                .contains("\nKeywords=portal two;\n")
+        );
+        assert!(
+            rendered(
+                &portal,
+                &[
+                    "--keywords-appid",
+                    "--keywords-file",
+                    path.to_str().unwrap()
+                ]
+            )
+            .contains("\nKeywords=620;https://store.steampowered.com/app/620/;portal two;\n")
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn no_categories_leaves_the_line_out() {
+        let portal = game("620", "Portal 2");
+        assert!(rendered(&portal, &[]).contains("\nCategories=Game;\n"));
+        let bare = rendered(&portal, &["--no-categories"]);
+        assert!(!bare.contains("Categories="));
+        assert!(bare.contains("\nType=Application\n"));
+    }
+
+    #[test]
+    fn option_order_doesnt_change_the_bytes() {
+        let mut portal = game("620", "Portal 2");
+        portal.localized_names = vec![
+            ("fr".to_string(), "Portal 2 (fr)".to_string()),
+            ("de".to_string(), "Portal 2 (de)".to_string()),
+            ("ja".to_string(), "ポータル2".to_string()),
+        ];
+        let mut reordered = portal.clone();
+        reordered.localized_names.reverse();
+
+        let flags = [
+            "--locales=ja,fr,de",
+            "--generic-name=de=Videospiel",
+            "--generic-name=Video Game",
+            "--generic-name=fr=Jeu vidéo",
+            "--keywords-appid",
+            "--comment-appid",
+        ];
+        let mut reversed_flags = flags;
+        reversed_flags.reverse();
+        let first = rendered(&portal, &flags);
+        assert_eq!(rendered(&portal, &flags), first);
+        assert_eq!(rendered(&reordered, &flags), first);
+        assert_eq!(rendered(&portal, &reversed_flags), first);
+        assert_eq!(
+            rendered(&portal, &["--locales=de,ja,fr", "--locales=de"]),
+            rendered(&portal, &["--locales=de", "--locales=fr,de,ja"])
+        );
+
+        let keys: Vec<&str> = first
+            .lines()
+            .filter_map(|line| line.split_once('=').map(|(key, _)| key))
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                "Name",
+                "Name[de]",
+                "Name[fr]",
+                "Name[ja]",
+                "GenericName",
+                "GenericName[de]",
+                "GenericName[fr]",
+                "Comment",
+                "Exec",
+                "Icon",
+                "Terminal",
+                "Type",
+                "Categories",
+                "Keywords",
+            ]
+        );
+        assert!(!first.contains('\r'));
+    }
+
+    #[test]
+    fn an_interrupted_run_gets_its_entries_back() {
+        use crate::report::LogFormat;
+        use crate::signals;
+
+        let reporter = Reporter::new(LogFormat::Human, false);
+        let app_dir =
+            std::env::temp_dir().join(format!("steamer-backup-test-{}", std::process::id()));
+        fs::create_dir_all(app_dir.join("P")).unwrap();
+        let bucketed = app_dir.join("P/steam-620.desktop");
+        let plain = app_dir.join("steam-400.desktop");
+        let other = app_dir.join("firefox.desktop");
+        fs::write(&bucketed, "[Desktop Entry]\nName=Portal 2\n").unwrap();
+        fs::write(&plain, "[Desktop Entry]\nName=Portal\n").unwrap();
+        fs::write(&other, "[Desktop Entry]\nName=Firefox\n").unwrap();
+
+        let backup = Backup::take(&app_dir).unwrap();
+        remove_generated_entries(&app_dir, Removal::Delete, &reporter).unwrap();
+        signals::install();
+        let mut written = 0;
+        for appid in ["570", "730", "440"] {
+            if signals::interrupted() {
+                break;
+            }
+            let path = app_dir.join(format!("steam-{}.desktop", appid));
+            create_desktop_file(&path, "[Desktop Entry]\n").unwrap();
+            written += 1;
+            if written == 2 {
+                unsafe { libc::raise(libc::SIGINT) };
+            }
+        }
+        assert!(signals::interrupted());
+        assert_eq!(written, 2);
+
+        assert_eq!(backup.restore(&app_dir, &reporter).unwrap(), 2);
+        assert_eq!(
+            generated_entries(&app_dir).unwrap(),
+            [bucketed.clone(), plain.clone()]
+        );
+        assert_eq!(
+            fs::read_to_string(&bucketed).unwrap(),
+            "[Desktop Entry]\nName=Portal 2\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&plain).unwrap(),
+            "[Desktop Entry]\nName=Portal\n"
+        );
+        assert!(other.is_file());
+        fs::remove_dir_all(&app_dir).unwrap();
+    }
+
+    #[test]
+    fn the_client_entry_runs_the_resolved_binary() {
+        let entry = render_client_entry(Some(Path::new("/opt/Steam Runtime/steam")));
+        for line in [
+            "\nTryExec=/opt/Steam Runtime/steam\n",
+            "\nExec=\"/opt/Steam Runtime/steam\"\n",
+            "\nIcon=steam\n",
+            "\nCategories=Network;FileTransfer;Game;\n",
+        ] {
+            assert!(entry.contains(line), "{:?} missing from\n{}", line, entry);
+        }
+
+        let entry = render_client_entry(None);
+        assert!(entry.contains("\nExec=steam\n"), "{}", entry);
+        assert!(!entry.contains("TryExec="));
+    }
+
+    #[test]
+    fn pantheon_drops_the_store_url_and_adds_the_full_name() {
+        let cyberpunk = game("1091500", "Cyberpunk 2077: Phantom Liberty");
+        let flags = ["--keywords-appid", "--max-name-length", "12"];
+        let generic = rendered(
+            &cyberpunk,
+            &[&flags[..], &["--desktop-environment", "generic"]].concat(),
+        );
+        let pantheon = rendered(
+            &cyberpunk,
+            &[&flags[..], &["--desktop-environment", "pantheon"]].concat(),
+        );
+        let keys = |entry: &str| -> Vec<String> {
+            entry
+                .lines()
+                .filter_map(|line| Some(line.split_once('=')?.0.to_string()))
+                .collect()
+        };
+        let mut expected = keys(&generic);
+        expected.push("X-GNOME-FullName".to_string());
+        assert_eq!(keys(&pantheon), expected);
+        assert!(generic.contains(";https://store.steampowered.com/app/1091500/;"));
+        assert!(pantheon.contains("\nKeywords=1091500;"), "{}", pantheon);
+        assert!(!pantheon.contains("store.steampowered.com"));
+        assert!(pantheon.contains("\nX-GNOME-FullName=Cyberpunk 2077: Phantom Liberty\n"));
+
+        // names that fit need no full name
+        let portal = rendered(
+            &game("620", "Portal 2"),
+            &[&flags[..], &["--desktop-environment", "pantheon"]].concat(),
+        );
+        assert!(!portal.contains("X-GNOME-FullName"));
+    }
+
+    #[test]
+    fn the_save_folder_action_opens_the_folder() {
+        let action = save_dir_action(
+            Path::new("/home/me/My Games/Portal 2"),
+            &EntryOptions::default(),
+        );
+        assert_eq!(
+            action,
+            "Actions=open-save-folder;\n\n[Desktop Action open-save-folder]\n\
+             Name=Open save folder\nExec=xdg-open \"/home/me/My Games/Portal 2\"\n"
+        );
+    }
+
+    #[test]
+    fn portable_paths_refer_to_the_home_directory() {
+        let options = EntryOptions {
+            steam_binary: Some(PathBuf::from("/home/me/.local/bin/steam")),
+            portable_home: Some(PathBuf::from("/home/me")),
+            ..EntryOptions::default()
+        };
+        let fields = entry_fields(&game("620", "Portal 2"), "/icons/620.jpg", &options);
+        assert_eq!(
+            field(&fields, "Exec"),
+            Some(r#"sh -c "exec \\"\\$HOME\\"/.local/bin/steam steam://rungameid/620""#)
+        );
+        // it would only exist on this machine
+        assert_eq!(field(&fields, "TryExec"), None);
+        assert_eq!(field(&fields, "Icon"), Some("/icons/620.jpg"));
+        assert_eq!(
+            save_dir_exec(Path::new("/home/me/My Games/Portal 2"), &options),
+            r#"sh -c "exec xdg-open \\"\\$HOME\\"/'My Games/Portal 2'""#
+        );
+
+        // commands outside the home directory stay as they are
+        let options = EntryOptions {
+            steam_binary: Some(PathBuf::from("/usr/bin/steam")),
+            ..options
+        };
+        let fields = entry_fields(&game("620", "Portal 2"), "/icons/620.jpg", &options);
+        assert_eq!(
+            field(&fields, "Exec"),
+            Some("/usr/bin/steam steam://rungameid/620")
+        );
+        assert_eq!(field(&fields, "TryExec"), Some("/usr/bin/steam"));
+    }
+
+    #[test]
+    fn buckets_go_by_the_first_letter() {
+        assert_eq!(letter_bucket("Portal 2"), "P");
+        assert_eq!(letter_bucket("the Witcher"), "T");
+        assert_eq!(letter_bucket("Ōkami HD"), "O");
+        assert_eq!(letter_bucket("\"Émile\""), "E");
+        assert_eq!(letter_bucket("7 Days to Die"), "0-9");
+        assert_eq!(letter_bucket("東方"), "東");
+        assert_eq!(letter_bucket("???"), "#");
+
+        for bucket in ["P", "0-9", "#", "東", "Ж"] {
+            assert!(is_bucket(bucket), "{}", bucket);
+        }
+        for other in ["p", "PP", "icons", "0", ""] {
+            assert!(!is_bucket(other), "{}", other);
+        }
+    }
+
+    #[test]
+    fn bucketed_entries_are_generated_ones_too() {
+        let dir = std::env::temp_dir().join(format!("steamer-buckets-test-{}", std::process::id()));
+        for sub in ["P", "0-9", "icons"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for path in [
+            "steam-400.desktop",
+            "P/steam-620.desktop",
+            "0-9/steam-251570.desktop",
+            "icons/steam-10.desktop",
+            "P/firefox.desktop",
+        ] {
+            fs::write(dir.join(path), "[Desktop Entry]\n").unwrap();
+        }
+        assert_eq!(
+            generated_entries(&dir).unwrap(),
+            [
+                dir.join("0-9/steam-251570.desktop"),
+                dir.join("P/steam-620.desktop"),
+                dir.join("steam-400.desktop"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_the_entries_of_an_older_scheme() {
+        let dir = std::env::temp_dir().join(format!("steamer-migrate-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, content) in [
+            ("game_620.desktop", ""),
+            (
+                "game_portal.desktop",
+                "[Desktop Entry]\nExec=steam steam://rungameid/400\n",
+            ),
+            (
+                "game_notes.desktop",
+                "[Desktop Entry]\nExec=gedit notes.txt\n",
+            ),
+            ("game_.desktop", ""),
+            (
+                "steam-10.desktop",
+                "[Desktop Entry]\nExec=steam steam://rungameid/10\n",
+            ),
+            ("firefox.desktop", "[Desktop Entry]\nExec=firefox\n"),
+        ] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        assert_eq!(
+            entries_with_prefix(&dir, "game_").unwrap(),
+            [
+                (dir.join("game_620.desktop"), "620".to_string()),
+                (dir.join("game_portal.desktop"), "400".to_string()),
+            ]
+        );
+        // steamer's own entries are never migrated
+        assert_eq!(entries_with_prefix(&dir, "steam-").unwrap(), []);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn verified_writes_read_back_what_was_written() {
+        let reporter = Reporter::new(crate::report::LogFormat::Human, false);
+        let dir = std::env::temp_dir().join(format!("steamer-verify-test-{}", std::process::id()));
+        let path = dir.join("P/steam-620.desktop");
+        assert!(create_verified(&path, "[Desktop Entry]\nName=Portal 2\n", &reporter).unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[Desktop Entry]\nName=Portal 2\n"
+        );
+
+        // everything written to it is lost, like on a disk that drops writes
+        let lossy = dir.join("steam-400.desktop");
+        std::os::unix::fs::symlink("/dev/null", &lossy).unwrap();
+        assert!(!create_verified(&lossy, "[Desktop Entry]\nName=Portal\n", &reporter).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! Unified diffs for `--diff-output`, in the format of `diff -u`. Entries are a dozen lines, so
+//! a plain longest-common-subsequence table is plenty.
+
+/// Lines of context around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// The diff turning `old` into `new`, or an empty string when they're the same. A missing
+/// file is passed as `None` and shown as `/dev/null`, like `diff -u --new-file` does.
+pub fn unified(old_name: Option<&str>, new_name: Option<&str>, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = edit_script(&old_lines, &new_lines);
+
+    let mut out = format!(
+        "--- {}\n+++ {}\n",
+        old_name.unwrap_or("/dev/null"),
+        new_name.unwrap_or("/dev/null")
+    );
+    // (op, old line index, new line index) for every step of the script
+    let mut steps = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        steps.push((op, i, j));
+        match op {
+            Op::Equal => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+
+    let mut start = 0;
+    while let Some(first_change) = steps[start..].iter().position(|s| s.0 != Op::Equal) {
+        let first_change = start + first_change;
+        let hunk_start = first_change.saturating_sub(CONTEXT).max(start);
+        // the hunk runs until CONTEXT * 2 unchanged lines in a row, or the end
+        let mut end = first_change;
+        let mut equal_run = 0;
+        for (k, step) in steps.iter().enumerate().skip(first_change) {
+            if step.0 == Op::Equal {
+                equal_run += 1;
+                if equal_run > CONTEXT * 2 {
+                    break;
+                }
+            } else {
+                equal_run = 0;
+                end = k;
+            }
+        }
+        let hunk_end = (end + 1 + CONTEXT).min(steps.len());
+        let hunk = &steps[hunk_start..hunk_end];
+
+        let old_count = hunk.iter().filter(|s| s.0 != Op::Insert).count();
+        let new_count = hunk.iter().filter(|s| s.0 != Op::Delete).count();
+        let (_, old_start, new_start) = hunk[0];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_count),
+            range(new_start, new_count)
+        ));
+        for &(op, i, j) in hunk {
+            match op {
+                Op::Equal => out.push_str(&format!(" {}\n", old_lines[i])),
+                Op::Delete => out.push_str(&format!("-{}\n", old_lines[i])),
+                Op::Insert => out.push_str(&format!("+{}\n", new_lines[j])),
+            }
+        }
+        start = hunk_end;
+    }
+    out
+}
+
+/// A hunk range as `diff -u` writes it: 1-based, the count left out when it's 1, and an empty
+/// side given as the line before it.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// The shortest list of steps from `old` to `new`, deletions before insertions where both fit.
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Equal);
+            (i, j) = (i + 1, j + 1);
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(Op::Delete);
+            i += 1;
+        } else {
+            ops.push(Op::Insert);
+            j += 1;
+        }
+    }
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_changed_lines_with_context() {
+        let old = "[Desktop Entry]\nName=Portal 2\nExec=steam steam://rungameid/620\nIcon=steam\nTerminal=false\nType=Application\nCategories=Game;\n";
+        let new = old.replace("Icon=steam", "Icon=/icons/620.jpg");
+        assert_eq!(
+            unified(Some("a"), Some("b"), old, &new),
+            "--- a\n+++ b\n@@ -1,7 +1,7 @@\n [Desktop Entry]\n Name=Portal 2\n \
+             Exec=steam steam://rungameid/620\n-Icon=steam\n+Icon=/icons/620.jpg\n \
+             Terminal=false\n Type=Application\n Categories=Game;\n"
+        );
+        assert_eq!(unified(Some("a"), Some("b"), old, old), "");
+    }
+
+    #[test]
+    fn far_apart_changes_get_their_own_hunks() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new = old
+            .replace("\n2\n", "\ntwo\n")
+            .replace("\n19\n", "\nnineteen\n");
+        let diff = unified(Some("a"), Some("b"), &old, &new);
+        let hunks: Vec<&str> = diff.lines().filter(|l| l.starts_with("@@")).collect();
+        assert_eq!(hunks, ["@@ -1,5 +1,5 @@", "@@ -16,5 +16,5 @@"]);
+    }
+
+    #[test]
+    fn new_and_removed_files_diff_against_nothing() {
+        assert_eq!(
+            unified(None, Some("b"), "", "one\ntwo\n"),
+            "--- /dev/null\n+++ b\n@@ -0,0 +1,2 @@\n+one\n+two\n"
+        );
+        assert_eq!(
+            unified(Some("a"), None, "one\n", ""),
+            "--- a\n+++ /dev/null\n@@ -1 +0,0 @@\n-one\n"
+        );
+    }
+}
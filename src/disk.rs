@@ -0,0 +1,81 @@
+use anyhow::{Result, bail};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+/// Directory walks stop descending past this depth, game installs are never this deep.
+pub const MAX_WALK_DEPTH: usize = 32;
+
+/// Disk usage of a directory tree in bytes. Symlinks are counted but not followed, so a link
+/// pointing back up the tree can't send the walk in circles.
+pub fn dir_size(path: &Path) -> u64 {
+    walk_size(path, 0)
+}
+
+fn walk_size(path: &Path, depth: usize) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    let own = meta.blocks() * 512;
+    if !meta.is_dir() || depth >= MAX_WALK_DEPTH {
+        return own;
+    }
+
+    let children: u64 = fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|entry| walk_size(&entry.path(), depth + 1))
+        .sum();
+    own + children
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 GiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Parses a size like `50MB`, `1.5GiB` or `2048`. KB, MB, GB and TB are powers of 1000, KiB,
+/// MiB, GiB and TiB and the single letters K, M, G and T powers of 1024, as in `du`. Case
+/// doesn't matter and a plain number is bytes.
+pub fn parse_size(text: &str) -> Result<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => bail!(
+            "Invalid size {:?}, expected a number with an optional unit like 50MB or 1.5GiB",
+            text
+        ),
+    };
+    let Ok(value) = number.parse::<f64>() else {
+        bail!("Invalid size {:?}, it doesn't start with a number", text);
+    };
+    let bytes = value * factor as f64;
+    if bytes >= u64::MAX as f64 {
+        bail!("The size {:?} is too large", text);
+    }
+    Ok(bytes.round() as u64)
+}
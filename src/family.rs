@@ -0,0 +1,314 @@
+//! Steam Family View, the parental controls that limit which games an account may launch.
+//! The settings sit in the account's `localconfig.vdf` as a hex encoded protobuf
+//! (`CParentalSettings`), so this carries just enough of a protobuf reader to pull the app
+//! lists out of it.
+
+use crate::vdf;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Added to a 32-bit account ID to get the SteamID64 of an individual account.
+const STEAMID64_BASE: u64 = 76_561_197_960_265_728;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum FamilyViewMode {
+    /// Don't generate entries for games the account may not launch
+    #[default]
+    Skip,
+    /// Generate them with NoDisplay=true, so they stay out of menus and search
+    Hide,
+}
+
+/// The games Family View lets an account launch.
+pub struct FamilyView {
+    allowed: HashSet<String>,
+}
+
+impl FamilyView {
+    pub fn allows(&self, appid: &str) -> bool {
+        self.allowed.contains(appid)
+    }
+
+    /// Reads the restrictions of `user`, an account name, persona name or Steam ID. Returns
+    /// `None` when Family View is off or lets the account play everything.
+    pub fn load(steam_root: &Path, user: &str) -> Result<Option<Self>> {
+        let account_id = find_account_id(steam_root, user)?;
+        let path = steam_root.join(format!("userdata/{}/config/localconfig.vdf", account_id));
+        let text =
+            fs::read_to_string(&path).with_context(|| format!("Could not read {:?}", path))?;
+        let root = vdf::parse_text(&text).with_context(|| format!("Could not parse {:?}", path))?;
+        let Some(settings) = root
+            .path("UserLocalConfigStore/ParentalSettings/settings")
+            .and_then(vdf::Node::as_str)
+        else {
+            return Ok(None);
+        };
+        parse_settings(&decode_hex(settings)?)
+    }
+}
+
+/// Looks `user` up in `config/loginusers.vdf`, returning the 32-bit account ID that names the
+/// account's `userdata` directory.
+fn find_account_id(steam_root: &Path, user: &str) -> Result<u64> {
+    let path = steam_root.join("config/loginusers.vdf");
+    let text = fs::read_to_string(&path).with_context(|| format!("Could not read {:?}", path))?;
+    let root = vdf::parse_text(&text).with_context(|| format!("Could not parse {:?}", path))?;
+    let users = root.get("users").map(vdf::Node::entries).unwrap_or(&[]);
+
+    fn field<'a>(node: &'a vdf::Node, key: &str) -> Option<&'a str> {
+        node.get(key).and_then(vdf::Node::as_str)
+    }
+    let found = users.iter().find(|(steam_id, node)| {
+        steam_id == user
+            || steam_id
+                .parse::<u64>()
+                .is_ok_and(|id| id.wrapping_sub(STEAMID64_BASE).to_string() == user)
+            || field(node, "AccountName").is_some_and(|n| n.eq_ignore_ascii_case(user))
+            || field(node, "PersonaName").is_some_and(|n| n == user)
+    });
+    match found {
+        Some((steam_id, _)) => steam_id
+            .parse::<u64>()
+            .ok()
+            .and_then(|id| id.checked_sub(STEAMID64_BASE))
+            .with_context(|| format!("Invalid Steam ID {:?} in {:?}", steam_id, path)),
+        None => bail!(
+            "No account named {:?} has logged in to Steam on this machine",
+            user
+        ),
+    }
+}
+
+fn decode_hex(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("Family View settings aren't valid hex");
+    }
+    Ok((0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+        .collect())
+}
+
+/// Field numbers in `CParentalSettings`, and in the `CParentalApp` entries of its lists.
+const FIELD_APPLIST_BASE: u64 = 4;
+const FIELD_APPLIST_CUSTOM: u64 = 5;
+const FIELD_IS_ENABLED: u64 = 9;
+const FIELD_APP_ID: u64 = 1;
+const FIELD_APP_ALLOWED: u64 = 2;
+
+fn parse_settings(data: &[u8]) -> Result<Option<FamilyView>> {
+    let mut enabled = false;
+    let mut listed = false;
+    let mut allowed = HashSet::new();
+    for field in Fields::new(data) {
+        match field? {
+            (FIELD_IS_ENABLED, Wire::Varint(value)) => enabled = value != 0,
+            (FIELD_APPLIST_BASE | FIELD_APPLIST_CUSTOM, Wire::Bytes(app)) => {
+                listed = true;
+                let (mut appid, mut is_allowed) = (None, false);
+                for field in Fields::new(app) {
+                    match field? {
+                        (FIELD_APP_ID, Wire::Varint(id)) => appid = Some(id),
+                        (FIELD_APP_ALLOWED, Wire::Varint(value)) => is_allowed = value != 0,
+                        _ => {}
+                    }
+                }
+                if let (Some(appid), true) = (appid, is_allowed) {
+                    allowed.insert(appid.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    // without an app list Family View only locks store and community features, not games
+    Ok((enabled && listed).then_some(FamilyView { allowed }))
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterates the `(field number, value)` pairs of one protobuf message.
+struct Fields<'a> {
+    data: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            failed: false,
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.data.split_first().context("Truncated varint")?;
+            self.data = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Varint is too long")
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            bail!("Truncated Family View settings");
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn field(&mut self) -> Result<(u64, Wire<'a>)> {
+        let key = self.varint()?;
+        let wire = match key & 7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Wire::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Wire::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Wire::Fixed
+            }
+            other => bail!("Unsupported protobuf wire type {}", other),
+        };
+        Ok((key >> 3, wire))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<(u64, Wire<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() || self.failed {
+            return None;
+        }
+        let field = self.field();
+        self.failed = field.is_err();
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(out: &mut Vec<u8>, mut value: u64) {
+        while value >= 0x80 {
+            out.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    /// `CParentalSettings` with Family View on or off and a `CParentalApp` per `(appid,
+    /// allowed)` in the base list.
+    fn settings(enabled: bool, apps: &[(u64, bool)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        // a steamid, fixed64, to be skipped
+        data.push(1 << 3 | 1);
+        data.extend_from_slice(&76_561_198_000_000_000u64.to_le_bytes());
+        for &(appid, allowed) in apps {
+            let mut app = Vec::new();
+            varint(&mut app, FIELD_APP_ID << 3);
+            varint(&mut app, appid);
+            varint(&mut app, FIELD_APP_ALLOWED << 3);
+            varint(&mut app, allowed as u64);
+            varint(&mut data, FIELD_APPLIST_BASE << 3 | 2);
+            varint(&mut data, app.len() as u64);
+            data.extend_from_slice(&app);
+        }
+        varint(&mut data, FIELD_IS_ENABLED << 3);
+        varint(&mut data, enabled as u64);
+        data
+    }
+
+    #[test]
+    fn allows_only_the_listed_games() {
+        let view = parse_settings(&settings(
+            true,
+            &[(620, true), (400, false), (1145360, true)],
+        ))
+        .unwrap()
+        .unwrap();
+        assert!(view.allows("620"));
+        assert!(view.allows("1145360"));
+        assert!(!view.allows("400"));
+        assert!(!view.allows("570"));
+    }
+
+    #[test]
+    fn off_or_without_a_list_restricts_nothing() {
+        assert!(
+            parse_settings(&settings(false, &[(620, true)]))
+                .unwrap()
+                .is_none()
+        );
+        assert!(parse_settings(&settings(true, &[])).unwrap().is_none());
+        assert!(parse_settings(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_settings() {
+        let data = settings(true, &[(620, true)]);
+        assert!(parse_settings(&data[..data.len() - 3]).is_err());
+        assert!(decode_hex("0a0").is_err());
+        assert!(decode_hex("zz").is_err());
+    }
+
+    #[test]
+    fn finds_the_account_by_any_of_its_names() {
+        let root = std::env::temp_dir().join(format!("steamer-family-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("config")).unwrap();
+        fs::write(
+            root.join("config/loginusers.vdf"),
+            "\"users\"\n{\n\t\"76561198000000042\"\n\t{\n\t\t\"AccountName\"\t\t\"kid_account\"\n\
+             \t\t\"PersonaName\"\t\t\"Kiddo\"\n\t}\n}\n",
+        )
+        .unwrap();
+        let account_id = 76_561_198_000_000_042 - STEAMID64_BASE;
+        let hex: String = settings(true, &[(620, true)])
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let config = root.join(format!("userdata/{}/config", account_id));
+        fs::create_dir_all(&config).unwrap();
+        fs::write(
+            config.join("localconfig.vdf"),
+            format!(
+                "\"UserLocalConfigStore\"\n{{\n\t\"ParentalSettings\"\n\t{{\n\t\t\"settings\"\t\t\"{}\"\n\t}}\n}}\n",
+                hex
+            ),
+        )
+        .unwrap();
+
+        for user in [
+            "KID_ACCOUNT",
+            "Kiddo",
+            "76561198000000042",
+            &account_id.to_string(),
+        ] {
+            let view = FamilyView::load(&root, user).unwrap().unwrap();
+            assert!(view.allows("620") && !view.allows("400"), "{}", user);
+        }
+        let error = FamilyView::load(&root, "kiddo").err().unwrap();
+        assert!(error.to_string().contains("No account named"), "{}", error);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,794 @@
+use crate::disk;
+use crate::state;
+use crate::steam::{GameInfo, PartialInstall};
+use crate::unicode;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// How skip keywords are compared against game names. All modes ignore case.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum KeywordMatch {
+    /// The keyword appears anywhere in the name ("Demo" matches "Demon Slayer")
+    #[default]
+    Substring,
+    /// The keyword appears as whole words ("Demo" matches "Portal Demo" only)
+    Word,
+    /// The whole name matches a shell-style pattern ("Proton *", "*Server*")
+    Glob,
+}
+
+pub struct KeywordMatcher {
+    keyword: String,
+    pattern: Regex,
+}
+
+impl KeywordMatcher {
+    pub fn new(keyword: &str, mode: KeywordMatch) -> Result<Self> {
+        let pattern = match mode {
+            KeywordMatch::Substring => regex::escape(keyword),
+            KeywordMatch::Word => format!(r"(?:^|\W){}(?:$|\W)", regex::escape(keyword)),
+            KeywordMatch::Glob => format!("^{}$", glob_to_regex(keyword)),
+        };
+        let pattern = RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .build()
+            .with_context(|| format!("Invalid keyword pattern {:?}", keyword))?;
+
+        Ok(Self {
+            keyword: keyword.to_string(),
+            pattern,
+        })
+    }
+
+    pub fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.pattern.is_match(name)
+    }
+}
+
+/// Translates `*`, `?` and `[...]` into their regex equivalents, escaping everything else.
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::new();
+    let mut in_class = false;
+
+    for c in glob.chars() {
+        match c {
+            '*' if !in_class => out.push_str(".*"),
+            '?' if !in_class => out.push('.'),
+            '[' if !in_class => {
+                in_class = true;
+                out.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                out.push(']');
+            }
+            '!' if in_class && out.ends_with('[') => out.push('^'),
+            _ if in_class && c != '\\' => out.push(c),
+            _ => out.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    // an unterminated class is taken literally, the same way shells treat it
+    if in_class {
+        let start = out.rfind('[').unwrap();
+        let class = out.split_off(start);
+        out.push_str(&regex::escape(&class));
+    }
+
+    out
+}
+
+/// A `.steamerignore` line up to a `#` that starts it or follows whitespace.
+pub fn strip_comment(line: &str) -> &str {
+    let mut previous = None;
+    for (i, c) in line.char_indices() {
+        if c == '#' && previous.is_none_or(char::is_whitespace) {
+            return &line[..i];
+        }
+        previous = Some(c);
+    }
+    line
+}
+
+/// Steam tools people start themselves, which the default skip keywords would leave out. With
+/// `--tools` they get entries like games, launched by their app ID. The names are for manifests
+/// that lack one.
+pub const USEFUL_TOOLS: &[(&str, &str)] = &[
+    ("250820", "SteamVR"),
+    ("323910", "SteamVR Performance Test"),
+];
+
+/// One `--ignored-app-ids` entry: an appid, an inclusive range like `2000-2100`, or a prefix
+/// ending in `*` like `12*`. Spaces around the entry and the bounds of a range don't matter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppIdMatcher {
+    Exact(String),
+    Range(u64, u64),
+    Prefix(String),
+}
+
+impl AppIdMatcher {
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if let Some(prefix) = text.strip_suffix('*') {
+            if prefix.is_empty() {
+                bail!("The app ID pattern \"*\" on its own would skip every game");
+            }
+            if !is_number(prefix) {
+                bail!(
+                    "Invalid app ID pattern {:?}, a '*' can only follow digits",
+                    text
+                );
+            }
+            return Ok(AppIdMatcher::Prefix(prefix.to_string()));
+        }
+        if let Some((start, end)) = text.split_once('-') {
+            let number = |s: &str| s.trim().parse::<u64>().ok().filter(|_| is_number(s.trim()));
+            let (Some(start), Some(end)) = (number(start), number(end)) else {
+                bail!(
+                    "Invalid app ID range {:?}, expected two numbers like 2000-2100",
+                    text
+                );
+            };
+            if start > end {
+                bail!(
+                    "Invalid app ID range {:?}, the start has to come first",
+                    text
+                );
+            }
+            return Ok(AppIdMatcher::Range(start, end));
+        }
+        if !is_number(text) {
+            bail!(
+                "Invalid app ID {:?}, expected a number, a range or a prefix ending in '*'",
+                text
+            );
+        }
+        Ok(AppIdMatcher::Exact(text.to_string()))
+    }
+
+    pub fn matches(&self, appid: &str) -> bool {
+        match self {
+            AppIdMatcher::Exact(id) => id == appid,
+            AppIdMatcher::Range(start, end) => appid
+                .parse::<u64>()
+                .is_ok_and(|id| (*start..=*end).contains(&id)),
+            AppIdMatcher::Prefix(prefix) => appid.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for AppIdMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppIdMatcher::Exact(id) => write!(f, "{}", id),
+            AppIdMatcher::Range(start, end) => write!(f, "{}-{}", start, end),
+            AppIdMatcher::Prefix(prefix) => write!(f, "{}*", prefix),
+        }
+    }
+}
+
+/// A `--size-range`: `MIN..MAX` keeps games from MIN up to but not including MAX, `MIN..=MAX`
+/// includes MAX too, and either end can be left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeRange {
+    source: String,
+    min: Option<u64>,
+    max: Option<u64>,
+    inclusive: bool,
+}
+
+impl SizeRange {
+    pub fn parse(text: &str) -> Result<Self> {
+        let Some((min, max)) = text.split_once("..") else {
+            bail!(
+                "Invalid size range {:?}, expected MIN..MAX, MIN.. or ..MAX like 50MB..200GB",
+                text
+            );
+        };
+        let (max, inclusive) = match max.strip_prefix('=') {
+            Some(max) => (max, true),
+            None => (max, false),
+        };
+        let bound = |s: &str| match s.trim() {
+            "" => Ok(None),
+            s => disk::parse_size(s).map(Some),
+        };
+        let (min, max) = (bound(min)?, bound(max)?);
+        if min.is_none() && max.is_none() {
+            bail!(
+                "The size range {:?} has no bounds, it would keep every game",
+                text
+            );
+        }
+        if inclusive && max.is_none() {
+            bail!(
+                "Invalid size range {:?}, ..= needs a maximum after it",
+                text
+            );
+        }
+        if let (Some(min), Some(max)) = (min, max)
+            && (max < min || (max == min && !inclusive))
+        {
+            bail!(
+                "The size range {:?} is empty, the minimum has to come first",
+                text
+            );
+        }
+        Ok(Self {
+            source: text.to_string(),
+            min,
+            max,
+            inclusive,
+        })
+    }
+
+    pub fn contains(&self, size: u64) -> bool {
+        let above_min = self.min.is_none_or(|min| size >= min);
+        let below_max = self.max.is_none_or(|max| match self.inclusive {
+            true => size <= max,
+            false => size < max,
+        });
+        above_min && below_max
+    }
+}
+
+impl fmt::Display for SizeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Matched an `--ignored-app-ids` entry, kept as written.
+    AppId(String),
+    Keyword(String),
+    /// `--skip-demos` and the name or appinfo says it's a demo.
+    Demo,
+    /// A playtest, with the name of its game when that's installed.
+    Playtest(Option<String>),
+    /// Another library already provided this appid.
+    Duplicate,
+    /// Family View doesn't let the `--user` account launch it.
+    FamilyView,
+    /// `--verify-installdir` found its install folder in no library.
+    MissingFiles,
+    /// `--skip-partial` and the manifest shows Steam never finished installing it.
+    PartialInstall(PartialInstall),
+    /// `--new-since` and the game was already installed that many syncs ago.
+    NotNew(usize),
+    /// `--icon-changes-only` and the game's icon is the one its entry was last written with.
+    IconUnchanged,
+    /// `--repair` and the entry is as steamer wrote it.
+    Intact,
+    /// `--repair` and the entry is marked to be kept as it is.
+    Preserved,
+    /// `--skip-emoji` and the name has emoji in it.
+    Emoji,
+    /// `--size-range` and the size on disk the manifest gives is outside it.
+    Size {
+        size: u64,
+        range: SizeRange,
+    },
+    /// An appid or name pattern from a `.steamerignore` file.
+    IgnoreFile {
+        pattern: String,
+        file: PathBuf,
+    },
+}
+
+impl SkipReason {
+    /// Stable identifier for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SkipReason::AppId(_) => "app_id",
+            SkipReason::Keyword(_) => "keyword",
+            SkipReason::Demo => "demo",
+            SkipReason::Playtest(_) => "playtest",
+            SkipReason::Duplicate => "duplicate",
+            SkipReason::FamilyView => "family_view",
+            SkipReason::MissingFiles => "missing_files",
+            SkipReason::PartialInstall(_) => "partial_install",
+            SkipReason::NotNew(_) => "not_new",
+            SkipReason::IconUnchanged => "icon_unchanged",
+            SkipReason::Intact => "intact",
+            SkipReason::Preserved => "preserved",
+            SkipReason::Emoji => "emoji",
+            SkipReason::Size { .. } => "size",
+            SkipReason::IgnoreFile { .. } => "ignore_file",
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::AppId(appid) => write!(f, "ignored app ID {}", appid),
+            SkipReason::Keyword(keyword) => write!(f, "matched keyword {:?}", keyword),
+            SkipReason::Demo => write!(f, "demo"),
+            SkipReason::Playtest(Some(parent)) => write!(f, "playtest of {}", parent),
+            SkipReason::Playtest(None) => write!(f, "playtest"),
+            SkipReason::Duplicate => write!(f, "already found in another library"),
+            SkipReason::FamilyView => write!(f, "not allowed by Family View"),
+            SkipReason::MissingFiles => write!(f, "install folder not found in any library"),
+            SkipReason::PartialInstall(partial) => write!(f, "not fully installed, {}", partial),
+            SkipReason::NotNew(1) => write!(f, "already installed at the last sync"),
+            SkipReason::NotNew(runs) => write!(f, "already installed {} syncs ago", runs),
+            SkipReason::IconUnchanged => write!(f, "icon unchanged since the last run"),
+            SkipReason::Intact => write!(f, "entry as steamer wrote it"),
+            SkipReason::Preserved => write!(f, "entry marked {}", state::PRESERVE_MARKER),
+            SkipReason::Emoji => write!(f, "emoji in the name"),
+            SkipReason::Size { size, range } => {
+                write!(f, "{} is outside {}", disk::format_size(*size), range)
+            }
+            SkipReason::IgnoreFile { pattern, file } => {
+                write!(f, "{:?} in {}", pattern, file.display())
+            }
+        }
+    }
+}
+
+enum IgnoreEntry {
+    AppId(String),
+    Name(KeywordMatcher),
+}
+
+pub struct SkipRules {
+    app_ids: Vec<AppIdMatcher>,
+    keywords: Vec<KeywordMatcher>,
+    /// `.steamerignore` lines, each with the file it came from.
+    ignored: Vec<(IgnoreEntry, PathBuf)>,
+    demos: Option<Regex>,
+    playtests: Option<Regex>,
+    partial: bool,
+    size_range: Option<SizeRange>,
+    emoji: bool,
+    tools: bool,
+}
+
+impl SkipRules {
+    pub fn new(app_ids: &[String], keywords: &[String], mode: KeywordMatch) -> Result<Self> {
+        let app_ids = app_ids
+            .iter()
+            .map(|id| AppIdMatcher::parse(id))
+            .collect::<Result<_>>()?;
+        let keywords = keywords
+            .iter()
+            .map(|k| KeywordMatcher::new(k, mode))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            app_ids,
+            keywords,
+            ignored: Vec::new(),
+            demos: None,
+            playtests: None,
+            partial: false,
+            size_range: None,
+            emoji: false,
+            tools: false,
+        })
+    }
+
+    /// Also skips games appinfo types as a demo, or whose name ends in the word "Demo"
+    /// ("Portal Demo", "Portal (Demo)", but not "Democracy").
+    pub fn skip_demos(mut self, skip: bool) -> Self {
+        self.demos = skip.then(|| {
+            RegexBuilder::new(r"\bdemo[\s)\]]*$")
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        });
+        self
+    }
+
+    /// Skips apps appinfo marks as playtests, or named "... Playtest" when appinfo has nothing.
+    pub fn skip_playtests(mut self, skip: bool) -> Self {
+        self.playtests = skip.then(|| {
+            RegexBuilder::new(r"\splaytest$")
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        });
+        self
+    }
+
+    /// Skips installs the manifest shows as unplayable: a first download or staging that
+    /// stopped part way, or no depots of the game itself. Games only waiting on an update
+    /// still launch, so they're kept.
+    pub fn skip_partial(mut self, skip: bool) -> Self {
+        self.partial = skip;
+        self
+    }
+
+    /// Skips games whose size on disk is outside `range`. Games whose manifest has no size are
+    /// kept.
+    pub fn size_range(mut self, range: Option<SizeRange>) -> Self {
+        self.size_range = range;
+        self
+    }
+
+    /// Skips games with emoji in their name, see [`unicode::is_emoji`] for what counts.
+    pub fn skip_emoji(mut self, skip: bool) -> Self {
+        self.emoji = skip;
+        self
+    }
+
+    /// Keeps the [`USEFUL_TOOLS`] whatever skip keywords their names match. App IDs and ignore
+    /// files still skip them, those name the tool on purpose.
+    pub fn keep_tools(mut self, keep: bool) -> Self {
+        self.tools = keep;
+        self
+    }
+
+    /// Adds the entries of every `.steamerignore` in `files` that exists. Each line is an appid
+    /// or a shell-style pattern for the whole name, ignoring case. A `#` at the start of a line
+    /// or after a space starts a comment, one inside a name like "Zero Escape #2" doesn't.
+    pub fn ignore_files(mut self, files: &[PathBuf]) -> Result<Self> {
+        for file in files {
+            let text = match fs::read_to_string(file) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).with_context(|| format!("Could not read {:?}", file)),
+            };
+            for line in text.lines() {
+                let line = strip_comment(line).trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let entry = if line.bytes().all(|b| b.is_ascii_digit()) {
+                    IgnoreEntry::AppId(line.to_string())
+                } else {
+                    IgnoreEntry::Name(KeywordMatcher::new(line, KeywordMatch::Glob)?)
+                };
+                self.ignored.push((entry, file.clone()));
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn check(&self, game: &GameInfo) -> Option<SkipReason> {
+        if let Some(matcher) = self.app_ids.iter().find(|m| m.matches(&game.appid)) {
+            return Some(SkipReason::AppId(matcher.to_string()));
+        }
+
+        if let Some((entry, file)) = self.ignored.iter().find(|(entry, _)| match entry {
+            IgnoreEntry::AppId(appid) => *appid == game.appid,
+            IgnoreEntry::Name(matcher) => matcher.matches(&game.name),
+        }) {
+            let pattern = match entry {
+                IgnoreEntry::AppId(appid) => appid.clone(),
+                IgnoreEntry::Name(matcher) => matcher.keyword().to_string(),
+            };
+            return Some(SkipReason::IgnoreFile {
+                pattern,
+                file: file.clone(),
+            });
+        }
+
+        if self.partial
+            && let Some(ref partial) = game.partial_install
+        {
+            return Some(SkipReason::PartialInstall(partial.clone()));
+        }
+
+        if let (Some(range), Some(size)) = (&self.size_range, game.size_on_disk)
+            && !range.contains(size)
+        {
+            return Some(SkipReason::Size {
+                size,
+                range: range.clone(),
+            });
+        }
+
+        if self.emoji && unicode::has_emoji(&game.name) {
+            return Some(SkipReason::Emoji);
+        }
+
+        if let Some(ref demo_name) = self.demos
+            && (game.app_type.as_deref() == Some("demo") || demo_name.is_match(&game.name))
+        {
+            return Some(SkipReason::Demo);
+        }
+
+        if let Some(ref playtest_name) = self.playtests
+            && (game.playtest || playtest_name.is_match(game.name.trim_end()))
+        {
+            return Some(SkipReason::Playtest(game.parent_name.clone()));
+        }
+
+        if self.tools && USEFUL_TOOLS.iter().any(|(appid, _)| *appid == game.appid) {
+            return None;
+        }
+
+        self.keywords
+            .iter()
+            .find(|k| k.matches(&game.name))
+            .map(|k| SkipReason::Keyword(k.keyword().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(keyword: &str, mode: KeywordMatch, name: &str) -> bool {
+        KeywordMatcher::new(keyword, mode).unwrap().matches(name)
+    }
+
+    #[test]
+    fn substring_matches_anywhere() {
+        assert!(matches("Demo", KeywordMatch::Substring, "Demon Slayer"));
+        assert!(matches("demo", KeywordMatch::Substring, "PORTAL DEMO"));
+        assert!(matches("c++", KeywordMatch::Substring, "Learn C++ Today"));
+        assert!(!matches("Demo", KeywordMatch::Substring, "Portal"));
+    }
+
+    #[test]
+    fn word_matches_whole_words() {
+        assert!(matches("Demo", KeywordMatch::Word, "Portal Demo"));
+        assert!(matches("Demo", KeywordMatch::Word, "Demo: Portal"));
+        assert!(matches("demo", KeywordMatch::Word, "Portal (DEMO)"));
+        assert!(matches(
+            "Dedicated Server",
+            KeywordMatch::Word,
+            "Rust Dedicated Server"
+        ));
+        assert!(!matches("Demo", KeywordMatch::Word, "Demon Slayer"));
+        assert!(!matches("Demo", KeywordMatch::Word, "Pandemonium"));
+    }
+
+    #[test]
+    fn glob_matches_the_whole_name() {
+        assert!(matches("Proton *", KeywordMatch::Glob, "Proton 8.0"));
+        assert!(matches(
+            "proton *",
+            KeywordMatch::Glob,
+            "PROTON EXPERIMENTAL"
+        ));
+        assert!(!matches(
+            "Proton *",
+            KeywordMatch::Glob,
+            "Steam Linux Runtime - Proton"
+        ));
+        assert!(matches(
+            "*Server*",
+            KeywordMatch::Glob,
+            "Rust Dedicated Server"
+        ));
+        assert!(matches("Portal ?", KeywordMatch::Glob, "Portal 2"));
+        assert!(!matches("Portal ?", KeywordMatch::Glob, "Portal 10"));
+        assert!(!matches("Portal", KeywordMatch::Glob, "Portal 2"));
+    }
+
+    #[test]
+    fn glob_classes_and_literals() {
+        assert!(matches("Portal [12]", KeywordMatch::Glob, "Portal 2"));
+        assert!(!matches("Portal [!12]", KeywordMatch::Glob, "Portal 2"));
+        assert!(matches("Portal [!12]", KeywordMatch::Glob, "Portal 3"));
+        assert!(matches(
+            "Half-Life (1998)",
+            KeywordMatch::Glob,
+            "half-life (1998)"
+        ));
+        assert!(matches("Portal [2", KeywordMatch::Glob, "Portal [2"));
+        assert!(!matches("Portal.", KeywordMatch::Glob, "Portal2"));
+    }
+
+    #[test]
+    fn app_id_matchers() {
+        let parse = |text: &str| AppIdMatcher::parse(text).unwrap();
+        assert_eq!(parse("480"), AppIdMatcher::Exact("480".to_string()));
+        assert_eq!(parse("10-20"), AppIdMatcher::Range(10, 20));
+        assert_eq!(parse("12*"), AppIdMatcher::Prefix("12".to_string()));
+        assert_eq!(parse(" 480 "), AppIdMatcher::Exact("480".to_string()));
+        assert_eq!(parse(" 10 - 20 "), AppIdMatcher::Range(10, 20));
+        assert_eq!(parse("7-7"), AppIdMatcher::Range(7, 7));
+
+        let range = parse("10-20");
+        assert!(range.matches("10") && range.matches("15") && range.matches("20"));
+        assert!(!range.matches("9") && !range.matches("21") && !range.matches("100"));
+        assert!(!range.matches("abc"));
+        assert!(parse("480").matches("480") && !parse("480").matches("4800"));
+        assert!(parse("12*").matches("12") && parse("12*").matches("1234"));
+        assert!(!parse("12*").matches("312"));
+        assert_eq!(parse("2000-2100").to_string(), "2000-2100");
+        assert_eq!(parse(" 12* ").to_string(), "12*");
+    }
+
+    #[test]
+    fn app_id_matchers_reject_bad_entries() {
+        for text in [
+            "*", " * ", "", "abc", "1a*", "*12", "1*2", "20-10", "10-", "-20", "10-20-30", "a-b",
+            "10--20", "4 80",
+        ] {
+            assert!(
+                AppIdMatcher::parse(text).is_err(),
+                "{:?} was accepted",
+                text
+            );
+        }
+        let error = AppIdMatcher::parse("20-10").unwrap_err();
+        assert!(error.to_string().contains("the start has to come first"));
+        let error = AppIdMatcher::parse("*").unwrap_err();
+        assert!(error.to_string().contains("would skip every game"));
+    }
+
+    #[test]
+    fn comments_start_a_line_or_follow_whitespace() {
+        assert_eq!(strip_comment("# Steam tools"), "");
+        assert_eq!(strip_comment("620 # Portal 2"), "620 ");
+        assert_eq!(strip_comment("620\t#Portal 2"), "620\t");
+        assert_eq!(strip_comment("Zero Escape #2"), "Zero Escape ");
+        assert_eq!(strip_comment("Zero Escape#2"), "Zero Escape#2");
+        assert_eq!(strip_comment("C# Tutorial # learning"), "C# Tutorial ");
+        assert_eq!(strip_comment("Portal *"), "Portal *");
+    }
+
+    #[test]
+    fn ignore_files_skip_appids_and_name_globs() {
+        let dir = std::env::temp_dir().join(format!("steamer-ignore-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(".steamerignore");
+        fs::write(
+            &file,
+            "# games I never play\n620\nProton * # compatibility tools\n\nC# Tutorial\n",
+        )
+        .unwrap();
+        let missing = dir.join("missing/.steamerignore");
+        let rules = SkipRules::new(&[], &[], KeywordMatch::Substring)
+            .unwrap()
+            .ignore_files(&[missing, file.clone()])
+            .unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        let game = |appid: &str, name: &str| GameInfo {
+            appid: appid.to_string(),
+            name: name.to_string(),
+            ..GameInfo::default()
+        };
+        for (appid, name) in [
+            ("620", "Portal 2"),
+            ("1493710", "Proton Experimental"),
+            ("9", "c# tutorial"),
+        ] {
+            assert!(
+                matches!(
+                    rules.check(&game(appid, name)),
+                    Some(SkipReason::IgnoreFile { .. })
+                ),
+                "{} wasn't skipped",
+                name
+            );
+        }
+        assert_eq!(rules.check(&game("400", "Portal")), None);
+        assert_eq!(rules.check(&game("6200", "Proton")), None);
+    }
+
+    #[test]
+    fn skips_demos_by_type_or_trailing_word() {
+        let game = |name: &str, app_type: Option<&str>| GameInfo {
+            appid: "400".to_string(),
+            name: name.to_string(),
+            app_type: app_type.map(str::to_string),
+            ..GameInfo::default()
+        };
+        let rules = SkipRules::new(&[], &[], KeywordMatch::Substring)
+            .unwrap()
+            .skip_demos(true);
+        for demo in [
+            game("Portal Demo", None),
+            game("Portal (DEMO)", None),
+            game("Portal [demo] ", None),
+            game("Portal: First Slice", Some("demo")),
+        ] {
+            assert_eq!(rules.check(&demo), Some(SkipReason::Demo), "{}", demo.name);
+        }
+        for kept in [
+            game("Democracy 4", None),
+            game("Demon's Souls", Some("game")),
+            game("Demo Disc Collection", None),
+        ] {
+            assert_eq!(rules.check(&kept), None, "{}", kept.name);
+        }
+
+        let rules = SkipRules::new(&[], &[], KeywordMatch::Substring).unwrap();
+        assert_eq!(rules.check(&game("Portal Demo", Some("demo"))), None);
+    }
+
+    #[test]
+    fn tools_are_kept_over_the_skip_keywords() {
+        let game = |appid: &str, name: &str| GameInfo {
+            appid: appid.to_string(),
+            name: name.to_string(),
+            ..GameInfo::default()
+        };
+        let keywords = ["SteamVR".to_string(), "Proton".to_string()];
+        let rules =
+            SkipRules::new(&["323910".to_string()], &keywords, KeywordMatch::Substring).unwrap();
+        let steamvr = game("250820", "SteamVR");
+        assert_eq!(
+            rules.check(&steamvr),
+            Some(SkipReason::Keyword("SteamVR".to_string()))
+        );
+
+        let rules = rules.keep_tools(true);
+        assert_eq!(rules.check(&steamvr), None);
+        // only the listed tools, and an app ID still names one on purpose
+        assert_eq!(
+            rules.check(&game("1493710", "Proton Experimental")),
+            Some(SkipReason::Keyword("Proton".to_string()))
+        );
+        assert_eq!(
+            rules.check(&game("323910", "SteamVR Performance Test")),
+            Some(SkipReason::AppId("323910".to_string()))
+        );
+    }
+
+    const GIB: u64 = 1 << 30;
+
+    #[test]
+    fn size_ranges_with_one_bound() {
+        let range = SizeRange::parse("1G..").unwrap();
+        assert!(!range.contains(GIB - 1));
+        assert!(range.contains(GIB));
+        assert!(range.contains(u64::MAX));
+
+        let range = SizeRange::parse("..10G").unwrap();
+        assert!(range.contains(0));
+        assert!(range.contains(10 * GIB - 1));
+        assert!(!range.contains(10 * GIB));
+        assert_eq!(range.to_string(), "..10G");
+    }
+
+    #[test]
+    fn size_ranges_mix_units() {
+        let range = SizeRange::parse("500MiB..1G").unwrap();
+        assert!(!range.contains(500 * (1 << 20) - 1));
+        assert!(range.contains(500 * (1 << 20)));
+        assert!(range.contains(GIB - 1));
+        assert!(!range.contains(GIB));
+
+        let range = SizeRange::parse(" 50MB .. 1.5GB ").unwrap();
+        assert!(range.contains(50_000_000));
+        assert!(!range.contains(1_500_000_000));
+    }
+
+    #[test]
+    fn size_ranges_include_the_maximum_after_equals() {
+        let range = SizeRange::parse("1G..=1G").unwrap();
+        assert!(range.contains(GIB));
+        assert!(!range.contains(GIB - 1));
+        assert!(!range.contains(GIB + 1));
+        assert!(SizeRange::parse("..=10G").unwrap().contains(10 * GIB));
+    }
+
+    #[test]
+    fn size_ranges_reject_bad_input() {
+        for text in [
+            "1G",
+            "",
+            "..",
+            "..=",
+            "1G..=",
+            "big..",
+            "1X..",
+            "..10 parsecs",
+            "10G..1G",
+            "1G..1G",
+        ] {
+            assert!(SizeRange::parse(text).is_err(), "{:?} parsed", text);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! The "Steam" folder in the GNOME app grid, kept in sync through the `gsettings` CLI.
+//! Only the folder steamer owns is ever touched; other folders and their order stay as they are.
+//! The dash's favorites are only changed to follow entries `--migrate-from` renamed.
+
+use anyhow::{Context, Result, bail};
+use std::env;
+use std::process::Command;
+
+const SCHEMA: &str = "org.gnome.desktop.app-folders";
+const FOLDER_SCHEMA: &str = "org.gnome.desktop.app-folders.folder";
+const SHELL_SCHEMA: &str = "org.gnome.shell";
+/// The folder id in `folder-children`. Users often name their own folder "Steam", so the id
+/// steamer owns is its own and only the display name says Steam.
+const FOLDER_ID: &str = "steamer";
+const FOLDER_NAME: &str = "Steam";
+
+/// `XDG_CURRENT_DESKTOP` is a colon separated list, e.g. `ubuntu:GNOME`.
+pub fn is_gnome_session() -> bool {
+    env::var("XDG_CURRENT_DESKTOP")
+        .is_ok_and(|desktops| desktops.split(':').any(|d| d.eq_ignore_ascii_case("gnome")))
+}
+
+/// Points the folder at exactly `desktop_ids`, or removes it when there are none.
+pub fn sync_folder(desktop_ids: &[String]) -> Result<()> {
+    if desktop_ids.is_empty() {
+        return remove_folder();
+    }
+
+    let mut children = folder_children()?;
+    if !children.iter().any(|c| c == FOLDER_ID) {
+        children.push(FOLDER_ID.to_string());
+        gsettings(&[
+            "set",
+            SCHEMA,
+            "folder-children",
+            &format_string_array(&children),
+        ])?;
+    }
+    let path = folder_path();
+    gsettings(&["set", &path, "name", &quote(FOLDER_NAME)])?;
+    gsettings(&["set", &path, "apps", &format_string_array(desktop_ids)])?;
+    Ok(())
+}
+
+/// Drops the folder from the grid and resets its keys. Missing folders are fine.
+pub fn remove_folder() -> Result<()> {
+    let children = folder_children()?;
+    if children.iter().any(|c| c == FOLDER_ID) {
+        let remaining: Vec<String> = children.into_iter().filter(|c| c != FOLDER_ID).collect();
+        gsettings(&[
+            "set",
+            SCHEMA,
+            "folder-children",
+            &format_string_array(&remaining),
+        ])?;
+    }
+    let path = folder_path();
+    gsettings(&["reset", &path, "apps"])?;
+    gsettings(&["reset", &path, "name"])?;
+    Ok(())
+}
+
+/// Whether the grid has steamer's folder.
+pub fn has_folder() -> Result<bool> {
+    Ok(folder_children()?.iter().any(|c| c == FOLDER_ID))
+}
+
+/// Swaps renamed desktop IDs in the dash's favorites, so pinned entries stay pinned. Other
+/// favorites and the order are kept.
+pub fn rename_favorites(renamed: &[(String, String)]) -> Result<()> {
+    let favorites = parse_string_array(gsettings(&["get", SHELL_SCHEMA, "favorite-apps"])?.trim())?;
+    let updated: Vec<String> = favorites
+        .iter()
+        .map(|id| match renamed.iter().find(|(old, _)| old == id) {
+            Some((_, new)) => new.clone(),
+            None => id.clone(),
+        })
+        .collect();
+    if updated != favorites {
+        gsettings(&[
+            "set",
+            SHELL_SCHEMA,
+            "favorite-apps",
+            &format_string_array(&updated),
+        ])?;
+    }
+    Ok(())
+}
+
+fn folder_path() -> String {
+    format!(
+        "{}:/org/gnome/desktop/app-folders/folders/{}/",
+        FOLDER_SCHEMA, FOLDER_ID
+    )
+}
+
+fn folder_children() -> Result<Vec<String>> {
+    let output = gsettings(&["get", SCHEMA, "folder-children"])?;
+    parse_string_array(output.trim())
+}
+
+fn gsettings(args: &[&str]) -> Result<String> {
+    let output = Command::new("gsettings")
+        .args(args)
+        .output()
+        .context("Could not run gsettings, is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "gsettings {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the GVariant text `gsettings get` prints for an `as`, e.g. `['a', "b's"]` or `@as []`.
+fn parse_string_array(text: &str) -> Result<Vec<String>> {
+    let text = text.strip_prefix("@as").unwrap_or(text).trim();
+    let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) else {
+        bail!("Unexpected gsettings value {:?}", text);
+    };
+
+    let mut values = Vec::new();
+    let mut chars = inner.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+        let Some(quote) = chars.next() else {
+            return Ok(values);
+        };
+        if quote != '\'' && quote != '"' {
+            bail!("Unexpected gsettings value {:?}", text);
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next() {
+                Some('\\') => value.extend(chars.next()),
+                Some(c) if c == quote => break,
+                Some(c) => value.push(c),
+                None => bail!("Unterminated string in gsettings value {:?}", text),
+            }
+        }
+        values.push(value);
+    }
+}
+
+fn format_string_array(values: &[String]) -> String {
+    let quoted: Vec<String> = values.iter().map(|v| quote(v)).collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_arrays_round_trip() {
+        let values = vec![
+            "steam-620.desktop".to_string(),
+            "it's \\ odd.desktop".to_string(),
+        ];
+        let text = format_string_array(&values);
+        assert_eq!(text, r"['steam-620.desktop', 'it\'s \\ odd.desktop']");
+        assert_eq!(parse_string_array(&text).unwrap(), values);
+    }
+
+    #[test]
+    fn reads_what_gsettings_prints() {
+        assert!(parse_string_array("@as []").unwrap().is_empty());
+        assert_eq!(
+            parse_string_array(r#"['Utilities', "Bob's Games", 'steamer']"#).unwrap(),
+            ["Utilities", "Bob's Games", "steamer"]
+        );
+        assert!(parse_string_array("['unterminated]").is_err());
+        assert!(parse_string_array("Utilities").is_err());
+    }
+}
@@ -0,0 +1,481 @@
+use crate::report::Reporter;
+use clap::ValueEnum;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Icon name used when nothing suitable is cached for a game.
+pub const FALLBACK_ICON: &str = "steam";
+
+/// Where an entry's icon can come from, asked in the order `--icon-sources` lists them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IconSource {
+    /// [`CustomIcons`], picked by hand for steamer.
+    Custom,
+    /// The icon in Steam's librarycache, the one fetched for a game that isn't installed, or
+    /// the one picked in the client for a non-Steam shortcut.
+    Steam,
+    /// [`GridIcons`], art set by hand in the Steam client for a non-Steam shortcut.
+    Grid,
+}
+
+/// One place icons come from.
+pub trait IconProvider {
+    /// The icon file for `appid`, if this source has one.
+    fn resolve(&self, appid: &str, reporter: &Reporter) -> Option<PathBuf>;
+}
+
+/// A lookup that depends on more than the appid, like the icon the metadata cache remembers
+/// for a game, made into a provider for one entry.
+impl<F: Fn(&str, &Reporter) -> Option<PathBuf>> IconProvider for F {
+    fn resolve(&self, appid: &str, reporter: &Reporter) -> Option<PathBuf> {
+        self(appid, reporter)
+    }
+}
+
+/// The providers of an entry's icon in the order they're asked, the first icon found wins.
+pub struct IconChain<'a> {
+    providers: Vec<(IconSource, &'a dyn IconProvider)>,
+}
+
+impl<'a> IconChain<'a> {
+    /// A chain asking the sources in `order`, through the provider `provider` gives for each.
+    /// Sources it gives none for are passed over.
+    pub fn new(
+        order: &[IconSource],
+        mut provider: impl FnMut(IconSource) -> Option<&'a dyn IconProvider>,
+    ) -> Self {
+        let providers = order
+            .iter()
+            .filter_map(|&source| Some((source, provider(source)?)))
+            .collect();
+        Self { providers }
+    }
+
+    /// The first icon found, with the source it came from.
+    pub fn resolve(&self, appid: &str, reporter: &Reporter) -> Option<(IconSource, PathBuf)> {
+        self.providers
+            .iter()
+            .find_map(|(source, provider)| Some((*source, provider.resolve(appid, reporter)?)))
+    }
+
+    /// The `Icon=` path for `appid`, or [`FALLBACK_ICON`] when no source has one.
+    pub fn icon_path(&self, appid: &str, reporter: &Reporter) -> String {
+        match self.resolve(appid, reporter) {
+            Some((_, path)) => path.to_string_lossy().into_owned(),
+            None => FALLBACK_ICON.to_string(),
+        }
+    }
+}
+
+/// Icons picked by hand, which win over Steam's: the `icon` of a game's `[game.<appid>]` table
+/// in the config, or else `<appid>.png` in `~/.config/steamer/icons`.
+pub struct CustomIcons {
+    configured: HashMap<String, PathBuf>,
+    dir: Option<PathBuf>,
+}
+
+impl CustomIcons {
+    pub fn new(configured: Vec<(String, PathBuf)>) -> Self {
+        Self {
+            configured: configured.into_iter().collect(),
+            dir: dirs::config_dir().map(|dir| dir.join("steamer/icons")),
+        }
+    }
+
+    /// The custom icon for a game. One set in the config that isn't there is warned about,
+    /// and the game gets the icon it would have had without it.
+    pub fn find(&self, appid: &str, reporter: &Reporter) -> Option<PathBuf> {
+        if let Some(path) = self.configured.get(appid) {
+            if path.is_file() {
+                return Some(path.clone());
+            }
+            reporter.warn(format!(
+                "The icon {:?} set for app {} doesn't exist, ignoring it",
+                path, appid
+            ));
+        }
+        let path = self.dir.as_ref()?.join(format!("{}.png", appid));
+        path.is_file().then_some(path)
+    }
+}
+
+impl IconProvider for CustomIcons {
+    fn resolve(&self, appid: &str, reporter: &Reporter) -> Option<PathBuf> {
+        self.find(appid, reporter)
+    }
+}
+
+/// Art set by hand in the Steam client, which it keeps in `userdata/<account>/config/grid` as
+/// `<appid>.png` for the wide grid capsule, `<appid>p.png` for the tall one, `<appid>_hero.png`,
+/// `<appid>_logo.png` and `<appid>_icon.png`, each also as .jpg. Only the square `_icon` fits
+/// an `Icon=`. It's only looked up for non-Steam shortcuts, which Steam caches no art for.
+/// Accounts are tried in the order of their IDs.
+pub struct GridIcons {
+    dirs: Vec<PathBuf>,
+}
+
+impl GridIcons {
+    pub fn new(steam_root: &Path) -> Self {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(steam_root.join("userdata"))
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|account| account.path().join("config/grid"))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        dirs.sort();
+        Self { dirs }
+    }
+
+    pub fn find(&self, appid: &str) -> Option<PathBuf> {
+        self.dirs
+            .iter()
+            .flat_map(|dir| {
+                ["png", "jpg"].map(|extension| dir.join(format!("{}_icon.{}", appid, extension)))
+            })
+            .find(|path| is_complete_image(path))
+    }
+}
+
+impl IconProvider for GridIcons {
+    fn resolve(&self, appid: &str, _: &Reporter) -> Option<PathBuf> {
+        self.find(appid)
+    }
+}
+
+/// Every candidate icon in `librarycache`, read in a single pass the first time a game asks.
+/// Slow mounts pay for one walk of the cache instead of a directory read per game.
+pub struct IconIndex {
+    dir: PathBuf,
+    index: OnceCell<HashMap<String, Vec<PathBuf>>>,
+}
+
+impl IconIndex {
+    pub fn new(icon_cache_dir: &Path) -> Self {
+        Self {
+            dir: icon_cache_dir.to_path_buf(),
+            index: OnceCell::new(),
+        }
+    }
+
+    /// Finds the cached icon for a game.
+    ///
+    /// idk how steam does the hash soooo this is good enough: the icon is a 40 char hash + .jpg
+    /// in `librarycache/<appid>/`, but the logo and hero art can be named the same way. When
+    /// several files fit, the one that sorts first by filename wins, so the pick is at least the
+    /// same on every run. Older clients kept a flat `<appid>_icon.jpg`, used when there's no hash.
+    /// Files an interrupted download left empty or cut short are passed over, with a warning
+    /// when they were all the game had.
+    pub fn find(&self, appid: &str, reporter: &Reporter) -> Option<&Path> {
+        let index = self.index.get_or_init(|| build_index(&self.dir));
+        let candidates = index.get(appid)?;
+        let found = candidates.iter().find(|path| is_complete_image(path));
+        if found.is_none() {
+            reporter.warn(format!(
+                "The icon Steam cached for app {} is empty or damaged, using the fallback icon: {:?}",
+                appid, candidates[0]
+            ));
+        }
+        found.map(PathBuf::as_path)
+    }
+}
+
+impl IconProvider for IconIndex {
+    fn resolve(&self, appid: &str, reporter: &Reporter) -> Option<PathBuf> {
+        self.find(appid, reporter).map(Path::to_path_buf)
+    }
+}
+
+/// Whether `path` is a whole JPEG or PNG: the format's signature at the start, and its end
+/// marker near the end, which is what a download that stopped part way lacks.
+pub fn is_complete_image(path: &Path) -> bool {
+    let Ok(data) = fs::read(path) else {
+        return false;
+    };
+    let tail = &data[data.len().saturating_sub(64)..];
+    let ends_with = |marker: &[u8]| tail.windows(marker.len()).any(|w| w == marker);
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        ends_with(&[0xff, 0xd9])
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        ends_with(b"IEND")
+    } else {
+        false
+    }
+}
+
+fn build_index(dir: &Path) -> HashMap<String, Vec<PathBuf>> {
+    let mut index: HashMap<String, Vec<PathBuf>> = HashMap::new();
+    let mut flat = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return index;
+    };
+
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            if !name.bytes().all(|b| b.is_ascii_digit()) {
+                continue;
+            }
+            let Ok(files) = fs::read_dir(&path) else {
+                continue;
+            };
+            let mut candidates: Vec<PathBuf> = files
+                .filter_map(Result::ok)
+                .map(|file| file.path())
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|n| n.to_str())
+                        .map(|s| s.len() == 44 && s.ends_with(".jpg"))
+                        .unwrap_or(false)
+                })
+                .collect();
+            candidates.sort();
+            if !candidates.is_empty() {
+                index.insert(name.to_string(), candidates);
+            }
+        } else if let Some(appid) = name.strip_suffix("_icon.jpg") {
+            flat.push((appid.to_string(), path));
+        }
+    }
+
+    for (appid, path) in flat {
+        index.entry(appid).or_default().push(path);
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const PNG: &[u8] = include_bytes!("../assets/proton-badge.png");
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-icons-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A 40 character hash named `.jpg`, like the ones Steam caches, starting with `first`.
+    fn hashed(first: char) -> String {
+        format!("{}{}.jpg", first, "0".repeat(39))
+    }
+
+    /// A provider that has an icon named after itself for the appids listed.
+    fn provider(name: &'static str, appids: &'static [&'static str]) -> impl IconProvider {
+        move |appid: &str, _: &Reporter| {
+            appids
+                .contains(&appid)
+                .then(|| PathBuf::from(format!("/{}/{}.png", name, appid)))
+        }
+    }
+
+    #[test]
+    fn icon_sources_are_asked_in_order() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let custom = provider("custom", &["620"]);
+        let steam = provider("steam", &["620", "400"]);
+        let grid = provider("grid", &["620", "400", "570"]);
+        let providers = |source| match source {
+            IconSource::Custom => Some(&custom as &dyn IconProvider),
+            IconSource::Steam => Some(&steam as &dyn IconProvider),
+            IconSource::Grid => Some(&grid as &dyn IconProvider),
+        };
+
+        let chain = IconChain::new(
+            &[IconSource::Custom, IconSource::Steam, IconSource::Grid],
+            providers,
+        );
+        assert_eq!(
+            chain.resolve("620", &reporter),
+            Some((IconSource::Custom, PathBuf::from("/custom/620.png")))
+        );
+        // a source without an icon passes the game on to the next
+        assert_eq!(
+            chain.resolve("400", &reporter),
+            Some((IconSource::Steam, PathBuf::from("/steam/400.png")))
+        );
+        assert_eq!(chain.icon_path("570", &reporter), "/grid/570.png");
+        assert_eq!(chain.resolve("10", &reporter), None);
+        assert_eq!(chain.icon_path("10", &reporter), FALLBACK_ICON);
+
+        let chain = IconChain::new(&[IconSource::Grid, IconSource::Custom], providers);
+        assert_eq!(chain.icon_path("620", &reporter), "/grid/620.png");
+        // sources left out of --icon-sources are never asked
+        let chain = IconChain::new(&[IconSource::Custom], providers);
+        assert_eq!(chain.icon_path("400", &reporter), FALLBACK_ICON);
+    }
+
+    #[test]
+    fn sources_without_a_provider_are_passed_over() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let grid = provider("grid", &["620"]);
+        let chain = IconChain::new(
+            &[IconSource::Steam, IconSource::Grid],
+            |source| match source {
+                IconSource::Grid => Some(&grid as &dyn IconProvider),
+                _ => None,
+            },
+        );
+        assert_eq!(
+            chain.resolve("620", &reporter),
+            Some((IconSource::Grid, PathBuf::from("/grid/620.png")))
+        );
+    }
+
+    #[test]
+    fn truncated_images_are_incomplete() {
+        let path = std::env::temp_dir().join(format!("steamer-icon-test-{}", std::process::id()));
+        let complete = |data: &[u8]| {
+            fs::write(&path, data).unwrap();
+            is_complete_image(&path)
+        };
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0];
+        jpeg.extend([0x42; 200]);
+        jpeg.extend([0xff, 0xd9]);
+
+        assert!(complete(PNG));
+        assert!(complete(&jpeg));
+        assert!(!complete(&PNG[..PNG.len() / 2]));
+        assert!(!complete(&PNG[..PNG.len() - 8]));
+        assert!(!complete(&jpeg[..jpeg.len() - 1]));
+        assert!(!complete(&jpeg[..100]));
+        assert!(!complete(b""));
+        assert!(!complete(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"));
+        fs::remove_file(&path).unwrap();
+        assert!(!is_complete_image(&path));
+    }
+
+    #[test]
+    fn tying_icons_go_by_filename() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let cache = temp_dir();
+        let game = cache.join("620");
+        fs::create_dir_all(&game).unwrap();
+        for name in [
+            hashed('c'),
+            hashed('a'),
+            hashed('b'),
+            "logo.jpg".to_string(),
+        ] {
+            fs::write(game.join(name), PNG).unwrap();
+        }
+
+        let index = IconIndex::new(&cache);
+        assert_eq!(
+            index.find("620", &reporter),
+            Some(game.join(hashed('a')).as_path())
+        );
+        assert_eq!(index.find("400", &reporter), None);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn cut_short_icons_are_passed_over() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let cache = temp_dir();
+        for appid in ["620", "400"] {
+            fs::create_dir_all(cache.join(appid)).unwrap();
+        }
+        // 'a' sorts first, but the download stopped half way
+        fs::write(cache.join("620").join(hashed('a')), &PNG[..PNG.len() / 2]).unwrap();
+        fs::write(cache.join("620").join(hashed('b')), PNG).unwrap();
+        fs::write(cache.join("400").join(hashed('a')), b"").unwrap();
+
+        let index = IconIndex::new(&cache);
+        assert_eq!(
+            index.find("620", &reporter),
+            Some(cache.join("620").join(hashed('b')).as_path())
+        );
+        assert_eq!(index.find("400", &reporter), None);
+        fs::remove_dir_all(&cache).unwrap();
+    }
+
+    #[test]
+    fn custom_icons_win_over_cached_art() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let dir = temp_dir();
+        for appid in ["620", "400", "570"] {
+            fs::create_dir_all(dir.join("cache").join(appid)).unwrap();
+            fs::write(dir.join("cache").join(appid).join(hashed('a')), PNG).unwrap();
+        }
+        fs::create_dir_all(dir.join("icons")).unwrap();
+        fs::write(dir.join("icons/620.png"), PNG).unwrap();
+        fs::write(dir.join("mine.png"), PNG).unwrap();
+
+        let custom = CustomIcons {
+            configured: [
+                ("570".to_string(), dir.join("mine.png")),
+                ("400".to_string(), dir.join("missing.png")),
+            ]
+            .into_iter()
+            .collect(),
+            dir: Some(dir.join("icons")),
+        };
+        let index = IconIndex::new(&dir.join("cache"));
+        let chain = IconChain::new(
+            &[IconSource::Custom, IconSource::Steam],
+            |source| match source {
+                IconSource::Custom => Some(&custom as &dyn IconProvider),
+                IconSource::Steam => Some(&index as &dyn IconProvider),
+                IconSource::Grid => None,
+            },
+        );
+        assert_eq!(
+            chain.resolve("620", &reporter),
+            Some((IconSource::Custom, dir.join("icons/620.png")))
+        );
+        assert_eq!(
+            chain.resolve("570", &reporter),
+            Some((IconSource::Custom, dir.join("mine.png")))
+        );
+        // a configured icon that isn't there falls back to the cached one
+        assert_eq!(
+            chain.resolve("400", &reporter),
+            Some((IconSource::Steam, dir.join("cache/400").join(hashed('a'))))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn grid_art_is_only_the_square_icon() {
+        let dir = temp_dir();
+        let first = dir.join("userdata/1001/config/grid");
+        let second = dir.join("userdata/1002/config/grid");
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        for name in [
+            "3456789012.png",
+            "3456789012p.jpg",
+            "3456789012_hero.png",
+            "3456789012_logo.png",
+        ] {
+            fs::write(first.join(name), PNG).unwrap();
+        }
+        fs::write(second.join("3456789012_icon.jpg"), PNG).unwrap();
+        fs::write(first.join("2999999999p.png"), PNG).unwrap();
+        // cut short, as when the client was still saving it
+        fs::write(first.join("2999999999_icon.png"), &PNG[..PNG.len() / 2]).unwrap();
+
+        let grid = GridIcons::new(&dir);
+        assert_eq!(
+            grid.find("3456789012"),
+            Some(second.join("3456789012_icon.jpg"))
+        );
+        assert_eq!(grid.find("2999999999"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
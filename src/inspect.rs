@@ -0,0 +1,534 @@
+//! Read-only subcommands: `list`, `doctor` and `stats`.
+
+use crate::appinfo::AppInfoSource;
+use crate::archive;
+use crate::cli::{Args, ListArgs, ParseVdfArgs, RenderArgs};
+use crate::config::Config;
+use crate::desktop::{self, ExecMode};
+use crate::disk;
+use crate::icons::{CustomIcons, IconChain, IconIndex, IconProvider, IconSource};
+use crate::leftovers::{self, OrphanedInstall, StalePrefix};
+use crate::output;
+use crate::paths::{self, Paths};
+use crate::report::Reporter;
+use crate::steam::{self, Library};
+use crate::vdf;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub fn list(args: &Args, list: &ListArgs) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, true);
+    let paths = Paths::resolve(args, true, &reporter)?;
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(!args.no_appinfo);
+    let (mut libraries, _) = steam::scan_libraries(&paths, &appinfo, None, &reporter)?;
+
+    if list.orphaned || list.stale_prefixes {
+        if list.orphaned {
+            print_orphans(&leftovers::find_orphaned_installs(&libraries));
+        }
+        if list.stale_prefixes {
+            let appinfo = appinfo.get(&reporter);
+            print_stale_prefixes(&leftovers::find_stale_prefixes(&libraries, appinfo));
+        }
+        return Ok(());
+    }
+
+    let skip_rules = args.skip_rules()?.ignore_files(&paths.ignore_files)?;
+    if (args.skip_demos || !args.include_playtests)
+        && let Some(info) = appinfo.get(&reporter)
+    {
+        steam::add_appinfo_details(&mut libraries, info);
+    }
+    for library in &libraries {
+        println!(
+            "{:?}{}",
+            library.path,
+            if library.extra { " (extra)" } else { "" }
+        );
+        for game in &library.games {
+            match skip_rules.check(game) {
+                Some(reason) => {
+                    println!("  {:>8}  {} (skipped: {})", game.appid, game.name, reason)
+                }
+                None => println!("  {:>8}  {}", game.appid, game.name),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--count-only`: manifests and skip rules only. Nothing reads appinfo or the icon cache, so
+/// demos and playtests are recognised by name alone, and apps without a manifest are counted
+/// under their placeholder names.
+pub fn count(args: &Args) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, true);
+    let paths = Paths::resolve(args, true, &reporter)?;
+    let skip_rules = args.skip_rules()?.ignore_files(&paths.ignore_files)?;
+
+    let mut seen_appids = HashSet::new();
+    let (mut total_games, mut total_skipped, mut libraries) = (0, 0, 0);
+    let (folders, _) = steam::read_library_folders(&paths, &reporter)?;
+    for folder in folders {
+        let Some(mut library) = steam::scan_library(&folder, None)? else {
+            continue;
+        };
+        steam::recover_unmatched_apps(&mut library, None);
+        let (mut games, mut skipped) = (0, 0);
+        for game in &library.games {
+            if skip_rules.check(game).is_some() || !seen_appids.insert(game.appid.clone()) {
+                skipped += 1;
+            } else {
+                games += 1;
+            }
+        }
+        println!(
+            "{:?}{}",
+            library.path,
+            if library.extra { " (extra)" } else { "" }
+        );
+        println!("  Games:   {}", games);
+        println!("  Skipped: {}", skipped);
+        total_games += games;
+        total_skipped += skipped;
+        libraries += 1;
+    }
+    println!(
+        "Total: {} games, {} skipped, in {} libraries",
+        total_games, total_skipped, libraries
+    );
+    Ok(())
+}
+
+/// `steamer render`: one manifest through the same parsing, skip rules and rendering a sync
+/// would use, for bug reports. Icons are only looked up when `--steam-path` says where.
+pub fn render(args: &Args, render: &RenderArgs) -> Result<()> {
+    let game = steam::parse_app_manifest(&render.manifest)
+        .with_context(|| format!("Could not parse {:?}", render.manifest))?;
+    let config = Config::load_default(args.profile.as_deref())?;
+    let entry_options = args.entry_options(&config, paths::find_steam_binary())?;
+
+    println!("Parsed {:?}", render.manifest);
+    println!("  AppID:       {}", game.appid);
+    println!("  Name:        {}", game.name);
+    println!(
+        "  Install dir: {}",
+        game.installdir.as_deref().unwrap_or("(none)")
+    );
+    match game.size_on_disk {
+        Some(size) => println!("  Size:        {}", disk::format_size(size)),
+        None => println!("  Size:        (unknown)"),
+    }
+    match args.skip_rules()?.check(&game) {
+        Some(reason) => println!("Skipped: {}", reason),
+        None => println!("Skipped: no"),
+    }
+
+    let reporter = Reporter::new(args.log_format, true);
+    let custom_icons = CustomIcons::new(config.custom_icons()?);
+    let paths = match args.steam_path {
+        Some(_) => Some(Paths::resolve(args, true, &reporter)?),
+        None => None,
+    };
+    let icon_index = paths
+        .as_ref()
+        .map(|paths| IconIndex::new(&paths.icon_cache));
+    let icon = IconChain::new(&args.icon_sources, |source| match source {
+        IconSource::Custom => Some(&custom_icons as &dyn IconProvider),
+        IconSource::Steam => icon_index.as_ref().map(|index| index as &dyn IconProvider),
+        IconSource::Grid => None,
+    })
+    .icon_path(&game.appid, &reporter);
+
+    println!();
+    println!("# {}", output::desktop_filename(&game));
+    print!("{}", desktop::render_entry(&game, &icon, &entry_options));
+    Ok(())
+}
+
+/// `steamer parse-vdf`: the VDF parsers on their own, for scripts and for debugging them.
+pub fn parse_vdf(parse: &ParseVdfArgs) -> Result<()> {
+    let data = fs::read(&parse.file).with_context(|| format!("Could not read {:?}", parse.file))?;
+    let root = if parse.binary || data.first() == Some(&0) {
+        let (root, _) = vdf::parse_binary(&data, None)
+            .with_context(|| format!("Could not parse {:?} as binary VDF", parse.file))?;
+        root
+    } else {
+        vdf::parse_text(&String::from_utf8_lossy(&data))
+            .with_context(|| format!("Could not parse {:?}", parse.file))?
+    };
+
+    let node = match parse.path {
+        Some(ref path) => root
+            .path(path)
+            .with_context(|| format!("{:?} has no key path {:?}", parse.file, path))?,
+        None => &root,
+    };
+    // a single value prints bare, so `$(steamer parse-vdf --path ...)` needs no JSON tooling
+    match node.to_text() {
+        Some(text) if parse.path.is_some() => println!("{}", text),
+        _ => println!("{}", node.to_json().to_pretty()),
+    }
+    Ok(())
+}
+
+/// `--from-archive`: what a library backup holds, without extracting it or writing anything.
+pub fn archive(args: &Args, path: &Path) -> Result<()> {
+    let skip_rules = args.skip_rules()?;
+
+    let manifests = archive::read_manifests(path)?;
+    println!("{:?} contains {} app manifests:", path, manifests.len());
+    let mut games = 0;
+    for manifest in &manifests {
+        let game = match steam::parse_manifest_text(&String::from_utf8_lossy(&manifest.content)) {
+            Ok(game) => game,
+            Err(e) => {
+                eprintln!("Warning: Could not parse {}: {:#}", manifest.name, e);
+                continue;
+            }
+        };
+        match skip_rules.check(&game) {
+            Some(reason) => {
+                println!("  {:>8}  {} (skipped: {})", game.appid, game.name, reason)
+            }
+            None => {
+                games += 1;
+                println!("  {:>8}  {}", game.appid, game.name)
+            }
+        }
+    }
+    println!(
+        "Dry run complete. {} launchers would be created once the library is restored.",
+        games
+    );
+    Ok(())
+}
+
+fn print_orphans(orphans: &[OrphanedInstall]) {
+    if orphans.is_empty() {
+        println!("No orphaned install directories found.");
+        return;
+    }
+
+    println!("Install directories with no manifest in any library:");
+    for orphan in orphans {
+        println!(
+            "  {:>10}  {:?}",
+            disk::format_size(orphan.size()),
+            orphan.path
+        );
+    }
+    let total: u64 = orphans.iter().map(OrphanedInstall::size).sum();
+    println!(
+        "{} directories, {} in total. Nothing was deleted.",
+        orphans.len(),
+        disk::format_size(total)
+    );
+}
+
+fn print_stale_prefixes(prefixes: &[StalePrefix]) {
+    if prefixes.is_empty() {
+        println!("No stale compatdata prefixes found.");
+        return;
+    }
+
+    println!("Proton prefixes for games that are no longer installed:");
+    for prefix in prefixes {
+        println!(
+            "  {:>10}  {:>8}  {}",
+            disk::format_size(prefix.size()),
+            prefix.appid,
+            prefix.name.as_deref().unwrap_or("(unknown)")
+        );
+    }
+    let total: u64 = prefixes.iter().map(StalePrefix::size).sum();
+    println!(
+        "{} prefixes, {} reclaimable. Nothing was deleted.",
+        prefixes.len(),
+        disk::format_size(total)
+    );
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// The results of `doctor`, printed as they come unless a debug bundle collects them.
+pub struct Checks {
+    print: bool,
+    pub lines: Vec<String>,
+}
+
+impl Checks {
+    pub fn collect() -> Self {
+        Self {
+            print: false,
+            lines: Vec::new(),
+        }
+    }
+
+    fn check(&mut self, status: Status, message: impl AsRef<str>) -> Status {
+        let label = match status {
+            Status::Ok => "[ OK ]",
+            Status::Warn => "[WARN]",
+            Status::Fail => "[FAIL]",
+        };
+        let line = format!("{} {}", label, message.as_ref());
+        if self.print {
+            println!("{}", line);
+        }
+        self.lines.push(line);
+        status
+    }
+}
+
+/// Runs every check and exits non-zero if any of them failed outright.
+pub fn doctor(args: &Args) -> Result<()> {
+    let mut checks = Checks {
+        print: true,
+        lines: Vec::new(),
+    };
+    if run_checks(args, &mut checks)? == Status::Fail {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Every `doctor` check into `checks`, returning the worst result.
+pub fn run_checks(args: &Args, checks: &mut Checks) -> Result<Status> {
+    let reporter = Reporter::new(args.log_format, true);
+    let paths = Paths::resolve(args, true, &reporter)?;
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(!args.no_appinfo);
+    let mut worst = Status::Ok;
+
+    worst = worst.max(if paths.steam_root.is_dir() {
+        checks.check(
+            Status::Ok,
+            format!("Steam root found at {:?}", paths.steam_root),
+        )
+    } else {
+        checks.check(
+            Status::Fail,
+            format!("Steam root {:?} is not a directory", paths.steam_root),
+        )
+    });
+
+    let libraries = match steam::parse_library_folders(&paths.library_vdf) {
+        Ok(folders) => {
+            worst = worst.max(if folders.is_empty() {
+                checks.check(
+                    Status::Warn,
+                    "libraryfolders.vdf lists no libraries, runs only scan the Steam root",
+                )
+            } else {
+                checks.check(
+                    Status::Ok,
+                    format!("libraryfolders.vdf lists {} libraries", folders.len()),
+                )
+            });
+            let mut libraries = Vec::new();
+            for folder in &folders {
+                match steam::scan_library(folder, None)? {
+                    Some(mut library) => {
+                        worst = worst.max(checks.check(
+                            Status::Ok,
+                            format!(
+                                "Library {:?} has {} manifests",
+                                library.path,
+                                library.games.len()
+                            ),
+                        ));
+                        for (path, error) in &library.failed {
+                            worst = worst.max(checks.check(
+                                Status::Warn,
+                                format!("Could not parse {:?}: {}", path, error),
+                            ));
+                        }
+                        if !library.unmatched_apps.is_empty() {
+                            steam::recover_unmatched_apps(&mut library, appinfo.get(&reporter));
+                        }
+                        libraries.push(library);
+                    }
+                    None => {
+                        worst = worst.max(checks.check(
+                            Status::Warn,
+                            format!(
+                                "Library {:?} has no steamapps folder, is the drive mounted?",
+                                folder.path
+                            ),
+                        ))
+                    }
+                }
+            }
+            libraries
+        }
+        Err(e) => {
+            worst =
+                worst.max(checks.check(
+                    Status::Fail,
+                    paths::library_vdf_problem(&paths).unwrap_or_else(|| {
+                        format!("Could not read {:?}: {:#}", paths.library_vdf, e)
+                    }),
+                ));
+            Vec::new()
+        }
+    };
+
+    worst = worst.max(match appinfo.get(&reporter) {
+        _ if args.no_appinfo => {
+            checks.check(Status::Ok, "appinfo.vdf isn't read with --no-appinfo")
+        }
+        Some(_) => checks.check(Status::Ok, "appinfo.vdf is readable"),
+        None => checks.check(
+            Status::Warn,
+            "appinfo.vdf could not be read, games without a manifest will get placeholder names",
+        ),
+    });
+
+    worst = worst.max(match fs::metadata(&paths.app_dir) {
+        Ok(meta) if meta.permissions().readonly() => checks.check(
+            Status::Fail,
+            format!("Applications directory {:?} is read-only", paths.app_dir),
+        ),
+        Ok(_) => checks.check(
+            Status::Ok,
+            format!("Applications directory {:?} exists", paths.app_dir),
+        ),
+        Err(_) => checks.check(
+            Status::Warn,
+            format!(
+                "Applications directory {:?} doesn't exist yet, it will be created",
+                paths.app_dir
+            ),
+        ),
+    });
+
+    if args.exec_mode == ExecMode::Steam {
+        worst = worst.max(match paths::find_steam_binary() {
+            Some(path) => checks.check(Status::Ok, format!("steam executable found at {:?}", path)),
+            None => checks.check(
+                Status::Fail,
+                "No steam executable on PATH or in the usual install locations, launchers won't start",
+            ),
+        });
+    }
+
+    if args.systemd_scope {
+        worst = worst.max(match paths::find_executable("systemd-run") {
+            Some(path) => checks.check(Status::Ok, format!("systemd-run found at {:?}", path)),
+            None => checks.check(
+                Status::Fail,
+                "systemd-run isn't on the PATH, --systemd-scope entries won't launch",
+            ),
+        });
+    }
+
+    let orphans = leftovers::find_orphaned_installs(&libraries);
+    worst = worst.max(if orphans.is_empty() {
+        checks.check(Status::Ok, "No orphaned install directories")
+    } else {
+        let total: u64 = orphans.iter().map(OrphanedInstall::size).sum();
+        checks.check(
+            Status::Warn,
+            format!(
+                "{} orphaned install directories use {} (see `steamer list --orphaned`)",
+                orphans.len(),
+                disk::format_size(total)
+            ),
+        )
+    });
+
+    let prefixes = leftovers::find_stale_prefixes(&libraries, appinfo.get(&reporter));
+    worst = worst.max(if prefixes.is_empty() {
+        checks.check(Status::Ok, "No stale compatdata prefixes")
+    } else {
+        let total: u64 = prefixes.iter().map(StalePrefix::size).sum();
+        checks.check(
+            Status::Warn,
+            format!(
+                "{} stale compatdata prefixes use {} (see `steamer list --stale-prefixes`)",
+                prefixes.len(),
+                disk::format_size(total)
+            ),
+        )
+    });
+
+    Ok(worst)
+}
+
+pub fn stats(args: &Args) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, true);
+    let paths = Paths::resolve(args, true, &reporter)?;
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(!args.no_appinfo);
+    let (libraries, _) = steam::scan_libraries(&paths, &appinfo, None, &reporter)?;
+    let orphans = leftovers::find_orphaned_installs(&libraries);
+    let prefixes = leftovers::find_stale_prefixes(&libraries, appinfo.get(&reporter));
+
+    let mut total_games = 0;
+    let mut total_size = 0;
+    for library in &libraries {
+        let size = installed_size(library);
+        let lib_orphans: Vec<_> = orphans
+            .iter()
+            .filter(|o| o.library == library.path)
+            .collect();
+        let lib_prefixes: Vec<_> = prefixes
+            .iter()
+            .filter(|p| p.library == library.path)
+            .collect();
+
+        println!(
+            "{:?}{}",
+            library.path,
+            if library.extra { " (extra)" } else { "" }
+        );
+        println!("  Games:     {}", library.games.len());
+        println!("  Installed: {}", disk::format_size(size));
+        if !lib_orphans.is_empty() {
+            println!(
+                "  Orphaned:  {} directories, {}",
+                lib_orphans.len(),
+                disk::format_size(lib_orphans.iter().map(|o| o.size()).sum())
+            );
+        }
+        if !lib_prefixes.is_empty() {
+            println!(
+                "  Stale prefixes: {}, {}",
+                lib_prefixes.len(),
+                disk::format_size(lib_prefixes.iter().map(|p| p.size()).sum())
+            );
+        }
+        total_games += library.games.len();
+        total_size += size;
+    }
+
+    println!(
+        "Total: {} games in {} libraries, {}",
+        total_games,
+        libraries.len(),
+        disk::format_size(total_size)
+    );
+    if !orphans.is_empty() {
+        println!(
+            "Orphaned install directories: {}, {}",
+            orphans.len(),
+            disk::format_size(orphans.iter().map(OrphanedInstall::size).sum())
+        );
+    }
+    if !prefixes.is_empty() {
+        println!(
+            "Stale compatdata prefixes: {}, {}",
+            prefixes.len(),
+            disk::format_size(prefixes.iter().map(StalePrefix::size).sum())
+        );
+    }
+    Ok(())
+}
+
+fn installed_size(library: &Library) -> u64 {
+    library.games.iter().filter_map(|g| g.size_on_disk).sum()
+}
@@ -0,0 +1,201 @@
+//! `--track-launches`: entries start games through `steamer launch-wrapper`, which appends one
+//! line to `launches.jsonl` in steamer's data directory and then replaces itself with the real
+//! launch command. `steamer history` reads the log back.
+
+use crate::cli::{Args, HistoryArgs, LaunchWrapperArgs};
+use crate::json::{self, Value};
+use crate::paths::Paths;
+use crate::report::Reporter;
+use crate::steam;
+use anyhow::{Context, Result, bail};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub fn log_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("steamer/launches.jsonl"))
+}
+
+/// Records the launch, then execs the command. Logging is a single append and its errors only
+/// print a warning, the game starts either way.
+pub fn launch_wrapper(wrapper: &LaunchWrapperArgs) -> Result<()> {
+    if let Err(e) = record(&wrapper.appid) {
+        eprintln!("Warning: Could not record the launch: {:#}", e);
+    }
+    let Some((program, args)) = wrapper.command.split_first() else {
+        bail!("launch-wrapper needs a command to run after --");
+    };
+    let error = Command::new(program).args(args).exec();
+    Err(error).with_context(|| format!("Could not run {:?}", program))
+}
+
+fn record(appid: &str) -> Result<()> {
+    let path = log_path().context("Could not find the data directory")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let line = Value::object()
+        .with("time", time)
+        .with("appid", appid)
+        .to_compact()
+        + "\n";
+    // one write to a file opened for appending, so launches at the same moment don't interleave
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+struct Launch {
+    time: i64,
+    appid: String,
+}
+
+/// Games by most recent launch with their launch counts, or with `--weekly` the launches of
+/// each week, newest first.
+pub fn history(args: &Args, history: &HistoryArgs) -> Result<()> {
+    let path = log_path().context("Could not find the data directory")?;
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("No launches recorded yet, sync with --track-launches to start.");
+            return Ok(());
+        }
+        Err(e) => return Err(e).with_context(|| format!("Could not read {:?}", path)),
+    };
+    // a line cut short by a full disk shouldn't hide the rest of the history
+    let launches: Vec<Launch> = text
+        .lines()
+        .filter_map(|line| json::parse(line).ok())
+        .filter_map(|record| {
+            Some(Launch {
+                time: i64::try_from(record.get("time")?.as_u64()?).ok()?,
+                appid: record.get("appid")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+    if launches.is_empty() {
+        println!("No launches recorded yet, sync with --track-launches to start.");
+        return Ok(());
+    }
+    let names = installed_names(args);
+    let name = |appid: &str| {
+        names
+            .get(appid)
+            .cloned()
+            .unwrap_or_else(|| steam::placeholder_name(appid))
+    };
+
+    if history.weekly {
+        // keyed by the date, a DST change could put two launches' Mondays an hour apart
+        let mut weeks: Vec<(String, Vec<(String, usize)>)> = Vec::new();
+        for launch in &launches {
+            let week = local_time(week_start(launch.time))[..10].to_string();
+            let index = match weeks.iter().position(|(w, _)| *w == week) {
+                Some(index) => index,
+                None => {
+                    weeks.push((week, Vec::new()));
+                    weeks.len() - 1
+                }
+            };
+            let games = &mut weeks[index].1;
+            match games.iter_mut().find(|(appid, _)| *appid == launch.appid) {
+                Some((_, count)) => *count += 1,
+                None => games.push((launch.appid.clone(), 1)),
+            }
+        }
+        weeks.sort_by(|a, b| b.0.cmp(&a.0));
+        for (week, mut games) in weeks {
+            games.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            let total: usize = games.iter().map(|(_, count)| count).sum();
+            println!("Week of {}: {} launches", week, total);
+            for (appid, count) in games {
+                println!("  {:>4}  {} (AppID: {})", count, name(&appid), appid);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut games: HashMap<&str, (i64, usize)> = HashMap::new();
+    for launch in &launches {
+        let entry = games.entry(&launch.appid).or_insert((launch.time, 0));
+        entry.0 = entry.0.max(launch.time);
+        entry.1 += 1;
+    }
+    let mut games: Vec<(&str, (i64, usize))> = games.into_iter().collect();
+    games.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(b.0)));
+    println!(
+        "{} launches of {} games since {}",
+        launches.len(),
+        games.len(),
+        local_time(launches.iter().map(|l| l.time).min().unwrap_or_default())
+    );
+    for (appid, (last, count)) in games {
+        println!(
+            "  {}  {:>4}  {} (AppID: {})",
+            local_time(last),
+            count,
+            name(appid),
+            appid
+        );
+    }
+    Ok(())
+}
+
+/// Names of the installed games, to label the log with. The log outlives installs, so games
+/// that are gone, or a Steam folder that can't be read, just fall back to the appid.
+fn installed_names(args: &Args) -> HashMap<String, String> {
+    let mut names = HashMap::new();
+    let reporter = Reporter::new(args.log_format, true);
+    let Ok(paths) = Paths::resolve(args, true, &reporter) else {
+        return names;
+    };
+    let Ok((folders, _)) = steam::read_library_folders(&paths, &reporter) else {
+        return names;
+    };
+    for folder in folders {
+        if let Ok(Some(library)) = steam::scan_library(&folder, None) {
+            for game in library.games {
+                names.entry(game.appid).or_insert(game.name);
+            }
+        }
+    }
+    names
+}
+
+/// Midnight at the start of the Monday of the week `time` falls in, local time.
+fn week_start(time: i64) -> i64 {
+    let tm = local_tm(time);
+    let days_since_monday = (tm.tm_wday as i64 + 6) % 7;
+    let seconds_today = tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64;
+    time - days_since_monday * SECONDS_PER_DAY - seconds_today
+}
+
+/// `YYYY-MM-DD hh:mm` in local time.
+pub fn local_time(time: i64) -> String {
+    let tm = local_tm(time);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
+
+fn local_tm(time: i64) -> libc::tm {
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    let time = time as libc::time_t;
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        // shown as 1900-01-01, localtime only fails for times far outside any launch
+        tm.tm_mday = 1;
+    }
+    tm
+}
@@ -0,0 +1,206 @@
+//! Things Steam leaves behind on disk after games are gone. Report-only: nothing here deletes.
+
+use crate::appinfo::AppInfo;
+use crate::disk;
+use crate::steam::Library;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// A directory in `steamapps/common` that no manifest claims.
+pub struct OrphanedInstall {
+    pub library: PathBuf,
+    pub path: PathBuf,
+    size: OnceCell<u64>,
+}
+
+impl OrphanedInstall {
+    /// Walks the directory the first time it's asked, these can be large.
+    pub fn size(&self) -> u64 {
+        *self.size.get_or_init(|| disk::dir_size(&self.path))
+    }
+}
+
+/// Lists install directories with no manifest in any library. A game moved between drives keeps
+/// its manifest in one library only, so every library's `installdir`s count against every
+/// `common` folder. Names are compared case-insensitively since the two don't always agree.
+pub fn find_orphaned_installs(libraries: &[Library]) -> Vec<OrphanedInstall> {
+    let claimed: HashSet<String> = libraries
+        .iter()
+        .flat_map(|lib| &lib.games)
+        .filter_map(|game| game.installdir.as_ref())
+        .map(|dir| dir.to_lowercase())
+        .collect();
+
+    let mut orphans = Vec::new();
+    for library in libraries {
+        let Ok(entries) = fs::read_dir(library.path.join("steamapps/common")) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .filter(|entry| !claimed.contains(&entry.file_name().to_string_lossy().to_lowercase()))
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+
+        orphans.extend(paths.into_iter().map(|path| OrphanedInstall {
+            library: library.path.clone(),
+            path,
+            size: OnceCell::new(),
+        }));
+    }
+    orphans
+}
+
+/// A Proton prefix in `steamapps/compatdata` for an app that is no longer installed.
+pub struct StalePrefix {
+    pub library: PathBuf,
+    pub appid: String,
+    /// From appinfo when it still knows the app.
+    pub name: Option<String>,
+    pub path: PathBuf,
+    size: OnceCell<u64>,
+}
+
+impl StalePrefix {
+    pub fn size(&self) -> u64 {
+        *self.size.get_or_init(|| disk::dir_size(&self.path))
+    }
+}
+
+/// Lists compatdata prefixes whose appid has no manifest in any library. Only plain Steam appids
+/// count: `0` and `pfx` show up in some setups without belonging to a game, and non-Steam
+/// shortcuts get ids with the high bit set that never have a manifest.
+pub fn find_stale_prefixes(libraries: &[Library], appinfo: Option<&AppInfo>) -> Vec<StalePrefix> {
+    let installed: HashSet<&str> = libraries
+        .iter()
+        .flat_map(|lib| &lib.games)
+        .map(|game| game.appid.as_str())
+        .collect();
+
+    let mut prefixes = Vec::new();
+    for library in libraries {
+        let Ok(entries) = fs::read_dir(library.path.join("steamapps/compatdata")) else {
+            continue;
+        };
+        let mut found: Vec<(u32, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+            .filter_map(|entry| {
+                let appid: u32 = entry.file_name().to_str()?.parse().ok()?;
+                Some((appid, entry.path()))
+            })
+            .filter(|&(appid, _)| appid != 0 && appid < 0x8000_0000)
+            .filter(|(appid, _)| !installed.contains(appid.to_string().as_str()))
+            .collect();
+        found.sort();
+
+        prefixes.extend(found.into_iter().map(|(appid, path)| {
+            let appid = appid.to_string();
+            StalePrefix {
+                library: library.path.clone(),
+                name: appinfo.and_then(|info| info.summary(&appid)?.name),
+                appid,
+                path,
+                size: OnceCell::new(),
+            }
+        }));
+    }
+    prefixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steam::GameInfo;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-leftovers-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A library at `path` with manifests for `(appid, installdir)`.
+    fn library(path: &Path, games: &[(&str, &str)]) -> Library {
+        Library {
+            path: path.to_path_buf(),
+            games: games
+                .iter()
+                .map(|(appid, installdir)| GameInfo {
+                    appid: appid.to_string(),
+                    installdir: Some(installdir.to_string()),
+                    ..GameInfo::default()
+                })
+                .collect(),
+            unmatched_apps: Vec::new(),
+            failed: Vec::new(),
+            extra: false,
+        }
+    }
+
+    #[test]
+    fn finds_install_folders_no_manifest_claims() {
+        let root = temp_dir();
+        let (home, drive) = (root.join("home"), root.join("drive"));
+        for dir in [
+            "home/steamapps/common/Portal 2",
+            "home/steamapps/common/Half-Life",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::create_dir_all(root.join("drive/steamapps/common/portal")).unwrap();
+        fs::write(root.join("drive/steamapps/common/notes.txt"), "").unwrap();
+
+        // a game moved to the other drive keeps its manifest there, and case doesn't matter
+        let libraries = [
+            library(&home, &[("620", "Portal 2")]),
+            library(&drive, &[("400", "Portal")]),
+        ];
+        let orphans = find_orphaned_installs(&libraries);
+        let found: Vec<(&Path, &Path)> = orphans
+            .iter()
+            .map(|orphan| (orphan.library.as_path(), orphan.path.as_path()))
+            .collect();
+        assert_eq!(
+            found,
+            [(
+                home.as_path(),
+                home.join("steamapps/common/Half-Life").as_path()
+            )]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn finds_prefixes_of_games_no_longer_installed() {
+        let root = temp_dir();
+        for appid in ["620", "400", "0", "pfx", "2147483649"] {
+            fs::create_dir_all(root.join("steamapps/compatdata").join(appid)).unwrap();
+        }
+        let libraries = [library(&root, &[("620", "Portal 2")])];
+
+        let prefixes = find_stale_prefixes(&libraries, None);
+        let found: Vec<(&str, &Path)> = prefixes
+            .iter()
+            .map(|prefix| (prefix.appid.as_str(), prefix.path.as_path()))
+            .collect();
+        // 0, pfx and non-Steam shortcut ids never belong to a game
+        assert_eq!(
+            found,
+            [("400", root.join("steamapps/compatdata/400").as_path())]
+        );
+        assert_eq!(prefixes[0].name, None);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
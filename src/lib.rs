@@ -0,0 +1,43 @@
+//! steamer's modules, shared by the binary and the benchmarks in `benches/`.
+
+pub mod appinfo;
+pub mod archive;
+pub mod badge;
+pub mod bundle;
+pub mod cli;
+pub mod config;
+pub mod configure;
+pub mod desktop;
+pub mod diff;
+pub mod disk;
+pub mod family;
+pub mod filter;
+pub mod gnome;
+pub mod icons;
+pub mod inspect;
+pub mod json;
+pub mod launches;
+pub mod leftovers;
+pub mod lock;
+pub mod login;
+pub mod metadata;
+pub mod names;
+pub mod net;
+pub mod nfc;
+pub mod output;
+pub mod owned;
+pub mod paths;
+pub mod protondb;
+pub mod report;
+pub mod runs;
+pub mod saves;
+pub mod select;
+pub mod shortcuts;
+pub mod signals;
+pub mod state;
+pub mod steam;
+pub mod template;
+pub mod trash;
+pub mod unicode;
+pub mod vdf;
+pub mod watch;
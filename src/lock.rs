@@ -0,0 +1,111 @@
+//! One writing run at a time. A timer and a manual run would otherwise race on the
+//! applications directory, one removing entries while the other writes them. The lock is an
+//! `flock` on `~/.cache/steamer/lock`, so the kernel drops it however the process ends, panics
+//! and signals included, and a stale file left behind never blocks anyone.
+
+use crate::signals;
+use anyhow::{Context, Result, bail};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Held for as long as it's alive.
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Takes the lock, waiting for another run to finish if `wait` is set and failing with the
+    /// other run's PID if not.
+    pub fn acquire(wait: bool) -> Result<Self> {
+        let path = lock_path().context("Could not find the cache directory for the lock file")?;
+        Self::acquire_at(&path, wait)
+    }
+
+    fn acquire_at(path: &Path, wait: bool) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Could not open the lock file {:?}", path))?;
+
+        if !try_lock(&file, false)? {
+            let mut holder = String::new();
+            file.read_to_string(&mut holder).ok();
+            let holder = match holder.trim() {
+                "" => String::new(),
+                pid => format!(" (PID {})", pid),
+            };
+            if !wait {
+                bail!(
+                    "Another steamer run{} is updating entries. Pass --wait to wait for it to finish",
+                    holder
+                );
+            }
+            eprintln!("Waiting for another steamer run{} to finish...", holder);
+            try_lock(&file, true)?;
+        }
+
+        // the PID is only for the message above, the lock itself is the flock
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+}
+
+fn lock_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("steamer/lock"))
+}
+
+/// `flock`s the file exclusively, returning whether it got the lock. A blocking call only
+/// returns once it has it, or fails when Ctrl-C or SIGTERM cut the wait short.
+fn try_lock(file: &File, block: bool) -> Result<bool> {
+    let operation = if block {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_EX | libc::LOCK_NB
+    };
+    loop {
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(true);
+        }
+        let error = io::Error::last_os_error();
+        match error.kind() {
+            io::ErrorKind::WouldBlock => return Ok(false),
+            io::ErrorKind::Interrupted if signals::interrupted() => {
+                bail!("Interrupted while waiting for the other run")
+            }
+            io::ErrorKind::Interrupted => continue,
+            _ => return Err(error).context("Could not lock the lock file"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_second_run_names_the_one_holding_the_lock() {
+        let path =
+            std::env::temp_dir().join(format!("steamer-lock-test-{}/lock", std::process::id()));
+        let held = RunLock::acquire_at(&path, false).unwrap();
+        let error = RunLock::acquire_at(&path, false).err().unwrap().to_string();
+        assert!(
+            error.contains(&format!("(PID {})", std::process::id())),
+            "{}",
+            error
+        );
+
+        drop(held);
+        RunLock::acquire_at(&path, false).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! `--wait-for-steam`: entries start games through `steamer wait-for-steam`, which starts the
+//! Steam client when it isn't running, waits until an account is logged in and then replaces
+//! itself with the launch command. Without it a `steam://` URL opened before Steam is up is
+//! lost behind the login window on a shared machine.
+
+use crate::cli::WaitForSteamArgs;
+use crate::vdf;
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for someone to log in before giving up.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What Steam's registry.vdf says about the running client.
+#[derive(Debug, Default)]
+struct ClientState {
+    running: bool,
+    logged_in: bool,
+}
+
+pub fn wait_for_steam(wait: &WaitForSteamArgs) -> Result<()> {
+    let Some((program, args)) = wait.command.split_first() else {
+        bail!("wait-for-steam needs a command to run after --");
+    };
+    if !client_state().logged_in {
+        if !client_state().running {
+            // the URL only brings up the client, the game is launched once it's logged in
+            Command::new(&wait.start)
+                .arg("steam://open/main")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .with_context(|| format!("Could not start Steam with {:?}", wait.start))?;
+        }
+        eprintln!("Waiting for Steam to start and log in...");
+        let start = Instant::now();
+        while !client_state().logged_in {
+            if start.elapsed() > LOGIN_TIMEOUT {
+                bail!(
+                    "Nobody logged in to Steam within {} minutes, not launching",
+                    LOGIN_TIMEOUT.as_secs() / 60
+                );
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+    let error = Command::new(program).args(args).exec();
+    Err(error).with_context(|| format!("Could not run {:?}", program))
+}
+
+/// The registry.vdf files of a native and a Flatpak Steam, whichever exist.
+fn registry_files() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    [
+        ".steam/registry.vdf",
+        ".var/app/com.valvesoftware.Steam/.steam/registry.vdf",
+    ]
+    .iter()
+    .map(|path| home.join(path))
+    .filter(|path| path.is_file())
+    .collect()
+}
+
+/// Steam writes its pid and the logged in account's ID to registry.vdf, and sets them back to
+/// 0 when it exits. A pid with no process behind it is a client that crashed.
+fn client_state() -> ClientState {
+    registry_files()
+        .iter()
+        .filter_map(|path| read_state(path))
+        .find(|state| state.running)
+        .unwrap_or_default()
+}
+
+fn read_state(path: &Path) -> Option<ClientState> {
+    let root = vdf::parse_text(&fs::read_to_string(path).ok()?).ok()?;
+    let process = root.path("Registry/HKCU/Software/Valve/Steam/ActiveProcess")?;
+    let number = |key: &str| {
+        process
+            .get(key)
+            .and_then(|node| node.to_text())
+            .and_then(|text| text.parse::<u64>().ok())
+            .unwrap_or(0)
+    };
+    let pid = number("pid");
+    let running = pid != 0 && Path::new("/proc").join(pid.to_string()).exists();
+    Some(ClientState {
+        running,
+        logged_in: running && number("ActiveUser") != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry(pid: u32, active_user: u32) -> String {
+        format!(
+            "\"Registry\"\n{{\n\t\"HKCU\"\n\t{{\n\t\t\"Software\"\n\t\t{{\n\t\t\t\"Valve\"\n\t\t\t{{\n\
+             \t\t\t\t\"Steam\"\n\t\t\t\t{{\n\t\t\t\t\t\"ActiveProcess\"\n\t\t\t\t\t{{\n\
+             \t\t\t\t\t\t\"pid\"\t\t\"{}\"\n\t\t\t\t\t\t\"ActiveUser\"\t\t\"{}\"\n\
+             \t\t\t\t\t}}\n\t\t\t\t}}\n\t\t\t}}\n\t\t}}\n\t}}\n}}\n",
+            pid, active_user
+        )
+    }
+
+    #[test]
+    fn reads_whether_someone_is_logged_in() {
+        let path =
+            std::env::temp_dir().join(format!("steamer-registry-test-{}", std::process::id()));
+        // this test's own process stands in for a running client
+        let state = |pid, active_user| {
+            fs::write(&path, registry(pid, active_user)).unwrap();
+            let state = read_state(&path).unwrap();
+            (state.running, state.logged_in)
+        };
+        assert_eq!(state(std::process::id(), 12345678), (true, true));
+        assert_eq!(state(std::process::id(), 0), (true, false));
+        assert_eq!(state(0, 0), (false, false));
+        // a client that crashed leaves its pid and account behind
+        assert_eq!(state(u32::MAX, 12345678), (false, false));
+
+        fs::write(&path, "\"Registry\"\n{\n}\n").unwrap();
+        assert!(read_state(&path).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
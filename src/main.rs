@@ -1,261 +1,1200 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
+use appinfo::AppInfoSource;
+use badge::ProtonBadges;
 use clap::Parser;
-use regex::Regex;
+use cli::{Args, Cli, Command, ExportArgs};
+use config::{Cleanup, Config, Removal};
+use desktop::ExecMode;
+use family::{FamilyView, FamilyViewMode};
+use filter::SkipReason;
+use icons::{IconChain, IconProvider, IconSource};
+use lock::RunLock;
+use metadata::{CachedOptions, MetadataCache};
+use net::HttpClient;
+use output::{
+    AliasSink, DesktopSink, DiffSink, EmitKind, Entry, ExportFormat, HomeManagerSink, HtmlSink,
+    JsonIndexSink, MarkdownSink, OutputSink, StdoutSink,
+};
+use owned::{OwnedGame, OwnedGames};
+use paths::Paths;
+use protondb::ProtonDb;
+use report::{Event, Phase, Reporter};
+use state::{Condition, EntryState};
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::fs;
-use std::io::Write;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
+use steam::{FolderSource, Library};
+use steamer::{
+    appinfo, badge, bundle, cli, config, configure, desktop, family, filter, gnome, icons, inspect,
+    launches, lock, login, metadata, net, output, owned, paths, protondb, report, runs, saves,
+    select, shortcuts, signals, state, steam, watch,
+};
 
-const DEFAULT_SKIP_KEYWORDS: &[&str] = &[
-    "Proton",
-    "Steam Linux Runtime",
-    "Steamworks",
-    "Common Redistributables",
-    "SteamVR",
-    "Dedicated Server",
-    "Soundtrack",
-];
-
-const DEFAULT_IGNORED_APP_IDS: &[&str] = &["480"];
-
-#[derive(Parser, Debug)]
-#[command(version)]
-struct Args {
-    /// Run without writing files to disk, only discovering applications
-    #[arg(short, long)]
-    dry_run: bool,
-    /// Path to Steam installation (defaults to ~/.local/share/Steam)
-    #[arg(short, long)]
-    steam_path: Option<String>,
-    /// Path to applications directory (defaults to ~/.local/share/applications)
-    #[arg(short, long)]
-    app_dir: Option<String>,
-    /// Comma separated list of keywords to skip (defaults to Proton,Steam Linux Runtime,Steamworks,Common Redistributables,SteamVR,Dedicated Server,Soundtrack)
-    #[arg(short = 'k', long)]
-    skip_keywords: Option<String>,
-    /// Comma separated list of app IDs to skip (defaults to 480)
-    #[arg(short, long)]
-    ignored_app_ids: Option<String>,
-}
+fn main() -> Result<()> {
+    let mut cli = Cli::parse();
+    cli.args.dry_run |= cli.args.dry_run_write
+        || cli.args.diff_output
+        || matches!(cli.command, Some(Command::Export(_)));
+    cli.args.repair |= matches!(cli.command, Some(Command::Watch(_)));
 
-struct GameInfo {
-    appid: String,
-    name: String,
+    match cli.command {
+        None => sync(&cli.args, None),
+        Some(Command::List(ref list)) => inspect::list(&cli.args, list),
+        Some(Command::Doctor) => inspect::doctor(&cli.args),
+        Some(Command::Stats) => inspect::stats(&cli.args),
+        Some(Command::Clean) => clean(&cli.args),
+        Some(Command::Purge) => purge(&cli.args),
+        Some(Command::Select) => select::select(&cli.args),
+        Some(Command::Render(ref render)) => inspect::render(&cli.args, render),
+        Some(Command::ParseVdf(ref parse)) => inspect::parse_vdf(parse),
+        Some(Command::Export(ref export)) => sync(&cli.args, Some(export)),
+        Some(Command::History(ref history)) => launches::history(&cli.args, history),
+        Some(Command::Config(ref config)) => configure::run(&cli.args, config),
+        Some(Command::DebugBundle(ref bundle)) => bundle::debug_bundle(&cli.args, bundle),
+        Some(Command::Watch(ref watch)) => watch::watch(&cli.args, watch, || sync(&cli.args, None)),
+        Some(Command::LaunchWrapper(ref wrapper)) => launches::launch_wrapper(wrapper),
+        Some(Command::WaitForSteam(ref wait)) => login::wait_for_steam(wait),
+    }
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+/// A run over every library. `export` sends the entries to stdout or a file in another format
+/// instead, the same way a dry run would find them.
+fn sync(args: &Args, export: Option<&ExportArgs>) -> Result<()> {
+    if let Some(ref archive) = args.from_archive {
+        return inspect::archive(args, Path::new(archive));
+    }
+    if args.count_only {
+        return inspect::count(args);
+    }
+    let _lock = (!args.dry_run && !args.report_duplicates)
+        .then(|| RunLock::acquire(args.wait))
+        .transpose()?;
 
     let start_time = std::time::Instant::now();
 
-    let ignored_keywords = match args.skip_keywords {
-        Some(ref s) => s.split(',').map(|s| s.trim()).collect::<Vec<_>>(),
-        None => DEFAULT_SKIP_KEYWORDS.to_vec(),
+    let config = Config::load_default(args.profile.as_deref())?;
+    let steam_binary = match args.exec_mode {
+        ExecMode::Steam => paths::find_steam_binary(),
+        ExecMode::XdgOpen => None,
     };
+    let entry_options = args.entry_options(&config, steam_binary.clone())?;
+    let owned_account = args
+        .include_uninstalled
+        .then(|| args.owned_games_account(&config))
+        .transpose()?;
+    let hide_uninstalled = args.hide_uninstalled || config.hide_uninstalled()?;
 
-    let ignored_app_ids = match args.ignored_app_ids {
-        Some(ref s) => s.split(',').map(|s| s.trim()).collect::<Vec<_>>(),
-        None => DEFAULT_IGNORED_APP_IDS.to_vec(),
+    let cleanup = if args.additive || args.icon_changes_only || args.repair {
+        Cleanup::Never
+    } else {
+        config.cleanup()?.unwrap_or_default()
     };
+    let removal = removal_mode(args, &config)?;
+    let servers = config.servers()?;
+    let server_files = desktop::server_filenames(&servers);
 
-    let home = dirs::home_dir().context("Could not find home directory")?;
+    let reporter = Reporter::new(args.log_format, args.dry_run)
+        .human_to_stderr(args.dry_run_write || args.diff_output || export.is_some())
+        .summary_format(args.summary_format.clone())
+        .keep_log();
+    for key in config.unknown_keys() {
+        reporter.warn(format!("Unknown config key {:?}", key));
+    }
+    if args.systemd_scope && paths::find_executable("systemd-run").is_none() {
+        reporter
+            .warn("systemd-run isn't on the PATH, entries made with --systemd-scope won't launch");
+    }
+    let paths = Paths::resolve(args, args.dry_run || args.report_duplicates, &reporter)?;
+    let skip_rules = args.skip_rules()?.ignore_files(&paths.ignore_files)?;
 
-    let steam_root = match args.steam_path {
-        Some(path) => PathBuf::from(path),
-        None => {
-            let home = dirs::home_dir().context("Could not find home directory")?;
-            home.join(".local/share/Steam")
-        }
-    };
+    if args.proxy.is_some() {
+        net::require_network("--proxy")?;
+    }
+    if args.protondb {
+        net::require_network("--protondb")?;
+    }
+    let http = HttpClient::new(args.proxy.clone(), args.offline);
+    reporter.emit(Event::RunStarted {
+        steam_root: &paths.steam_root,
+        app_dir: &paths.app_dir,
+        icon_cache: &paths.icon_cache,
+    });
 
-    let library_vdf = steam_root.join("steamapps/libraryfolders.vdf");
-    let icon_cache_dir = steam_root.join("appcache/librarycache");
+    let writes_entries =
+        !args.dry_run && !args.report_duplicates && args.emit.contains(&EmitKind::Desktop);
+    if args.exec_mode == ExecMode::Steam && steam_binary.is_none() {
+        let message = "Could not find the steam executable on PATH or in the usual install \
+            locations, so launchers that run `steam` wouldn't start. Install Steam, or use \
+            --exec-mode xdg-open to hand steam:// URLs to the desktop instead";
+        if writes_entries {
+            reporter.emit(Event::Error(message.to_string()));
+            std::process::exit(1);
+        }
+        reporter.warn(message);
+    }
 
-    let desktop_dir = match args.app_dir {
-        Some(path) => PathBuf::from(path),
-        None => home.join(".local/share/applications"),
+    let family_view = match args.user {
+        Some(ref user) => match FamilyView::load(&paths.steam_root, user) {
+            Ok(Some(view)) => Some(view),
+            Ok(None) => {
+                reporter.warn(format!(
+                    "Family View doesn't limit the games {:?} can launch, generating entries for all of them",
+                    user
+                ));
+                None
+            }
+            Err(e) => {
+                reporter.warn(format!(
+                    "Could not read the Family View settings of {:?}, generating entries for every game: {:#}",
+                    user, e
+                ));
+                None
+            }
+        },
+        None => None,
     };
 
-    println!("Steam Root Directory: {:?}", steam_root);
-    println!("Desktop Entry Directory: {:?}", desktop_dir);
-    println!("Icon Cache Directory: {:?}", icon_cache_dir);
+    if let Some(problem) = paths::library_vdf_problem(&paths) {
+        reporter.emit(Event::Error(problem));
+        std::process::exit(1);
+    }
 
-    if args.dry_run {
-        println!("----------------------------------");
-        println!("DRY RUN ENABLED - No files will be written.");
-        println!("----------------------------------");
+    reporter.emit(Event::PhaseStarted(Phase::Scan));
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(!args.no_appinfo);
+    // playtests fall back to their " Playtest" name suffix, so they only read appinfo.vdf when
+    // Steam left one
+    let with_details = !args.no_appinfo
+        && (!entry_options.locales.is_empty()
+            || args.skip_demos
+            || (!args.include_playtests && appinfo.exists()));
+    let cached_options = CachedOptions {
+        appinfo: !args.no_appinfo,
+        details: with_details,
+        tool_names: args.tools,
+        owned_names: owned_account.is_some(),
+    };
+    let mut metadata = if args.no_cache {
+        MetadataCache::empty(&paths.cache_dir, cached_options)
     } else {
-        fs::create_dir_all(&desktop_dir)?;
+        MetadataCache::load(&paths.cache_dir, cached_options)
+    };
+    let (mut libraries, folder_source) =
+        steam::scan_libraries(&paths, &appinfo, Some(&metadata), &reporter)?;
+    let mut run_history = runs::RunHistory::load(&paths.cache_dir);
+    let installed_before = match args.new_since {
+        Some(n) => match run_history.installed_before(n) {
+            Some(appids) => Some((n, appids.clone())),
+            None => {
+                reporter.warn(format!(
+                    "--new-since {} needs the games of {} earlier syncs, only {} are remembered yet, every game counts as new",
+                    n,
+                    n,
+                    run_history.len()
+                ));
+                None
+            }
+        },
+        None => None,
+    };
+    // a stale or missing library list could be missing a drive, so don't remove entries based
+    // on it
+    let mut cleanup = match (folder_source, cleanup) {
+        (FolderSource::LastRun | FolderSource::RootOnly, Cleanup::Always) => {
+            reporter.warn("Not removing any existing entries, the library list may be out of date or incomplete");
+            Cleanup::Never
+        }
+        (_, cleanup) => cleanup,
+    };
+    let all_current = libraries.iter().all(|library| {
+        library
+            .games
+            .iter()
+            .all(|game| metadata.is_current(&library.path, game))
+    });
+    if with_details
+        && !all_current
+        && let Some(info) = appinfo.get(&reporter)
+    {
+        steam::add_appinfo_details(&mut libraries, info);
+    }
 
-        println!("Cleaning up old Steam desktop entries...");
+    if args.report_duplicates {
+        report_duplicates(&libraries);
+        return Ok(());
+    }
 
-        for entry in fs::read_dir(&desktop_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str())
-                && filename.starts_with("steam-")
-                && filename.ends_with(".desktop")
-            {
-                fs::remove_file(path)?;
+    let owned_games = OwnedGames::new(&http, &paths.cache_dir);
+    let mut uninstalled = match owned_account {
+        Some((ref api_key, ref steam_id)) => match owned_games.load(api_key, steam_id, &reporter) {
+            Ok(games) => games,
+            Err(e) => {
+                reporter.warn(format!(
+                    "Not adding entries for games that aren't installed: {:#}",
+                    e
+                ));
+                Vec::new()
             }
+        },
+        None => Vec::new(),
+    };
+    steam::name_from_owned_games(&mut libraries, &uninstalled);
+    if args.tools {
+        steam::name_useful_tools(&mut libraries);
+    }
+    let installed: HashSet<&str> = libraries
+        .iter()
+        .flat_map(|library| &library.games)
+        .map(|game| game.appid.as_str())
+        .collect();
+    uninstalled.retain(|game| !installed.contains(game.appid.as_str()));
+    let shortcuts = match args.include_shortcuts {
+        true => shortcuts::load(&paths.steam_root, &reporter),
+        false => Vec::new(),
+    };
+    if args.protondb {
+        ProtonDb::new(&http, &paths.cache_dir).add_tiers(
+            &mut libraries,
+            |game| skip_rules.check(game).is_none(),
+            &reporter,
+        );
+    }
+
+    let emits_desktop = args.emit.contains(&EmitKind::Desktop);
+    let verify_failures = Cell::new(0);
+    let entry_state = RefCell::new(EntryState::load(&paths.cache_dir));
+    let mut sinks: Vec<Box<dyn OutputSink + '_>> = Vec::new();
+    if args.dry_run_write {
+        sinks.push(Box::new(StdoutSink::new(&paths.app_dir, &entry_options)));
+    }
+    if args.diff_output {
+        sinks.push(Box::new(DiffSink::new(
+            &paths.app_dir,
+            &entry_options,
+            cleanup == Cleanup::Always,
+        )));
+    }
+    if let Some(export) = export {
+        match export.format {
+            ExportFormat::HomeManager => sinks.push(Box::new(HomeManagerSink::new(
+                &entry_options,
+                export.output.clone(),
+            ))),
         }
     }
+    // a repair leaves out the intact entries, which the indexes would lose
+    for kind in args
+        .emit
+        .iter()
+        .filter(|kind| !args.dry_run && (!args.repair || **kind == EmitKind::Desktop))
+    {
+        let sink: Box<dyn OutputSink> = match kind {
+            EmitKind::Desktop => {
+                let sink = DesktopSink::new(&paths.app_dir, &entry_options).record_in(&entry_state);
+                match args.verify_writes {
+                    true => Box::new(sink.verify_writes(&reporter, &verify_failures)),
+                    false => Box::new(sink),
+                }
+            }
+            EmitKind::Json => Box::new(JsonIndexSink::new(&paths.index_dir)),
+            EmitKind::Html => Box::new(HtmlSink::new(&paths.index_dir)),
+        };
+        sinks.push(sink);
+    }
 
-    if !library_vdf.exists() {
-        eprintln!("Error: libraryfolders.vdf not found at {:?}", library_vdf);
-        std::process::exit(1);
+    if let Some(ref path) = args.emit_aliases
+        && !args.dry_run
+        && !args.repair
+    {
+        sinks.push(Box::new(AliasSink::new(path.clone(), &entry_options)));
+    }
+    if let Some(ref path) = args.emit_markdown
+        && !args.dry_run
+        && !args.repair
+    {
+        sinks.push(Box::new(MarkdownSink::new(path.clone(), args.sort)));
+    }
+
+    if let Some(ref prefix) = args.migrate_from
+        && emits_desktop
+    {
+        migrate_entries(
+            prefix,
+            &paths.app_dir,
+            &entry_options,
+            &libraries,
+            args.dry_run,
+            &reporter,
+        );
     }
 
-    let libraries = parse_library_folders(&library_vdf)?;
+    let mut backup = None;
+    if !args.dry_run && emits_desktop && cleanup == Cleanup::Always {
+        let taken = desktop::Backup::take(&paths.app_dir)?;
+        if removals_allowed(
+            args,
+            &taken,
+            &libraries,
+            &uninstalled,
+            &shortcuts,
+            &server_files,
+            &reporter,
+        ) {
+            backup = Some(taken);
+        } else {
+            cleanup = Cleanup::Never;
+        }
+    }
+    // only now, the handler would hold back a Ctrl-C at the removal prompt
+    signals::install();
+    if backup.is_some() {
+        // the entries it doesn't write again are gone, so their hashes go too
+        entry_state.replace(EntryState::empty(&paths.cache_dir));
+        reporter.emit(Event::PhaseStarted(Phase::Cleanup));
+        desktop::remove_generated_entries(&paths.app_dir, removal, &reporter)?;
+    }
 
+    reporter.emit(Event::PhaseStarted(Phase::Write));
     let mut created_count = 0;
     let mut skipped_count = 0;
+    let mut seen_appids = HashSet::new();
+    let mut desktop_ids = Vec::new();
+    let icon_index = icons::IconIndex::new(&paths.icon_cache);
+    // only for the shortcuts, Steam's own games have their icons in its cache
+    let grid_icons = icons::GridIcons::new(&paths.steam_root);
+    let custom_icons = icons::CustomIcons::new(config.custom_icons()?);
+    let save_dirs = saves::SaveDirs::new(config.save_dirs()?);
+    let proton_badges = args
+        .badge_proton
+        .then(|| ProtonBadges::new(&paths.cache_dir, args.dry_run));
 
-    for lib_path in libraries {
-        let steamapps = lib_path.join("steamapps");
-        if !steamapps.exists() {
-            continue;
+    // skipped games are kept too, or they would send the next run back to appinfo.vdf
+    for library in &libraries {
+        for game in &library.games {
+            metadata.insert(&library.path, game, None);
+        }
+    }
+
+    for library in &libraries {
+        reporter.emit(Event::LibraryScanned {
+            path: &library.path,
+            games: library.games.len(),
+            extra: library.extra,
+        });
+        for (path, error) in &library.failed {
+            let message = format!("Could not parse {:?}: {}", path, error);
+            if args.fail_fast {
+                abort_run(&reporter, &message, created_count);
+            }
+            reporter.warn(message);
         }
 
-        println!("Checking Library: {:?}", lib_path);
+        for game in &library.games {
+            if signals::interrupted() {
+                stop_interrupted(&reporter, backup.as_ref(), &paths.app_dir, created_count);
+            }
+            if let Some(reason) = skip_rules.check(game) {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &reason,
+                });
+                skipped_count += 1;
+                continue;
+            }
+            if let Some(ref range) = args.size_range
+                && game.size_on_disk.is_none()
+            {
+                reporter.warn(format!(
+                    "{} has no size in its manifest, keeping it despite --size-range {}",
+                    game.name, range
+                ));
+            }
 
-        let entries = fs::read_dir(&steamapps)?;
+            // the first library listed keeps the launcher, see --report-duplicates for the rest
+            if !seen_appids.insert(game.appid.as_str()) {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &SkipReason::Duplicate,
+                });
+                continue;
+            }
 
-        for entry in entries {
-            let entry = entry?;
-            let path = entry.path();
+            if let Some((runs, ref appids)) = installed_before
+                && appids.contains(&game.appid)
+            {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &SkipReason::NotNew(runs),
+                });
+                skipped_count += 1;
+                continue;
+            }
 
-            // filter for appmanifest_*.acf
-            if let Some(filename) = path.file_name().and_then(|n| n.to_str())
-                && filename.starts_with("appmanifest_")
-                && filename.ends_with(".acf")
-                && let Ok(game) = parse_app_manifest(&path)
+            if args.verify_installdir
+                && let Some(ref installdir) = game.installdir
             {
-                if should_skip(&game.name, &game.appid, &ignored_app_ids, &ignored_keywords) {
-                    println!("  Found Tool/Runtime, skipping: {}", game.name);
-                    skipped_count += 1;
-                    continue;
+                match steam::find_install_library(installdir, &library.path, &libraries) {
+                    None => {
+                        if let Some(e) = steam::broken_install_link(installdir, &library.path) {
+                            reporter.warn(format!(
+                                "The install folder of {} is a symlink that can't be followed: {}",
+                                game.name, e
+                            ));
+                        }
+                        reporter.emit(Event::GameSkipped {
+                            game,
+                            reason: &SkipReason::MissingFiles,
+                        });
+                        skipped_count += 1;
+                        continue;
+                    }
+                    Some(found) if found != library.path => reporter.warn(format!(
+                        "{} has its manifest in {:?} but its files in {:?}",
+                        game.name, library.path, found
+                    )),
+                    Some(_) => {}
                 }
+            }
+
+            let hidden = family_view
+                .as_ref()
+                .is_some_and(|view| !view.allows(&game.appid));
+            if hidden && args.family_view == FamilyViewMode::Skip {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &SkipReason::FamilyView,
+                });
+                skipped_count += 1;
+                continue;
+            }
 
-                // idk how steam does the hash soooo this is good enough
-                // 40 char hash + .jpg :pray:
-                let found_icon = fs::read_dir(icon_cache_dir.join(&game.appid))
-                    .ok()
-                    .into_iter()
-                    .flatten()
-                    .filter_map(Result::ok)
-                    .map(|entry| entry.path())
-                    .find(|path| {
-                        path.file_name()
-                            .and_then(|n| n.to_str())
-                            .map(|s| s.len() == 44 && s.ends_with(".jpg"))
-                            .unwrap_or(false)
-                    });
-
-                let icon_path = match found_icon {
-                    Some(path) => path.to_string_lossy().to_string(),
-                    None => "steam".to_string(),
-                };
-
-                let desktop_filename = format!("steam-{}.desktop", game.appid);
-                let desktop_file_path = desktop_dir.join(&desktop_filename);
-
-                if args.dry_run {
-                    println!("  Found game: {} (AppID: {})", game.name, game.appid);
-                } else {
-                    create_desktop_file(&desktop_file_path, &game, &icon_path)?;
-                    println!("  Created Launcher for {}", game.name);
+            if args.repair
+                && emits_desktop
+                && let Some(reason) = repair_skip(
+                    &entry_state.borrow(),
+                    &paths.app_dir,
+                    game,
+                    &entry_options,
+                    &reporter,
+                )
+            {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &reason,
+                });
+                // its entry stays, so it isn't reported as left over
+                desktop_ids.push(entry_options.desktop_id(game));
+                skipped_count += 1;
+                continue;
+            }
+
+            reporter.emit(Event::GameDiscovered(game));
+
+            let cached_icon = metadata.icon(&library.path, game);
+            let steam_icon = |appid: &str, reporter: &Reporter| {
+                cached_icon
+                    .as_ref()
+                    .map(PathBuf::from)
+                    .or_else(|| icon_index.resolve(appid, reporter))
+            };
+            let found = IconChain::new(&args.icon_sources, |source| match source {
+                IconSource::Custom => Some(&custom_icons as &dyn IconProvider),
+                IconSource::Steam => Some(&steam_icon),
+                IconSource::Grid => None,
+            })
+            .resolve(&game.appid, &reporter);
+            // the cache keeps Steam's icon, so removing a custom one brings it back
+            let icon = match found {
+                Some((IconSource::Steam, ref path)) => Some(path.to_string_lossy().to_string()),
+                _ => cached_icon.clone(),
+            };
+            metadata.insert(&library.path, game, icon.as_deref());
+            if args.icon_changes_only
+                && !metadata.icon_changed(&library.path, game, icon.as_deref())
+            {
+                reporter.emit(Event::GameSkipped {
+                    game,
+                    reason: &SkipReason::IconUnchanged,
+                });
+                // its entry stays, so it isn't reported as left over
+                if emits_desktop {
+                    desktop_ids.push(entry_options.desktop_id(game));
                 }
-                created_count += 1;
+                skipped_count += 1;
+                continue;
+            }
+            let mut icon_path = match found {
+                Some((_, path)) => path.to_string_lossy().to_string(),
+                None => icons::FALLBACK_ICON.to_string(),
+            };
+            if let Some(ref badges) = proton_badges {
+                icon_path = badges.apply(
+                    &game.appid,
+                    &game.name,
+                    &library.path,
+                    &icon_path,
+                    &reporter,
+                );
             }
+            let save_dir = args
+                .save_dir_action
+                .then(|| save_dirs.find(game, &library.path, &reporter))
+                .flatten();
+
+            let entry = Entry {
+                game,
+                library: Some(&library.path),
+                icon: &icon_path,
+                hidden,
+                save_dir: save_dir.as_deref(),
+            };
+            write_entry(&mut sinks, &entry, args, &reporter, created_count);
+            if emits_desktop {
+                desktop_ids.push(entry_options.desktop_id(game));
+            }
+            created_count += 1;
         }
     }
 
-    let elapsed = start_time.elapsed().as_millis();
+    // installing one of these later gives its launcher the same filename, replacing this entry
+    for owned_game in &uninstalled {
+        if signals::interrupted() {
+            stop_interrupted(&reporter, backup.as_ref(), &paths.app_dir, created_count);
+        }
+        let game = owned_game.to_game_info();
+        if let Some(reason) = skip_rules.check(&game) {
+            reporter.emit(Event::GameSkipped {
+                game: &game,
+                reason: &reason,
+            });
+            skipped_count += 1;
+            continue;
+        }
+        let family_hidden = family_view
+            .as_ref()
+            .is_some_and(|view| !view.allows(&game.appid));
+        if family_hidden && args.family_view == FamilyViewMode::Skip {
+            reporter.emit(Event::GameSkipped {
+                game: &game,
+                reason: &SkipReason::FamilyView,
+            });
+            skipped_count += 1;
+            continue;
+        }
+        if args.repair
+            && emits_desktop
+            && let Some(reason) = repair_skip(
+                &entry_state.borrow(),
+                &paths.app_dir,
+                &game,
+                &entry_options,
+                &reporter,
+            )
+        {
+            reporter.emit(Event::GameSkipped {
+                game: &game,
+                reason: &reason,
+            });
+            desktop_ids.push(entry_options.desktop_id(&game));
+            skipped_count += 1;
+            continue;
+        }
 
-    if args.dry_run {
-        println!(
-            "Dry run complete. Found {} games, skipped {} tools. Took {:.2?} milliseconds.",
-            created_count, skipped_count, elapsed
-        );
-    } else {
-        println!(
-            "Done! {} shortcuts created (skipped {} tools) in {:?}. Took {:.2?} milliseconds.",
-            created_count, skipped_count, desktop_dir, elapsed
-        );
+        reporter.emit(Event::GameDiscovered(&game));
+        let fetched_icon = |_: &str, _: &Reporter| owned_games.icon(owned_game);
+        let icon_path = IconChain::new(&args.icon_sources, |source| match source {
+            IconSource::Custom => Some(&custom_icons as &dyn IconProvider),
+            IconSource::Steam => Some(&fetched_icon),
+            IconSource::Grid => None,
+        })
+        .icon_path(&game.appid, &reporter);
+        let entry = Entry {
+            game: &game,
+            library: None,
+            icon: &icon_path,
+            hidden: family_hidden || hide_uninstalled,
+            save_dir: None,
+        };
+        write_entry(&mut sinks, &entry, args, &reporter, created_count);
+        if emits_desktop {
+            desktop_ids.push(entry_options.desktop_id(&game));
+        }
+        created_count += 1;
+    }
+
+    // their appids are far above Steam's, so the filenames never meet those of its games
+    for shortcut in &shortcuts {
+        if signals::interrupted() {
+            stop_interrupted(&reporter, backup.as_ref(), &paths.app_dir, created_count);
+        }
+        let game = shortcut.to_game_info();
+        if let Some(reason) = skip_rules.check(&game) {
+            reporter.emit(Event::GameSkipped {
+                game: &game,
+                reason: &reason,
+            });
+            skipped_count += 1;
+            continue;
+        }
+        if args.repair
+            && emits_desktop
+            && let Some(reason) = repair_skip(
+                &entry_state.borrow(),
+                &paths.app_dir,
+                &game,
+                &entry_options,
+                &reporter,
+            )
+        {
+            reporter.emit(Event::GameSkipped {
+                game: &game,
+                reason: &reason,
+            });
+            desktop_ids.push(entry_options.desktop_id(&game));
+            skipped_count += 1;
+            continue;
+        }
+
+        reporter.emit(Event::GameDiscovered(&game));
+        let own_icon = |_: &str, _: &Reporter| shortcut.icon.clone();
+        let icon_path = IconChain::new(&args.icon_sources, |source| match source {
+            IconSource::Custom => Some(&custom_icons as &dyn IconProvider),
+            IconSource::Steam => Some(&own_icon),
+            IconSource::Grid => Some(&grid_icons),
+        })
+        .icon_path(&game.appid, &reporter);
+        let entry = Entry {
+            game: &game,
+            library: None,
+            icon: &icon_path,
+            hidden: false,
+            save_dir: None,
+        };
+        write_entry(&mut sinks, &entry, args, &reporter, created_count);
+        if emits_desktop {
+            desktop_ids.push(entry_options.desktop_id(&game));
+        }
+        created_count += 1;
+    }
+
+    if signals::interrupted() {
+        stop_interrupted(&reporter, backup.as_ref(), &paths.app_dir, created_count);
+    }
+    if args.with_steam_entry && writes_entries {
+        let path = paths.app_dir.join(desktop::STEAM_CLIENT_ENTRY);
+        let binary = steam_binary.clone().or_else(paths::find_steam_binary);
+        let content = desktop::render_client_entry(binary.as_deref());
+        match write_file(&path, &content, args, &reporter, &verify_failures) {
+            Ok(()) => {
+                reporter.emit(Event::OutputWritten { path: &path });
+                desktop_ids.push(desktop::STEAM_CLIENT_ENTRY.to_string());
+            }
+            Err(e) => reporter.warn(format!("Could not write {:?}: {:#}", path, e)),
+        }
+    }
+    if writes_entries {
+        let server_icons = IconChain::new(&args.icon_sources, |source| match source {
+            IconSource::Custom => Some(&custom_icons as &dyn IconProvider),
+            IconSource::Steam => Some(&icon_index),
+            IconSource::Grid => None,
+        });
+        for (server, filename) in servers.iter().zip(&server_files) {
+            let path = paths.app_dir.join(filename);
+            let icon = match server.appid {
+                Some(ref appid) => server_icons.icon_path(appid, &reporter),
+                None => icons::FALLBACK_ICON.to_string(),
+            };
+            let content = desktop::render_server_entry(server, &icon);
+            match write_file(&path, &content, args, &reporter, &verify_failures) {
+                Ok(()) => {
+                    reporter.emit(Event::OutputWritten { path: &path });
+                    desktop_ids.push(filename.clone());
+                }
+                Err(e) => reporter.warn(format!("Could not write {:?}: {:#}", path, e)),
+            }
+        }
+    }
+    if let Err(e) = metadata.save() {
+        reporter.warn(format!("Could not save the metadata cache: {:#}", e));
+    }
+    if writes_entries && let Err(e) = entry_state.borrow().save() {
+        reporter.warn(format!("Could not save the entry hashes: {:#}", e));
+    }
+    // every installed game counts, so what --new-since leaves out this run isn't new next run.
+    // A repair isn't a sync of its own, or watching would push the older syncs out.
+    if !args.dry_run && !args.repair {
+        let appids = libraries
+            .iter()
+            .flat_map(|library| &library.games)
+            .map(|game| game.appid.clone())
+            .collect();
+        if let Err(e) = run_history.save(appids) {
+            reporter.warn(format!("Could not save the run history: {:#}", e));
+        }
+    }
+    for sink in sinks.iter_mut() {
+        match sink.finish() {
+            Ok(Some(path)) => reporter.emit(Event::OutputWritten { path: &path }),
+            Ok(None) => {}
+            Err(e) => reporter.warn(format!("{:#}", e)),
+        }
+    }
+
+    if emits_desktop && cleanup == Cleanup::Never {
+        report_leftover_entries(&paths.app_dir, &desktop_ids, &reporter);
+    }
+
+    if args.gnome_folder && !args.dry_run {
+        update_gnome_folder(&desktop_ids, &reporter);
+    }
+
+    if http.is_offline() && http.skipped_requests() > 0 {
+        reporter.warn(format!(
+            "Offline mode skipped {} network requests, local data was used instead",
+            http.skipped_requests()
+        ));
+    }
+
+    // entries the run wrote again are back in place, the ones still missing were removed
+    let removed_count = backup.as_ref().map_or(0, |backup| {
+        backup.paths().filter(|path| !path.exists()).count()
+    });
+    reporter.emit(Event::Summary {
+        created: created_count,
+        skipped: skipped_count,
+        removed: removed_count,
+        app_dir: &paths.app_dir,
+        cleanup_skipped: cleanup == Cleanup::Never,
+        elapsed_ms: start_time.elapsed().as_millis(),
+    });
+    if let Err(e) = reporter.save_log(&report::last_run_log(&paths.cache_dir)) {
+        reporter.warn(format!("Could not save the run log: {:#}", e));
+    }
+    if verify_failures.get() > 0 {
+        reporter.emit(Event::Error(format!(
+            "{} entries didn't read back as they were written, check the disk holding {:?}",
+            verify_failures.get(),
+            paths.app_dir
+        )));
+        std::process::exit(1);
     }
 
     Ok(())
 }
 
-fn parse_library_folders(path: &Path) -> Result<Vec<PathBuf>> {
-    let content = fs::read_to_string(path)?;
-    let mut paths = Vec::new();
+/// `--repair`: why the entry of `game` stays as it is, or `None` to write it, noting what
+/// happened to it when it's restored.
+fn repair_skip(
+    state: &EntryState,
+    app_dir: &Path,
+    game: &steam::GameInfo,
+    options: &desktop::EntryOptions,
+    reporter: &Reporter,
+) -> Option<SkipReason> {
+    let path = options.entry_path(app_dir, game);
+    match state.condition(&path) {
+        Condition::Intact => Some(SkipReason::Intact),
+        Condition::Preserved => Some(SkipReason::Preserved),
+        Condition::Damaged(damage) => {
+            reporter.emit(Event::EntryDamaged {
+                game,
+                path: &path,
+                damage,
+            });
+            None
+        }
+        Condition::Unrecorded => None,
+    }
+}
 
-    let re = Regex::new(r#""path"\s+"([^"]+)""#).unwrap();
+fn removal_mode(args: &Args, config: &Config) -> Result<Removal> {
+    if args.trash {
+        return Ok(Removal::Trash);
+    }
+    Ok(config.cleanup_mode()?.unwrap_or_default())
+}
 
-    for cap in re.captures_iter(&content) {
-        if let Some(matched_path) = cap.get(1) {
-            paths.push(PathBuf::from(matched_path.as_str()));
+/// Whether the cleanup can go ahead. One that would remove more than `--removal-margin`
+/// entries beyond the ones the run writes again, as when a mis-detected Steam folder finds no
+/// games at all and the cleanup would empty the menu, asks first. `--safe` stops the run
+/// instead. Runs nobody can answer, and a no, keep the entries and go on without removing any.
+fn removals_allowed(
+    args: &Args,
+    backup: &desktop::Backup,
+    libraries: &[Library],
+    uninstalled: &[OwnedGame],
+    shortcuts: &[shortcuts::Shortcut],
+    server_files: &[String],
+    reporter: &Reporter,
+) -> bool {
+    let mut found: HashSet<String> = libraries
+        .iter()
+        .flat_map(|library| &library.games)
+        .map(output::desktop_filename)
+        .collect();
+    found.extend(
+        uninstalled
+            .iter()
+            .map(|game| output::desktop_filename(&game.to_game_info())),
+    );
+    found.extend(
+        shortcuts
+            .iter()
+            .map(|shortcut| output::desktop_filename(&shortcut.to_game_info())),
+    );
+    if args.with_steam_entry {
+        found.insert(desktop::STEAM_CLIENT_ENTRY.to_string());
+    }
+    found.extend(server_files.iter().cloned());
+    let removed = backup
+        .paths()
+        .filter_map(|path| path.file_name()?.to_str())
+        .filter(|name| !found.contains(*name))
+        .count();
+    if !too_many_removals(removed, found.len(), args.removal_margin) {
+        return true;
+    }
+
+    let message = format!(
+        "This run would remove {} entries for games it didn't find and write {}",
+        removed,
+        found.len()
+    );
+    let advice =
+        "Check that --steam-path is right, or raise --removal-margin if they're really gone";
+    if args.safe {
+        reporter.emit(Event::Error(format!("{}, stopping. {}", message, advice)));
+        std::process::exit(1);
+    }
+    if io::stdin().is_terminal() && io::stderr().is_terminal() {
+        eprint!("{}. Remove them? [y/N] ", message);
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer).is_ok() && matches!(answer.trim(), "y" | "Y" | "yes")
+        {
+            return true;
         }
     }
+    reporter.warn(format!("{}, keeping them. {}", message, advice));
+    false
+}
 
-    Ok(paths)
+/// Whether a cleanup removing `removed` entries in a run writing `created` removes more than
+/// `margin` beyond them.
+fn too_many_removals(removed: usize, created: usize, margin: usize) -> bool {
+    removed > created.saturating_add(margin)
 }
 
-fn parse_app_manifest(path: &Path) -> Result<GameInfo> {
-    let content = fs::read_to_string(path)?;
+/// Ends a run after Ctrl-C or SIGTERM. The signal is only acted on between entries, so no file
+/// is half written; if the cleanup already ran, the entries from before the run are put back.
+fn stop_interrupted(
+    reporter: &Reporter,
+    backup: Option<&desktop::Backup>,
+    app_dir: &Path,
+    created: usize,
+) -> ! {
+    let message = match backup.map(|b| b.restore(app_dir, reporter)) {
+        Some(Ok(restored)) => format!(
+            "Interrupted, restored the {} entries from before the run",
+            restored
+        ),
+        Some(Err(e)) => format!(
+            "Interrupted, and restoring the previous entries failed: {:#}",
+            e
+        ),
+        None => format!("Interrupted after writing {} entries", created),
+    };
+    reporter.emit(Event::Error(message));
+    std::process::exit(signals::EXIT_INTERRUPTED);
+}
 
-    let re_id = Regex::new(r#""appid"\s+"(\d+)""#).unwrap();
-    let re_name = Regex::new(r#""name"\s+"([^"]+)""#).unwrap();
+/// Writes an entry that isn't a game's, read back with `--verify-writes` like the games' are.
+fn write_file(
+    path: &Path,
+    content: &str,
+    args: &Args,
+    reporter: &Reporter,
+    verify_failures: &Cell<usize>,
+) -> Result<()> {
+    if !args.verify_writes {
+        return desktop::create_desktop_file(path, content);
+    }
+    if !desktop::create_verified(path, content, reporter)? {
+        verify_failures.set(verify_failures.get() + 1);
+        bail!(
+            "it didn't read back as written after {} tries",
+            desktop::VERIFY_ATTEMPTS
+        );
+    }
+    Ok(())
+}
 
-    let appid = re_id
-        .captures(&content)
-        .and_then(|c| c.get(1))
-        .map(|m| m.as_str().to_string())
-        .context("Failed to find appid")?;
+/// Hands one entry to every sink, reporting the files written and the sinks that failed.
+fn write_entry(
+    sinks: &mut [Box<dyn OutputSink + '_>],
+    entry: &Entry,
+    args: &Args,
+    reporter: &Reporter,
+    created: usize,
+) {
+    let game = entry.game;
+    for sink in sinks.iter_mut() {
+        match sink.write(entry) {
+            Ok(Some(path)) => reporter.emit(Event::EntryWritten { game, path: &path }),
+            Ok(None) => {}
+            Err(e) => {
+                let message = format!("{} (AppID: {}): {:#}", game.name, game.appid, e);
+                if args.fail_fast {
+                    abort_run(reporter, &message, created);
+                }
+                reporter.warn(message);
+            }
+        }
+    }
+}
 
-    let name = re_name
-        .captures(&content)
-        .and_then(|c| c.get(1))
-        .map(|m| m.as_str().to_string())
-        .unwrap_or_else(|| "Unknown Game".to_string());
+/// Stops a `--fail-fast` run at its first error, saying how far it got.
+fn abort_run(reporter: &Reporter, message: &str, created: usize) -> ! {
+    reporter.emit(Event::Error(format!(
+        "{} (stopped by --fail-fast after writing {} entries)",
+        message, created
+    )));
+    std::process::exit(1);
+}
 
-    Ok(GameInfo { appid, name })
+/// With cleanup off, entries for games that are gone stay behind, so point them out instead.
+fn report_leftover_entries(app_dir: &Path, written: &[String], reporter: &Reporter) {
+    let Ok(entries) = desktop::generated_entries(app_dir) else {
+        return;
+    };
+    for path in entries {
+        if !written.contains(&desktop::desktop_id(app_dir, &path)) {
+            reporter.warn(format!(
+                "{:?} matches no installed game and was left in place",
+                path
+            ));
+        }
+    }
 }
 
-fn should_skip(
-    name: &str,
-    appid: &str,
-    ignored_app_ids: &Vec<&str>,
-    ignored_key_words: &Vec<&str>,
-) -> bool {
-    let name_lower = name.to_lowercase();
+/// `--migrate-from`: renames the entries an older naming scheme left to the names steamer
+/// uses, so this run updates them instead of writing a second entry next to each. Entries of
+/// games that aren't installed, and ones whose new name is taken, are left alone.
+fn migrate_entries(
+    prefix: &str,
+    app_dir: &Path,
+    options: &desktop::EntryOptions,
+    libraries: &[Library],
+    dry_run: bool,
+    reporter: &Reporter,
+) {
+    if !app_dir.is_dir() {
+        return;
+    }
+    let found = match desktop::entries_with_prefix(app_dir, prefix) {
+        Ok(found) => found,
+        Err(e) => {
+            reporter.warn(format!(
+                "Could not look for entries to migrate in {:?}: {:#}",
+                app_dir, e
+            ));
+            return;
+        }
+    };
+    let mut targets = HashSet::new();
+    let mut renamed = Vec::new();
+    for (from, appid) in found {
+        let game = libraries
+            .iter()
+            .flat_map(|library| &library.games)
+            .find(|game| game.appid == appid);
+        let Some(game) = game else {
+            reporter.warn(format!(
+                "Not renaming {:?}, app {} isn't installed",
+                from, appid
+            ));
+            continue;
+        };
+        let to = options.entry_path(app_dir, game);
+        if to.exists() || !targets.insert(to.clone()) {
+            reporter.warn(format!("Not renaming {:?}, {:?} already exists", from, to));
+            continue;
+        }
+        if !dry_run {
+            let result = match to.parent() {
+                Some(parent) => fs::create_dir_all(parent),
+                None => Ok(()),
+            }
+            .and_then(|_| fs::rename(&from, &to));
+            if let Err(e) = result {
+                reporter.warn(format!("Could not rename {:?}: {}", from, e));
+                continue;
+            }
+        }
+        reporter.emit(Event::EntryMigrated {
+            from: &from,
+            to: &to,
+        });
+        renamed.push((
+            desktop::desktop_id(app_dir, &from),
+            desktop::desktop_id(app_dir, &to),
+        ));
+    }
+    if !dry_run
+        && !renamed.is_empty()
+        && gnome::is_gnome_session()
+        && let Err(e) = gnome::rename_favorites(&renamed)
+    {
+        reporter.warn(format!("Could not update the GNOME favorites: {:#}", e));
+    }
+}
 
-    if ignored_app_ids.contains(&appid) {
-        return true;
+fn update_gnome_folder(desktop_ids: &[String], reporter: &Reporter) {
+    if !gnome::is_gnome_session() {
+        reporter.warn("--gnome-folder only works in GNOME sessions, leaving app folders alone");
+        return;
+    }
+    if let Err(e) = gnome::sync_folder(desktop_ids) {
+        reporter.warn(format!("Could not update the GNOME app folder: {:#}", e));
     }
+}
 
-    for keyword in ignored_key_words {
-        if name_lower.contains(&keyword.to_lowercase()) {
-            return true;
+/// Removes every generated entry, and the GNOME folder with `--gnome-folder`.
+fn clean(args: &Args) -> Result<()> {
+    let _lock = (!args.dry_run)
+        .then(|| RunLock::acquire(args.wait))
+        .transpose()?;
+    let config = Config::load_default(args.profile.as_deref())?;
+    let removal = removal_mode(args, &config)?;
+    let reporter = Reporter::new(args.log_format, args.dry_run);
+    let paths = Paths::resolve(args, true, &reporter)?;
+
+    if args.dry_run {
+        let count = desktop::generated_entries(&paths.app_dir).map_or(0, |paths| paths.len());
+        println!("Would remove {} entries from {:?}.", count, paths.app_dir);
+        return Ok(());
+    }
+
+    let removed = match fs::read_dir(&paths.app_dir) {
+        Ok(_) => desktop::remove_generated_entries(&paths.app_dir, removal, &reporter)?,
+        Err(_) => 0,
+    };
+    if args.gnome_folder {
+        if gnome::is_gnome_session() {
+            if let Err(e) = gnome::remove_folder() {
+                reporter.warn(format!("Could not remove the GNOME app folder: {:#}", e));
+            }
+        } else {
+            reporter.warn("--gnome-folder only works in GNOME sessions, leaving app folders alone");
         }
     }
-    false
+    println!("Removed {} entries from {:?}.", removed, paths.app_dir);
+    Ok(())
 }
 
-fn create_desktop_file(path: &Path, game: &GameInfo, icon_path: &str) -> Result<()> {
-    let content = format!(
-        "[Desktop Entry]\n\
-        Name={}\n\
-        Exec=steam steam://rungameid/{}\n\
-        Icon={}\n\
-        Terminal=false\n\
-        Type=Application\n\
-        Categories=Game;\n",
-        game.name, game.appid, icon_path
-    );
+/// `clean`, then the rest of what steamer wrote: its cache directory, the json and html indexes,
+/// the launch history and the GNOME app folder. Only files steamer names itself are removed
+/// from directories that could hold others, and config.toml is left alone.
+fn purge(args: &Args) -> Result<()> {
+    let _lock = (!args.dry_run)
+        .then(|| RunLock::acquire(args.wait))
+        .transpose()?;
+    let config = Config::load_default(args.profile.as_deref())?;
+    let removal = removal_mode(args, &config)?;
+    let reporter = Reporter::new(args.log_format, args.dry_run);
+    let paths = Paths::resolve(args, true, &reporter)?;
 
-    let mut file = fs::File::create(path)?;
-    file.write_all(content.as_bytes())?;
+    let entries = desktop::generated_entries(&paths.app_dir).unwrap_or_default();
+    let mut files: Vec<PathBuf> = [
+        paths.index_dir.join(output::JSON_INDEX),
+        paths.index_dir.join(output::HTML_INDEX),
+    ]
+    .into_iter()
+    .chain(launches::log_path())
+    .filter(|path| path.exists())
+    .collect();
+    files.dedup();
+    // the lock this run holds is in there too, and doesn't count
+    let cache_dir = fs::read_dir(&paths.cache_dir)
+        .is_ok_and(|mut dir| dir.any(|e| e.is_ok_and(|e| e.file_name() != "lock")))
+        .then_some(&paths.cache_dir);
+    let gnome_folder = gnome::is_gnome_session() && gnome::has_folder().unwrap_or(false);
+
+    let verb = if args.dry_run {
+        "Would remove"
+    } else {
+        "Removed"
+    };
+    if !args.dry_run {
+        if !entries.is_empty() {
+            desktop::remove_generated_entries(&paths.app_dir, removal, &reporter)?;
+        }
+        for path in &files {
+            fs::remove_file(path).with_context(|| format!("Could not remove {:?}", path))?;
+        }
+        if paths.cache_dir.is_dir() {
+            fs::remove_dir_all(&paths.cache_dir)
+                .with_context(|| format!("Could not remove {:?}", paths.cache_dir))?;
+        }
+        if gnome_folder && let Err(e) = gnome::remove_folder() {
+            reporter.warn(format!("Could not remove the GNOME app folder: {:#}", e));
+        }
+        // steamer's data directory, holding the history and by default the indexes, once
+        // they were the last things in it
+        if let Some(dir) = launches::log_path().as_deref().and_then(Path::parent) {
+            let _ = fs::remove_dir(dir);
+        }
+    }
+
+    for path in entries.iter().chain(&files) {
+        println!("{} {:?}", verb, path);
+    }
+    if let Some(dir) = cache_dir {
+        println!("{} {:?} and everything in it", verb, dir);
+    }
+    if gnome_folder {
+        println!("{} the \"Steam\" folder from the GNOME app grid", verb);
+    }
+    if entries.is_empty() && files.is_empty() && cache_dir.is_none() && !gnome_folder {
+        println!("Nothing to remove.");
+    }
     Ok(())
 }
+
+fn report_duplicates(libraries: &[Library]) {
+    let duplicates = steam::find_duplicates(libraries);
+    if duplicates.is_empty() {
+        println!("No games are installed in more than one library.");
+        return;
+    }
+
+    println!(
+        "Found {} games installed in more than one library:",
+        duplicates.len()
+    );
+    for dup in duplicates {
+        println!("  {} (AppID: {})", dup.game.name, dup.game.appid);
+        for path in dup.libraries {
+            println!("    {:?}", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removals_beyond_the_margin_need_a_yes() {
+        // Steam found nothing, every entry would go
+        assert!(too_many_removals(150, 0, 20));
+        assert!(too_many_removals(21, 0, 20));
+        assert!(!too_many_removals(20, 0, 20));
+        // a large library losing a few games is an uninstall, not a mistake
+        assert!(!too_many_removals(30, 500, 20));
+        assert!(!too_many_removals(520, 500, 20));
+        assert!(too_many_removals(521, 500, 20));
+        assert!(too_many_removals(1, 0, 0));
+        assert!(!too_many_removals(1, usize::MAX, usize::MAX));
+    }
+}
@@ -0,0 +1,437 @@
+//! `games.json` in steamer's cache directory: every game as the last run resolved it, with its
+//! appinfo details and icon, keyed by library and appid. A game whose manifest has the same
+//! modification time as last time is taken from here, so repeated runs skip reading its
+//! manifest, and skip parsing appinfo.vdf and walking the icon cache when nothing changed.
+//! Skip rules are still checked on every run, they depend on flags and `.steamerignore` files.
+
+use crate::json::{self, Value};
+use crate::steam::{GameInfo, InstallDetails, NameSource, PartialInstall};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever what a cached game holds changes, so older files are ignored.
+const FORMAT_VERSION: u32 = 9;
+
+struct Cached {
+    mtime: u64,
+    game: GameInfo,
+    icon: Option<String>,
+    /// [`icon_mtime`] of the icon when it was found.
+    icon_mtime: Option<u64>,
+}
+
+/// The options that change what a cached game holds. A cache written with different ones
+/// counts as empty.
+///
+/// Everything else is safe to leave out. Names are cleaned up (`--raw-names`,
+/// `--strip-suffixes`, NFC, emoji) and entries rendered after the cache, from the raw manifest
+/// name. `--locales` only picks among the translations, all of which are kept. ProtonDB ratings
+/// aren't cached. The icon is only ever the one in Steam's cache, and is checked against the
+/// file each run, so `--icon-sources` and custom icons don't reach it.
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct CachedOptions {
+    /// appinfo.vdf is read at all, for the names of games whose manifest has none.
+    pub appinfo: bool,
+    /// The appinfo details are filled in: type, playtest, parent and translated names.
+    pub details: bool,
+    /// `--tools` names the useful tools nothing else has a name for.
+    pub tool_names: bool,
+    /// The owned games list names games nothing installed has a name for.
+    pub owned_names: bool,
+}
+
+pub struct MetadataCache {
+    path: PathBuf,
+    options: String,
+    /// What the last run saved.
+    previous: HashMap<(PathBuf, String), Cached>,
+    /// What this run saw, the only games the next run gets.
+    current: HashMap<(PathBuf, String), Cached>,
+}
+
+impl MetadataCache {
+    /// An empty cache that will be saved to `dir`, for `--no-cache`.
+    pub fn empty(dir: &Path, options: CachedOptions) -> Self {
+        Self {
+            path: dir.join("games.json"),
+            options: options_hash(options),
+            previous: HashMap::new(),
+            current: HashMap::new(),
+        }
+    }
+
+    /// Reads the cache left by the last run. A file written with different `options`, or one
+    /// that can't be read, counts as empty.
+    pub fn load(dir: &Path, options: CachedOptions) -> Self {
+        let mut cache = Self::empty(dir, options);
+        let Ok(text) = fs::read_to_string(&cache.path) else {
+            return cache;
+        };
+        let Ok(root) = json::parse(&text) else {
+            return cache;
+        };
+        if root.get("options").and_then(Value::as_str) != Some(cache.options.as_str()) {
+            return cache;
+        }
+        for item in root.get("games").map(Value::as_array).unwrap_or_default() {
+            if let Some((library, cached)) = parse_cached(item) {
+                cache
+                    .previous
+                    .insert((library, cached.game.appid.clone()), cached);
+            }
+        }
+        cache
+    }
+
+    /// The cached game, if its manifest in `library` is the one it was read from.
+    pub fn game(&self, library: &Path, appid: &str, mtime: u64) -> Option<GameInfo> {
+        self.previous
+            .get(&(library.to_path_buf(), appid.to_string()))
+            .filter(|cached| cached.mtime == mtime)
+            .map(|cached| cached.game.clone())
+    }
+
+    /// Whether `game` came unchanged from the cache, with the details and icon of last run.
+    pub fn is_current(&self, library: &Path, game: &GameInfo) -> bool {
+        game.manifest_mtime
+            .is_some_and(|mtime| self.game(library, &game.appid, mtime).is_some())
+    }
+
+    /// The icon file the last run found for an unchanged game, if it's still there and neither
+    /// it nor its folder changed since. Games that got the fallback icon are looked up again,
+    /// Steam may have cached one since.
+    pub fn icon(&self, library: &Path, game: &GameInfo) -> Option<String> {
+        if !self.is_current(library, game) {
+            return None;
+        }
+        let cached = self
+            .previous
+            .get(&(library.to_path_buf(), game.appid.clone()))?;
+        let icon = cached.icon.as_deref()?;
+        let mtime = icon_mtime(Path::new(icon), &game.appid);
+        (mtime.is_some() && mtime == cached.icon_mtime).then(|| icon.to_string())
+    }
+
+    /// Whether `icon`, the icon this run found in Steam's cache, isn't the one the last run
+    /// found for the game, or was changed since. Games the last run didn't see count as changed.
+    pub fn icon_changed(&self, library: &Path, game: &GameInfo, icon: Option<&str>) -> bool {
+        let Some(cached) = self
+            .previous
+            .get(&(library.to_path_buf(), game.appid.clone()))
+        else {
+            return true;
+        };
+        cached.icon.as_deref() != icon
+            || icon
+                .is_some_and(|icon| icon_mtime(Path::new(icon), &game.appid) != cached.icon_mtime)
+    }
+
+    /// Remembers a game for the next run, with the icon file found for it. Games without a
+    /// manifest time aren't kept.
+    pub fn insert(&mut self, library: &Path, game: &GameInfo, icon: Option<&str>) {
+        let Some(mtime) = game.manifest_mtime else {
+            return;
+        };
+        self.current.insert(
+            (library.to_path_buf(), game.appid.clone()),
+            Cached {
+                mtime,
+                game: game.clone(),
+                icon: icon.map(str::to_string),
+                icon_mtime: icon.and_then(|icon| icon_mtime(Path::new(icon), &game.appid)),
+            },
+        );
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let mut keys: Vec<&(PathBuf, String)> = self.current.keys().collect();
+        keys.sort();
+        let games: Vec<Value> = keys
+            .into_iter()
+            .map(|key| {
+                let cached = &self.current[key];
+                let game = &cached.game;
+                let localized: Vec<Value> = game
+                    .localized_names
+                    .iter()
+                    .map(|(locale, name)| Value::from(vec![locale.as_str(), name.as_str()]))
+                    .collect();
+                Value::object()
+                    .with("library", key.0.as_path())
+                    .with("appid", game.appid.as_str())
+                    .with("mtime", cached.mtime)
+                    .with("name", game.name.as_str())
+                    .with("name_source", game.name_source.kind())
+                    .with("installdir", game.installdir.as_deref())
+                    .with("size_on_disk", game.size_on_disk)
+                    .with("app_type", game.app_type.as_deref())
+                    .with("playtest", game.playtest)
+                    .with("parent_name", game.parent_name.as_deref())
+                    .with("localized_names", Value::Array(localized))
+                    .with(
+                        "partial_install",
+                        game.partial_install.as_ref().map(partial_to_json),
+                    )
+                    .with("depots", game.depots.clone())
+                    .with("dlc", game.dlc.clone())
+                    .with("scheduled_update", game.scheduled_update)
+                    .with("last_played", game.last_played)
+                    .with(
+                        "install_details",
+                        game.install_details.as_ref().map(InstallDetails::to_json),
+                    )
+                    .with("icon", cached.icon.as_deref())
+                    .with("icon_mtime", cached.icon_mtime)
+            })
+            .collect();
+        let root = Value::object()
+            .with("options", self.options.as_str())
+            .with("games", Value::Array(games));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+        }
+        fs::write(&self.path, root.to_compact() + "\n")
+            .with_context(|| format!("Could not write {:?}", self.path))
+    }
+}
+
+/// Modification time of a manifest in nanoseconds, which decides whether its cached game is
+/// used. Seconds could miss Steam rewriting a manifest twice in a row.
+pub fn manifest_mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|meta| meta.modified()).ok()?;
+    u64::try_from(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos()).ok()
+}
+
+/// When a game's icon last changed, in nanoseconds: the later modification time of the file
+/// and of the `librarycache/<appid>` folder it's in. Steam replaces art by adding a file under a
+/// new name next to the old one, which only the folder's time shows. The flat `librarycache`
+/// of older clients changes with every game's art, so only the file counts there.
+fn icon_mtime(path: &Path, appid: &str) -> Option<u64> {
+    let file = manifest_mtime(path)?;
+    let folder = path
+        .parent()
+        .filter(|dir| dir.file_name().is_some_and(|name| name == appid))
+        .and_then(manifest_mtime);
+    Some(folder.unwrap_or(0).max(file))
+}
+
+/// Everything that changes what a cached game holds. The hash only has to match between runs
+/// of the same build, a new build that hashes differently just starts with an empty cache.
+fn options_hash(options: CachedOptions) -> String {
+    let mut hasher = DefaultHasher::new();
+    (FORMAT_VERSION, options).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn parse_cached(item: &Value) -> Option<(PathBuf, Cached)> {
+    let text = |key: &str| item.get(key).and_then(Value::as_str).map(str::to_string);
+    let localized_names = item
+        .get("localized_names")
+        .map(Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|pair| match pair.as_array() {
+            [locale, name] => Some((locale.as_str()?.to_string(), name.as_str()?.to_string())),
+            _ => None,
+        })
+        .collect();
+    let game = GameInfo {
+        appid: text("appid")?,
+        name: text("name")?,
+        name_source: NameSource::from_kind(&text("name_source")?)?,
+        installdir: text("installdir"),
+        size_on_disk: item.get("size_on_disk").and_then(Value::as_u64),
+        app_type: text("app_type"),
+        playtest: item.get("playtest").and_then(Value::as_bool)?,
+        parent_name: text("parent_name"),
+        localized_names,
+        manifest_mtime: Some(item.get("mtime").and_then(Value::as_u64)?),
+        partial_install: item.get("partial_install").and_then(partial_from_json),
+        depots: strings_from_json(item.get("depots")?)?,
+        dlc: strings_from_json(item.get("dlc")?)?,
+        scheduled_update: item.get("scheduled_update").and_then(Value::as_u64),
+        last_played: item.get("last_played").and_then(Value::as_u64),
+        install_details: item
+            .get("install_details")
+            .filter(|details| matches!(details, Value::Object(_)))
+            .map(InstallDetails::from_json),
+        ..Default::default()
+    };
+    let cached = Cached {
+        mtime: game.manifest_mtime?,
+        icon: text("icon"),
+        icon_mtime: item.get("icon_mtime").and_then(Value::as_u64),
+        game,
+    };
+    Some((PathBuf::from(text("library")?), cached))
+}
+
+fn strings_from_json(item: &Value) -> Option<Vec<String>> {
+    item.as_array()
+        .iter()
+        .map(|s| s.as_str().map(str::to_string))
+        .collect()
+}
+
+fn partial_to_json(partial: &PartialInstall) -> Value {
+    let (kind, bytes) = match *partial {
+        PartialInstall::NoDepots => ("no_depots", None),
+        PartialInstall::OnlyDlc => ("only_dlc", None),
+        PartialInstall::Downloading { done, total } => ("downloading", Some((done, total))),
+        PartialInstall::Staging { done, total } => ("staging", Some((done, total))),
+    };
+    Value::object()
+        .with("kind", kind)
+        .with("done", bytes.map(|b| b.0))
+        .with("total", bytes.map(|b| b.1))
+}
+
+fn partial_from_json(item: &Value) -> Option<PartialInstall> {
+    let bytes = |key: &str| item.get(key).and_then(Value::as_u64);
+    Some(match item.get("kind")?.as_str()? {
+        "no_depots" => PartialInstall::NoDepots,
+        "only_dlc" => PartialInstall::OnlyDlc,
+        "downloading" => PartialInstall::Downloading {
+            done: bytes("done")?,
+            total: bytes("total")?,
+        },
+        "staging" => PartialInstall::Staging {
+            done: bytes("done")?,
+            total: bytes("total")?,
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: CachedOptions = CachedOptions {
+        appinfo: true,
+        details: false,
+        tool_names: false,
+        owned_names: false,
+    };
+
+    fn game() -> GameInfo {
+        GameInfo {
+            appid: "620".to_string(),
+            name: "Portal 2".to_string(),
+            installdir: Some("Portal 2".to_string()),
+            manifest_mtime: Some(1_700_000_000_000_000_000),
+            depots: vec!["621".to_string()],
+            dlc: vec!["650".to_string()],
+            localized_names: vec![("de".to_string(), "Portal 2".to_string())],
+            install_details: Some(InstallDetails {
+                bytes_downloaded: Some(500),
+                bytes_to_download: Some(1000),
+                auto_update: Some(2),
+                ..InstallDetails::default()
+            }),
+            ..GameInfo::default()
+        }
+    }
+
+    #[test]
+    fn reuses_games_whose_manifest_is_unchanged() {
+        let dir = std::env::temp_dir().join(format!("steamer-metadata-{}", std::process::id()));
+        let library = Path::new("/games/steam");
+        let mut cache = MetadataCache::empty(&dir, OPTIONS);
+        cache.insert(library, &game(), None);
+        cache.save().unwrap();
+
+        let cache = MetadataCache::load(&dir, OPTIONS);
+        let mtime = game().manifest_mtime.unwrap();
+        let cached = cache.game(library, "620", mtime).unwrap();
+        assert_eq!(cached.name, "Portal 2");
+        assert_eq!(cached.depots, ["621"]);
+        assert_eq!(cached.dlc, ["650"]);
+        assert_eq!(cached.localized_names, game().localized_names);
+        assert_eq!(cached.install_details, game().install_details);
+        assert!(cache.is_current(library, &game()));
+
+        // a rewritten manifest, or the same game in another library, is read again
+        assert!(cache.game(library, "620", mtime + 1).is_none());
+        assert!(cache.game(Path::new("/mnt/games"), "620", mtime).is_none());
+        assert!(cache.game(library, "400", mtime).is_none());
+
+        for options in [
+            CachedOptions {
+                appinfo: false,
+                ..OPTIONS
+            },
+            CachedOptions {
+                details: true,
+                ..OPTIONS
+            },
+            CachedOptions {
+                tool_names: true,
+                ..OPTIONS
+            },
+            CachedOptions {
+                owned_names: true,
+                ..OPTIONS
+            },
+        ] {
+            let cache = MetadataCache::load(&dir, options);
+            assert!(cache.game(library, "620", mtime).is_none(), "{:?}", options);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn games_without_a_manifest_time_are_not_kept() {
+        let dir =
+            std::env::temp_dir().join(format!("steamer-metadata-none-{}", std::process::id()));
+        let mut cache = MetadataCache::empty(&dir, OPTIONS);
+        let game = GameInfo {
+            manifest_mtime: None,
+            ..game()
+        };
+        cache.insert(Path::new("/games/steam"), &game, None);
+        assert!(cache.current.is_empty());
+    }
+
+    #[test]
+    fn notices_icons_changed_since_the_last_run() {
+        let dir =
+            std::env::temp_dir().join(format!("steamer-metadata-icon-{}", std::process::id()));
+        let art = dir.join("librarycache/620");
+        fs::create_dir_all(&art).unwrap();
+        let old = art.join(format!("{}.jpg", "a".repeat(40)));
+        fs::write(&old, b"icon").unwrap();
+        let old = old.to_str().unwrap();
+        let library = Path::new("/games/steam");
+        let mut cache = MetadataCache::empty(&dir, OPTIONS);
+        cache.insert(library, &game(), Some(old));
+        cache.save().unwrap();
+
+        let cache = MetadataCache::load(&dir, OPTIONS);
+        assert!(!cache.icon_changed(library, &game(), Some(old)));
+        assert!(cache.icon_changed(library, &game(), None));
+        assert!(cache.icon_changed(library, &game(), Some("/elsewhere/620_icon.jpg")));
+        let unseen = GameInfo {
+            appid: "400".to_string(),
+            ..game()
+        };
+        assert!(cache.icon_changed(library, &unseen, None));
+
+        // the same file rewritten in place
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(old)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(cache.icon_changed(library, &game(), Some(old)));
+        assert_eq!(cache.icon(library, &game()), None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
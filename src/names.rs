@@ -0,0 +1,278 @@
+//! Cleanup of the names shown in `Name=`. Only the displayed name changes; the original stays
+//! in `Keywords=`, so searching for it still finds the game.
+//!
+//! Rules run in a fixed order: the name is put in Unicode NFC, trademark symbols are removed, then
+//! emoji with `--strip-emoji`, then the `--strip-suffixes` suffixes, then the `[[name_rules]]` from the config in file order, and whitespace is
+//! collapsed last so every earlier rule can leave gaps behind.
+
+use crate::nfc;
+use crate::unicode;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::{Regex, RegexBuilder};
+
+/// Suffixes `--strip-suffixes` can drop, each its own toggle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NameSuffix {
+    /// "Demo"
+    Demo,
+    /// "Definitive Edition"
+    DefinitiveEdition,
+    /// "Deluxe Edition"
+    DeluxeEdition,
+    /// "Game of the Year Edition" and "GOTY Edition"
+    GotyEdition,
+    /// "Complete Edition"
+    CompleteEdition,
+    /// "Remastered"
+    Remastered,
+}
+
+impl NameSuffix {
+    /// The suffix as a regex alternation, matched without regard to case.
+    fn pattern(self) -> &'static str {
+        match self {
+            NameSuffix::Demo => "demo",
+            NameSuffix::DefinitiveEdition => "definitive edition",
+            NameSuffix::DeluxeEdition => "deluxe edition",
+            NameSuffix::GotyEdition => "(?:game of the year|goty) edition",
+            NameSuffix::CompleteEdition => "complete edition",
+            NameSuffix::Remastered => "remastered",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NameRules {
+    /// Remove ™, ® and © and collapse whitespace; on unless `--raw-names`.
+    builtin: bool,
+    /// Compose names to NFC; on unless `--no-nfc`.
+    nfc: bool,
+    /// `--strip-emoji`.
+    emoji: bool,
+    suffixes: Option<Regex>,
+    custom: Vec<(Regex, String)>,
+}
+
+impl NameRules {
+    /// `custom` holds the `(pattern, replace)` pairs from the config, checked here so a bad one
+    /// is reported with its position in the file.
+    pub fn new(
+        builtin: bool,
+        suffixes: &[NameSuffix],
+        custom: &[(String, String)],
+    ) -> Result<Self> {
+        let suffixes = (!suffixes.is_empty())
+            .then(|| {
+                let alternatives: Vec<&str> = suffixes.iter().map(|s| s.pattern()).collect();
+                // a separator before the suffix, or brackets around it, go with it
+                RegexBuilder::new(&format!(
+                    r"\s*[-–—:,]?\s*[(\[]?\b(?:{})\b[)\]]?\s*$",
+                    alternatives.join("|")
+                ))
+                .case_insensitive(true)
+                .build()
+            })
+            .transpose()?;
+        let custom = custom
+            .iter()
+            .enumerate()
+            .map(|(index, (pattern, replace))| {
+                Regex::new(pattern)
+                    .map(|regex| (regex, replace.clone()))
+                    .with_context(|| {
+                        format!("Invalid pattern in [[name_rules]] entry {}", index + 1)
+                    })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            builtin,
+            nfc: true,
+            emoji: false,
+            suffixes,
+            custom,
+        })
+    }
+
+    pub fn nfc(mut self, enabled: bool) -> Self {
+        self.nfc = enabled;
+        self
+    }
+
+    /// Removes emoji from names, see [`unicode::is_emoji`] for what counts.
+    pub fn strip_emoji(mut self, enabled: bool) -> Self {
+        self.emoji = enabled;
+        self
+    }
+
+    /// The name in NFC, unless that's turned off. This is all `apply` changes about names
+    /// without anything to clean up.
+    pub fn normalize(&self, name: &str) -> String {
+        if self.nfc {
+            nfc::nfc(name)
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// The name to display. A rule that would leave nothing is ignored, every game needs a name.
+    pub fn apply(&self, name: &str) -> String {
+        let mut out = self.normalize(name);
+        if self.builtin {
+            out.retain(|c| !matches!(c, '™' | '®' | '©'));
+        }
+        if self.emoji {
+            let stripped = unicode::strip_emoji(&out);
+            if !stripped.trim().is_empty() {
+                out = stripped.trim().to_string();
+            }
+        }
+        if let Some(ref suffixes) = self.suffixes {
+            let stripped = suffixes.replace(&out, "");
+            if !stripped.trim().is_empty() {
+                out = stripped.into_owned();
+            }
+        }
+        for (regex, replace) in &self.custom {
+            let replaced = regex.replace_all(&out, replace.as_str());
+            if !replaced.trim().is_empty() {
+                out = replaced.into_owned();
+            }
+        }
+        if self.builtin {
+            out = out.split_whitespace().collect::<Vec<_>>().join(" ");
+        }
+        if out.is_empty() {
+            name.to_string()
+        } else {
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_rules() {
+        let rules = NameRules::new(true, &[], &[]).unwrap();
+        for (name, expected) in [
+            ("Portal 2", "Portal 2"),
+            (
+                "Tom Clancy's Rainbow Six® Siege",
+                "Tom Clancy's Rainbow Six Siege",
+            ),
+            (
+                "Tom Clancy's™ Ghost Recon®  Wildlands",
+                "Tom Clancy's Ghost Recon Wildlands",
+            ),
+            ("DOOM Eternal ™", "DOOM Eternal"),
+            ("  Half-Life\t2  ", "Half-Life 2"),
+            (
+                "Sid Meier's Civilization® VI ©",
+                "Sid Meier's Civilization VI",
+            ),
+            // nothing but symbols keeps the name as it was
+            ("™", "™"),
+        ] {
+            assert_eq!(rules.apply(name), expected, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn raw_names_are_left_alone() {
+        let rules = NameRules::new(false, &[], &[]).unwrap();
+        for name in ["Tom Clancy's Rainbow Six® Siege", "  Half-Life\t2  "] {
+            assert_eq!(rules.apply(name), name);
+        }
+    }
+
+    #[test]
+    fn suffixes() {
+        let rules = NameRules::new(
+            true,
+            &[
+                NameSuffix::Demo,
+                NameSuffix::DefinitiveEdition,
+                NameSuffix::DeluxeEdition,
+                NameSuffix::GotyEdition,
+                NameSuffix::CompleteEdition,
+                NameSuffix::Remastered,
+            ],
+            &[],
+        )
+        .unwrap();
+        for (name, expected) in [
+            ("Portal 2 Demo", "Portal 2"),
+            ("Portal 2 (Demo)", "Portal 2"),
+            ("Democracy 4", "Democracy 4"),
+            ("Age of Empires II: Definitive Edition", "Age of Empires II"),
+            (
+                "Tom Clancy's™ Ghost Recon® — Deluxe Edition",
+                "Tom Clancy's Ghost Recon",
+            ),
+            (
+                "Batman: Arkham City - Game of the Year Edition",
+                "Batman: Arkham City",
+            ),
+            ("Fallout: New Vegas GOTY Edition", "Fallout: New Vegas"),
+            ("Mass Effect [Complete Edition]", "Mass Effect"),
+            ("Crysis Remastered", "Crysis"),
+            ("Remastered", "Remastered"),
+            ("Edition Wars", "Edition Wars"),
+        ] {
+            assert_eq!(rules.apply(name), expected, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn only_chosen_suffixes() {
+        let rules = NameRules::new(true, &[NameSuffix::Demo], &[]).unwrap();
+        assert_eq!(rules.apply("Portal Demo"), "Portal");
+        assert_eq!(rules.apply("Crysis Remastered"), "Crysis Remastered");
+    }
+
+    #[test]
+    fn custom_rules_run_in_order() {
+        let custom = [
+            (" - Deluxe Edition$".to_string(), String::new()),
+            (r"^The (.+)$".to_string(), "$1, The".to_string()),
+            (r"Counter-Strike 2".to_string(), "CS2".to_string()),
+            (r".*".to_string(), String::new()),
+        ];
+        let rules = NameRules::new(true, &[], &custom).unwrap();
+        for (name, expected) in [
+            ("The Witcher 3 - Deluxe Edition", "Witcher 3, The"),
+            ("Counter-Strike 2", "CS2"),
+            ("Portal", "Portal"),
+        ] {
+            assert_eq!(rules.apply(name), expected, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_custom_rules_name_their_index() {
+        let custom = [
+            ("ok".to_string(), String::new()),
+            ("(unclosed".to_string(), String::new()),
+        ];
+        let error = NameRules::new(true, &[], &custom).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid pattern in [[name_rules]] entry 2"
+        );
+    }
+
+    #[test]
+    fn nfc_and_emoji() {
+        let decomposed = "Poke\u{301}mon";
+        let rules = NameRules::new(true, &[], &[]).unwrap();
+        assert_eq!(rules.apply(decomposed), "Pokémon");
+        let rules = NameRules::new(true, &[], &[]).unwrap().nfc(false);
+        assert_eq!(rules.apply(decomposed), decomposed);
+        let rules = NameRules::new(true, &[], &[]).unwrap().strip_emoji(true);
+        assert_eq!(rules.apply("Cat Quest 🐱 Deluxe"), "Cat Quest Deluxe");
+        assert_eq!(rules.apply("🐱"), "🐱");
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Result, bail};
+use std::cell::Cell;
+#[cfg(feature = "network")]
+use {anyhow::Context, std::process::Command, std::time::Duration};
+
+#[cfg(feature = "network")]
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Fails for `flag` when steamer was built without the `network` feature, so the flags of
+/// network features still parse but say why they can't work.
+pub fn require_network(flag: &str) -> Result<()> {
+    if !cfg!(feature = "network") {
+        bail!(
+            "{} needs network support, and this steamer was built without it",
+            flag
+        );
+    }
+    Ok(())
+}
+
+/// The one place HTTP requests are made from. Requests go through the system `curl`, with the
+/// proxy settings resolved here so every network feature behaves the same way.
+pub struct HttpClient {
+    #[cfg_attr(not(feature = "network"), allow(dead_code))]
+    proxy: Option<String>,
+    offline: bool,
+    skipped: Cell<usize>,
+}
+
+impl HttpClient {
+    /// `proxy` overrides `HTTP_PROXY`/`HTTPS_PROXY`; `NO_PROXY` is honored either way.
+    pub fn new(proxy: Option<String>, offline: bool) -> Self {
+        Self {
+            proxy,
+            offline,
+            skipped: Cell::new(0),
+        }
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Number of requests that were not made because of `--offline`.
+    pub fn skipped_requests(&self) -> usize {
+        self.skipped.get()
+    }
+
+    /// Fetches `url`, returning `None` without touching the network when offline.
+    pub fn get(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        if self.offline {
+            self.skipped.set(self.skipped.get() + 1);
+            return Ok(None);
+        }
+        self.fetch(url).map(Some)
+    }
+
+    #[cfg(not(feature = "network"))]
+    fn fetch(&self, _url: &str) -> Result<Vec<u8>> {
+        bail!("steamer was built without network support")
+    }
+
+    #[cfg(feature = "network")]
+    fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--fail", "--location"])
+            .arg("--max-time")
+            .arg(TIMEOUT.as_secs().to_string());
+
+        if let Some(proxy) = self.proxy_for(url) {
+            cmd.arg("--proxy").arg(proxy);
+        }
+        if let Some(no_proxy) = env_any(&["NO_PROXY", "no_proxy"]) {
+            cmd.arg("--noproxy").arg(no_proxy);
+        }
+
+        let output = cmd
+            .arg(url)
+            .output()
+            .context("Could not run curl, is it installed?")?;
+        if !output.status.success() {
+            bail!(
+                "Request to {} failed: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(output.stdout)
+    }
+
+    /// curl ignores an uppercase `HTTP_PROXY`, so the environment is resolved here instead.
+    #[cfg(feature = "network")]
+    fn proxy_for(&self, url: &str) -> Option<String> {
+        if self.proxy.is_some() {
+            return self.proxy.clone();
+        }
+        let scheme_vars: &[&str] = if url.starts_with("https://") {
+            &["HTTPS_PROXY", "https_proxy"]
+        } else {
+            &["HTTP_PROXY", "http_proxy"]
+        };
+        env_any(scheme_vars).or_else(|| env_any(&["ALL_PROXY", "all_proxy"]))
+    }
+}
+
+#[cfg(feature = "network")]
+fn env_any(names: &[&str]) -> Option<String> {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offline_clients_skip_every_request() {
+        let http = HttpClient::new(None, true);
+        assert_eq!(http.get("https://example.com/a").unwrap(), None);
+        assert_eq!(http.get("https://example.com/b").unwrap(), None);
+        assert_eq!(http.skipped_requests(), 2);
+    }
+
+    #[test]
+    #[cfg(not(feature = "network"))]
+    fn builds_without_network_refuse_requests() {
+        let error = require_network("--protondb").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--protondb needs network support, and this steamer was built without it"
+        );
+        let http = HttpClient::new(None, false);
+        let error = http.get("https://example.com/").unwrap_err();
+        assert!(error.to_string().contains("without network support"));
+        assert_eq!(http.skipped_requests(), 0);
+    }
+
+    #[test]
+    #[cfg(feature = "network")]
+    fn builds_with_network_allow_requests() {
+        assert!(require_network("--protondb").is_ok());
+    }
+}
@@ -0,0 +1,952 @@
+//! Where the write phase sends each discovered game. Every sink sees the same entries from the
+//! one discovery pass, so `--emit desktop,json,html` costs a single scan.
+
+use crate::desktop::{self, EntryOptions};
+use crate::diff;
+use crate::disk;
+use crate::json::Value;
+use crate::launches;
+use crate::report::{Reporter, SCHEMA_VERSION};
+use crate::state::EntryState;
+use crate::steam::GameInfo;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmitKind {
+    /// steam-<appid>.desktop files in the applications directory
+    Desktop,
+    /// An index of every entry as JSON
+    Json,
+    /// A browsable HTML page with launch links
+    Html,
+}
+
+/// One game on its way out, with everything the sinks need to describe it.
+pub struct Entry<'a> {
+    pub game: &'a GameInfo,
+    /// The library it's installed in, `None` for owned games that aren't installed.
+    pub library: Option<&'a Path>,
+    pub icon: &'a str,
+    /// Kept out of menus, for games Family View only lets other accounts launch.
+    pub hidden: bool,
+    /// The folder the "Open save folder" action opens, with `--save-dir-action`.
+    pub save_dir: Option<&'a Path>,
+}
+
+/// The desktop file for an entry, with the keys that depend on more than the game itself.
+fn render(entry: &Entry, options: &EntryOptions) -> String {
+    let mut content = desktop::render_entry(entry.game, entry.icon, options);
+    if entry.hidden {
+        content.push_str("NoDisplay=true\n");
+    }
+    if let Some(dir) = entry.save_dir {
+        content.push_str(&desktop::save_dir_action(dir, options));
+    }
+    content
+}
+
+pub trait OutputSink {
+    /// Handles one entry, returning the file written for it if the sink writes one per entry.
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>>;
+
+    /// Called once every entry was seen, returning the file written if there is one.
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        Ok(None)
+    }
+}
+
+pub struct DesktopSink<'a> {
+    app_dir: &'a Path,
+    options: &'a EntryOptions,
+    /// With `--verify-writes`, where to count the entries that never read back right.
+    verify: Option<(&'a Reporter, &'a Cell<usize>)>,
+    /// Where to note each entry written, for `--repair` to check it against later.
+    state: Option<&'a RefCell<EntryState>>,
+}
+
+impl<'a> DesktopSink<'a> {
+    pub fn new(app_dir: &'a Path, options: &'a EntryOptions) -> Self {
+        Self {
+            app_dir,
+            options,
+            verify: None,
+            state: None,
+        }
+    }
+
+    /// Records the hash of every entry written in `state`.
+    pub fn record_in(mut self, state: &'a RefCell<EntryState>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Reads every entry back after writing it, see [`desktop::create_verified`].
+    pub fn verify_writes(mut self, reporter: &'a Reporter, failures: &'a Cell<usize>) -> Self {
+        self.verify = Some((reporter, failures));
+        self
+    }
+}
+
+impl OutputSink for DesktopSink<'_> {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        let path = self.options.entry_path(self.app_dir, entry.game);
+        let content = render(entry, self.options);
+        match self.verify {
+            None => desktop::create_desktop_file(&path, &content)
+                .with_context(|| format!("Could not write {:?}", path))?,
+            Some((reporter, failures)) => {
+                let verified = desktop::create_verified(&path, &content, reporter)
+                    .with_context(|| format!("Could not write {:?}", path))?;
+                if !verified {
+                    failures.set(failures.get() + 1);
+                    bail!(
+                        "{:?} didn't read back as written after {} tries",
+                        path,
+                        desktop::VERIFY_ATTEMPTS
+                    );
+                }
+            }
+        }
+        if let Some(state) = self.state {
+            state.borrow_mut().record(&path, &content);
+        }
+        Ok(Some(path))
+    }
+}
+
+/// `--dry-run-write`: every entry that would be written, printed to stdout at the end as one
+/// stream sorted by filename, each file under a comment naming its target path.
+pub struct StdoutSink<'a> {
+    app_dir: &'a Path,
+    options: &'a EntryOptions,
+    files: Vec<(PathBuf, String)>,
+}
+
+impl<'a> StdoutSink<'a> {
+    pub fn new(app_dir: &'a Path, options: &'a EntryOptions) -> Self {
+        Self {
+            app_dir,
+            options,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for StdoutSink<'_> {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        let content = render(entry, self.options);
+        self.files
+            .push((self.options.entry_path(self.app_dir, entry.game), content));
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        self.files.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut out = io::stdout().lock();
+        for (path, content) in &self.files {
+            writeln!(out, "# ---- {} ----", path.display())?;
+            writeln!(out, "{}", content)?;
+        }
+        Ok(None)
+    }
+}
+
+/// `--diff-output`: a unified diff between the entries on disk and the ones the run would
+/// write, printed at the end sorted by filename. With `removes_stale` the generated entries the
+/// run didn't write are diffed against nothing too, as the cleanup would delete them.
+pub struct DiffSink<'a> {
+    app_dir: &'a Path,
+    options: &'a EntryOptions,
+    removes_stale: bool,
+    files: Vec<(PathBuf, String)>,
+}
+
+impl<'a> DiffSink<'a> {
+    pub fn new(app_dir: &'a Path, options: &'a EntryOptions, removes_stale: bool) -> Self {
+        Self {
+            app_dir,
+            options,
+            removes_stale,
+            files: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for DiffSink<'_> {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        let content = render(entry, self.options);
+        self.files
+            .push((self.options.entry_path(self.app_dir, entry.game), content));
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        let mut diffs: Vec<(PathBuf, String)> = Vec::new();
+        for (path, content) in &self.files {
+            let name = path.display().to_string();
+            let diff = match fs::read_to_string(path) {
+                Ok(old) => diff::unified(Some(&name), Some(&name), &old, content),
+                Err(_) => diff::unified(None, Some(&name), "", content),
+            };
+            diffs.push((path.clone(), diff));
+        }
+        if self.removes_stale
+            && let Ok(paths) = desktop::generated_entries(self.app_dir)
+        {
+            for path in paths {
+                if self.files.iter().any(|(p, _)| *p == path) {
+                    continue;
+                }
+                let Ok(old) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let name = path.display().to_string();
+                diffs.push((path, diff::unified(Some(&name), None, &old, "")));
+            }
+        }
+        diffs.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut out = io::stdout().lock();
+        for (_, diff) in diffs {
+            out.write_all(diff.as_bytes())?;
+        }
+        Ok(None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// A Nix attribute set for Home Manager's xdg.desktopEntries
+    HomeManager,
+}
+
+/// `steamer export --format home-manager`: the entries as a Home Manager module, so they can
+/// be managed declaratively instead of written into the applications directory. Each value is
+/// the one the desktop file would have, since Home Manager writes them out as given. Entries
+/// are sorted by name of the desktop file so the expression only changes when the games do.
+pub struct HomeManagerSink<'a> {
+    options: &'a EntryOptions,
+    output: Option<PathBuf>,
+    entries: Vec<(String, String)>,
+}
+
+impl<'a> HomeManagerSink<'a> {
+    pub fn new(options: &'a EntryOptions, output: Option<PathBuf>) -> Self {
+        Self {
+            options,
+            output,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for HomeManagerSink<'_> {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        let mut attrs = String::new();
+        let mut settings = Vec::new();
+        for (key, value) in desktop::entry_fields(entry.game, entry.icon, self.options) {
+            let option = match key.as_str() {
+                "Name" => "name",
+                "GenericName" => "genericName",
+                "Comment" => "comment",
+                "Exec" => "exec",
+                "Icon" => "icon",
+                "Type" => "type",
+                "Terminal" => {
+                    attrs.push_str(&format!("      terminal = {};\n", value));
+                    continue;
+                }
+                "Categories" => {
+                    let categories: Vec<String> = value
+                        .split(';')
+                        .filter(|c| !c.is_empty())
+                        .map(nix_string)
+                        .collect();
+                    attrs.push_str(&format!(
+                        "      categories = [ {} ];\n",
+                        categories.join(" ")
+                    ));
+                    continue;
+                }
+                _ => {
+                    settings.push((key, value));
+                    continue;
+                }
+            };
+            attrs.push_str(&format!("      {} = {};\n", option, nix_string(&value)));
+        }
+        if entry.hidden {
+            attrs.push_str("      noDisplay = true;\n");
+        }
+        if !settings.is_empty() {
+            attrs.push_str("      settings = {\n");
+            for (key, value) in &settings {
+                attrs.push_str(&format!(
+                    "        {} = {};\n",
+                    nix_attr_name(key),
+                    nix_string(value)
+                ));
+            }
+            attrs.push_str("      };\n");
+        }
+        if let Some(dir) = entry.save_dir {
+            attrs.push_str("      actions = {\n");
+            attrs.push_str(&format!(
+                "        {} = {{\n",
+                nix_attr_name(desktop::SAVE_DIR_ACTION)
+            ));
+            attrs.push_str(&format!(
+                "          name = {};\n",
+                nix_string("Open save folder")
+            ));
+            attrs.push_str(&format!(
+                "          exec = {};\n",
+                nix_string(&desktop::save_dir_exec(dir, self.options))
+            ));
+            attrs.push_str("        };\n      };\n");
+        }
+        let id = desktop_filename(entry.game);
+        let id = id.trim_end_matches(".desktop").to_string();
+        self.entries.push((id, attrs));
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        self.entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut module = String::from("{\n  xdg.desktopEntries = {\n");
+        for (id, attrs) in &self.entries {
+            module.push_str(&format!(
+                "    {} = {{\n{}    }};\n",
+                nix_attr_name(id),
+                attrs
+            ));
+        }
+        module.push_str("  };\n}\n");
+        match self.output {
+            Some(ref path) => {
+                write_file(path, &module)?;
+                Ok(Some(path.clone()))
+            }
+            None => {
+                io::stdout().lock().write_all(module.as_bytes())?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+pub fn desktop_filename(game: &GameInfo) -> String {
+    format!("steam-{}.desktop", game.appid)
+}
+
+/// Filenames of the index files in the index directory.
+pub const JSON_INDEX: &str = "steamer-index.json";
+pub const HTML_INDEX: &str = "steamer-index.html";
+
+/// `steamer-index.json`, rewritten whole at the end of the run.
+pub struct JsonIndexSink {
+    path: PathBuf,
+    entries: Vec<Value>,
+}
+
+impl JsonIndexSink {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(JSON_INDEX),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for JsonIndexSink {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        let game = entry.game;
+        self.entries.push(
+            Value::object()
+                .with("appid", game.appid.as_str())
+                .with("name", game.name.as_str())
+                .with("icon", entry.icon)
+                .with("library", entry.library)
+                .with("installdir", game.installdir.as_deref())
+                .with("size_on_disk", game.size_on_disk)
+                .with("incomplete", game.incomplete)
+                .with("installed", !game.not_installed)
+                .with("hidden", entry.hidden)
+                .with("launch_url", launch_url(game)),
+        );
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        let index = Value::object()
+            .with("schema_version", SCHEMA_VERSION)
+            .with("entries", Value::Array(std::mem::take(&mut self.entries)));
+        write_file(&self.path, &(index.to_pretty() + "\n"))?;
+        Ok(Some(self.path.clone()))
+    }
+}
+
+/// `steamer-index.html`, a plain page linking every game to its `steam://` URL.
+pub struct HtmlSink {
+    path: PathBuf,
+    rows: String,
+}
+
+impl HtmlSink {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(HTML_INDEX),
+            rows: String::new(),
+        }
+    }
+}
+
+impl OutputSink for HtmlSink {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        if entry.hidden {
+            return Ok(None);
+        }
+        let game = entry.game;
+        let icon = if entry.icon.starts_with('/') {
+            format!(
+                "<img src=\"file://{}\" alt=\"\" width=\"32\" height=\"32\">",
+                escape_html(entry.icon)
+            )
+        } else {
+            String::new()
+        };
+        self.rows.push_str(&format!(
+            "<tr><td>{}</td><td><a href=\"{}\">{}</a></td><td>{}</td></tr>\n",
+            icon,
+            launch_url(game),
+            escape_html(&game.name),
+            escape_html(&game.appid)
+        ));
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        let page = format!(
+            "<!DOCTYPE html>\n\
+            <html>\n\
+            <head><meta charset=\"utf-8\"><title>Steam games</title></head>\n\
+            <body>\n\
+            <table>\n\
+            <tr><th></th><th>Name</th><th>AppID</th></tr>\n\
+            {}</table>\n\
+            </body>\n\
+            </html>\n",
+            self.rows
+        );
+        write_file(&self.path, &page)?;
+        Ok(Some(self.path.clone()))
+    }
+}
+
+/// The order of the `--emit-markdown` rows, ties going by name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MarkdownSort {
+    /// By name, ignoring case, the order `steamer select` lists games in
+    #[default]
+    Name,
+    /// By app ID, as a number
+    AppId,
+    /// Largest first, games of unknown size last
+    Size,
+    /// Most recently played first, games never played last
+    LastPlayed,
+}
+
+/// `--emit-markdown <path>`: a table of the games with their app ID, size, last played time and
+/// library, to paste into a wiki or a README. Rows are sorted by `--sort`, then by name ignoring
+/// case and by app ID, so the table only changes when the games do.
+pub struct MarkdownSink {
+    path: PathBuf,
+    sort: MarkdownSort,
+    rows: Vec<MarkdownRow>,
+}
+
+struct MarkdownRow {
+    /// Lowercased, for sorting.
+    name: String,
+    appid: String,
+    size: Option<u64>,
+    last_played: Option<u64>,
+    cells: String,
+}
+
+impl MarkdownSink {
+    pub fn new(path: PathBuf, sort: MarkdownSort) -> Self {
+        Self {
+            path,
+            sort,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for MarkdownSink {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        if entry.hidden {
+            return Ok(None);
+        }
+        let game = entry.game;
+        let size = game.size_on_disk.map(disk::format_size).unwrap_or_default();
+        let last_played = game
+            .last_played
+            .and_then(|time| i64::try_from(time).ok())
+            .map(launches::local_time)
+            .unwrap_or_default();
+        let library = match entry.library {
+            Some(path) => escape_markdown(&path.to_string_lossy()),
+            None => "not installed".to_string(),
+        };
+        let cells = format!(
+            "| {} | {} | {} | {} | {} |\n",
+            escape_markdown(&game.name),
+            game.appid,
+            size,
+            last_played,
+            library
+        );
+        self.rows.push(MarkdownRow {
+            name: game.name.to_lowercase(),
+            appid: game.appid.clone(),
+            size: game.size_on_disk,
+            last_played: game.last_played,
+            cells,
+        });
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        let by_name =
+            |a: &MarkdownRow, b: &MarkdownRow| (&a.name, &a.appid).cmp(&(&b.name, &b.appid));
+        let appid = |row: &MarkdownRow| row.appid.parse::<u64>().ok();
+        match self.sort {
+            MarkdownSort::Name => self.rows.sort_by(by_name),
+            MarkdownSort::AppId => self
+                .rows
+                .sort_by(|a, b| appid(a).cmp(&appid(b)).then_with(|| by_name(a, b))),
+            // reversed, so the games without one come last
+            MarkdownSort::Size => self
+                .rows
+                .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| by_name(a, b))),
+            MarkdownSort::LastPlayed => self.rows.sort_by(|a, b| {
+                b.last_played
+                    .cmp(&a.last_played)
+                    .then_with(|| by_name(a, b))
+            }),
+        }
+        let mut table = String::from(
+            "| Name | App ID | Size | Last played | Library |\n\
+            | --- | ---: | ---: | --- | --- |\n",
+        );
+        for row in &self.rows {
+            table.push_str(&row.cells);
+        }
+        write_file(&self.path, &table)?;
+        Ok(Some(self.path.clone()))
+    }
+}
+
+/// `--emit-aliases <path>`: a shell function per game, named `steam_` and the game's name as an
+/// identifier, that launches it with the command of its `Exec=`, wrappers and all. A path ending
+/// in `.fish` gets fish functions, anything else functions for sh, bash and zsh. Functions are
+/// sorted by name, so the file only changes when the games do.
+pub struct AliasSink<'a> {
+    path: PathBuf,
+    options: &'a EntryOptions,
+    /// (function name, appid, command) of each game
+    games: Vec<(String, String, String)>,
+}
+
+impl<'a> AliasSink<'a> {
+    pub fn new(path: PathBuf, options: &'a EntryOptions) -> Self {
+        Self {
+            path,
+            options,
+            games: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for AliasSink<'_> {
+    fn write(&mut self, entry: &Entry) -> Result<Option<PathBuf>> {
+        if !entry.hidden {
+            let game = entry.game;
+            let command: Vec<String> = self
+                .options
+                .launch_args(game)
+                .iter()
+                .map(|arg| desktop::quote_shell_arg(arg))
+                .collect();
+            self.games
+                .push((alias_name(game), game.appid.clone(), command.join(" ")));
+        }
+        Ok(None)
+    }
+
+    fn finish(&mut self) -> Result<Option<PathBuf>> {
+        // games whose names make the same identifier all get their appid added
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (name, _, _) in &self.games {
+            *counts.entry(name).or_default() += 1;
+        }
+        let mut functions: Vec<(String, &str)> = self
+            .games
+            .iter()
+            .map(|(name, appid, command)| match counts[name.as_str()] {
+                1 => (name.clone(), command.as_str()),
+                _ => (format!("{}_{}", name, appid), command.as_str()),
+            })
+            .collect();
+        functions.sort();
+
+        let fish = self.path.extension().is_some_and(|ext| ext == "fish");
+        let mut script = String::from("# Written by steamer, a function launching each game.\n");
+        for (name, command) in functions {
+            if fish {
+                script.push_str(&format!("function {}; {}; end\n", name, command));
+            } else {
+                script.push_str(&format!("{}() {{ {}; }}\n", name, command));
+            }
+        }
+        write_file(&self.path, &script)?;
+        Ok(Some(self.path.clone()))
+    }
+}
+
+/// `steam_` and the words of the name in lowercase ASCII, joined by `_`. A name without any,
+/// like one in another script, gets the appid instead.
+fn alias_name(game: &GameInfo) -> String {
+    // "Tom Clancy's" reads better as tom_clancys than tom_clancy_s
+    let name = game.name.replace(['\'', '’'], "");
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    if words.is_empty() {
+        format!("steam_{}", game.appid)
+    } else {
+        format!("steam_{}", words.join("_"))
+    }
+}
+
+fn launch_url(game: &GameInfo) -> String {
+    if game.not_installed {
+        format!("steam://install/{}", game.appid)
+    } else {
+        format!("steam://rungameid/{}", game.run_id())
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(path, content).with_context(|| format!("Could not write {:?}", path))
+}
+
+/// Backslash-escapes the characters that would start formatting, a link or a new cell. Line
+/// breaks, which would end the table row, become a space.
+fn escape_markdown(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '\n' || c == '\r' {
+            if !out.ends_with(' ') {
+                out.push(' ');
+            }
+            continue;
+        }
+        if "\\`*_[]<>|~#".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// A double quoted Nix string. `${` would start an interpolation, so its `$` is escaped.
+fn nix_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// An attribute name, quoted unless it's a plain identifier like `steam-620`.
+fn nix_attr_name(name: &str) -> String {
+    let mut chars = name.chars();
+    let plain = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '\''));
+    if plain {
+        name.to_string()
+    } else {
+        nix_string(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::desktop::ExecMode;
+
+    fn game(appid: &str, name: &str) -> GameInfo {
+        GameInfo {
+            appid: appid.to_string(),
+            name: name.to_string(),
+            ..GameInfo::default()
+        }
+    }
+
+    /// The script `--emit-aliases` writes to a file named `filename` for `games`.
+    fn aliases(filename: &str, options: &EntryOptions, games: &[GameInfo]) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "steamer-aliases-test-{}-{}",
+            std::process::id(),
+            filename
+        ));
+        let mut sink = AliasSink::new(path.clone(), options);
+        for game in games {
+            let entry = Entry {
+                game,
+                library: None,
+                icon: "steam",
+                hidden: false,
+                save_dir: None,
+            };
+            sink.write(&entry).unwrap();
+        }
+        sink.finish().unwrap();
+        let script = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        script
+    }
+
+    #[test]
+    fn markdown_has_a_row_per_game() {
+        let path =
+            std::env::temp_dir().join(format!("steamer-markdown-test-{}.md", std::process::id()));
+        let mut sink = MarkdownSink::new(path.clone(), MarkdownSort::Name);
+        let games = [
+            game("620", "portal 2"),
+            game("400", "Portal\r\nDemo\n"),
+            game("1091500", "*Cyber_punk* [2077] | <Deluxe>"),
+            game("570", "Dota 2"),
+        ];
+        for (i, game) in games.iter().enumerate() {
+            let entry = Entry {
+                game,
+                library: Some(Path::new("/games")),
+                icon: "steam",
+                hidden: i == 3,
+                save_dir: None,
+            };
+            sink.write(&entry).unwrap();
+        }
+        sink.finish().unwrap();
+        let table = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            [
+                "| Name | App ID | Size | Last played | Library |",
+                "| --- | ---: | ---: | --- | --- |",
+                r"| \*Cyber\_punk\* \[2077\] \| \<Deluxe\> | 1091500 |  |  | /games |",
+                "| Portal Demo  | 400 |  |  | /games |",
+                "| portal 2 | 620 |  |  | /games |",
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_rows_follow_the_sort() {
+        let game =
+            |appid: &str, name: &str, size: Option<u64>, last_played: Option<u64>| GameInfo {
+                size_on_disk: size,
+                last_played,
+                ..game(appid, name)
+            };
+        let games = [
+            game("620", "Portal 2", Some(12 << 30), Some(1_700_000_000)),
+            game("1145360", "Hades", Some(15 << 30), None),
+            game("400", "Portal", None, Some(1_600_000_000)),
+            game("570", "Dota 2", Some(12 << 30), Some(1_700_000_000)),
+        ];
+        let sorted = |sort: MarkdownSort| {
+            let path = std::env::temp_dir().join(format!(
+                "steamer-markdown-{:?}-test-{}.md",
+                sort,
+                std::process::id()
+            ));
+            let mut sink = MarkdownSink::new(path.clone(), sort);
+            for game in &games {
+                let entry = Entry {
+                    game,
+                    library: None,
+                    icon: "steam",
+                    hidden: false,
+                    save_dir: None,
+                };
+                sink.write(&entry).unwrap();
+            }
+            sink.finish().unwrap();
+            let table = fs::read_to_string(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            table
+                .lines()
+                .skip(2)
+                .map(|row| row.split(" | ").nth(1).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sorted(MarkdownSort::Name), ["570", "1145360", "400", "620"]);
+        assert_eq!(
+            sorted(MarkdownSort::AppId),
+            ["400", "570", "620", "1145360"]
+        );
+        assert_eq!(sorted(MarkdownSort::Size), ["1145360", "570", "620", "400"]);
+        assert_eq!(
+            sorted(MarkdownSort::LastPlayed),
+            ["570", "620", "400", "1145360"]
+        );
+    }
+
+    #[test]
+    fn names_functions_after_games() {
+        let games = [
+            game("1145360", "Hades"),
+            game("1091500", "Tom Clancy's Rainbow Six"),
+            game("400", "Portal"),
+            game("401", "PORTAL"),
+            game("570", "ドータ"),
+        ];
+        let script = aliases("names.sh", &EntryOptions::default(), &games);
+        assert_eq!(
+            script.lines().skip(1).collect::<Vec<_>>(),
+            [
+                "steam_570() { steam steam://rungameid/570; }",
+                "steam_hades() { steam steam://rungameid/1145360; }",
+                "steam_portal_400() { steam steam://rungameid/400; }",
+                "steam_portal_401() { steam steam://rungameid/401; }",
+                "steam_tom_clancys_rainbow_six() { steam steam://rungameid/1091500; }",
+            ]
+        );
+    }
+
+    #[test]
+    fn launches_like_the_entries() {
+        let options = EntryOptions {
+            steam_binary: Some(PathBuf::from("/opt/Steam Runtime/steam")),
+            big_picture: true,
+            wait_for_steam: Some(PathBuf::from("/usr/bin/steamer")),
+            ..EntryOptions::default()
+        };
+        let script = aliases("wrapped.fish", &options, &[game("620", "Portal 2")]);
+        assert_eq!(
+            script.lines().nth(1),
+            Some(
+                "function steam_portal_2; /usr/bin/steamer wait-for-steam \
+                 '--start=/opt/Steam Runtime/steam' -- '/opt/Steam Runtime/steam' -bigpicture \
+                 -applaunch 620; end"
+            )
+        );
+
+        let options = EntryOptions {
+            exec_mode: ExecMode::XdgOpen,
+            ..EntryOptions::default()
+        };
+        let script = aliases("xdg.sh", &options, &[game("620", "Portal 2")]);
+        assert!(script.contains("steam_portal_2() { xdg-open steam://rungameid/620; }\n"));
+    }
+
+    #[test]
+    fn home_manager_module_escapes_nix_strings() {
+        let path =
+            std::env::temp_dir().join(format!("steamer-nix-test-{}.nix", std::process::id()));
+        let options = EntryOptions {
+            exec_mode: ExecMode::XdgOpen,
+            ..EntryOptions::default()
+        };
+        let mut sink = HomeManagerSink::new(&options, Some(path.clone()));
+        let games = [
+            game("620", "Portal 2"),
+            game("1091500", "Say \"${HOME}\" \\ $5"),
+        ];
+        for (i, game) in games.iter().enumerate() {
+            let entry = Entry {
+                game,
+                library: None,
+                icon: "/icons/game.jpg",
+                hidden: i == 1,
+                save_dir: None,
+            };
+            sink.write(&entry).unwrap();
+        }
+        sink.finish().unwrap();
+        let module = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            module.lines().collect::<Vec<_>>(),
+            [
+                "{",
+                "  xdg.desktopEntries = {",
+                "    steam-1091500 = {",
+                // the desktop file's `\\` for a backslash, escaped again for Nix
+                r#"      name = "Say \"\${HOME}\" \\\\ $5";"#,
+                r#"      exec = "xdg-open steam://rungameid/1091500";"#,
+                r#"      icon = "/icons/game.jpg";"#,
+                "      terminal = false;",
+                r#"      type = "Application";"#,
+                r#"      categories = [ "Game" ];"#,
+                "      noDisplay = true;",
+                "    };",
+                "    steam-620 = {",
+                r#"      name = "Portal 2";"#,
+                r#"      exec = "xdg-open steam://rungameid/620";"#,
+                r#"      icon = "/icons/game.jpg";"#,
+                "      terminal = false;",
+                r#"      type = "Application";"#,
+                r#"      categories = [ "Game" ];"#,
+                "    };",
+                "  };",
+                "}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! `--include-uninstalled`: games the account owns but hasn't installed, from the Steam Web
+//! API. The list and the icons fetched for it are cached, so runs without a network still
+//! produce the same entries, and runs with one only ask again once a day.
+
+use crate::icons;
+use crate::json;
+use crate::net::HttpClient;
+use crate::report::Reporter;
+use crate::steam::{self, GameInfo};
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const OWNED_GAMES_URL: &str = "https://api.steampowered.com/IPlayerService/GetOwnedGames/v1/";
+const ICON_URL: &str = "https://media.steampowered.com/steamcommunity/public/images/apps/";
+
+/// How long a fetched list is used before asking the API again.
+const MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// An owned game as the Web API describes it.
+pub struct OwnedGame {
+    pub appid: String,
+    pub name: String,
+    /// Hash naming the game's icon on the community CDN.
+    icon_hash: Option<String>,
+}
+
+impl OwnedGame {
+    pub fn to_game_info(&self) -> GameInfo {
+        GameInfo {
+            appid: self.appid.clone(),
+            name: self.name.clone(),
+            not_installed: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The owned games list of one account, read through the cache in `cache_dir`.
+pub struct OwnedGames<'a> {
+    http: &'a HttpClient,
+    cache_dir: &'a Path,
+}
+
+impl<'a> OwnedGames<'a> {
+    pub fn new(http: &'a HttpClient, cache_dir: &'a Path) -> Self {
+        Self { http, cache_dir }
+    }
+
+    /// Every game `steam_id` owns. A cached list younger than a day is used as is; an older
+    /// one only when the API can't be reached, with a warning. With neither, this is an error.
+    pub fn load(
+        &self,
+        api_key: &str,
+        steam_id: &str,
+        reporter: &Reporter,
+    ) -> Result<Vec<OwnedGame>> {
+        let cache = self
+            .cache_dir
+            .join(format!("owned-games-{}.json", steam_id));
+        let cached = fs::read_to_string(&cache).ok();
+        let age = fs::metadata(&cache)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if let Some(ref text) = cached
+            && age.is_some_and(|age| age < MAX_AGE)
+            && let Ok(games) = parse_owned_games(text)
+        {
+            return Ok(games);
+        }
+
+        let url = format!(
+            "{}?key={}&steamid={}&include_appinfo=1&include_played_free_games=1&format=json",
+            OWNED_GAMES_URL, api_key, steam_id
+        );
+        // the key is part of the URL, so errors from curl are reported without it
+        let fetched = match self.http.get(&url) {
+            Ok(Some(body)) => Some(String::from_utf8_lossy(&body).into_owned()),
+            Ok(None) => None,
+            Err(_) => {
+                reporter.warn("Could not fetch the owned games list from the Steam Web API");
+                None
+            }
+        };
+        if let Some(text) = fetched {
+            let games = parse_owned_games(&text).context("Unexpected owned games response")?;
+            if let Err(e) = write_cache(&cache, text.as_bytes()) {
+                reporter.warn(format!("{:#}", e));
+            }
+            return Ok(games);
+        }
+
+        match cached {
+            Some(text) => {
+                if !self.http.is_offline() {
+                    reporter.warn("Using the cached owned games list, it may be out of date");
+                }
+                parse_owned_games(&text).with_context(|| format!("Could not parse {:?}", cache))
+            }
+            None => bail!("The list couldn't be fetched and none is cached yet"),
+        }
+    }
+
+    /// The game's icon in the cache, downloaded from the CDN the first time it's asked for.
+    /// `None` when there's no icon or it can't be fetched, so the entry falls back to Steam's.
+    /// Icons are named by the CDN's hash of their content, so games sharing art, like a game and
+    /// its soundtrack, share one file. A file an earlier version saved by appid is renamed, and
+    /// one it left unfinished is removed once the icon was fetched again.
+    pub fn icon(&self, game: &OwnedGame) -> Option<PathBuf> {
+        let hash = game
+            .icon_hash
+            .as_deref()
+            .filter(|hash| !hash.is_empty() && hash.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        let path = self.cache_dir.join(format!("icons/{}.jpg", hash));
+        // one an earlier run didn't finish downloading is fetched again
+        if icons::is_complete_image(&path) {
+            return Some(path);
+        }
+        let legacy = self.cache_dir.join(format!("icons/{}.jpg", game.appid));
+        if icons::is_complete_image(&legacy) && fs::rename(&legacy, &path).is_ok() {
+            return Some(path);
+        }
+        let url = format!("{}{}/{}.jpg", ICON_URL, game.appid, hash);
+        let body = self.http.get(&url).ok()??;
+        write_cache(&path, &body).ok()?;
+        if !icons::is_complete_image(&path) {
+            return None;
+        }
+        fs::remove_file(&legacy).ok();
+        Some(path)
+    }
+}
+
+fn parse_owned_games(text: &str) -> Result<Vec<OwnedGame>> {
+    let root = json::parse(text)?;
+    let response = root.get("response").context("Missing response")?;
+    Ok(response
+        .get("games")
+        .map(json::Value::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|game| {
+            let appid = game
+                .get("appid")?
+                .to_text()
+                .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))?;
+            let name = game
+                .get("name")
+                .and_then(json::Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| steam::placeholder_name(&appid));
+            let icon_hash = game
+                .get("img_icon_url")
+                .and_then(json::Value::as_str)
+                .filter(|hash| !hash.is_empty() && hash.bytes().all(|b| b.is_ascii_hexdigit()))
+                .map(str::to_string);
+            Some(OwnedGame {
+                appid,
+                name,
+                icon_hash,
+            })
+        })
+        .collect())
+}
+
+fn write_cache(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(path, content).with_context(|| format!("Could not write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+
+    #[test]
+    fn reads_games_from_the_api_response() {
+        let games = parse_owned_games(
+            r#"{"response": {"game_count": 4, "games": [
+                {"appid": 620, "name": "Portal 2", "img_icon_url": "2e478fc6874d06ae5baf0d147f6f21203291aa02"},
+                {"appid": 1145360, "img_icon_url": ""},
+                {"appid": "abc", "name": "Not a game"},
+                {"appid": 400, "name": "Portal", "img_icon_url": "../../etc/passwd"}
+            ]}}"#,
+        )
+        .unwrap();
+        let games: Vec<(&str, &str, Option<&str>)> = games
+            .iter()
+            .map(|game| {
+                (
+                    game.appid.as_str(),
+                    game.name.as_str(),
+                    game.icon_hash.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            games,
+            [
+                (
+                    "620",
+                    "Portal 2",
+                    Some("2e478fc6874d06ae5baf0d147f6f21203291aa02")
+                ),
+                ("1145360", steam::placeholder_name("1145360").as_str(), None),
+                ("400", "Portal", None),
+            ]
+        );
+
+        // an account with no games, or a private profile, has no games array
+        assert!(parse_owned_games(r#"{"response": {}}"#).unwrap().is_empty());
+        assert!(parse_owned_games(r#"{"error": "bad key"}"#).is_err());
+    }
+
+    #[test]
+    fn offline_runs_use_the_cached_list_however_old() {
+        let reporter = Reporter::new(LogFormat::Human, false);
+        let dir = std::env::temp_dir().join(format!("steamer-owned-test-{}", std::process::id()));
+        let http = HttpClient::new(None, true);
+        let owned = OwnedGames::new(&http, &dir);
+
+        let error = owned.load("KEY", "76561197960287930", &reporter).err();
+        assert!(error.is_some_and(|e| e.to_string().contains("none is cached yet")));
+
+        let cache = dir.join("owned-games-76561197960287930.json");
+        write_cache(
+            &cache,
+            br#"{"response": {"games": [{"appid": 620, "name": "Portal 2"}]}}"#,
+        )
+        .unwrap();
+        let week_ago = SystemTime::now() - Duration::from_secs(7 * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(&cache)
+            .unwrap()
+            .set_modified(week_ago)
+            .unwrap();
+        let games = owned.load("KEY", "76561197960287930", &reporter).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].to_game_info().name, "Portal 2");
+        assert!(games[0].to_game_info().not_installed);
+        assert_eq!(http.skipped_requests(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn icons_saved_by_appid_are_renamed_to_their_hash() {
+        let dir =
+            std::env::temp_dir().join(format!("steamer-owned-icons-test-{}", std::process::id()));
+        let http = HttpClient::new(None, true);
+        let owned = OwnedGames::new(&http, &dir);
+        let game = |appid: &str| OwnedGame {
+            appid: appid.to_string(),
+            name: "Portal 2".to_string(),
+            icon_hash: Some("2e478fc6874d06ae5baf0d147f6f21203291aa02".to_string()),
+        };
+        let png = include_bytes!("../assets/proton-badge.png");
+        write_cache(&dir.join("icons/620.jpg"), png).unwrap();
+
+        let hashed = dir.join("icons/2e478fc6874d06ae5baf0d147f6f21203291aa02.jpg");
+        assert_eq!(owned.icon(&game("620")), Some(hashed.clone()));
+        assert!(!dir.join("icons/620.jpg").exists());
+        assert_eq!(fs::read(&hashed).unwrap(), png);
+        // a game with the same art finds it without a request
+        assert_eq!(owned.icon(&game("1145360")), Some(hashed));
+        assert_eq!(http.skipped_requests(), 0);
+
+        // one cut short is left for the fetch, which an offline run can't do
+        write_cache(&dir.join("icons/400.jpg"), &png[..png.len() / 2]).unwrap();
+        let portal = OwnedGame {
+            icon_hash: Some("aa".repeat(20)),
+            ..game("400")
+        };
+        assert_eq!(owned.icon(&portal), None);
+        assert_eq!(http.skipped_requests(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,620 @@
+use crate::cli::Args;
+use crate::config::Config;
+use crate::desktop::DesktopEnvironment;
+use crate::report::Reporter;
+use crate::steam;
+use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// Where steamer reads from and writes to, resolved once from the arguments.
+pub struct Paths {
+    pub steam_root: PathBuf,
+    pub library_vdf: PathBuf,
+    /// The last libraryfolders.vdf that could be read, for when Steam is mid-write.
+    pub library_cache: PathBuf,
+    /// steamer's own cache, for the owned games list and the icons fetched with it.
+    pub cache_dir: PathBuf,
+    pub icon_cache: PathBuf,
+    pub app_dir: PathBuf,
+    /// Where the json and html sinks write their index files.
+    pub index_dir: PathBuf,
+    /// Libraries Steam doesn't list, from `--extra-library` and the config.
+    pub extra_libraries: Vec<PathBuf>,
+    /// `.steamerignore` files to read skip entries from, whether or not they exist.
+    pub ignore_files: Vec<PathBuf>,
+}
+
+impl Paths {
+    /// With `read_only` set the applications directory is never created. Extra libraries are
+    /// read from the config file here, so every command sees the same set.
+    pub fn resolve(args: &Args, read_only: bool, reporter: &Reporter) -> Result<Self> {
+        let home = dirs::home_dir().context("Could not find home directory")?;
+        let config = Config::load_default(args.profile.as_deref())?;
+
+        let mut merged_libraries = Vec::new();
+        let candidates = config.steam_roots()?;
+        let steam_root = match args.steam_path {
+            Some(ref path) => PathBuf::from(path),
+            None if candidates.is_empty() => detect_steam_root(&home, reporter),
+            None => {
+                let merge = config.merge_steam_roots()?;
+                let (root, others) = pick_steam_root(&candidates, merge, reporter);
+                for other in others {
+                    match steam::library_paths(&other.join("steamapps/libraryfolders.vdf")) {
+                        Ok(libraries) => merged_libraries.extend(libraries),
+                        Err(e) => reporter
+                            .warn(format!("Not merging the libraries of {:?}: {:#}", other, e)),
+                    }
+                }
+                root
+            }
+        };
+        let steam_root = real_steam_root(steam_root);
+
+        let data_dir = dirs::data_dir().unwrap_or_else(|| home.join(".local/share"));
+        let app_dir = match args.app_dir {
+            Some(ref path) => expand_home(Path::new(path), &home),
+            None => default_app_dir(&data_dir, &desktops(args), &config.app_dirs()?),
+        };
+
+        // resolve symlinks once up front, so the cleanup only ever touches the real directory
+        let app_dir = resolve_app_dir(&app_dir, read_only)?;
+
+        let index_dir = match args.index_dir {
+            Some(ref path) => PathBuf::from(path),
+            None => data_dir.join("steamer"),
+        };
+
+        let mut extra_libraries: Vec<PathBuf> =
+            args.extra_library.iter().map(PathBuf::from).collect();
+        extra_libraries.extend(config.extra_libraries()?);
+        extra_libraries.extend(merged_libraries);
+
+        let mut ignore_files = vec![app_dir.join(".steamerignore")];
+        ignore_files.extend(dirs::config_dir().map(|dir| dir.join("steamer/.steamerignore")));
+
+        let cache_dir = dirs::cache_dir()
+            .unwrap_or_else(|| home.join(".cache"))
+            .join("steamer");
+
+        Ok(Self {
+            library_vdf: steam_root.join("steamapps/libraryfolders.vdf"),
+            library_cache: cache_dir.join("libraryfolders.vdf"),
+            cache_dir,
+            icon_cache: steam_root.join("appcache/librarycache"),
+            steam_root,
+            app_dir,
+            index_dir,
+            extra_libraries,
+            ignore_files,
+        })
+    }
+}
+
+/// The Steam root with every symlink resolved. A relative symlink only resolves against its own
+/// parent, so every path derived from the root has to start from the real directory. A missing
+/// root is given back as it is, to be reported later with the path the user knows.
+fn real_steam_root(steam_root: PathBuf) -> PathBuf {
+    fs::canonicalize(&steam_root).unwrap_or(steam_root)
+}
+
+/// What keeps libraryfolders.vdf from being read, if anything. A file steamer isn't allowed to
+/// read is told apart from a missing one: Steam started once with sudo leaves root-owned files
+/// behind, which would otherwise look like no install at all.
+pub fn library_vdf_problem(paths: &Paths) -> Option<String> {
+    let steamapps = paths.steam_root.join("steamapps");
+    let checks: [(&Path, io::Result<()>); 3] = [
+        (&paths.steam_root, fs::metadata(&paths.steam_root).map(drop)),
+        (&steamapps, fs::read_dir(&steamapps).map(drop)),
+        (&paths.library_vdf, File::open(&paths.library_vdf).map(drop)),
+    ];
+    for (path, result) in checks {
+        match result {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                return Some(permission_denied(path, &paths.steam_root));
+            }
+            Err(_) => {
+                return Some(format!(
+                    "libraryfolders.vdf not found at {:?}",
+                    paths.library_vdf
+                ));
+            }
+        }
+    }
+    None
+}
+
+fn permission_denied(path: &Path, steam_root: &Path) -> String {
+    let uid = unsafe { libc::getuid() };
+    let owner = match fs::metadata(path).map(|meta| meta.uid()) {
+        Ok(0) => ", which is owned by root".to_string(),
+        Ok(owner) if owner != uid => format!(", which belongs to user {}", owner),
+        _ => String::new(),
+    };
+    format!(
+        "Permission denied reading {:?}{}. Running Steam with sudo leaves files like this \
+        behind, `sudo chown -R \"$USER\": {:?}` makes them yours again",
+        path, owner, steam_root
+    )
+}
+
+/// Prefers the native install, then the `~/.steam` symlinks the client maintains. In split
+/// setups `steam` and `root` can point at different places; `steam` wins as long as it actually
+/// holds a `steamapps` folder. When none of them has one, a running client is asked.
+fn detect_steam_root(home: &Path, reporter: &Reporter) -> PathBuf {
+    let native = home.join(".local/share/Steam");
+    if native.join("steamapps").is_dir() {
+        return native;
+    }
+
+    let steam_link = home.join(".steam/steam");
+    let root_link = home.join(".steam/root");
+    if let (Ok(steam), Ok(root)) = (fs::canonicalize(&steam_link), fs::canonicalize(&root_link))
+        && steam != root
+    {
+        reporter.warn(format!(
+            "~/.steam/steam points to {:?} but ~/.steam/root points to {:?}",
+            steam, root
+        ));
+    }
+
+    [steam_link, root_link]
+        .into_iter()
+        .find(|link| link.join("steamapps").is_dir())
+        .or_else(|| running_steam_root(Path::new("/proc")))
+        .unwrap_or(native)
+}
+
+/// The root of a Steam client that's running, from `proc`: the client binary sits in the root's
+/// `ubuntu12_32`, and steam.sh starts it from the root, so the executable's and the working
+/// directory's ancestors are searched for the one holding `steamapps`. Only processes of this
+/// user can be looked into, which are the only ones whose libraries matter.
+fn running_steam_root(proc: &Path) -> Option<PathBuf> {
+    let processes = fs::read_dir(proc).ok()?;
+    for process in processes.filter_map(Result::ok) {
+        let dir = process.path();
+        let is_pid = process
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+        // processes can exit while they're looked at
+        let comm = fs::read_to_string(dir.join("comm")).unwrap_or_default();
+        if !is_pid || comm.trim() != "steam" {
+            continue;
+        }
+        let found = ["exe", "cwd"]
+            .iter()
+            .filter_map(|link| fs::read_link(dir.join(link)).ok())
+            .find_map(|path| {
+                path.ancestors()
+                    .find(|dir| dir.join("steamapps").is_dir())
+                    .map(Path::to_path_buf)
+            });
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+/// The first of the config's `steam_roots` with a libraryfolders.vdf, and with `merge` the
+/// other roots that have one. When none has, the first is used so the error names it.
+fn pick_steam_root(
+    candidates: &[PathBuf],
+    merge: bool,
+    reporter: &Reporter,
+) -> (PathBuf, Vec<PathBuf>) {
+    let mut valid = candidates
+        .iter()
+        .filter(|root| root.join("steamapps/libraryfolders.vdf").is_file())
+        .cloned();
+    match valid.next() {
+        Some(root) => {
+            let others = if merge { valid.collect() } else { Vec::new() };
+            (root, others)
+        }
+        None => {
+            reporter.warn("None of the steam_roots in the config has a libraryfolders.vdf");
+            (candidates[0].clone(), Vec::new())
+        }
+    }
+}
+
+/// The desktops the entries are for, as `XDG_CURRENT_DESKTOP` names them: the session's, or the
+/// one `--desktop-environment` tunes for when it's given.
+fn desktops(args: &Args) -> Vec<String> {
+    match args.desktop_environment {
+        Some(DesktopEnvironment::Generic) => Vec::new(),
+        Some(DesktopEnvironment::Pantheon) => vec!["Pantheon".to_string()],
+        None => std::env::var("XDG_CURRENT_DESKTOP")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|desktop| !desktop.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+/// The applications directory without `--app-dir`: the one `[app_dirs]` in the config sets for
+/// the first of `desktops` it has, ignoring case, or else the XDG one every desktop reads.
+fn default_app_dir(
+    data_dir: &Path,
+    desktops: &[String],
+    configured: &[(String, PathBuf)],
+) -> PathBuf {
+    desktops
+        .iter()
+        .find_map(|desktop| {
+            configured
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(desktop))
+        })
+        .map_or_else(|| data_dir.join("applications"), |(_, dir)| dir.clone())
+}
+
+/// `~` and a leading `~/` are the home directory, for a `--app-dir=~/...` the shell left alone.
+fn expand_home(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// The applications directory with every symlink resolved, created first unless `dry_run`. A
+/// relative path is taken from the current directory, and a dry run against a directory that
+/// doesn't exist yet gets the absolute path as given. Symlinks that lead back to themselves
+/// are an error, instead of the failed create they'd otherwise end in.
+fn resolve_app_dir(path: &Path, dry_run: bool) -> Result<PathBuf> {
+    if let Err(e) = fs::metadata(path)
+        && e.raw_os_error() == Some(libc::ELOOP)
+    {
+        anyhow::bail!(
+            "Could not resolve applications directory {:?}: its symlinks form a loop",
+            path
+        );
+    }
+    if !dry_run {
+        // a dangling symlink makes create_dir_all fail, so create the link target instead
+        let target = match fs::read_link(path) {
+            Ok(link) if !path.exists() => path.parent().map(|p| p.join(&link)).unwrap_or(link),
+            _ => path.to_path_buf(),
+        };
+        fs::create_dir_all(&target)
+            .with_context(|| format!("Could not create applications directory {:?}", target))?;
+    }
+
+    match fs::canonicalize(path) {
+        Ok(resolved) => Ok(resolved),
+        Err(e) if dry_run && e.kind() == std::io::ErrorKind::NotFound => {
+            Ok(std::path::absolute(path)?)
+        }
+        Err(e) => {
+            Err(e).with_context(|| format!("Could not resolve applications directory {:?}", path))
+        }
+    }
+}
+
+/// Fixed places distributions and sandboxed packages put the Steam launcher, for sessions
+/// whose `$PATH` doesn't include it. Relative entries are under the home directory.
+const STEAM_LOCATIONS: &[&str] = &[
+    "/usr/games/steam",
+    "/usr/lib/steam/bin_steam.sh",
+    "/usr/local/bin/steam",
+    "/snap/bin/steam",
+    "/var/lib/flatpak/exports/bin/com.valvesoftware.Steam",
+    ".local/share/flatpak/exports/bin/com.valvesoftware.Steam",
+];
+
+/// Finds the executable that `steam` in an `Exec=` line should be: `$PATH` first, then the
+/// usual install locations.
+pub fn find_steam_binary() -> Option<PathBuf> {
+    find_executable("steam").or_else(|| {
+        let home = dirs::home_dir().unwrap_or_default();
+        STEAM_LOCATIONS
+            .iter()
+            .map(|location| home.join(location))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+/// Looks `name` up on `$PATH` the way a shell would, returning the first executable match.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    find_on_path(&std::env::var_os("PATH")?, name)
+}
+
+fn find_on_path(path: &OsStr, name: &str) -> Option<PathBuf> {
+    std::env::split_paths(path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+    use std::process;
+
+    /// A fresh directory under the system temp dir, removed again by the test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("steamer-paths-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn expands_the_home_directory() {
+        let home = Path::new("/home/user");
+        assert_eq!(
+            expand_home(Path::new("~/apps"), home),
+            Path::new("/home/user/apps")
+        );
+        assert_eq!(expand_home(Path::new("~"), home), home);
+        assert_eq!(expand_home(Path::new("/tmp/~"), home), Path::new("/tmp/~"));
+        assert_eq!(
+            expand_home(Path::new("~user/apps"), home),
+            Path::new("~user/apps")
+        );
+    }
+
+    #[test]
+    fn asks_a_running_client_for_its_root() {
+        let dir = temp_dir("proc");
+        let root = dir.join("opt/Steam");
+        fs::create_dir_all(root.join("steamapps")).unwrap();
+        fs::create_dir_all(root.join("ubuntu12_32")).unwrap();
+        let process = |pid: &str, comm: &str, exe: &Path| {
+            let process = dir.join("proc").join(pid);
+            fs::create_dir_all(&process).unwrap();
+            fs::write(process.join("comm"), comm).unwrap();
+            symlink(exe, process.join("exe")).unwrap();
+        };
+        process("self", "steam\n", &root.join("ubuntu12_32/steam"));
+        process(
+            "100",
+            "steamwebhelper\n",
+            &root.join("ubuntu12_32/steamwebhelper"),
+        );
+        assert_eq!(running_steam_root(&dir.join("proc")), None);
+
+        process("200", "steam\n", &root.join("ubuntu12_32/steam"));
+        assert_eq!(running_steam_root(&dir.join("proc")), Some(root));
+        assert_eq!(running_steam_root(&dir.join("missing")), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn picks_the_first_steam_root_with_libraries() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let dir = temp_dir("roots");
+        let roots: Vec<PathBuf> = ["missing", "flatpak", "native", "snap"]
+            .iter()
+            .map(|name| dir.join(name))
+            .collect();
+        for root in &roots[1..] {
+            fs::create_dir_all(root.join("steamapps")).unwrap();
+            fs::write(root.join("steamapps/libraryfolders.vdf"), "").unwrap();
+        }
+
+        assert_eq!(
+            pick_steam_root(&roots, false, &reporter),
+            (roots[1].clone(), Vec::new())
+        );
+        assert_eq!(
+            pick_steam_root(&roots, true, &reporter),
+            (roots[1].clone(), roots[2..].to_vec())
+        );
+        // with none there, the first is named in the errors that follow
+        assert_eq!(
+            pick_steam_root(&roots[..1], true, &reporter),
+            (roots[0].clone(), Vec::new())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tells_unreadable_libraries_from_missing_ones() {
+        let dir = temp_dir("unreadable");
+        let steam_root = dir.join("Steam");
+        let paths = Paths {
+            library_vdf: steam_root.join("steamapps/libraryfolders.vdf"),
+            library_cache: dir.join("cache/libraryfolders.vdf"),
+            cache_dir: dir.join("cache"),
+            icon_cache: steam_root.join("appcache/librarycache"),
+            app_dir: dir.join("applications"),
+            index_dir: dir.join("index"),
+            extra_libraries: Vec::new(),
+            ignore_files: Vec::new(),
+            steam_root,
+        };
+        assert_eq!(
+            library_vdf_problem(&paths),
+            Some(format!(
+                "libraryfolders.vdf not found at {:?}",
+                paths.library_vdf
+            ))
+        );
+
+        fs::create_dir_all(paths.steam_root.join("steamapps")).unwrap();
+        fs::write(&paths.library_vdf, "\"libraryfolders\"\n{\n}\n").unwrap();
+        assert_eq!(library_vdf_problem(&paths), None);
+
+        let message = permission_denied(&paths.library_vdf, &paths.steam_root);
+        assert!(message.starts_with(&format!(
+            "Permission denied reading {:?}",
+            paths.library_vdf
+        )));
+        assert!(message.ends_with(&format!(
+            "`sudo chown -R \"$USER\": {:?}` makes them yours again",
+            paths.steam_root
+        )));
+        // root reads every file, anyone else is stopped by the mode
+        fs::set_permissions(&paths.library_vdf, fs::Permissions::from_mode(0o000)).unwrap();
+        if unsafe { libc::geteuid() } != 0 {
+            assert_eq!(library_vdf_problem(&paths), Some(message));
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn relative_paths_start_from_the_current_directory() {
+        let cwd = std::env::current_dir().unwrap();
+        let missing = Path::new("steamer-no-such-applications-dir");
+        assert_eq!(resolve_app_dir(missing, true).unwrap(), cwd.join(missing));
+        assert_eq!(
+            resolve_app_dir(Path::new("."), true).unwrap(),
+            fs::canonicalize(&cwd).unwrap()
+        );
+    }
+
+    #[test]
+    fn creates_a_missing_directory_and_resolves_it() {
+        let dir = temp_dir("missing");
+        let app_dir = dir.join("share/applications");
+        assert_eq!(resolve_app_dir(&app_dir, false).unwrap(), app_dir);
+        assert!(app_dir.is_dir());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn a_dry_run_creates_nothing() {
+        let dir = temp_dir("dry");
+        let app_dir = dir.join("applications");
+        assert_eq!(resolve_app_dir(&app_dir, true).unwrap(), app_dir);
+        assert!(!app_dir.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn follows_a_symlinked_directory() {
+        let dir = temp_dir("symlink");
+        let target = dir.join("dotfiles/applications");
+        fs::create_dir_all(&target).unwrap();
+        let link = dir.join("applications");
+        symlink(&target, &link).unwrap();
+        assert_eq!(resolve_app_dir(&link, false).unwrap(), target);
+
+        // a dangling relative link gets its target created next to it
+        let dangling = dir.join("dangling");
+        symlink("elsewhere/applications", &dangling).unwrap();
+        assert_eq!(
+            resolve_app_dir(&dangling, false).unwrap(),
+            dir.join("elsewhere/applications")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn symlinks_that_loop_are_an_error() {
+        let dir = temp_dir("symlink-loop");
+        symlink("second", dir.join("applications")).unwrap();
+        symlink("applications", dir.join("second")).unwrap();
+        for dry_run in [false, true] {
+            let error = resolve_app_dir(&dir.join("applications"), dry_run).unwrap_err();
+            assert!(error.to_string().contains("form a loop"), "{:#}", error);
+        }
+        assert!(resolve_app_dir(&dir.join("applications/steam"), false).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn steam_root_falls_back_to_the_link_holding_steamapps() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let home = temp_dir("links");
+        fs::create_dir_all(home.join("runtime")).unwrap();
+        fs::create_dir_all(home.join("install/steamapps")).unwrap();
+        fs::create_dir_all(home.join(".steam")).unwrap();
+        symlink(home.join("runtime"), home.join(".steam/steam")).unwrap();
+        symlink(home.join("install"), home.join(".steam/root")).unwrap();
+        // steam points somewhere without games, so root wins
+        assert_eq!(
+            detect_steam_root(&home, &reporter),
+            home.join(".steam/root")
+        );
+
+        fs::create_dir_all(home.join(".local/share/Steam/steamapps")).unwrap();
+        assert_eq!(
+            detect_steam_root(&home, &reporter),
+            home.join(".local/share/Steam")
+        );
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn resolves_a_relative_steam_root_link() {
+        let home = temp_dir("relative");
+        fs::create_dir_all(home.join(".local/share/Steam/steamapps")).unwrap();
+        fs::create_dir_all(home.join(".steam")).unwrap();
+        symlink("../.local/share/Steam", home.join(".steam/steam")).unwrap();
+
+        let root = real_steam_root(home.join(".steam/steam"));
+        assert_eq!(root, home.join(".local/share/Steam"));
+        assert!(root.join("steamapps").is_dir());
+
+        let missing = home.join("no-steam");
+        assert_eq!(real_steam_root(missing.clone()), missing);
+        fs::remove_dir_all(home).unwrap();
+    }
+
+    #[test]
+    fn finds_the_first_executable_on_the_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("path");
+        for (bin, mode) in [("a", 0o644), ("b", 0o755), ("c", 0o755)] {
+            fs::create_dir_all(dir.join(bin)).unwrap();
+            let steam = dir.join(bin).join("steam");
+            fs::write(&steam, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&steam, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        fs::create_dir_all(dir.join("d/steam")).unwrap();
+
+        let path =
+            std::env::join_paths(["missing", "d", "a", "b", "c"].map(|bin| dir.join(bin))).unwrap();
+        assert_eq!(find_on_path(&path, "steam"), Some(dir.join("b/steam")));
+        assert_eq!(find_on_path(&path, "xdg-open"), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn desktops_can_have_their_own_app_dir() {
+        let data = Path::new("/home/me/.local/share");
+        let configured = vec![
+            ("KDE".to_string(), PathBuf::from("/home/me/kde-apps")),
+            (
+                "pantheon".to_string(),
+                PathBuf::from("/home/me/pantheon-apps"),
+            ),
+        ];
+        let desktops =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|name| name.to_string()).collect() };
+        assert_eq!(
+            default_app_dir(data, &desktops(&["Pantheon", "GNOME"]), &configured),
+            Path::new("/home/me/pantheon-apps")
+        );
+        assert_eq!(
+            default_app_dir(data, &desktops(&["GNOME", "KDE"]), &configured),
+            Path::new("/home/me/kde-apps")
+        );
+        assert_eq!(
+            default_app_dir(data, &desktops(&["GNOME"]), &configured),
+            Path::new("/home/me/.local/share/applications")
+        );
+        assert_eq!(
+            default_app_dir(data, &[], &configured),
+            Path::new("/home/me/.local/share/applications")
+        );
+    }
+}
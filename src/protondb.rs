@@ -0,0 +1,226 @@
+//! `--protondb`: the ProtonDB rating of each game that runs in Proton, for the `Comment=`
+//! line. Ratings are cached per game and asked for again once a week, and requests are spaced
+//! out, so a large library doesn't hammer the site on its first run.
+
+use crate::badge;
+use crate::json;
+use crate::net::HttpClient;
+use crate::report::Reporter;
+use crate::steam::{GameInfo, Library};
+use anyhow::{Context, Result};
+use std::cell::Cell;
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const SUMMARY_URL: &str = "https://www.protondb.com/api/v1/reports/summaries/";
+
+/// How long a fetched rating is used before asking again.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Shortest time between two requests.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Where rating summaries come from.
+pub trait SummarySource {
+    /// The summary JSON of `appid`, `None` when requests are off.
+    fn summary(&self, appid: &str) -> Result<Option<Vec<u8>>>;
+}
+
+/// The ProtonDB API.
+impl SummarySource for HttpClient {
+    fn summary(&self, appid: &str) -> Result<Option<Vec<u8>>> {
+        self.get(&format!("{}{}.json", SUMMARY_URL, appid))
+    }
+}
+
+/// A stand-in for the API.
+impl<F: Fn(&str) -> Result<Option<Vec<u8>>>> SummarySource for F {
+    fn summary(&self, appid: &str) -> Result<Option<Vec<u8>>> {
+        self(appid)
+    }
+}
+
+/// Ratings read through the cache in `cache_dir/protondb`.
+pub struct ProtonDb<'a> {
+    source: &'a dyn SummarySource,
+    cache_dir: &'a Path,
+    last_request: Cell<Option<Instant>>,
+    /// Set after the first failed request, the rest of the run only uses the cache.
+    unreachable: Cell<bool>,
+}
+
+impl<'a> ProtonDb<'a> {
+    pub fn new(source: &'a dyn SummarySource, cache_dir: &'a Path) -> Self {
+        Self {
+            source,
+            cache_dir,
+            last_request: Cell::new(None),
+            unreachable: Cell::new(false),
+        }
+    }
+
+    /// Fills in the rating of every game with a Proton prefix in its library. Native games
+    /// don't get one, a rating of how well the Windows version runs says nothing about them.
+    pub fn add_tiers(
+        &self,
+        libraries: &mut [Library],
+        wanted: impl Fn(&GameInfo) -> bool,
+        reporter: &Reporter,
+    ) {
+        for library in libraries.iter_mut() {
+            for game in library.games.iter_mut().filter(|game| wanted(game)) {
+                if badge::runs_in_proton(&library.path, &game.appid) {
+                    game.protondb_tier = self.tier(&game.appid, reporter);
+                }
+            }
+        }
+    }
+
+    /// The rating of `appid`, like "Gold". A cached one younger than a week is used as is, an
+    /// older one when the site can't be reached.
+    fn tier(&self, appid: &str, reporter: &Reporter) -> Option<String> {
+        let cache = self.cache_dir.join(format!("protondb/{}.json", appid));
+        let cached = fs::read_to_string(&cache).ok();
+        let age = fs::metadata(&cache)
+            .and_then(|meta| meta.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if let Some(ref text) = cached
+            && age.is_some_and(|age| age < MAX_AGE)
+        {
+            return parse_tier(text);
+        }
+
+        if !self.unreachable.get() {
+            if let Some(last) = self.last_request.get() {
+                thread::sleep(REQUEST_INTERVAL.saturating_sub(last.elapsed()));
+            }
+            self.last_request.set(Some(Instant::now()));
+            match self.source.summary(appid) {
+                Ok(Some(body)) => {
+                    let text = String::from_utf8_lossy(&body);
+                    if let Err(e) = write_cache(&cache, text.as_bytes()) {
+                        reporter.warn(format!("{:#}", e));
+                    }
+                    return parse_tier(&text);
+                }
+                Ok(None) => {}
+                // games nobody reported on are a 404, remembered like a rating so they aren't
+                // asked for on every run
+                Err(e) if format!("{:#}", e).contains("error: 404") => {
+                    if let Err(e) = write_cache(&cache, b"{}") {
+                        reporter.warn(format!("{:#}", e));
+                    }
+                    return None;
+                }
+                Err(_) => {
+                    reporter.warn("Could not reach ProtonDB, only using the ratings cached before");
+                    self.unreachable.set(true);
+                }
+            }
+        }
+        cached.as_deref().and_then(parse_tier)
+    }
+}
+
+/// The `tier` of a summary, capitalized. "pending" means there aren't enough reports yet.
+fn parse_tier(text: &str) -> Option<String> {
+    let root = json::parse(text).ok()?;
+    let tier = root.get("tier")?.as_str()?;
+    if tier.is_empty() || tier == "pending" || !tier.bytes().all(|b| b.is_ascii_alphabetic()) {
+        return None;
+    }
+    let mut chars = tier.chars();
+    let first = chars.next()?.to_ascii_uppercase();
+    Some(first.to_string() + &chars.as_str().to_ascii_lowercase())
+}
+
+fn write_cache(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(path, content).with_context(|| format!("Could not write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+    use anyhow::bail;
+
+    fn summary(tier: &str) -> Result<Option<Vec<u8>>> {
+        Ok(Some(
+            format!("{{\"tier\":\"{}\",\"total\":42}}", tier).into_bytes(),
+        ))
+    }
+
+    #[test]
+    fn maps_tiers() {
+        assert_eq!(parse_tier(r#"{"tier":"gold"}"#).as_deref(), Some("Gold"));
+        assert_eq!(
+            parse_tier(r#"{"tier":"PLATINUM","score":0.9}"#).as_deref(),
+            Some("Platinum")
+        );
+        assert_eq!(
+            parse_tier(r#"{"tier":"borked"}"#).as_deref(),
+            Some("Borked")
+        );
+        // not enough reports yet
+        assert_eq!(parse_tier(r#"{"tier":"pending"}"#), None);
+        assert_eq!(parse_tier(r#"{"tier":""}"#), None);
+        assert_eq!(parse_tier(r#"{"tier":"gold\nExec=x"}"#), None);
+        assert_eq!(parse_tier("{}"), None);
+        assert_eq!(parse_tier("<html>"), None);
+    }
+
+    #[test]
+    fn falls_back_to_the_cache_when_unreachable() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let dir =
+            std::env::temp_dir().join(format!("steamer-protondb-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let site = |appid: &str| match appid {
+            "620" => summary("platinum"),
+            "400" => bail!("curl: (22) The requested URL returned error: 404"),
+            _ => summary("silver"),
+        };
+        let protondb = ProtonDb::new(&site, &dir);
+        assert_eq!(protondb.tier("620", &reporter).as_deref(), Some("Platinum"));
+        // games nobody reported on are remembered without a rating
+        assert_eq!(protondb.tier("400", &reporter), None);
+        assert_eq!(
+            fs::read_to_string(dir.join("protondb/400.json")).unwrap(),
+            "{}"
+        );
+
+        // a week old rating is asked for again, and kept when the site is down
+        let week_ago = SystemTime::now() - MAX_AGE - Duration::from_secs(60);
+        fs::File::options()
+            .write(true)
+            .open(dir.join("protondb/620.json"))
+            .unwrap()
+            .set_modified(week_ago)
+            .unwrap();
+        let asked = Cell::new(0);
+        let down = |_: &str| -> Result<Option<Vec<u8>>> {
+            asked.set(asked.get() + 1);
+            bail!("curl: (6) Could not resolve host: www.protondb.com")
+        };
+        let protondb = ProtonDb::new(&down, &dir);
+        assert_eq!(protondb.tier("620", &reporter).as_deref(), Some("Platinum"));
+        assert_eq!(protondb.tier("570", &reporter), None);
+        // after the first failure nothing more is asked for
+        assert_eq!(asked.get(), 1);
+
+        // a fresh one is used without asking
+        let protondb = ProtonDb::new(&site, &dir);
+        assert_eq!(protondb.tier("570", &reporter).as_deref(), Some("Silver"));
+        let protondb = ProtonDb::new(&down, &dir);
+        assert_eq!(protondb.tier("570", &reporter).as_deref(), Some("Silver"));
+        assert_eq!(asked.get(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
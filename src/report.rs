@@ -0,0 +1,616 @@
+use crate::filter::SkipReason;
+use crate::json::Value;
+use crate::state::Damage;
+use crate::steam::{GameInfo, InstallDetails, NameSource};
+use crate::template::Template;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Bumped whenever a JSON event loses or renames a field.
+pub const SCHEMA_VERSION: i64 = 1;
+
+/// The placeholders `--summary-format` can use, `{elapsed}` being in milliseconds.
+pub const SUMMARY_PLACEHOLDERS: &[&str] = &["created", "skipped", "removed", "elapsed"];
+
+/// The events of the last sync as JSON lines, in steamer's cache directory, for bug reports.
+pub fn last_run_log(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("last-run.jsonl")
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Readable progress lines on stdout
+    #[default]
+    Human,
+    /// One JSON object per event on stderr, for wrappers and GUIs
+    Json,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Scan,
+    Cleanup,
+    Write,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Scan => "scan",
+            Phase::Cleanup => "cleanup",
+            Phase::Write => "write",
+        }
+    }
+}
+
+/// Everything a run reports. Each variant has both a human and a JSON rendering.
+pub enum Event<'a> {
+    RunStarted {
+        steam_root: &'a Path,
+        app_dir: &'a Path,
+        icon_cache: &'a Path,
+    },
+    PhaseStarted(Phase),
+    LibraryScanned {
+        path: &'a Path,
+        games: usize,
+        /// Added from the flags or config rather than libraryfolders.vdf.
+        extra: bool,
+    },
+    GameDiscovered(&'a GameInfo),
+    GameSkipped {
+        game: &'a GameInfo,
+        reason: &'a SkipReason,
+    },
+    EntryWritten {
+        game: &'a GameInfo,
+        path: &'a Path,
+    },
+    /// `--repair` found the entry of a game deleted or changed, and writes it again.
+    EntryDamaged {
+        game: &'a GameInfo,
+        path: &'a Path,
+        damage: Damage,
+    },
+    /// `--migrate-from` renamed an entry of the old scheme to the one steamer uses.
+    EntryMigrated {
+        from: &'a Path,
+        to: &'a Path,
+    },
+    /// An index file written by a non-desktop sink at the end of the run.
+    OutputWritten {
+        path: &'a Path,
+    },
+    Warning(String),
+    Error(String),
+    Summary {
+        created: usize,
+        skipped: usize,
+        /// Entries from before the run that it didn't write again.
+        removed: usize,
+        app_dir: &'a Path,
+        cleanup_skipped: bool,
+        elapsed_ms: u128,
+    },
+}
+
+pub struct Reporter {
+    format: LogFormat,
+    dry_run: bool,
+    human_to_stderr: bool,
+    /// Every event as a JSON line, when the run is kept for `last_run_log`.
+    log: Option<RefCell<Vec<String>>>,
+    /// `--summary-format`, replacing the human summary lines.
+    summary_format: Option<Template>,
+}
+
+impl Reporter {
+    pub fn new(format: LogFormat, dry_run: bool) -> Self {
+        Self {
+            format,
+            dry_run,
+            human_to_stderr: false,
+            log: None,
+            summary_format: None,
+        }
+    }
+
+    /// Prints the human summary with `template` instead of the usual wording.
+    pub fn summary_format(mut self, template: Option<Template>) -> Self {
+        self.summary_format = template;
+        self
+    }
+
+    /// Also keeps every event in memory, for `save_log`.
+    pub fn keep_log(mut self) -> Self {
+        self.log = Some(RefCell::new(Vec::new()));
+        self
+    }
+
+    /// Writes the kept events as JSON lines, whatever the log format.
+    pub fn save_log(&self, path: &Path) -> Result<()> {
+        let Some(ref log) = self.log else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+        }
+        let mut text = log.borrow().join("\n");
+        text.push('\n');
+        fs::write(path, text).with_context(|| format!("Could not write {:?}", path))
+    }
+
+    /// Sends the human progress lines to stderr, keeping stdout free for generated content.
+    pub fn human_to_stderr(mut self, enabled: bool) -> Self {
+        self.human_to_stderr = enabled;
+        self
+    }
+
+    pub fn emit(&self, event: Event) {
+        if let Some(ref log) = self.log {
+            log.borrow_mut().push(self.to_json(&event).to_compact());
+        }
+        match self.format {
+            LogFormat::Human => self.print_human(&event),
+            LogFormat::Json => eprintln!("{}", self.to_json(&event).to_compact()),
+        }
+    }
+
+    pub fn warn(&self, message: impl Into<String>) {
+        self.emit(Event::Warning(message.into()));
+    }
+
+    fn say(&self, line: std::fmt::Arguments) {
+        if self.human_to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    fn print_human(&self, event: &Event) {
+        match event {
+            Event::RunStarted {
+                steam_root,
+                app_dir,
+                icon_cache,
+            } => {
+                self.say(format_args!("Steam Root Directory: {:?}", steam_root));
+                self.say(format_args!("Desktop Entry Directory: {:?}", app_dir));
+                self.say(format_args!("Icon Cache Directory: {:?}", icon_cache));
+                if self.dry_run {
+                    self.say(format_args!("----------------------------------"));
+                    self.say(format_args!("DRY RUN ENABLED - No files will be written."));
+                    self.say(format_args!("----------------------------------"));
+                }
+            }
+            Event::PhaseStarted(Phase::Cleanup) => {
+                self.say(format_args!("Cleaning up old Steam desktop entries..."))
+            }
+            Event::PhaseStarted(_) => {}
+            Event::LibraryScanned { path, extra, .. } => self.say(format_args!(
+                "Checking Library: {:?}{}",
+                path,
+                if *extra { " (extra)" } else { "" }
+            )),
+            Event::GameDiscovered(game) => {
+                if self.dry_run {
+                    self.say(format_args!(
+                        "  Found game: {} (AppID: {}){}{}",
+                        game.name,
+                        game.appid,
+                        incomplete_note(game),
+                        name_note(game)
+                    ));
+                }
+            }
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::Duplicate,
+            } => self.say(format_args!(
+                "  Already found in another library, skipping: {}",
+                game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::FamilyView,
+            } => self.say(format_args!(
+                "  Not allowed by Family View, skipping: {}",
+                game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::MissingFiles,
+            } => self.say(format_args!(
+                "  Install folder not found in any library, skipping: {}",
+                game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::IgnoreFile { pattern, file },
+            } => self.say(format_args!(
+                "  Ignored by {} ({:?}), skipping: {}",
+                file.display(),
+                pattern,
+                game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::PartialInstall(partial),
+            } => self.say(format_args!(
+                "  Not fully installed ({}), skipping: {}",
+                partial, game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: reason @ SkipReason::Size { .. },
+            } => self.say(format_args!("  Size {}, skipping: {}", reason, game.name)),
+            Event::GameSkipped {
+                game,
+                reason: reason @ SkipReason::NotNew(_),
+            } => self.say(format_args!(
+                "  Not new, {}, skipping: {}",
+                reason, game.name
+            )),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::Emoji,
+            } => self.say(format_args!("  Emoji in the name, skipping: {}", game.name)),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::IconUnchanged,
+            } => self.say(format_args!("  Icon unchanged, skipping: {}", game.name)),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::Intact,
+            } => self.say(format_args!("  Entry intact, skipping: {}", game.name)),
+            Event::GameSkipped {
+                game,
+                reason: SkipReason::Preserved,
+            } => self.say(format_args!("  Entry preserved, skipping: {}", game.name)),
+            Event::GameSkipped { game, reason } => self.say(format_args!(
+                "  Found Tool/Runtime, skipping: {} ({})",
+                game.name, reason
+            )),
+            Event::EntryWritten { game, .. } => self.say(format_args!(
+                "  Created Launcher for {}{}",
+                game.name,
+                incomplete_note(game)
+            )),
+            Event::EntryDamaged { game, path, damage } => self.say(format_args!(
+                "  {:?} was {}, restoring the launcher for {}",
+                path, damage, game.name
+            )),
+            Event::EntryMigrated { from, to } => self.say(format_args!(
+                "{} {:?} to {:?}",
+                if self.dry_run {
+                    "Would rename"
+                } else {
+                    "Renamed"
+                },
+                from,
+                to
+            )),
+            Event::OutputWritten { path } => self.say(format_args!("Wrote {:?}", path)),
+            Event::Warning(message) => eprintln!("Warning: {}", message),
+            Event::Error(message) => eprintln!("Error: {}", message),
+            Event::Summary {
+                created,
+                skipped,
+                removed,
+                app_dir,
+                cleanup_skipped,
+                elapsed_ms,
+            } => {
+                if let Some(ref template) = self.summary_format {
+                    self.say(format_args!(
+                        "{}",
+                        template.render(&[
+                            ("created", &created.to_string()),
+                            ("skipped", &skipped.to_string()),
+                            ("removed", &removed.to_string()),
+                            ("elapsed", &elapsed_ms.to_string()),
+                        ])
+                    ));
+                    return;
+                }
+                if self.dry_run {
+                    self.say(format_args!(
+                        "Dry run complete. Found {} games, skipped {} tools. Took {:.2?} milliseconds.",
+                        created, skipped, elapsed_ms
+                    ));
+                } else {
+                    self.say(format_args!(
+                        "Done! {} shortcuts created (skipped {} tools) in {:?}. Took {:.2?} milliseconds.",
+                        created, skipped, app_dir, elapsed_ms
+                    ));
+                }
+                if *cleanup_skipped {
+                    self.say(format_args!(
+                        "Cleanup was skipped, existing entries were not removed."
+                    ));
+                }
+            }
+        }
+    }
+
+    fn to_json(&self, event: &Event) -> Value {
+        let base = |name: &str| {
+            Value::object()
+                .with("schema_version", SCHEMA_VERSION)
+                .with("event", name)
+        };
+
+        match event {
+            Event::RunStarted {
+                steam_root,
+                app_dir,
+                icon_cache,
+            } => base("run_started")
+                .with("steam_root", *steam_root)
+                .with("app_dir", *app_dir)
+                .with("icon_cache", *icon_cache)
+                .with("dry_run", self.dry_run),
+            Event::PhaseStarted(phase) => base("phase_started").with("phase", phase.name()),
+            Event::LibraryScanned { path, games, extra } => base("library_scanned")
+                .with("path", *path)
+                .with("games", *games)
+                .with("extra", *extra),
+            Event::GameDiscovered(game) => base("game_discovered")
+                .with("appid", game.appid.as_str())
+                .with("name", game.name.as_str())
+                .with("incomplete", game.incomplete)
+                .with("name_source", game.name_source.kind())
+                .with("depots", game.depots.clone())
+                .with("dlc", game.dlc.clone())
+                .with("scheduled_update", game.scheduled_update)
+                .with(
+                    "install_details",
+                    game.install_details.as_ref().map(InstallDetails::to_json),
+                ),
+            Event::GameSkipped { game, reason } => base("game_skipped")
+                .with("appid", game.appid.as_str())
+                .with("name", game.name.as_str())
+                .with("reason", reason.kind())
+                .with("detail", reason.to_string()),
+            Event::EntryWritten { game, path } => base("entry_written")
+                .with("appid", game.appid.as_str())
+                .with("name", game.name.as_str())
+                .with("path", *path)
+                .with("incomplete", game.incomplete),
+            Event::EntryDamaged { game, path, damage } => base("entry_damaged")
+                .with("appid", game.appid.as_str())
+                .with("name", game.name.as_str())
+                .with("path", *path)
+                .with("damage", damage.kind()),
+            Event::EntryMigrated { from, to } => {
+                base("entry_migrated").with("from", *from).with("to", *to)
+            }
+            Event::OutputWritten { path } => base("output_written").with("path", *path),
+            Event::Warning(message) => base("warning").with("message", message.as_str()),
+            Event::Error(message) => base("error").with("message", message.as_str()),
+            Event::Summary {
+                created,
+                skipped,
+                removed,
+                app_dir,
+                cleanup_skipped,
+                elapsed_ms,
+            } => base("summary")
+                .with("created", *created)
+                .with("skipped", *skipped)
+                .with("removed", *removed)
+                .with("app_dir", *app_dir)
+                .with("dry_run", self.dry_run)
+                .with("cleanup_skipped", *cleanup_skipped)
+                .with("elapsed_ms", *elapsed_ms as u64),
+        }
+    }
+}
+
+/// Where the name came from, when the manifest didn't have it.
+fn name_note(game: &GameInfo) -> &'static str {
+    match game.name_source {
+        NameSource::Manifest => "",
+        NameSource::AppInfo => " [no name in the manifest, named from appinfo.vdf]",
+        NameSource::OwnedGames => " [no name in the manifest, named from the owned games list]",
+        NameSource::BuiltIn => " [no name in the manifest, named from steamer's list of tools]",
+        NameSource::InstallDir => {
+            " [no name in the manifest or anywhere else, named after its folder]"
+        }
+        NameSource::Placeholder => " [no name in the manifest or anywhere else]",
+    }
+}
+
+fn incomplete_note(game: &GameInfo) -> &'static str {
+    if game.incomplete {
+        " [no manifest, listed in libraryfolders.vdf]"
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+
+    fn game() -> GameInfo {
+        GameInfo {
+            appid: "620".to_string(),
+            name: "Portal 2 \"Co-op\"".to_string(),
+            depots: vec!["621".to_string()],
+            install_details: Some(InstallDetails {
+                bytes_downloaded: Some(10),
+                ..InstallDetails::default()
+            }),
+            ..GameInfo::default()
+        }
+    }
+
+    /// Every event once, each with the `event` name it should carry.
+    fn every_event<'a>(
+        game: &'a GameInfo,
+        reason: &'a SkipReason,
+    ) -> Vec<(&'static str, Event<'a>)> {
+        let path = Path::new("/home/user/.local/share/applications");
+        vec![
+            (
+                "run_started",
+                Event::RunStarted {
+                    steam_root: path,
+                    app_dir: path,
+                    icon_cache: path,
+                },
+            ),
+            ("phase_started", Event::PhaseStarted(Phase::Cleanup)),
+            (
+                "library_scanned",
+                Event::LibraryScanned {
+                    path,
+                    games: 2,
+                    extra: false,
+                },
+            ),
+            ("game_discovered", Event::GameDiscovered(game)),
+            ("game_skipped", Event::GameSkipped { game, reason }),
+            ("entry_written", Event::EntryWritten { game, path }),
+            (
+                "entry_damaged",
+                Event::EntryDamaged {
+                    game,
+                    path,
+                    damage: Damage::Deleted,
+                },
+            ),
+            (
+                "entry_migrated",
+                Event::EntryMigrated {
+                    from: path,
+                    to: path,
+                },
+            ),
+            ("output_written", Event::OutputWritten { path }),
+            ("warning", Event::Warning("line\nbreak".to_string())),
+            ("error", Event::Error("tab\there".to_string())),
+            (
+                "summary",
+                Event::Summary {
+                    created: 1,
+                    skipped: 2,
+                    removed: 3,
+                    app_dir: path,
+                    cleanup_skipped: false,
+                    elapsed_ms: 42,
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_event_is_valid_json() {
+        let reporter = Reporter::new(LogFormat::Json, true);
+        let reason = SkipReason::AppId("620".to_string());
+        for (name, event) in every_event(&game(), &reason) {
+            let line = reporter.to_json(&event).to_compact();
+            assert!(!line.contains('\n'), "{} spans lines", name);
+            let value = json::parse(&line).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(value.get("event").and_then(Value::as_str), Some(name));
+            assert_eq!(
+                value.get("schema_version").and_then(Value::as_u64),
+                Some(SCHEMA_VERSION as u64)
+            );
+        }
+    }
+
+    #[test]
+    fn events_serialize_the_same_every_time() {
+        let reporter = Reporter::new(LogFormat::Json, false);
+        let reason = SkipReason::AppId("620".to_string());
+        let first: Vec<String> = every_event(&game(), &reason)
+            .iter()
+            .map(|(_, event)| reporter.to_json(event).to_compact())
+            .collect();
+        let second: Vec<String> = every_event(&game(), &reason)
+            .iter()
+            .map(|(_, event)| reporter.to_json(event).to_compact())
+            .collect();
+        assert_eq!(first, second);
+
+        let game = game();
+        let written = Event::EntryWritten {
+            game: &game,
+            path: Path::new("/apps/steamer-620.desktop"),
+        };
+        assert_eq!(
+            reporter.to_json(&written).to_compact(),
+            r#"{"schema_version":1,"event":"entry_written","appid":"620","name":"Portal 2 \"Co-op\"","path":"/apps/steamer-620.desktop","incomplete":false}"#
+        );
+        let skipped = Event::GameSkipped {
+            game: &game,
+            reason: &reason,
+        };
+        assert_eq!(
+            reporter.to_json(&skipped).to_compact(),
+            r#"{"schema_version":1,"event":"game_skipped","appid":"620","name":"Portal 2 \"Co-op\"","reason":"app_id","detail":"ignored app ID 620"}"#
+        );
+    }
+
+    #[test]
+    fn discovered_games_carry_their_scheduled_update() {
+        let reporter = Reporter::new(LogFormat::Json, false);
+        let scheduled = GameInfo {
+            scheduled_update: Some(1_700_000_000),
+            ..game()
+        };
+        let line = reporter
+            .to_json(&Event::GameDiscovered(&scheduled))
+            .to_compact();
+        let value = json::parse(&line).unwrap();
+        assert_eq!(
+            value.get("scheduled_update").and_then(Value::as_u64),
+            Some(1_700_000_000)
+        );
+
+        let line = reporter
+            .to_json(&Event::GameDiscovered(&game()))
+            .to_compact();
+        assert!(line.contains(r#""scheduled_update":null"#), "{}", line);
+    }
+
+    #[test]
+    fn discovered_games_carry_their_install_details() {
+        let reporter = Reporter::new(LogFormat::Json, false);
+        let downloading = GameInfo {
+            install_details: Some(InstallDetails {
+                bytes_downloaded: Some(500),
+                bytes_to_download: Some(1000),
+                ..InstallDetails::default()
+            }),
+            ..game()
+        };
+        let line = reporter
+            .to_json(&Event::GameDiscovered(&downloading))
+            .to_compact();
+        let value = json::parse(&line).unwrap();
+        let details = value.get("install_details").unwrap();
+        assert_eq!(
+            details.get("bytes_downloaded").and_then(Value::as_u64),
+            Some(500)
+        );
+        assert!(line.contains(r#""download_percent":50"#), "{}", line);
+        assert!(line.contains(r#""auto_update":null"#), "{}", line);
+
+        let settled = GameInfo {
+            install_details: None,
+            ..game()
+        };
+        let line = reporter
+            .to_json(&Event::GameDiscovered(&settled))
+            .to_compact();
+        assert!(line.contains(r#""install_details":null"#), "{}", line);
+    }
+}
@@ -0,0 +1,124 @@
+//! `runs.json` in steamer's cache directory: the app IDs each of the last few syncs found
+//! installed, newest last. `--new-since N` compares against it to find the games that showed up
+//! in the last N runs.
+
+use crate::json::{self, Value};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How many runs are kept, and so the largest `--new-since` that can find anything old.
+pub const MAX_RUNS: usize = 50;
+
+pub struct RunHistory {
+    path: PathBuf,
+    runs: Vec<HashSet<String>>,
+}
+
+impl RunHistory {
+    /// The history saved by earlier runs. A file that can't be read counts as no history.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join("runs.json");
+        let runs = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| json::parse(&text).ok())
+            .map(|root| {
+                root.get("runs")
+                    .map(Value::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|run| {
+                        run.as_array()
+                            .iter()
+                            .filter_map(|appid| appid.as_str().map(str::to_string))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { path, runs }
+    }
+
+    /// How many runs are remembered.
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// The app IDs found `n` runs before this one, which games new in the last `n` runs aren't
+    /// among. `None` while fewer runs were saved, every game counts as new then.
+    pub fn installed_before(&self, n: usize) -> Option<&HashSet<String>> {
+        self.runs.len().checked_sub(n).map(|i| &self.runs[i])
+    }
+
+    /// Adds this run's games, dropping the oldest run once there are more than [`MAX_RUNS`].
+    pub fn save(&mut self, appids: HashSet<String>) -> Result<()> {
+        self.runs.push(appids);
+        let excess = self.runs.len().saturating_sub(MAX_RUNS);
+        self.runs.drain(..excess);
+        let runs: Vec<Value> = self
+            .runs
+            .iter()
+            .map(|run| {
+                let mut appids: Vec<&str> = run.iter().map(String::as_str).collect();
+                appids.sort();
+                Value::from(appids)
+            })
+            .collect();
+        let root = Value::object().with("runs", Value::Array(runs));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+        }
+        fs::write(&self.path, root.to_compact() + "\n")
+            .with_context(|| format!("Could not write {:?}", self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn appids(appids: &[&str]) -> HashSet<String> {
+        appids.iter().map(|appid| appid.to_string()).collect()
+    }
+
+    #[test]
+    fn remembers_the_games_of_the_last_runs() {
+        let dir = std::env::temp_dir().join(format!("steamer-runs-test-{}", std::process::id()));
+        let mut history = RunHistory::load(&dir);
+        assert!(history.is_empty());
+        assert!(history.installed_before(1).is_none());
+
+        history.save(appids(&["620"])).unwrap();
+        history.save(appids(&["620", "400"])).unwrap();
+        let history = RunHistory::load(&dir);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.installed_before(1), Some(&appids(&["620", "400"])));
+        assert_eq!(history.installed_before(2), Some(&appids(&["620"])));
+        assert!(history.installed_before(3).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_only_the_newest_runs() {
+        let dir =
+            std::env::temp_dir().join(format!("steamer-runs-max-test-{}", std::process::id()));
+        let mut history = RunHistory::load(&dir);
+        for run in 0..MAX_RUNS + 5 {
+            history.save(appids(&[&run.to_string()])).unwrap();
+        }
+        let history = RunHistory::load(&dir);
+        assert_eq!(history.len(), MAX_RUNS);
+        assert_eq!(history.installed_before(MAX_RUNS), Some(&appids(&["5"])));
+
+        // an unreadable file is no history rather than an error
+        fs::write(dir.join("runs.json"), "not json").unwrap();
+        assert!(RunHistory::load(&dir).is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! `--save-dir-action`: where a game keeps its saves, for the "Open save folder" action. Steam
+//! has no record of it, so unless the `save_dir` of the game's `[game.<appid>]` table says, it's
+//! a guess from the places games usually save to. Games whose folder can't be found get no
+//! action rather than one that opens nothing.
+
+use crate::report::Reporter;
+use crate::steam::GameInfo;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folders in the Proton prefix's user folder that Windows games save to, most specific first.
+const PREFIX_SAVE_DIRS: &[&str] = &[
+    "Saved Games",
+    "Documents/My Games",
+    "AppData/LocalLow",
+    "Documents",
+    "AppData/Roaming",
+    "AppData/Local",
+];
+
+/// What Wine puts in those folders itself, which says nothing about the game.
+const WINE_DEFAULTS: &[&str] = &[
+    "Microsoft",
+    "Temp",
+    "My Music",
+    "My Pictures",
+    "My Videos",
+    "openxr",
+];
+
+pub struct SaveDirs {
+    configured: HashMap<String, PathBuf>,
+}
+
+impl SaveDirs {
+    pub fn new(configured: Vec<(String, PathBuf)>) -> Self {
+        Self {
+            configured: configured.into_iter().collect(),
+        }
+    }
+
+    /// The save folder of a game installed in `library`. One set in the config that isn't
+    /// there is warned about, and no guess is made in its place, a guess is what it replaced.
+    pub fn find(&self, game: &GameInfo, library: &Path, reporter: &Reporter) -> Option<PathBuf> {
+        if let Some(path) = self.configured.get(&game.appid) {
+            if path.is_dir() {
+                return Some(path.clone());
+            }
+            reporter.warn(format!(
+                "The save folder {:?} set for app {} doesn't exist, leaving out its action",
+                path, game.appid
+            ));
+            return None;
+        }
+        let prefix = library
+            .join("steamapps/compatdata")
+            .join(&game.appid)
+            .join("pfx");
+        if prefix.is_dir() {
+            return prefix_save_dir(&prefix.join("drive_c/users/steamuser"));
+        }
+        native_save_dir(game)
+    }
+}
+
+/// The first of [`PREFIX_SAVE_DIRS`] something other than Wine wrote to, or else the user
+/// folder as a whole.
+fn prefix_save_dir(user: &Path) -> Option<PathBuf> {
+    for dir in PREFIX_SAVE_DIRS {
+        let path = user.join(dir);
+        let Ok(entries) = fs::read_dir(&path) else {
+            continue;
+        };
+        let used = entries.filter_map(Result::ok).any(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            // desktop.ini, and the other folders of the list nested in this one
+            !WINE_DEFAULTS.contains(&name.as_ref())
+                && !name.eq_ignore_ascii_case("desktop.ini")
+                && !PREFIX_SAVE_DIRS
+                    .iter()
+                    .any(|d| d.rsplit('/').next() == Some(name.as_ref()))
+        });
+        if used {
+            return Some(path);
+        }
+    }
+    user.is_dir().then(|| user.to_path_buf())
+}
+
+/// A folder named after the install directory in `~/.local/share` or `~/.config`, where
+/// native games following the XDG base directories save.
+fn native_save_dir(game: &GameInfo) -> Option<PathBuf> {
+    let installdir = game.installdir.as_deref()?;
+    let lower = installdir.to_lowercase();
+    let mut names = vec![
+        installdir.to_string(),
+        lower.replace(' ', ""),
+        lower.replace(' ', "-"),
+        lower.replace(' ', "_"),
+        lower,
+    ];
+    names.dedup();
+    [dirs::data_dir(), dirs::config_dir()]
+        .into_iter()
+        .flatten()
+        .flat_map(|base| names.iter().map(move |name| base.join(name)))
+        .find(|path| path.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+
+    #[test]
+    fn finds_where_proton_games_save() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let library =
+            std::env::temp_dir().join(format!("steamer-saves-test-{}", std::process::id()));
+        let user = library.join("steamapps/compatdata/620/pfx/drive_c/users/steamuser");
+        let game = GameInfo {
+            appid: "620".to_string(),
+            ..GameInfo::default()
+        };
+        let saves = SaveDirs::new(Vec::new());
+
+        // only what Wine made itself
+        fs::create_dir_all(user.join("Saved Games")).unwrap();
+        fs::write(user.join("Saved Games/desktop.ini"), "").unwrap();
+        fs::create_dir_all(user.join("Documents/My Games")).unwrap();
+        fs::create_dir_all(user.join("AppData/Roaming/Microsoft")).unwrap();
+        assert_eq!(saves.find(&game, &library, &reporter), Some(user.clone()));
+
+        fs::create_dir_all(user.join("AppData/Roaming/Portal 2")).unwrap();
+        assert_eq!(
+            saves.find(&game, &library, &reporter),
+            Some(user.join("AppData/Roaming"))
+        );
+        fs::create_dir_all(user.join("Documents/My Games/Portal 2")).unwrap();
+        assert_eq!(
+            saves.find(&game, &library, &reporter),
+            Some(user.join("Documents/My Games"))
+        );
+
+        // the config wins, and one that's gone means no action instead of a guess
+        let saves = SaveDirs::new(vec![("620".to_string(), user.join("AppData"))]);
+        assert_eq!(
+            saves.find(&game, &library, &reporter),
+            Some(user.join("AppData"))
+        );
+        let saves = SaveDirs::new(vec![("620".to_string(), library.join("gone"))]);
+        assert_eq!(saves.find(&game, &library, &reporter), None);
+        fs::remove_dir_all(&library).unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+//! `steamer select`: picks the games that get entries from a numbered list in the terminal.
+//! Games left out are written to `~/.config/steamer/.steamerignore` as app IDs, so every later
+//! run skips them, and the file stays one people can edit by hand.
+
+use crate::appinfo::AppInfoSource;
+use crate::cli::Args;
+use crate::filter::{self, SkipReason};
+use crate::paths::Paths;
+use crate::report::Reporter;
+use crate::steam;
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+
+/// A game in the list.
+struct Choice {
+    appid: String,
+    name: String,
+    selected: bool,
+    /// Why another rule skips the game, which picking it here can't change.
+    locked: Option<String>,
+}
+
+pub fn select(args: &Args) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, true);
+    let paths = Paths::resolve(args, true, &reporter)?;
+    let ignore_file = dirs::config_dir()
+        .context("Could not find the config directory")?
+        .join("steamer/.steamerignore");
+    let appinfo = AppInfoSource::new(&paths.steam_root).enabled(!args.no_appinfo);
+    let (mut libraries, _) = steam::scan_libraries(&paths, &appinfo, None, &reporter)?;
+    if (args.skip_demos || !args.include_playtests)
+        && let Some(info) = appinfo.get(&reporter)
+    {
+        steam::add_appinfo_details(&mut libraries, info);
+    }
+    let skip_rules = args.skip_rules()?.ignore_files(&paths.ignore_files)?;
+
+    let mut seen = HashSet::new();
+    let mut choices: Vec<Choice> = libraries
+        .iter()
+        .flat_map(|library| &library.games)
+        .filter(|game| seen.insert(game.appid.clone()))
+        .map(|game| {
+            let (selected, locked) = match skip_rules.check(game) {
+                None => (true, None),
+                Some(SkipReason::IgnoreFile { pattern, file })
+                    if pattern == game.appid && file == ignore_file =>
+                {
+                    (false, None)
+                }
+                Some(reason) => (false, Some(reason.to_string())),
+            };
+            Choice {
+                appid: game.appid.clone(),
+                name: game.name.clone(),
+                selected,
+                locked,
+            }
+        })
+        .collect();
+    choices.sort_by_cached_key(|choice| choice.name.to_lowercase());
+    if choices.is_empty() {
+        println!("No games found.");
+        return Ok(());
+    }
+
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        print_choices(&choices);
+        println!("Not running in a terminal, nothing was changed.");
+        return Ok(());
+    }
+    loop {
+        print_choices(&choices);
+        print!(
+            "Numbers or ranges like 3 5-7 to toggle, a for all, n for none, Enter to save, q to quit: "
+        );
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        if io::stdin().read_line(&mut answer)? == 0 {
+            return Ok(());
+        }
+        match answer.trim() {
+            "" => break,
+            "q" => return Ok(()),
+            "a" | "n" => {
+                let selected = answer.trim() == "a";
+                for choice in choices.iter_mut().filter(|c| c.locked.is_none()) {
+                    choice.selected = selected;
+                }
+            }
+            input => match toggle(&mut choices, input) {
+                Ok(()) => {}
+                Err(e) => println!("{:#}", e),
+            },
+        }
+    }
+
+    let text = match fs::read_to_string(&ignore_file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {:?}", ignore_file)),
+    };
+    write_ignore_file(&ignore_file, &apply_selection(&text, &choices))?;
+    let left_out = choices
+        .iter()
+        .filter(|c| !c.selected && c.locked.is_none())
+        .count();
+    println!("Saved to {:?}, {} games left out.", ignore_file, left_out);
+    Ok(())
+}
+
+fn print_choices(choices: &[Choice]) {
+    let width = choices.len().to_string().len();
+    for (index, choice) in choices.iter().enumerate() {
+        let mark = if choice.selected { "x" } else { " " };
+        match choice.locked {
+            Some(ref reason) => println!(
+                "  {:>width$}  -  {} ({}, skipped: {})",
+                "",
+                choice.name,
+                choice.appid,
+                reason,
+                width = width
+            ),
+            None => println!(
+                "  {:>width$} [{}] {} ({})",
+                index + 1,
+                mark,
+                choice.name,
+                choice.appid,
+                width = width
+            ),
+        }
+    }
+}
+
+/// Toggles the games numbered in `input`, checking all of it before changing anything.
+fn toggle(choices: &mut [Choice], input: &str) -> Result<()> {
+    let mut picked = Vec::new();
+    for part in input.split([' ', ',']).filter(|p| !p.is_empty()) {
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .ok()
+                .filter(|&n| (1..=choices.len()).contains(&n))
+        };
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (number(start), number(end)),
+            None => (number(part), number(part)),
+        };
+        let (Some(start), Some(end)) = (start, end) else {
+            bail!(
+                "{:?} isn't a number or range from 1 to {}",
+                part,
+                choices.len()
+            );
+        };
+        picked.extend(start.min(end)..=start.max(end));
+    }
+    for number in picked {
+        let choice = &mut choices[number - 1];
+        if choice.locked.is_none() {
+            choice.selected = !choice.selected;
+        }
+    }
+    Ok(())
+}
+
+/// The ignore file with an app ID line for every game left out, and none for the picked ones.
+/// Lines for other games, name patterns and comments stay as they are.
+fn apply_selection(text: &str, choices: &[Choice]) -> String {
+    let picked: HashSet<&str> = choices
+        .iter()
+        .filter(|c| c.selected)
+        .map(|c| c.appid.as_str())
+        .collect();
+    let appid_of = |line: &str| {
+        let line = filter::strip_comment(line).trim();
+        (!line.is_empty() && line.bytes().all(|b| b.is_ascii_digit())).then(|| line.to_string())
+    };
+
+    let mut out = String::new();
+    let mut listed = HashSet::new();
+    for line in text.lines() {
+        match appid_of(line) {
+            Some(appid) if picked.contains(appid.as_str()) => continue,
+            Some(appid) => {
+                listed.insert(appid);
+            }
+            None => {}
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    for choice in choices.iter().filter(|c| !c.selected && c.locked.is_none()) {
+        if !listed.contains(&choice.appid) {
+            out.push_str(&format!("{}  # {}\n", choice.appid, choice.name));
+        }
+    }
+    out
+}
+
+fn write_ignore_file(path: &Path, text: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+    }
+    fs::write(path, text).with_context(|| format!("Could not write {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choices() -> Vec<Choice> {
+        [("400", "Portal"), ("620", "Portal 2"), ("570", "Dota 2")]
+            .into_iter()
+            .map(|(appid, name)| Choice {
+                appid: appid.to_string(),
+                name: name.to_string(),
+                selected: true,
+                locked: None,
+            })
+            .collect()
+    }
+
+    fn selected(choices: &[Choice]) -> Vec<&str> {
+        choices
+            .iter()
+            .filter(|c| c.selected)
+            .map(|c| c.appid.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn toggles_numbers_and_ranges() {
+        let mut list = choices();
+        toggle(&mut list, "1 3").unwrap();
+        assert_eq!(selected(&list), ["620"]);
+        toggle(&mut list, "3-1").unwrap();
+        assert_eq!(selected(&list), ["400", "570"]);
+
+        // a locked game stays as another rule has it
+        list[1].locked = Some("ignored app ID 620".to_string());
+        toggle(&mut list, "1-3").unwrap();
+        assert!(selected(&list).is_empty());
+
+        let error = toggle(&mut list, "1 4").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "\"4\" isn't a number or range from 1 to 3"
+        );
+        assert!(selected(&list).is_empty(), "nothing changes on a bad input");
+        assert!(toggle(&mut list, "x-2").is_err());
+    }
+
+    #[test]
+    fn writes_the_games_left_out_to_the_ignore_file() {
+        let mut list = choices();
+        list[0].selected = false;
+        list[2].selected = false;
+        let text = "# my ignores\n620\n*Soundtrack*\n570  # Dota 2\n";
+        assert_eq!(
+            apply_selection(text, &list),
+            "# my ignores\n*Soundtrack*\n570  # Dota 2\n400  # Portal\n"
+        );
+
+        // games another rule skips aren't written
+        list[0].locked = Some("demo".to_string());
+        assert_eq!(apply_selection("", &list), "570  # Dota 2\n");
+    }
+}
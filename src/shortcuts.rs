@@ -0,0 +1,175 @@
+//! `--include-shortcuts`: entries for the non-Steam games added to Steam, which it keeps per
+//! account in `userdata/<account>/config/shortcuts.vdf`. They launch through Steam like its
+//! own games, so the overlay, Proton and controller settings picked for them still apply.
+
+use crate::report::Reporter;
+use crate::steam::GameInfo;
+use crate::vdf::{self, Node};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set in the low half of the ID `steam://rungameid/` takes for a shortcut.
+const SHORTCUT_BIT: u64 = 0x0200_0000;
+
+/// A non-Steam game, as the client lists it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortcut {
+    /// The ID the client gave the shortcut. It names the grid art too.
+    pub appid: u32,
+    pub name: String,
+    /// The icon picked for it in the client, when it's still there.
+    pub icon: Option<PathBuf>,
+}
+
+impl Shortcut {
+    pub fn to_game_info(&self) -> GameInfo {
+        GameInfo {
+            appid: self.appid.to_string(),
+            name: self.name.clone(),
+            shortcut: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The ID `steam://rungameid/` starts the shortcut `appid` by: the appid in the upper half and
+/// [`SHORTCUT_BIT`] in the lower one.
+pub fn game_id(appid: &str) -> Option<u64> {
+    let appid: u32 = appid.parse().ok()?;
+    Some((appid as u64) << 32 | SHORTCUT_BIT)
+}
+
+/// The shortcuts of every account, those of the lowest account ID first where two have the
+/// same one. The ones hidden in the client are left out, and an unreadable file is warned
+/// about and passed over.
+pub fn load(steam_root: &Path, reporter: &Reporter) -> Vec<Shortcut> {
+    let mut files: Vec<PathBuf> = fs::read_dir(steam_root.join("userdata"))
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .map(|account| account.path().join("config/shortcuts.vdf"))
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+
+    let mut seen = HashSet::new();
+    let mut shortcuts = Vec::new();
+    for path in files {
+        match read(&path) {
+            Ok(found) => {
+                shortcuts.extend(found.into_iter().filter(|s| seen.insert(s.appid)));
+            }
+            Err(e) => reporter.warn(format!("Skipping the shortcuts in {:?}: {:#}", path, e)),
+        }
+    }
+    shortcuts
+}
+
+fn read(path: &Path) -> Result<Vec<Shortcut>> {
+    let data = fs::read(path).with_context(|| format!("Could not read {:?}", path))?;
+    let (root, _) = vdf::parse_binary(&data, None)?;
+    Ok(root
+        .get("shortcuts")
+        .map(Node::entries)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(_, node)| parse_shortcut(node))
+        .collect())
+}
+
+/// One entry of the `shortcuts` map. The client has written the keys in more than one case,
+/// which [`Node::get`] doesn't mind, and stores the appid as a signed 32 bit number.
+fn parse_shortcut(node: &Node) -> Option<Shortcut> {
+    let appid = match node.get("appid")? {
+        Node::Int(id) => *id as u32,
+        _ => return None,
+    };
+    let name = node.get("AppName")?.as_str()?.trim();
+    let hidden = matches!(node.get("IsHidden"), Some(Node::Int(1)));
+    if name.is_empty() || hidden {
+        return None;
+    }
+    let icon = node
+        .get("icon")
+        .and_then(Node::as_str)
+        .map(|icon| PathBuf::from(icon.trim_matches('"')))
+        .filter(|icon| icon.is_file());
+    Some(Shortcut {
+        appid,
+        name: name.to_string(),
+        icon,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::LogFormat;
+
+    /// A binary shortcuts.vdf of `(appid, name, hidden)` entries.
+    fn shortcuts_vdf(shortcuts: &[(u32, &str, bool)]) -> Vec<u8> {
+        let mut data = b"\x00shortcuts\x00".to_vec();
+        for (index, (appid, name, hidden)) in shortcuts.iter().enumerate() {
+            data.push(0x00);
+            data.extend(format!("{}\x00", index).bytes());
+            data.extend(b"\x02appid\x00");
+            data.extend(appid.to_le_bytes());
+            data.extend(format!("\x01AppName\x00{}\x00", name).bytes());
+            data.extend(b"\x01Exe\x00\"/opt/game/run\"\x00");
+            data.extend(b"\x01icon\x00\x00");
+            data.extend(b"\x02IsHidden\x00");
+            data.extend(u32::from(*hidden).to_le_bytes());
+            data.push(0x08);
+        }
+        data.extend(b"\x08\x08");
+        data
+    }
+
+    #[test]
+    fn reads_the_shortcuts_of_every_account() {
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let steam_root =
+            std::env::temp_dir().join(format!("steamer-shortcuts-test-{}", std::process::id()));
+        let write = |account: &str, data: &[u8]| {
+            let dir = steam_root.join("userdata").join(account).join("config");
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("shortcuts.vdf"), data).unwrap();
+        };
+        write(
+            "1001",
+            &shortcuts_vdf(&[
+                (3_456_789_012, "Heroic", false),
+                (2_900_000_000, "Old Emulator", true),
+            ]),
+        );
+        write(
+            "1002",
+            &shortcuts_vdf(&[
+                (3_456_789_012, "Heroic", false),
+                (2_999_999_999, "Itch", false),
+            ]),
+        );
+        write("1003", b"\x00shortcuts\x00\x09");
+
+        let names: Vec<(u32, String)> = load(&steam_root, &reporter)
+            .into_iter()
+            .map(|shortcut| (shortcut.appid, shortcut.name))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                (3_456_789_012, "Heroic".to_string()),
+                (2_999_999_999, "Itch".to_string()),
+            ]
+        );
+        fs::remove_dir_all(&steam_root).unwrap();
+    }
+
+    #[test]
+    fn shortcuts_run_by_their_game_id() {
+        assert_eq!(game_id("3456789012"), Some(14_846_795_755_745_705_984));
+        assert_eq!(game_id("620x"), None);
+    }
+}
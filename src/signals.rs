@@ -0,0 +1,31 @@
+//! Ctrl-C and SIGTERM handling. The first signal only raises a flag the write phase checks
+//! between entries, so it can stop cleanly and roll back; a second one exits on the spot.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Exit code for a run stopped by a signal, the usual 128 + SIGINT.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handle(_signal: libc::c_int) {
+    // only async-signal-safe calls in here: an atomic and _exit
+    if RECEIVED.fetch_add(1, Ordering::SeqCst) >= 1 {
+        unsafe { libc::_exit(EXIT_INTERRUPTED) };
+    }
+}
+
+pub fn install() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::sigemptyset(&mut action.sa_mask);
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+pub fn interrupted() -> bool {
+    RECEIVED.load(Ordering::SeqCst) > 0
+}
@@ -0,0 +1,239 @@
+//! `entries.json` in steamer's cache directory: a hash of every entry as steamer last wrote it.
+//! `--repair` checks the files against it to find the entries another tool deleted or changed,
+//! and `steamer watch` does the same whenever something in the applications directory changes.
+
+use crate::json::{self, Value};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The line that keeps a repair away from an entry, for entries changed by hand on purpose.
+pub const PRESERVE_MARKER: &str = "X-Steamer-Preserve=true";
+
+/// What happened to an entry since steamer wrote it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Damage {
+    Deleted,
+    Changed,
+}
+
+impl Damage {
+    /// Stable identifier for machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Damage::Deleted => "deleted",
+            Damage::Changed => "changed",
+        }
+    }
+}
+
+impl fmt::Display for Damage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Damage::Deleted => write!(f, "deleted"),
+            Damage::Changed => write!(f, "changed since steamer wrote it"),
+        }
+    }
+}
+
+/// What a repair finds where an entry belongs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// steamer has no record of writing it, so it's written like on any sync.
+    Unrecorded,
+    /// The file is what steamer wrote.
+    Intact,
+    /// The file has [`PRESERVE_MARKER`] in it and stays as it is.
+    Preserved,
+    Damaged(Damage),
+}
+
+pub struct EntryState {
+    path: PathBuf,
+    entries: BTreeMap<PathBuf, u64>,
+}
+
+impl EntryState {
+    /// No entries, to be saved to `dir`. A run that removes the entries it doesn't write again
+    /// starts from this, so the file never lists entries steamer removed.
+    pub fn empty(dir: &Path) -> Self {
+        Self {
+            path: dir.join("entries.json"),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// The entries saved by earlier runs. A file that can't be read counts as no entries.
+    pub fn load(dir: &Path) -> Self {
+        let mut state = Self::empty(dir);
+        let Some(root) = fs::read_to_string(&state.path)
+            .ok()
+            .and_then(|text| json::parse(&text).ok())
+        else {
+            return state;
+        };
+        for item in root.get("entries").map(Value::as_array).unwrap_or_default() {
+            let path = item.get("path").and_then(Value::as_str);
+            let hash = item
+                .get("hash")
+                .and_then(Value::as_str)
+                .and_then(|hash| u64::from_str_radix(hash, 16).ok());
+            if let (Some(path), Some(hash)) = (path, hash) {
+                state.entries.insert(PathBuf::from(path), hash);
+            }
+        }
+        state
+    }
+
+    /// Notes that steamer wrote `content` to `path`.
+    pub fn record(&mut self, path: &Path, content: &str) {
+        self.entries
+            .insert(path.to_path_buf(), content_hash(content.as_bytes()));
+    }
+
+    /// How the file at `path` compares to what steamer last wrote there. A preserved entry is
+    /// left alone even when steamer never wrote it.
+    pub fn condition(&self, path: &Path) -> Condition {
+        let content = match fs::read(path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            // one that can't be read is written again, which says what's wrong if it still fails
+            Err(_) => Some(Vec::new()),
+        };
+        if content.as_deref().is_some_and(is_preserved) {
+            return Condition::Preserved;
+        }
+        let Some(&hash) = self.entries.get(path) else {
+            return Condition::Unrecorded;
+        };
+        match content {
+            None => Condition::Damaged(Damage::Deleted),
+            Some(content) if content_hash(&content) == hash => Condition::Intact,
+            Some(_) => Condition::Damaged(Damage::Changed),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|(path, hash)| {
+                Value::object()
+                    .with("path", path.as_path())
+                    .with("hash", format!("{:016x}", hash))
+            })
+            .collect();
+        let root = Value::object().with("entries", Value::Array(entries));
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Could not create {:?}", parent))?;
+        }
+        fs::write(&self.path, root.to_compact() + "\n")
+            .with_context(|| format!("Could not write {:?}", self.path))
+    }
+}
+
+/// Whether an entry has [`PRESERVE_MARKER`] on a line of its own.
+fn is_preserved(content: &[u8]) -> bool {
+    String::from_utf8_lossy(content)
+        .lines()
+        .any(|line| line.trim() == PRESERVE_MARKER)
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it's the same in every build, so upgrading steamer
+/// doesn't make every entry look changed.
+fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-state-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const ENTRY: &str = "[Desktop Entry]\nName=Portal 2\nExec=steam steam://rungameid/620\n";
+
+    #[test]
+    fn hashes_are_stable() {
+        assert_eq!(content_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(content_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(
+            content_hash(ENTRY.as_bytes()),
+            content_hash(b"[Desktop Entry]\n")
+        );
+    }
+
+    #[test]
+    fn finds_deleted_and_changed_entries() {
+        let dir = temp_dir();
+        let path = dir.join("steam-620.desktop");
+        let mut state = EntryState::empty(&dir);
+        assert_eq!(state.condition(&path), Condition::Unrecorded);
+
+        fs::write(&path, ENTRY).unwrap();
+        assert_eq!(state.condition(&path), Condition::Unrecorded);
+        state.record(&path, ENTRY);
+        assert_eq!(state.condition(&path), Condition::Intact);
+
+        fs::write(&path, ENTRY.replace("Portal 2", "Portal 3")).unwrap();
+        assert_eq!(state.condition(&path), Condition::Damaged(Damage::Changed));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(state.condition(&path), Condition::Damaged(Damage::Deleted));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn never_repairs_preserved_entries() {
+        let dir = temp_dir();
+        let path = dir.join("steam-620.desktop");
+        let mut state = EntryState::empty(&dir);
+        state.record(&path, ENTRY);
+
+        fs::write(&path, format!("{}{}\n", ENTRY, PRESERVE_MARKER)).unwrap();
+        assert_eq!(state.condition(&path), Condition::Preserved);
+        // only a line of its own counts
+        fs::write(&path, format!("{}Comment={}\n", ENTRY, PRESERVE_MARKER)).unwrap();
+        assert_eq!(state.condition(&path), Condition::Damaged(Damage::Changed));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_entries_load_again() {
+        let dir = temp_dir();
+        let path = dir.join("steam-620.desktop");
+        fs::write(&path, ENTRY).unwrap();
+        let mut state = EntryState::empty(&dir);
+        state.record(&path, ENTRY);
+        state.record(&dir.join("steam-400.desktop"), "[Desktop Entry]\n");
+        state.save().unwrap();
+
+        let loaded = EntryState::load(&dir);
+        assert_eq!(loaded.entries, state.entries);
+        assert_eq!(loaded.condition(&path), Condition::Intact);
+        assert_eq!(
+            loaded.condition(&dir.join("steam-400.desktop")),
+            Condition::Damaged(Damage::Deleted)
+        );
+
+        fs::write(dir.join("entries.json"), "{not json").unwrap();
+        assert!(EntryState::load(&dir).entries.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
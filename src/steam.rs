@@ -0,0 +1,1300 @@
+use crate::appinfo::{AppInfo, AppInfoSource};
+use crate::disk;
+use crate::filter;
+use crate::json::Value;
+use crate::metadata::{self, MetadataCache};
+use crate::owned::OwnedGame;
+use crate::paths::Paths;
+use crate::report::Reporter;
+use crate::shortcuts;
+use crate::vdf::{self, Node};
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub struct GameInfo {
+    pub appid: String,
+    pub name: String,
+    /// Folder name under `steamapps/common`.
+    pub installdir: Option<String>,
+    pub size_on_disk: Option<u64>,
+    /// Known only from the libraryfolders.vdf apps list, the manifest was missing or unreadable.
+    pub incomplete: bool,
+    /// `game`, `demo`, `application`, ... from appinfo, lowercased. Only filled in when needed.
+    pub app_type: Option<String>,
+    /// appinfo marks this as a playtest of another app.
+    pub playtest: bool,
+    /// Name of the app this one belongs to, when that one is installed too.
+    pub parent_name: Option<String>,
+    /// `(locale, name)` pairs from appinfo, only looked up when `--locales` asks for them.
+    pub localized_names: Vec<(String, String)>,
+    /// Owned but not installed, from `--include-uninstalled`; its entry starts the install.
+    pub not_installed: bool,
+    /// Modification time of the manifest in nanoseconds, `None` for games without one.
+    pub manifest_mtime: Option<u64>,
+    /// Why the manifest says the game can't be played yet, for `--skip-partial`.
+    pub partial_install: Option<PartialInstall>,
+    /// Where `name` came from, for manifests that don't have one.
+    pub name_source: NameSource,
+    /// Ids of the depots in `InstalledDepots` and `MountedDepots`, sorted. Only reported, for
+    /// a look at which optional content like language or texture packs is there.
+    pub depots: Vec<String>,
+    /// Appids of the DLC those depots belong to, sorted.
+    pub dlc: Vec<String>,
+    /// When Steam plans to update the game, in seconds since the epoch, from the manifest's
+    /// `ScheduledAutoUpdate`. Only reported in the JSON log.
+    pub scheduled_update: Option<u64>,
+    /// When the game was last started, in seconds since the epoch, from the manifest's
+    /// `LastPlayed`. Only reported, in `--emit-markdown` tables.
+    pub last_played: Option<u64>,
+    /// Download fields of the manifest, `None` when it has none of them. Only reported in the
+    /// JSON log.
+    pub install_details: Option<InstallDetails>,
+    /// ProtonDB rating like "Gold", only looked up with `--protondb` for games run in Proton.
+    pub protondb_tier: Option<String>,
+    /// A non-Steam game added to Steam, from `--include-shortcuts`.
+    pub shortcut: bool,
+}
+
+impl GameInfo {
+    /// The ID `steam://rungameid/` starts the game by, which is only the appid for Steam's own.
+    pub fn run_id(&self) -> String {
+        match shortcuts::game_id(&self.appid).filter(|_| self.shortcut) {
+            Some(id) => id.to_string(),
+            None => self.appid.clone(),
+        }
+    }
+}
+
+/// Where a game's name came from, tried in this order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameSource {
+    #[default]
+    Manifest,
+    AppInfo,
+    /// The cached owned games list from the Web API, with `--include-uninstalled`.
+    OwnedGames,
+    /// steamer's own list of the tools `--tools` keeps.
+    BuiltIn,
+    /// The game's folder under `steamapps/common`, which is usually close to its name.
+    InstallDir,
+    /// Nothing knew it, the name is "Unknown Game (<appid>)".
+    Placeholder,
+}
+
+impl NameSource {
+    /// Identifier used in the JSON log and the metadata cache.
+    pub fn kind(self) -> &'static str {
+        match self {
+            NameSource::Manifest => "manifest",
+            NameSource::AppInfo => "appinfo",
+            NameSource::OwnedGames => "owned_games",
+            NameSource::BuiltIn => "built_in",
+            NameSource::InstallDir => "installdir",
+            NameSource::Placeholder => "placeholder",
+        }
+    }
+
+    /// Whether a later source should replace the name: the folder name is only a stand-in
+    /// for the real one, like the placeholder.
+    pub fn is_fallback(self) -> bool {
+        matches!(self, NameSource::InstallDir | NameSource::Placeholder)
+    }
+
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        [
+            NameSource::Manifest,
+            NameSource::AppInfo,
+            NameSource::OwnedGames,
+            NameSource::BuiltIn,
+            NameSource::InstallDir,
+            NameSource::Placeholder,
+        ]
+        .into_iter()
+        .find(|source| source.kind() == kind)
+    }
+}
+
+/// Ways a manifest shows an install Steam hasn't finished.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartialInstall {
+    /// No depots installed at all.
+    NoDepots,
+    /// Only depots that belong to DLC, the game itself isn't there.
+    OnlyDlc,
+    /// The first download stopped part way, in bytes.
+    Downloading { done: u64, total: u64 },
+    /// Downloaded into the staging folder, but not yet moved into place.
+    Staging { done: u64, total: u64 },
+}
+
+impl fmt::Display for PartialInstall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartialInstall::NoDepots => write!(f, "no depots installed"),
+            PartialInstall::OnlyDlc => write!(f, "only DLC depots installed"),
+            PartialInstall::Downloading { done, total } => write!(
+                f,
+                "download stopped at {} of {}",
+                disk::format_size(*done),
+                disk::format_size(*total)
+            ),
+            PartialInstall::Staging { done, total } => write!(
+                f,
+                "{} of {} staged, not moved into place",
+                disk::format_size(*done),
+                disk::format_size(*total)
+            ),
+        }
+    }
+}
+
+/// Manifest fields about the state of an install that frontends may want to show, read as they
+/// are. Any of them can be missing, older clients don't write them all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallDetails {
+    pub bytes_downloaded: Option<u64>,
+    pub bytes_to_download: Option<u64>,
+    /// `AllowOtherDownloadsWhileRunning`: 0 follows the client setting, 1 allows, 2 doesn't.
+    pub allow_other_downloads: Option<u64>,
+    /// `AutoUpdateBehavior`: 0 keeps the game updated, 1 updates it on launch, 2 first.
+    pub auto_update: Option<u64>,
+}
+
+impl InstallDetails {
+    fn read(state: &Node) -> Option<Self> {
+        let number = |key: &str| {
+            state
+                .get(key)
+                .and_then(Node::to_text)
+                .and_then(|s| s.trim().parse::<u64>().ok())
+        };
+        let details = Self {
+            bytes_downloaded: number("BytesDownloaded"),
+            bytes_to_download: number("BytesToDownload"),
+            allow_other_downloads: number("AllowOtherDownloadsWhileRunning"),
+            auto_update: number("AutoUpdateBehavior"),
+        };
+        (details != Self::default()).then_some(details)
+    }
+
+    /// How much of the last download is done, rounded to a tenth of a percent. `None` when
+    /// nothing was to be downloaded.
+    pub fn download_percent(&self) -> Option<f64> {
+        let (done, total) = (self.bytes_downloaded?, self.bytes_to_download?);
+        (total > 0).then(|| (done.min(total) as f64 * 1000.0 / total as f64).round() / 10.0)
+    }
+
+    /// The fields as the JSON log and the metadata cache write them.
+    pub fn to_json(&self) -> Value {
+        Value::object()
+            .with("bytes_downloaded", self.bytes_downloaded)
+            .with("bytes_to_download", self.bytes_to_download)
+            .with("download_percent", self.download_percent())
+            .with("allow_other_downloads", self.allow_other_downloads)
+            .with("auto_update", self.auto_update)
+    }
+
+    pub fn from_json(item: &Value) -> Self {
+        let number = |key: &str| item.get(key).and_then(Value::as_u64);
+        Self {
+            bytes_downloaded: number("bytes_downloaded"),
+            bytes_to_download: number("bytes_to_download"),
+            allow_other_downloads: number("allow_other_downloads"),
+            auto_update: number("auto_update"),
+        }
+    }
+}
+
+/// `StateFlags` bit Steam sets once every depot is in place.
+const STATE_FULLY_INSTALLED: u64 = 4;
+
+/// Reads the partial install markers of an `AppState`. A game Steam flags as fully installed
+/// is playable even with an update pending, so only the DLC check applies to it; manifests
+/// without `StateFlags`, from old clients, are taken as complete.
+fn partial_install(state: &Node) -> Option<PartialInstall> {
+    let number = |key: &str| {
+        state
+            .get(key)
+            .and_then(Node::to_text)
+            .and_then(|s| s.parse::<u64>().ok())
+    };
+    let depots = state.get("InstalledDepots").map(Node::entries);
+    if let Some(depots) = depots
+        && !depots.is_empty()
+        && depots
+            .iter()
+            .all(|(_, depot)| depot.get("dlcappid").is_some())
+    {
+        return Some(PartialInstall::OnlyDlc);
+    }
+
+    let flags = number("StateFlags")?;
+    if flags & STATE_FULLY_INSTALLED != 0 {
+        return None;
+    }
+    let (downloaded, to_download) = (number("BytesDownloaded"), number("BytesToDownload"));
+    if let (Some(done), Some(total)) = (downloaded, to_download)
+        && done < total
+    {
+        return Some(PartialInstall::Downloading { done, total });
+    }
+    let (staged, to_stage) = (number("BytesStaged"), number("BytesToStage"));
+    if let (Some(done), Some(total)) = (staged, to_stage)
+        && done < total
+    {
+        return Some(PartialInstall::Staging { done, total });
+    }
+    if depots.is_none_or(<[_]>::is_empty) {
+        return Some(PartialInstall::NoDepots);
+    }
+    None
+}
+
+/// A library as listed in libraryfolders.vdf.
+pub struct LibraryFolder {
+    pub path: PathBuf,
+    /// Appids Steam records as installed there, empty for the pre-2021 file format.
+    pub apps: Vec<String>,
+    /// Added with `--extra-library` or `extra_libraries`, not listed by Steam.
+    pub extra: bool,
+}
+
+/// A Steam library and the games whose manifests live in its `steamapps` folder.
+pub struct Library {
+    pub path: PathBuf,
+    pub games: Vec<GameInfo>,
+    /// Appids from the libraryfolders.vdf apps list that have no usable manifest here.
+    pub unmatched_apps: Vec<String>,
+    /// Manifests that couldn't be parsed, with the reason.
+    pub failed: Vec<(PathBuf, String)>,
+    pub extra: bool,
+}
+
+/// An appid whose manifest shows up in more than one library.
+pub struct Duplicate {
+    pub game: GameInfo,
+    pub libraries: Vec<PathBuf>,
+}
+
+pub fn parse_library_folders(path: &Path) -> Result<Vec<LibraryFolder>> {
+    parse_library_folders_text(&fs::read_to_string(path)?, path)
+}
+
+fn parse_library_folders_text(content: &str, path: &Path) -> Result<Vec<LibraryFolder>> {
+    let root = vdf::parse_text(content).with_context(|| format!("Could not parse {:?}", path))?;
+    let Some((_, folders)) = root.entries().first() else {
+        return Ok(Vec::new());
+    };
+
+    let mut libraries = Vec::new();
+    for (key, folder) in folders.entries() {
+        match folder {
+            Node::Map(_) => {
+                if let Some(path) = folder.get("path").and_then(Node::as_str) {
+                    let apps = folder
+                        .get("apps")
+                        .map(|apps| apps.entries().iter().map(|(id, _)| id.clone()).collect())
+                        .unwrap_or_default();
+                    libraries.push(LibraryFolder {
+                        path: PathBuf::from(path),
+                        apps,
+                        extra: false,
+                    });
+                }
+            }
+            // the old format maps "1", "2", ... straight to paths
+            Node::Str(path) if key.parse::<u32>().is_ok() => libraries.push(LibraryFolder {
+                path: PathBuf::from(path),
+                apps: Vec::new(),
+                extra: false,
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(libraries)
+}
+
+pub fn parse_app_manifest(path: &Path) -> Result<GameInfo> {
+    parse_manifest_text(&fs::read_to_string(path)?)
+}
+
+/// Parses the contents of an `appmanifest_*.acf`, wherever they came from.
+pub fn parse_manifest_text(content: &str) -> Result<GameInfo> {
+    let root = vdf::parse_text(content)?;
+    let state = root.get("AppState").context("Missing AppState section")?;
+    let field = |key: &str| state.get(key).and_then(Node::as_str).map(str::to_string);
+
+    let appid = field("appid")
+        .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        .context("Failed to find appid")?;
+
+    // interrupted installs can leave a manifest without a name, scan_libraries looks it up
+    let installdir = field("installdir").filter(|dir| !dir.is_empty());
+    let (name, name_source) =
+        fallback_name(field("name"), NameSource::Manifest, &installdir, &appid);
+    Ok(GameInfo {
+        name,
+        name_source,
+        appid,
+        installdir,
+        size_on_disk: field("SizeOnDisk").and_then(|s| s.parse().ok()),
+        partial_install: partial_install(state),
+        depots: depots(state),
+        dlc: dlc(state),
+        // 0 when no update is planned
+        scheduled_update: field("ScheduledAutoUpdate")
+            .and_then(|s| s.trim().parse().ok())
+            .filter(|&time| time > 0),
+        // 0 for a game never started
+        last_played: field("LastPlayed")
+            .and_then(|s| s.trim().parse().ok())
+            .filter(|&time| time > 0),
+        install_details: InstallDetails::read(state),
+        ..Default::default()
+    })
+}
+
+fn depots(state: &Node) -> Vec<String> {
+    let ids: BTreeSet<&str> = ["InstalledDepots", "MountedDepots"]
+        .into_iter()
+        .filter_map(|section| state.get(section))
+        .flat_map(Node::entries)
+        .map(|(id, _)| id.as_str())
+        .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+        .collect();
+    ids.into_iter().map(str::to_string).collect()
+}
+
+fn dlc(state: &Node) -> Vec<String> {
+    let appids: BTreeSet<String> = state
+        .get("InstalledDepots")
+        .map(Node::entries)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(_, depot)| depot.get("dlcappid")?.to_text())
+        .collect();
+    appids.into_iter().collect()
+}
+
+/// Parses every `appmanifest_*.acf` in the library, in filename order, taking unchanged ones
+/// from `cache` instead. Returns `None` when the library has no `steamapps` folder (e.g. an
+/// unmounted drive).
+pub fn scan_library(
+    folder: &LibraryFolder,
+    cache: Option<&MetadataCache>,
+) -> Result<Option<Library>> {
+    let steamapps = folder.path.join("steamapps");
+    if !steamapps.exists() {
+        return Ok(None);
+    }
+
+    let mut manifests = Vec::new();
+    for entry in fs::read_dir(&steamapps)? {
+        let path = entry?.path();
+
+        // filter for appmanifest_*.acf
+        if let Some(filename) = path.file_name().and_then(|n| n.to_str())
+            && filename.starts_with("appmanifest_")
+            && filename.ends_with(".acf")
+        {
+            manifests.push(path);
+        }
+    }
+    manifests.sort();
+
+    let mut games = Vec::new();
+    let mut failed = Vec::new();
+    for path in manifests {
+        let mtime = metadata::manifest_mtime(&path);
+        let appid = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("appmanifest_")?.strip_suffix(".acf"));
+        if let (Some(cache), Some(appid), Some(mtime)) = (cache, appid, mtime)
+            && let Some(game) = cache.game(&folder.path, appid, mtime)
+        {
+            games.push(game);
+            continue;
+        }
+        match parse_app_manifest(&path) {
+            Ok(game) => games.push(GameInfo {
+                manifest_mtime: mtime,
+                ..game
+            }),
+            Err(e) => failed.push((path, e.to_string())),
+        }
+    }
+
+    let found: HashSet<&str> = games.iter().map(|g| g.appid.as_str()).collect();
+    let unmatched_apps = folder
+        .apps
+        .iter()
+        .filter(|appid| !found.contains(appid.as_str()))
+        .cloned()
+        .collect();
+
+    Ok(Some(Library {
+        path: folder.path.clone(),
+        games,
+        unmatched_apps,
+        failed,
+        extra: folder.extra,
+    }))
+}
+
+/// Where the list of library folders came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FolderSource {
+    /// libraryfolders.vdf itself.
+    Steam,
+    /// The copy saved by the last run that could read it.
+    LastRun,
+    /// Only the Steam root, libraryfolders.vdf listing no libraries at all.
+    RootOnly,
+}
+
+const READ_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// The libraries from libraryfolders.vdf, followed by the extra ones from the flags and config
+/// that exist and aren't listed already.
+pub fn read_library_folders(
+    paths: &Paths,
+    reporter: &Reporter,
+) -> Result<(Vec<LibraryFolder>, FolderSource)> {
+    let (mut folders, source) = read_steam_library_folders(paths, reporter)?;
+    let listed: Vec<PathBuf> = folders
+        .iter()
+        .map(|f| fs::canonicalize(&f.path).unwrap_or_else(|_| f.path.clone()))
+        .collect();
+    for path in &paths.extra_libraries {
+        if !path.join("steamapps").is_dir() {
+            reporter.warn(format!(
+                "Extra library {:?} has no steamapps folder, is the drive mounted?",
+                path
+            ));
+            continue;
+        }
+        let resolved = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if listed.contains(&resolved) || folders.iter().any(|f| f.path == *path) {
+            continue;
+        }
+        folders.push(LibraryFolder {
+            path: path.clone(),
+            apps: Vec::new(),
+            extra: true,
+        });
+    }
+    Ok((folders, source))
+}
+
+/// The libraries another Steam root's libraryfolders.vdf lists, for `merge_steam_roots`.
+pub fn library_paths(library_vdf: &Path) -> Result<Vec<PathBuf>> {
+    let content = fs::read_to_string(library_vdf)
+        .with_context(|| format!("Could not read {:?}", library_vdf))?;
+    Ok(parse_library_folders_text(&content, library_vdf)?
+        .into_iter()
+        .map(|folder| folder.path)
+        .collect())
+}
+
+/// Reads libraryfolders.vdf, taking an empty, truncated or library-less file for Steam being
+/// halfway through rewriting it. The read is retried a few times, then the copy saved by the
+/// last good read is used instead, with a warning. A file that still lists no libraries and
+/// has no saved copy, as on a new install, leaves the Steam root as the only library.
+fn read_steam_library_folders(
+    paths: &Paths,
+    reporter: &Reporter,
+) -> Result<(Vec<LibraryFolder>, FolderSource)> {
+    let mut problem = String::new();
+    let mut listed_none = false;
+    for attempt in 1..=READ_ATTEMPTS {
+        if attempt > 1 {
+            thread::sleep(RETRY_DELAY);
+        }
+        let content = fs::read_to_string(&paths.library_vdf)
+            .with_context(|| format!("Could not read {:?}", paths.library_vdf))?;
+        match parse_library_folders_text(&content, &paths.library_vdf) {
+            Ok(folders) if !folders.is_empty() => {
+                if let Err(e) = save_library_cache(paths, &content) {
+                    reporter.warn(format!("Could not save the library list: {:#}", e));
+                }
+                return Ok((folders, FolderSource::Steam));
+            }
+            Ok(_) if content.trim().is_empty() => {
+                problem = format!("{:?} is empty", paths.library_vdf);
+                listed_none = true;
+            }
+            Ok(_) => {
+                problem = format!("{:?} lists no libraries", paths.library_vdf);
+                listed_none = true;
+            }
+            Err(e) => {
+                problem = format!("{:#}", e);
+                listed_none = false;
+            }
+        }
+    }
+
+    match load_library_cache(paths) {
+        Some(folders) => {
+            reporter.warn(format!(
+                "{} (tried {} times), using the library list from the last run that could read it",
+                problem, READ_ATTEMPTS
+            ));
+            Ok((folders, FolderSource::LastRun))
+        }
+        None if listed_none && paths.steam_root.join("steamapps").is_dir() => {
+            reporter.warn(format!(
+                "{} (tried {} times), only scanning the Steam root {:?}",
+                problem, READ_ATTEMPTS, paths.steam_root
+            ));
+            let folder = LibraryFolder {
+                path: paths.steam_root.clone(),
+                apps: Vec::new(),
+                extra: false,
+            };
+            Ok((vec![folder], FolderSource::RootOnly))
+        }
+        None => bail!(
+            "{} (tried {} times), and no earlier run saved a library list to fall back to",
+            problem,
+            READ_ATTEMPTS
+        ),
+    }
+}
+
+/// The cached copy starts with a comment naming the file it was copied from, so a run against
+/// another Steam root never picks it up.
+fn save_library_cache(paths: &Paths, content: &str) -> Result<()> {
+    if let Some(parent) = paths.library_cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let header = format!("// {}\n", paths.library_vdf.display());
+    fs::write(&paths.library_cache, header + content)
+        .with_context(|| format!("Could not write {:?}", paths.library_cache))
+}
+
+fn load_library_cache(paths: &Paths) -> Option<Vec<LibraryFolder>> {
+    let cached = fs::read_to_string(&paths.library_cache).ok()?;
+    let (header, content) = cached.split_once('\n')?;
+    if header.strip_prefix("// ")? != paths.library_vdf.to_string_lossy() {
+        return None;
+    }
+    parse_library_folders_text(content, &paths.library_cache)
+        .ok()
+        .filter(|folders| !folders.is_empty())
+}
+
+/// Scans every library listed in libraryfolders.vdf, skipping ones that aren't mounted.
+pub fn scan_libraries(
+    paths: &Paths,
+    appinfo: &AppInfoSource,
+    cache: Option<&MetadataCache>,
+    reporter: &Reporter,
+) -> Result<(Vec<Library>, FolderSource)> {
+    let (folders, source) = read_library_folders(paths, reporter)?;
+    let mut libraries = Vec::new();
+    for folder in folders {
+        if let Some(mut library) = scan_library(&folder, cache)? {
+            if !library.unmatched_apps.is_empty() {
+                recover_unmatched_apps(&mut library, appinfo.get(reporter));
+            }
+            if library
+                .games
+                .iter()
+                .any(|game| game.name_source.is_fallback())
+                && let Some(info) = appinfo.get(reporter)
+            {
+                name_from_appinfo(&mut library.games, info);
+            }
+            libraries.push(library);
+        }
+    }
+    Ok((libraries, source))
+}
+
+/// App types from appinfo that are worth a launcher when the manifest is gone.
+const LAUNCHABLE_TYPES: &[&str] = &["game", "application", "demo"];
+
+/// Turns apps that libraryfolders.vdf lists as installed but that have no usable manifest
+/// into games, so they still get a launcher. Names come from appinfo when it knows the app,
+/// and apps appinfo identifies as tools, DLC and the like are left out.
+pub fn recover_unmatched_apps(library: &mut Library, appinfo: Option<&AppInfo>) {
+    for appid in std::mem::take(&mut library.unmatched_apps) {
+        let summary = appinfo.and_then(|info| info.summary(&appid));
+        let (name, app_type, installdir) = match summary {
+            Some(summary) => (summary.name, summary.app_type, summary.installdir),
+            None => (None, None, None),
+        };
+
+        if app_type
+            .as_ref()
+            .is_some_and(|t| !LAUNCHABLE_TYPES.contains(&t.as_str()))
+        {
+            continue;
+        }
+
+        let (name, name_source) = fallback_name(name, NameSource::AppInfo, &installdir, &appid);
+        library.games.push(GameInfo {
+            name,
+            name_source,
+            appid,
+            installdir,
+            incomplete: true,
+            app_type,
+            ..Default::default()
+        });
+    }
+}
+
+/// `name` from `source` when it has one, otherwise the install folder, and "Unknown Game
+/// (<appid>)" without either, so two such games can still be told apart.
+fn fallback_name(
+    name: Option<String>,
+    source: NameSource,
+    installdir: &Option<String>,
+    appid: &str,
+) -> (String, NameSource) {
+    if let Some(name) = name.filter(|name| !name.trim().is_empty()) {
+        return (name, source);
+    }
+    match installdir {
+        Some(dir) if !dir.trim().is_empty() => (dir.clone(), NameSource::InstallDir),
+        _ => (placeholder_name(appid), NameSource::Placeholder),
+    }
+}
+
+/// The name of a game nothing knows the name of.
+pub fn placeholder_name(appid: &str) -> String {
+    format!("Unknown Game ({})", appid)
+}
+
+/// Names the games whose manifest has no name after their appinfo entry.
+fn name_from_appinfo(games: &mut [GameInfo], appinfo: &AppInfo) {
+    for game in games
+        .iter_mut()
+        .filter(|game| game.name_source.is_fallback())
+    {
+        if let Some(name) = appinfo
+            .summary(&game.appid)
+            .and_then(|summary| summary.name)
+            .filter(|name| !name.trim().is_empty())
+        {
+            game.name = name;
+            game.name_source = NameSource::AppInfo;
+        }
+    }
+}
+
+/// Names the `--tools` no manifest or appinfo had a name for after the built-in list.
+pub fn name_useful_tools(libraries: &mut [Library]) {
+    for game in libraries
+        .iter_mut()
+        .flat_map(|lib| &mut lib.games)
+        .filter(|game| game.name_source.is_fallback())
+    {
+        if let Some((_, name)) = filter::USEFUL_TOOLS
+            .iter()
+            .find(|(appid, _)| *appid == game.appid)
+        {
+            game.name = name.to_string();
+            game.name_source = NameSource::BuiltIn;
+        }
+    }
+}
+
+/// Names the games nothing installed knew the name of after the owned games list.
+pub fn name_from_owned_games(libraries: &mut [Library], owned: &[OwnedGame]) {
+    for game in libraries
+        .iter_mut()
+        .flat_map(|lib| &mut lib.games)
+        .filter(|game| game.name_source.is_fallback())
+    {
+        if let Some(owned) = owned.iter().find(|owned| owned.appid == game.appid) {
+            game.name = owned.name.clone();
+            game.name_source = NameSource::OwnedGames;
+        }
+    }
+}
+
+/// Fills in what only appinfo knows about games that came from a manifest. This parses every
+/// game's appinfo entry, so it only runs when a flag asks for the details.
+pub fn add_appinfo_details(libraries: &mut [Library], appinfo: &AppInfo) {
+    let installed: HashMap<String, String> = libraries
+        .iter()
+        .flat_map(|lib| &lib.games)
+        .map(|game| (game.appid.clone(), game.name.clone()))
+        .collect();
+
+    for game in libraries.iter_mut().flat_map(|lib| &mut lib.games) {
+        if let Some(summary) = appinfo.summary(&game.appid) {
+            if game.app_type.is_none() {
+                game.app_type = summary.app_type;
+            }
+            // playtests are free betas hanging off their game; demos have a parent too
+            game.playtest = summary.parent.is_some()
+                && game.app_type.as_deref() != Some("demo")
+                && (game.app_type.as_deref() == Some("beta") || summary.free_to_download);
+            game.parent_name = summary.parent.and_then(|p| installed.get(&p).cloned());
+        }
+        game.localized_names = appinfo.localized_names(&game.appid);
+    }
+}
+
+/// The library whose `steamapps/common` holds `installdir`: `own` when the files are where the
+/// manifest is, otherwise the first other library that has them, as after a move Steam didn't
+/// finish recording. `None` when no library has the folder.
+pub fn find_install_library<'a>(
+    installdir: &str,
+    own: &'a Path,
+    libraries: &'a [Library],
+) -> Option<&'a Path> {
+    std::iter::once(own)
+        .chain(
+            libraries
+                .iter()
+                .map(|l| l.path.as_path())
+                .filter(|p| *p != own),
+        )
+        .find(|library| library.join("steamapps/common").join(installdir).is_dir())
+}
+
+/// Why the `installdir` symlink in `library`'s `steamapps/common` can't be followed, when it
+/// loops back on itself or points at something that's gone, such as a drive that isn't mounted.
+/// `None` when there's no link by that name or it leads somewhere.
+pub fn broken_install_link(installdir: &str, library: &Path) -> Option<std::io::Error> {
+    let common = library.join("steamapps/common");
+    let path = common.join(installdir);
+    [common, path]
+        .iter()
+        .filter(|path| path.is_symlink())
+        .find_map(|path| fs::metadata(path).err())
+}
+
+/// Finds appids installed in several libraries, usually left behind by a failed move.
+/// Libraries are listed in scan order, so the first one is the copy that gets a launcher.
+pub fn find_duplicates(libraries: &[Library]) -> Vec<Duplicate> {
+    let mut order = Vec::new();
+    let mut by_appid: HashMap<&str, Duplicate> = HashMap::new();
+
+    for library in libraries {
+        for game in &library.games {
+            by_appid
+                .entry(&game.appid)
+                .or_insert_with(|| {
+                    order.push(game.appid.as_str());
+                    Duplicate {
+                        game: game.clone(),
+                        libraries: Vec::new(),
+                    }
+                })
+                .libraries
+                .push(library.path.clone());
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|appid| by_appid.remove(appid))
+        .filter(|dup| dup.libraries.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn manifest(fields: &[(&str, &str)]) -> GameInfo {
+        let fields: String = fields
+            .iter()
+            .map(|(key, value)| format!("\t\"{}\"\t\t\"{}\"\n", key, value))
+            .collect();
+        parse_manifest_text(&format!("\"AppState\"\n{{\n{}}}\n", fields)).unwrap()
+    }
+
+    /// An appinfo.vdf in the oldest format steamer reads, knowing the name of each app.
+    fn appinfo(apps: &[(&str, &str)]) -> AppInfo {
+        let apps: Vec<(&str, Vec<(&str, &str)>)> = apps
+            .iter()
+            .map(|&(appid, name)| (appid, vec![("name", name)]))
+            .collect();
+        appinfo_common(&apps)
+    }
+
+    /// Like [`appinfo`], with the given strings in each app's `common` section.
+    fn appinfo_common(apps: &[(&str, Vec<(&str, &str)>)]) -> AppInfo {
+        let mut data = 0x07564427u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        for (appid, common) in apps {
+            let mut body = vec![0u8; 40];
+            body.extend_from_slice(b"\x00appinfo\x00\x00common\x00");
+            for (key, value) in common {
+                body.push(0x01);
+                body.extend_from_slice(key.as_bytes());
+                body.push(0);
+                body.extend_from_slice(value.as_bytes());
+                body.push(0);
+            }
+            body.extend_from_slice(b"\x08\x08\x08");
+            data.extend_from_slice(&appid.parse::<u32>().unwrap().to_le_bytes());
+            data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+            data.extend_from_slice(&body);
+        }
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        // tests run side by side, each needs a file of its own
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "steamer-appinfo-{}-{}.vdf",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, data).unwrap();
+        let info = AppInfo::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        info
+    }
+
+    #[test]
+    fn names_come_from_the_manifest_first() {
+        let game = manifest(&[
+            ("appid", "620"),
+            ("name", "Portal 2"),
+            ("installdir", "portal2"),
+        ]);
+        assert_eq!(game.name, "Portal 2");
+        assert_eq!(game.name_source, NameSource::Manifest);
+
+        let mut games = vec![game];
+        name_from_appinfo(&mut games, &appinfo(&[("620", "Portal 2 (appinfo)")]));
+        assert_eq!(games[0].name, "Portal 2");
+    }
+
+    #[test]
+    fn names_fall_back_to_appinfo() {
+        let game = manifest(&[("appid", "620"), ("name", " "), ("installdir", "portal2")]);
+        let mut games = vec![game];
+        name_from_appinfo(&mut games, &appinfo(&[("620", "Portal 2")]));
+        assert_eq!(games[0].name, "Portal 2");
+        assert_eq!(games[0].name_source, NameSource::AppInfo);
+    }
+
+    #[test]
+    fn names_fall_back_to_the_install_folder() {
+        let game = manifest(&[("appid", "620"), ("installdir", "Portal 2")]);
+        assert_eq!(game.name, "Portal 2");
+        assert_eq!(game.name_source, NameSource::InstallDir);
+
+        // appinfo not knowing the game leaves the folder name
+        let mut games = vec![game];
+        name_from_appinfo(&mut games, &appinfo(&[("400", "Portal")]));
+        assert_eq!(games[0].name, "Portal 2");
+        assert_eq!(games[0].name_source, NameSource::InstallDir);
+    }
+
+    #[test]
+    fn names_fall_back_to_a_placeholder() {
+        let game = manifest(&[("appid", "620"), ("installdir", "")]);
+        assert_eq!(game.name, "Unknown Game (620)");
+        assert_eq!(game.name_source, NameSource::Placeholder);
+
+        let mut games = vec![game, manifest(&[("appid", "400")])];
+        name_from_appinfo(&mut games, &appinfo(&[]));
+        assert_eq!(games[0].name, "Unknown Game (620)");
+        assert_eq!(games[1].name, "Unknown Game (400)");
+    }
+
+    fn library(path: &str, appids: &[&str]) -> Library {
+        Library {
+            path: PathBuf::from(path),
+            games: appids
+                .iter()
+                .map(|appid| manifest(&[("appid", appid), ("name", "Game")]))
+                .collect(),
+            unmatched_apps: Vec::new(),
+            failed: Vec::new(),
+            extra: false,
+        }
+    }
+
+    #[test]
+    fn reads_the_apps_each_library_lists() {
+        let text = r#"
+"libraryfolders"
+{
+	"0"
+	{
+		"path"		"/home/user/.local/share/Steam"
+		"apps"
+		{
+			"620"		"12979870536"
+			"400"		"3959148226"
+		}
+	}
+	"1"
+	{
+		"path"		"/mnt/games"
+	}
+}
+"#;
+        let folders = parse_library_folders_text(text, Path::new("libraryfolders.vdf")).unwrap();
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0].path, Path::new("/home/user/.local/share/Steam"));
+        assert_eq!(folders[0].apps, ["620", "400"]);
+        assert!(folders[1].apps.is_empty());
+    }
+
+    #[test]
+    fn listed_apps_without_a_manifest_are_named_from_appinfo() {
+        let mut library = library("/home", &["620"]);
+        library.unmatched_apps = vec!["400".to_string(), "70".to_string()];
+        recover_unmatched_apps(&mut library, Some(&appinfo(&[("400", "Portal")])));
+
+        assert!(library.unmatched_apps.is_empty());
+        let games: Vec<(&str, &str, bool)> = library
+            .games
+            .iter()
+            .map(|game| (game.appid.as_str(), game.name.as_str(), game.incomplete))
+            .collect();
+        assert_eq!(
+            games,
+            [
+                ("620", "Game", false),
+                ("400", "Portal", true),
+                ("70", "Unknown Game (70)", true),
+            ]
+        );
+        assert_eq!(library.games[1].name_source, NameSource::AppInfo);
+    }
+
+    #[test]
+    fn finds_games_installed_in_several_libraries() {
+        let libraries = [
+            library("/home", &["620", "400"]),
+            library("/mnt/games", &["70", "620"]),
+            library("/mnt/old", &["400", "620"]),
+        ];
+        let duplicates = find_duplicates(&libraries);
+        let found: Vec<(&str, Vec<&str>)> = duplicates
+            .iter()
+            .map(|dup| {
+                let libraries = dup.libraries.iter().map(|p| p.to_str().unwrap()).collect();
+                (dup.game.appid.as_str(), libraries)
+            })
+            .collect();
+        // in scan order, so the first library is the one whose copy gets the entry
+        assert_eq!(
+            found,
+            [
+                ("620", vec!["/home", "/mnt/games", "/mnt/old"]),
+                ("400", vec!["/home", "/mnt/old"]),
+            ]
+        );
+        assert!(find_duplicates(&libraries[..1]).is_empty());
+    }
+
+    #[test]
+    fn name_sources_round_trip() {
+        for source in [
+            NameSource::Manifest,
+            NameSource::AppInfo,
+            NameSource::OwnedGames,
+            NameSource::BuiltIn,
+            NameSource::InstallDir,
+            NameSource::Placeholder,
+        ] {
+            assert_eq!(NameSource::from_kind(source.kind()), Some(source));
+        }
+    }
+
+    #[test]
+    fn playtests_are_free_betas_of_another_app() {
+        let mut libraries = vec![library("/games", &["1145360", "1372810", "2000", "3000"])];
+        let info = appinfo_common(&[
+            ("1145360", vec![("type", "Game")]),
+            (
+                "1372810",
+                vec![
+                    ("type", "Game"),
+                    ("parent", "1145360"),
+                    ("freetodownload", "1"),
+                ],
+            ),
+            ("2000", vec![("type", "Beta"), ("parent", "999")]),
+            (
+                "3000",
+                vec![
+                    ("type", "Demo"),
+                    ("parent", "1145360"),
+                    ("freetodownload", "1"),
+                ],
+            ),
+        ]);
+        add_appinfo_details(&mut libraries, &info);
+        let games = &libraries[0].games;
+
+        assert!(!games[0].playtest);
+        assert!(games[1].playtest);
+        assert_eq!(games[1].parent_name.as_deref(), Some("Game"));
+        // the parent isn't installed, so there's no name to give
+        assert!(games[2].playtest);
+        assert_eq!(games[2].parent_name, None);
+        assert!(!games[3].playtest);
+        assert_eq!(games[3].app_type.as_deref(), Some("demo"));
+    }
+
+    #[test]
+    fn finds_install_folders_in_sibling_libraries() {
+        let dir = std::env::temp_dir().join(format!("steamer-installdir-{}", process::id()));
+        let (own, moved, empty) = (dir.join("ssd"), dir.join("hdd"), dir.join("usb"));
+        fs::create_dir_all(own.join("steamapps/common/Portal 2")).unwrap();
+        fs::create_dir_all(moved.join("steamapps/common/Portal 2")).unwrap();
+        fs::create_dir_all(moved.join("steamapps/common/Hades")).unwrap();
+        fs::create_dir_all(empty.join("steamapps/common")).unwrap();
+        let libraries: Vec<Library> = [&empty, &moved, &own]
+            .iter()
+            .map(|path| library(path.to_str().unwrap(), &[]))
+            .collect();
+
+        // the manifest's own library wins over one scanned earlier
+        assert_eq!(
+            find_install_library("Portal 2", &own, &libraries),
+            Some(own.as_path())
+        );
+        assert_eq!(
+            find_install_library("Hades", &own, &libraries),
+            Some(moved.as_path())
+        );
+        assert_eq!(find_install_library("Celeste", &own, &libraries), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    const LIBRARY_FOLDERS: &str =
+        "\"libraryfolders\"\n{\n\t\"0\"\n\t{\n\t\t\"path\"\t\t\"/games\"\n\t}\n}\n";
+
+    #[test]
+    fn empty_and_truncated_library_lists() {
+        let path = Path::new("libraryfolders.vdf");
+        assert!(parse_library_folders_text("", path).unwrap().is_empty());
+        assert!(
+            parse_library_folders_text("\"libraryfolders\"\n{\n}\n", path)
+                .unwrap()
+                .is_empty()
+        );
+        let truncated = &LIBRARY_FOLDERS[..LIBRARY_FOLDERS.len() - 6];
+        let error = parse_library_folders_text(truncated, path).err().unwrap();
+        assert!(
+            format!("{:#}", error).contains("libraryfolders.vdf"),
+            "{:#}",
+            error
+        );
+    }
+
+    #[test]
+    fn a_half_written_library_list_falls_back_to_the_last_good_one() {
+        use crate::report::LogFormat;
+
+        let dir = std::env::temp_dir().join(format!("steamer-folders-{}", process::id()));
+        fs::create_dir_all(dir.join("Steam/steamapps")).unwrap();
+        let paths = Paths {
+            steam_root: dir.join("Steam"),
+            library_vdf: dir.join("Steam/steamapps/libraryfolders.vdf"),
+            library_cache: dir.join("cache/libraryfolders.vdf"),
+            cache_dir: dir.join("cache"),
+            icon_cache: dir.join("Steam/appcache/librarycache"),
+            app_dir: dir.join("applications"),
+            index_dir: dir.join("index"),
+            extra_libraries: Vec::new(),
+            ignore_files: Vec::new(),
+        };
+        let reporter = Reporter::new(LogFormat::Human, true);
+        let read = || {
+            read_steam_library_folders(&paths, &reporter).map(|(folders, source)| {
+                (
+                    folders.into_iter().map(|f| f.path).collect::<Vec<_>>(),
+                    source,
+                )
+            })
+        };
+
+        // an empty file on a new install leaves just the Steam root
+        fs::write(&paths.library_vdf, "").unwrap();
+        assert_eq!(
+            read().unwrap(),
+            (vec![dir.join("Steam")], FolderSource::RootOnly)
+        );
+
+        // a truncated file with nothing saved can't be read at all
+        fs::write(&paths.library_vdf, &LIBRARY_FOLDERS[..20]).unwrap();
+        assert!(read().is_err());
+
+        fs::write(&paths.library_vdf, LIBRARY_FOLDERS).unwrap();
+        assert_eq!(
+            read().unwrap(),
+            (vec![PathBuf::from("/games")], FolderSource::Steam)
+        );
+        for broken in ["", &LIBRARY_FOLDERS[..20]] {
+            fs::write(&paths.library_vdf, broken).unwrap();
+            assert_eq!(
+                read().unwrap(),
+                (vec![PathBuf::from("/games")], FolderSource::LastRun)
+            );
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lists_installed_and_mounted_depots() {
+        let game = parse_manifest_text(concat!(
+            "\"AppState\"\n{\n\t\"appid\"\t\"620\"\n\t\"name\"\t\"Portal 2\"\n",
+            "\t\"InstalledDepots\"\n\t{\n",
+            "\t\t\"621\"\n\t\t{\n\t\t\t\"manifest\"\t\"1\"\n\t\t}\n",
+            "\t\t\"651\"\n\t\t{\n\t\t\t\"manifest\"\t\"1\"\n\t\t\t\"dlcappid\"\t\"650\"\n\t\t}\n",
+            "\t}\n",
+            "\t\"MountedDepots\"\n\t{\n\t\t\"228988\"\t\"1\"\n\t\t\"621\"\t\"1\"\n\t}\n",
+            "}\n"
+        ))
+        .unwrap();
+        assert_eq!(game.depots, ["228988", "621", "651"]);
+        assert_eq!(game.dlc, ["650"]);
+
+        let game = parse_manifest_text(
+            "\"AppState\"\n{\n\t\"appid\"\t\"620\"\n\t\"name\"\t\"Portal 2\"\n}\n",
+        )
+        .unwrap();
+        assert!(game.depots.is_empty());
+        assert!(game.dlc.is_empty());
+    }
+
+    #[test]
+    fn reads_the_scheduled_update() {
+        let scheduled = |value: &str| {
+            parse_manifest_text(&format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\"620\"\n\t\"ScheduledAutoUpdate\"\t\"{}\"\n}}\n",
+                value
+            ))
+            .unwrap()
+            .scheduled_update
+        };
+        assert_eq!(scheduled("1700000000"), Some(1_700_000_000));
+        assert_eq!(scheduled(" 1700000000 "), Some(1_700_000_000));
+        // nothing planned
+        assert_eq!(scheduled("0"), None);
+        assert_eq!(scheduled("soon"), None);
+    }
+
+    #[test]
+    fn reads_the_install_details() {
+        let game = parse_manifest_text(concat!(
+            "\"AppState\"\n{\n\t\"appid\"\t\"620\"\n",
+            "\t\"BytesToDownload\"\t\"3000\"\n\t\"BytesDownloaded\"\t\"1000\"\n",
+            "\t\"AutoUpdateBehavior\"\t\"1\"\n}\n",
+        ))
+        .unwrap();
+        let details = game.install_details.unwrap();
+        assert_eq!(
+            details,
+            InstallDetails {
+                bytes_downloaded: Some(1000),
+                bytes_to_download: Some(3000),
+                allow_other_downloads: None,
+                auto_update: Some(1),
+            }
+        );
+        assert_eq!(details.download_percent(), Some(33.3));
+
+        // nothing to download, or a manifest from a client that doesn't write the fields
+        let nothing = InstallDetails {
+            bytes_downloaded: Some(0),
+            bytes_to_download: Some(0),
+            ..InstallDetails::default()
+        };
+        assert_eq!(nothing.download_percent(), None);
+        let bare = parse_manifest_text("\"AppState\"\n{\n\t\"appid\"\t\"620\"\n}\n").unwrap();
+        assert_eq!(bare.install_details, None);
+    }
+
+    #[test]
+    fn reads_partial_install_markers() {
+        let partial = |body: &str| {
+            parse_manifest_text(&format!(
+                "\"AppState\"\n{{\n\t\"appid\"\t\"620\"\n\t\"name\"\t\"Portal 2\"\n{}}}\n",
+                body
+            ))
+            .unwrap()
+            .partial_install
+        };
+        let depot = "\t\"InstalledDepots\"\n\t{\n\t\t\"621\"\n\t\t{\n\t\t\t\"manifest\"\t\"1\"\n\t\t}\n\t}\n";
+        let dlc_depot = "\t\"InstalledDepots\"\n\t{\n\t\t\"651\"\n\t\t{\n\t\t\t\"manifest\"\t\"1\"\n\t\t\t\"dlcappid\"\t\"650\"\n\t\t}\n\t}\n";
+
+        assert_eq!(
+            partial(&format!("\t\"StateFlags\"\t\"4\"\n{}", depot)),
+            None
+        );
+        // an update waiting to download doesn't stop the installed version from running
+        assert_eq!(
+            partial(&format!(
+                "\t\"StateFlags\"\t\"6\"\n\t\"BytesToDownload\"\t\"100\"\n\t\"BytesDownloaded\"\t\"10\"\n{}",
+                depot
+            )),
+            None
+        );
+        // old clients write no StateFlags
+        assert_eq!(partial(""), None);
+        assert_eq!(
+            partial("\t\"StateFlags\"\t\"4\"\n"),
+            None,
+            "fully installed without a depot list"
+        );
+
+        assert_eq!(
+            partial(&format!("\t\"StateFlags\"\t\"4\"\n{}", dlc_depot)),
+            Some(PartialInstall::OnlyDlc)
+        );
+        assert_eq!(
+            partial(
+                "\t\"StateFlags\"\t\"1026\"\n\t\"BytesToDownload\"\t\"2147483648\"\n\t\"BytesDownloaded\"\t\"1073741824\"\n"
+            ),
+            Some(PartialInstall::Downloading {
+                done: 1 << 30,
+                total: 1 << 31
+            })
+        );
+        assert_eq!(
+            partial(
+                "\t\"StateFlags\"\t\"1026\"\n\t\"BytesToStage\"\t\"200\"\n\t\"BytesStaged\"\t\"50\"\n\t\"StagingFolder\"\t\"0\"\n"
+            ),
+            Some(PartialInstall::Staging {
+                done: 50,
+                total: 200
+            })
+        );
+        assert_eq!(
+            partial("\t\"StateFlags\"\t\"2\"\n"),
+            Some(PartialInstall::NoDepots)
+        );
+
+        use crate::filter::{KeywordMatch, SkipRules};
+        let game = parse_manifest_text(
+            "\"AppState\"\n{\n\t\"appid\"\t\"620\"\n\t\"name\"\t\"Portal 2\"\n\t\"StateFlags\"\t\"1026\"\n\t\"BytesToDownload\"\t\"2147483648\"\n\t\"BytesDownloaded\"\t\"1073741824\"\n}\n",
+        )
+        .unwrap();
+        let rules = SkipRules::new(&[], &[], KeywordMatch::Substring).unwrap();
+        assert_eq!(rules.check(&game), None);
+        let reason = rules.skip_partial(true).check(&game).unwrap();
+        assert_eq!(
+            reason.to_string(),
+            "not fully installed, download stopped at 1.0 GiB of 2.0 GiB"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Result, bail};
+
+/// A string with `{placeholder}` substitutions, validated against a fixed set of names
+/// when it's parsed so typos are reported up front. `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+impl Template {
+    pub fn parse(source: &str, allowed: &[&str]) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("Unclosed placeholder in {:?}", source),
+                        }
+                    }
+                    if !allowed.contains(&name.as_str()) {
+                        bail!(
+                            "Unknown placeholder {{{}}} in {:?} (expected one of {})",
+                            name,
+                            source,
+                            allowed
+                                .iter()
+                                .map(|a| format!("{{{}}}", a))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(name));
+                }
+                '}' => bail!("Unmatched '}}' in {:?}", source),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Substitutes placeholders; any placeholder without a value renders as empty.
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => out.push_str(text),
+                Segment::Placeholder(name) => {
+                    if let Some((_, value)) = values.iter().find(|(key, _)| key == name) {
+                        out.push_str(value);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALLOWED: &[&str] = &["name", "appid"];
+
+    #[test]
+    fn substitutes_placeholders_and_literal_braces() {
+        let template = Template::parse("{name} {{{appid}}}", ALLOWED).unwrap();
+        assert_eq!(
+            template.render(&[("name", "Portal 2"), ("appid", "620")]),
+            "Portal 2 {620}"
+        );
+        assert_eq!(template.render(&[("name", "Portal 2")]), "Portal 2 {}");
+    }
+
+    #[test]
+    fn rejects_typos_up_front() {
+        let error = Template::parse("{nmae}", ALLOWED).unwrap_err().to_string();
+        assert!(error.contains("Unknown placeholder {nmae}"), "{}", error);
+        assert!(error.contains("{name}, {appid}"), "{}", error);
+        assert!(Template::parse("{name", ALLOWED).is_err());
+        assert!(Template::parse("name}", ALLOWED).is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! Moving files to the freedesktop.org trash instead of unlinking them, so a cleanup can be
+//! undone from the file manager. Follows the Trash spec: the home trash for files on the same
+//! filesystem, otherwise `$topdir/.Trash/$uid` or `$topdir/.Trash-$uid` on the file's mount.
+
+use anyhow::{Context, Result, bail};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Moves `path` into the trash that belongs to its filesystem. A symlink is trashed itself, not
+/// what it points to.
+pub fn move_to_trash(path: &Path) -> Result<()> {
+    let path = resolve_parent(path)?;
+    let device = fs::symlink_metadata(&path)?.dev();
+    let (trash, top) = trash_dir_for(&path, device)?;
+    trash_into(&path, &trash, top.as_deref())
+}
+
+/// `path` with the directories leading to it resolved but not the file itself, which could be a
+/// symlink that has to stay one.
+fn resolve_parent(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .context("Cannot trash a path without a file name")?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let parent =
+        fs::canonicalize(parent).with_context(|| format!("Could not resolve {:?}", path))?;
+    Ok(parent.join(name))
+}
+
+/// Moves `path` into `trash`, whose paths are relative to `top` when it's on another mount.
+fn trash_into(path: &Path, trash: &Path, top: Option<&Path>) -> Result<()> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    for dir in [&files, &info] {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {:?}", dir))?;
+    }
+
+    let name = path
+        .file_name()
+        .context("Cannot trash a path without a file name")?
+        .to_string_lossy()
+        .into_owned();
+    let info_text = trash_info(path, top);
+
+    // claiming the .trashinfo name first is what keeps two deletions from picking the same slot
+    for attempt in 1.. {
+        let trashed_name = match attempt {
+            1 => name.clone(),
+            n => format!("{}.{}", name, n),
+        };
+        let info_path = info.join(format!("{}.trashinfo", trashed_name));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(mut file) => {
+                file.write_all(info_text.as_bytes())?;
+                if let Err(e) = fs::rename(path, files.join(&trashed_name)) {
+                    let _ = fs::remove_file(&info_path);
+                    return Err(e)
+                        .with_context(|| format!("Could not move {:?} to {:?}", path, files));
+                }
+                return Ok(());
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("Could not create {:?}", info_path)),
+        }
+    }
+    unreachable!()
+}
+
+/// The `.trashinfo` contents for `path`.
+fn trash_info(path: &Path, top: Option<&Path>) -> String {
+    format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        // trashes on other mounts store paths relative to the mount, so the drive can move
+        encode_path(top.and_then(|t| path.strip_prefix(t).ok()).unwrap_or(path)),
+        local_timestamp()
+    )
+}
+
+/// The trash to use and, for trashes on another mount, that mount's top directory.
+fn trash_dir_for(path: &Path, device: u64) -> Result<(PathBuf, Option<PathBuf>)> {
+    let home_trash = match std::env::var_os("XDG_DATA_HOME").filter(|v| !v.is_empty()) {
+        Some(data) => PathBuf::from(data).join("Trash"),
+        None => dirs::home_dir()
+            .context("Could not find home directory")?
+            .join(".local/share/Trash"),
+    };
+    let home_device = existing_ancestor(&home_trash).and_then(|p| fs::metadata(p).ok());
+    if home_device.is_some_and(|m| m.dev() == device) {
+        return Ok((home_trash, None));
+    }
+
+    let top = mount_top(path, device);
+    let uid = unsafe { libc::getuid() };
+
+    // an admin-provided .Trash only counts if it's a real, sticky directory
+    let shared = top.join(".Trash");
+    if let Ok(meta) = fs::symlink_metadata(&shared)
+        && meta.is_dir()
+        && meta.permissions().mode() & 0o1000 != 0
+    {
+        let dir = shared.join(uid.to_string());
+        if fs::create_dir_all(&dir).is_ok() {
+            return Ok((dir, Some(top)));
+        }
+    }
+
+    let own = top.join(format!(".Trash-{}", uid));
+    match fs::symlink_metadata(&own) {
+        Ok(meta) if meta.is_dir() && meta.uid() == uid => Ok((own, Some(top))),
+        Ok(_) => bail!("{:?} isn't a trash directory owned by this user", own),
+        Err(_) => {
+            fs::create_dir(&own).with_context(|| format!("No usable trash on {:?}", top))?;
+            fs::set_permissions(&own, fs::Permissions::from_mode(0o700))?;
+            Ok((own, Some(top)))
+        }
+    }
+}
+
+/// The highest directory above `path` that is still on `device`.
+fn mount_top(path: &Path, device: u64) -> PathBuf {
+    let mut top = path.to_path_buf();
+    while let Some(parent) = top.parent() {
+        match fs::metadata(parent) {
+            Ok(meta) if meta.dev() == device => top = parent.to_path_buf(),
+            _ => break,
+        }
+    }
+    top
+}
+
+fn existing_ancestor(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+/// Percent-encodes a path the way the spec asks for, keeping `/` and unreserved characters.
+fn encode_path(path: &Path) -> String {
+    let mut out = String::new();
+    for &b in path.as_os_str().as_encoded_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// `YYYY-MM-DDThh:mm:ss` in local time.
+fn local_timestamp() -> String {
+    let now = unsafe { libc::time(std::ptr::null_mut()) };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&now, &mut tm) }.is_null() {
+        return String::from("1970-01-01T00:00:00");
+    }
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-trash-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::canonicalize(dir).unwrap()
+    }
+
+    #[test]
+    fn percent_encodes_paths() {
+        assert_eq!(
+            encode_path(Path::new(
+                "/home/user/.local/share/applications/steam-620.desktop"
+            )),
+            "/home/user/.local/share/applications/steam-620.desktop"
+        );
+        assert_eq!(
+            encode_path(Path::new("/home/a b/\u{e9}%#?.desktop")),
+            "/home/a%20b/%C3%A9%25%23%3F.desktop"
+        );
+    }
+
+    #[test]
+    fn deletion_dates_are_local_iso_8601() {
+        let timestamp = local_timestamp();
+        let bytes = timestamp.as_bytes();
+        assert_eq!(bytes.len(), 19, "{}", timestamp);
+        for (i, &b) in bytes.iter().enumerate() {
+            match i {
+                4 | 7 => assert_eq!(b, b'-', "{}", timestamp),
+                10 => assert_eq!(b, b'T', "{}", timestamp),
+                13 | 16 => assert_eq!(b, b':', "{}", timestamp),
+                _ => assert!(b.is_ascii_digit(), "{}", timestamp),
+            }
+        }
+    }
+
+    #[test]
+    fn other_mounts_store_paths_relative_to_the_top() {
+        let path = Path::new("/mnt/games/applications/steam-620.desktop");
+        let info = trash_info(path, Some(Path::new("/mnt/games")));
+        assert!(info.starts_with("[Trash Info]\nPath=applications/steam-620.desktop\n"));
+        let info = trash_info(path, None);
+        assert!(info.contains("\nPath=/mnt/games/applications/steam-620.desktop\n"));
+        assert!(info.ends_with('\n'));
+    }
+
+    #[test]
+    fn trashes_into_a_topdir_trash() {
+        let top = temp_dir();
+        let entry = top.join("apps/steam-620.desktop");
+        fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        fs::write(&entry, "[Desktop Entry]\n").unwrap();
+        let trash = top.join(".Trash-1000");
+
+        trash_into(&entry, &trash, Some(&top)).unwrap();
+        assert!(!entry.exists());
+        assert!(trash.join("files/steam-620.desktop").is_file());
+        let info = fs::read_to_string(trash.join("info/steam-620.desktop.trashinfo")).unwrap();
+        assert!(info.contains("\nPath=apps/steam-620.desktop\n"), "{}", info);
+
+        // a second entry of the same name gets the next free slot
+        fs::write(&entry, "[Desktop Entry]\n").unwrap();
+        trash_into(&entry, &trash, Some(&top)).unwrap();
+        assert!(trash.join("files/steam-620.desktop.2").is_file());
+        assert!(trash.join("info/steam-620.desktop.2.trashinfo").is_file());
+        fs::remove_dir_all(&top).unwrap();
+    }
+
+    #[test]
+    fn trashes_a_symlink_not_its_target() {
+        let dir = temp_dir();
+        let target = dir.join("target.desktop");
+        fs::write(&target, "[Desktop Entry]\n").unwrap();
+        let link = dir.join("link/steam-620.desktop");
+        fs::create_dir_all(link.parent().unwrap()).unwrap();
+        symlink(&target, &link).unwrap();
+
+        let resolved = resolve_parent(&link).unwrap();
+        assert_eq!(resolved, link);
+        let trash = dir.join("Trash");
+        trash_into(&resolved, &trash, None).unwrap();
+        assert!(target.is_file());
+        assert!(fs::symlink_metadata(&link).is_err());
+        let trashed = fs::symlink_metadata(trash.join("files/steam-620.desktop")).unwrap();
+        assert!(trashed.file_type().is_symlink());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! Emoji and grapheme handling for game names. Graphemes come from `unicode-segmentation`, so a
+//! name is never cut or stripped inside a user-perceived character.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Code points shown as emoji even without a variation selector asking for it, the flag halves
+/// among them. Generated from the regex crate's `Emoji_Presentation` property (Unicode 16.0).
+#[rustfmt::skip]
+const EMOJI_PRESENTATION: &[(u32, u32)] = &[
+    (0x231A, 0x231B), (0x23E9, 0x23EC), (0x23F0, 0x23F0), (0x23F3, 0x23F3), (0x25FD, 0x25FE),
+    (0x2614, 0x2615), (0x2648, 0x2653), (0x267F, 0x267F), (0x2693, 0x2693), (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB), (0x26BD, 0x26BE), (0x26C4, 0x26C5), (0x26CE, 0x26CE), (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA), (0x26F2, 0x26F3), (0x26F5, 0x26F5), (0x26FA, 0x26FA), (0x26FD, 0x26FD),
+    (0x2705, 0x2705), (0x270A, 0x270B), (0x2728, 0x2728), (0x274C, 0x274C), (0x274E, 0x274E),
+    (0x2753, 0x2755), (0x2757, 0x2757), (0x2795, 0x2797), (0x27B0, 0x27B0), (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C), (0x2B50, 0x2B50), (0x2B55, 0x2B55), (0x1F004, 0x1F004), (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E), (0x1F191, 0x1F19A), (0x1F1E6, 0x1F1FF), (0x1F201, 0x1F201),
+    (0x1F21A, 0x1F21A), (0x1F22F, 0x1F22F), (0x1F232, 0x1F236), (0x1F238, 0x1F23A),
+    (0x1F250, 0x1F251), (0x1F300, 0x1F320), (0x1F32D, 0x1F335), (0x1F337, 0x1F37C),
+    (0x1F37E, 0x1F393), (0x1F3A0, 0x1F3CA), (0x1F3CF, 0x1F3D3), (0x1F3E0, 0x1F3F0),
+    (0x1F3F4, 0x1F3F4), (0x1F3F8, 0x1F43E), (0x1F440, 0x1F440), (0x1F442, 0x1F4FC),
+    (0x1F4FF, 0x1F53D), (0x1F54B, 0x1F54E), (0x1F550, 0x1F567), (0x1F57A, 0x1F57A),
+    (0x1F595, 0x1F596), (0x1F5A4, 0x1F5A4), (0x1F5FB, 0x1F64F), (0x1F680, 0x1F6C5),
+    (0x1F6CC, 0x1F6CC), (0x1F6D0, 0x1F6D2), (0x1F6D5, 0x1F6D7), (0x1F6DC, 0x1F6DF),
+    (0x1F6EB, 0x1F6EC), (0x1F6F4, 0x1F6FC), (0x1F7E0, 0x1F7EB), (0x1F7F0, 0x1F7F0),
+    (0x1F90C, 0x1F93A), (0x1F93C, 0x1F945), (0x1F947, 0x1F9FF), (0x1FA70, 0x1FA7C),
+    (0x1FA80, 0x1FA89), (0x1FA8F, 0x1FAC6), (0x1FACE, 0x1FADC), (0x1FADF, 0x1FAE9),
+    (0x1FAF0, 0x1FAF8),
+];
+
+/// Asks for the character before it to be shown as emoji.
+const EMOJI_SELECTOR: char = '\u{FE0F}';
+
+/// Makes the character before it a keycap, like 1️⃣.
+const KEYCAP: char = '\u{20E3}';
+
+fn in_ranges(ranges: &[(u32, u32)], c: char) -> bool {
+    let cp = c as u32;
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < cp {
+                std::cmp::Ordering::Less
+            } else if start > cp {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Splits `text` into user-perceived characters, the extended grapheme clusters of UAX #29: a
+/// base with its combining marks, emoji joined with ZWJ, and flag pairs each stay whole.
+pub fn graphemes(text: &str) -> Vec<&str> {
+    text.graphemes(true).collect()
+}
+
+/// Whether a grapheme from [`graphemes`] is an emoji: it has a character shown as emoji by
+/// default, or one asked to be with U+FE0F, or it's a keycap. ™, © and symbols like ♥ or ★
+/// are text unless a selector says otherwise, so titles using them keep them.
+pub fn is_emoji(cluster: &str) -> bool {
+    cluster
+        .chars()
+        .any(|c| in_ranges(EMOJI_PRESENTATION, c) || c == EMOJI_SELECTOR || c == KEYCAP)
+}
+
+/// Whether `text` has any emoji in it.
+pub fn has_emoji(text: &str) -> bool {
+    graphemes(text).into_iter().any(is_emoji)
+}
+
+/// `text` without its emoji, each taken out whole with its skin tone, joiners and selectors.
+pub fn strip_emoji(text: &str) -> String {
+    graphemes(text)
+        .into_iter()
+        .filter(|cluster| !is_emoji(cluster))
+        .collect()
+}
+
+/// Shortens `text` to at most `max` graphemes, the last one being an ellipsis.
+/// Returns `None` when it already fits.
+pub fn truncate(text: &str, max: usize) -> Option<String> {
+    let clusters = graphemes(text);
+    if clusters.len() <= max {
+        return None;
+    }
+    let kept: String = clusters[..max.saturating_sub(1)].concat();
+    Some(format!("{}\u{2026}", kept.trim_end()))
+}
+
+/// The ASCII letter a Latin letter with a diacritic is sorted under, like `A` for `Å`, or
+/// `None` for letters of other scripts.
+pub fn base_letter(c: char) -> Option<char> {
+    if c.is_ascii_alphabetic() {
+        return Some(c.to_ascii_uppercase());
+    }
+    let base = match c {
+        'À'..='Æ' | 'à'..='æ' | '\u{100}'..='\u{105}' => 'A',
+        'Ç' | 'ç' | '\u{106}'..='\u{10d}' => 'C',
+        'Ð' | 'ð' | '\u{10e}'..='\u{111}' => 'D',
+        'È'..='Ë' | 'è'..='ë' | '\u{112}'..='\u{11b}' => 'E',
+        '\u{11c}'..='\u{123}' => 'G',
+        '\u{124}'..='\u{127}' => 'H',
+        'Ì'..='Ï' | 'ì'..='ï' | '\u{128}'..='\u{133}' => 'I',
+        '\u{134}'..='\u{135}' => 'J',
+        '\u{136}'..='\u{138}' => 'K',
+        '\u{139}'..='\u{142}' => 'L',
+        'Ñ' | 'ñ' | '\u{143}'..='\u{14b}' => 'N',
+        'Ò'..='Ö' | 'Ø' | 'ò'..='ö' | 'ø' | '\u{14c}'..='\u{153}' => 'O',
+        '\u{154}'..='\u{159}' => 'R',
+        'ß' | '\u{15a}'..='\u{161}' => 'S',
+        'Þ' | 'þ' | '\u{162}'..='\u{167}' => 'T',
+        'Ù'..='Ü' | 'ù'..='ü' | '\u{168}'..='\u{173}' => 'U',
+        '\u{174}'..='\u{175}' => 'W',
+        'Ý' | 'ý' | 'ÿ' | '\u{176}'..='\u{178}' => 'Y',
+        '\u{179}'..='\u{17e}' => 'Z',
+        _ => return None,
+    };
+    Some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{KeywordMatch, SkipReason, SkipRules};
+    use crate::names::NameRules;
+    use crate::steam::GameInfo;
+
+    const FAMILY: &str = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+    const FLAG_JP: &str = "\u{1f1ef}\u{1f1f5}";
+    const FLAG_FR: &str = "\u{1f1eb}\u{1f1f7}";
+    const KEYCAP_ONE: &str = "1\u{fe0f}\u{20e3}";
+    const WAVE_DARK: &str = "\u{1f44b}\u{1f3ff}";
+
+    #[test]
+    fn emoji_sequences_are_one_grapheme() {
+        assert_eq!(graphemes(FAMILY), [FAMILY]);
+        assert_eq!(graphemes(WAVE_DARK), [WAVE_DARK]);
+        assert_eq!(graphemes(KEYCAP_ONE), [KEYCAP_ONE]);
+        // regional indicators pair up in order, so two flags stay two
+        let flags = format!("{}{}", FLAG_JP, FLAG_FR);
+        assert_eq!(graphemes(&flags), [FLAG_JP, FLAG_FR]);
+        assert_eq!(
+            graphemes(&format!("Go{}!", FAMILY)),
+            ["G", "o", FAMILY, "!"]
+        );
+    }
+
+    #[test]
+    fn truncates_to_whole_graphemes() {
+        assert_eq!(truncate("Democracy 4", 11), None);
+        assert_eq!(
+            truncate("Democracy 4: Special Edition", 12).as_deref(),
+            Some("Democracy 4\u{2026}")
+        );
+        // the space before the cut goes, the ellipsis sits right after the word
+        assert_eq!(
+            truncate("Democracy 4: Special Edition", 11).as_deref(),
+            Some("Democracy\u{2026}")
+        );
+        // a decomposed é counts once and keeps its accent
+        assert_eq!(
+            truncate("De\u{301}mocratie Directe", 4).as_deref(),
+            Some("De\u{301}m\u{2026}")
+        );
+        assert_eq!(truncate("Portal", 1).as_deref(), Some("\u{2026}"));
+    }
+
+    #[test]
+    fn truncation_keeps_emoji_whole() {
+        let name = format!("{}{}{} Rally", FAMILY, FLAG_JP, FLAG_FR);
+        assert_eq!(
+            truncate(&name, 3).as_deref(),
+            Some(format!("{}{}\u{2026}", FAMILY, FLAG_JP).as_str())
+        );
+        assert_eq!(
+            truncate(&format!("Hi{}{}", WAVE_DARK, KEYCAP_ONE), 3).as_deref(),
+            Some("Hi\u{2026}")
+        );
+        assert_eq!(truncate(&format!("Hi{}", WAVE_DARK), 3), None);
+    }
+
+    #[test]
+    fn finds_emoji_but_not_title_symbols() {
+        for emoji in [FAMILY, FLAG_JP, KEYCAP_ONE, WAVE_DARK, "\u{2764}\u{fe0f}"] {
+            assert!(is_emoji(emoji), "{:?}", emoji);
+        }
+        for text in [
+            "A", "1", "#", "\u{2122}", "\u{a9}", "\u{2665}", "\u{2605}", "\u{2764}",
+        ] {
+            assert!(!is_emoji(text), "{:?}", text);
+        }
+        assert!(!has_emoji("Heart\u{2665}Beat \u{2605} Edition\u{2122}"));
+        assert!(has_emoji(&format!("Party {}", FLAG_FR)));
+    }
+
+    #[test]
+    fn strips_whole_emoji() {
+        assert_eq!(
+            strip_emoji(&format!("Family{} Trip", FAMILY)),
+            "Family Trip"
+        );
+        assert_eq!(
+            strip_emoji(&format!("{}Tokyo{}", FLAG_JP, WAVE_DARK)),
+            "Tokyo"
+        );
+        assert_eq!(strip_emoji(&format!("Level {}", KEYCAP_ONE)), "Level ");
+        // a lone joiner or skin tone left behind would show as a box
+        assert!(!strip_emoji(&format!("a{}b", FAMILY)).contains('\u{200d}'));
+        assert_eq!(
+            strip_emoji("Heart\u{2665}Beat\u{2122}"),
+            "Heart\u{2665}Beat\u{2122}"
+        );
+    }
+
+    #[test]
+    fn emoji_only_names_are_skipped_or_kept() {
+        let name = format!("{}{}", FLAG_JP, FAMILY);
+        let game = GameInfo {
+            appid: "620".to_string(),
+            name: name.clone(),
+            ..GameInfo::default()
+        };
+
+        // --skip-emoji leaves the game out
+        let rules = SkipRules::new(&[], &[], KeywordMatch::Substring)
+            .unwrap()
+            .skip_emoji(true);
+        assert_eq!(rules.check(&game), Some(SkipReason::Emoji));
+
+        // --strip-emoji would leave nothing, so the name stays as it is
+        let rules = NameRules::new(true, &[], &[]).unwrap().strip_emoji(true);
+        assert_eq!(rules.apply(&name), name);
+        assert_eq!(
+            rules.apply(&format!("{} Rally {}", FLAG_FR, KEYCAP_ONE)),
+            "Rally"
+        );
+    }
+}
@@ -0,0 +1,468 @@
+//! Valve's KeyValues format, in both the text flavour (`libraryfolders.vdf`, `*.acf`)
+//! and the binary one (`appinfo.vdf`, `shortcuts.vdf`).
+
+use crate::json::Value;
+use anyhow::{Result, bail};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Map(Vec<(String, Node)>),
+}
+
+impl Node {
+    /// Looks up a child by key. Steam isn't consistent about key case, so this ignores it.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Map(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follows a `/` separated path of keys, e.g. `common/name`.
+    pub fn path(&self, path: &str) -> Option<&Node> {
+        path.split('/')
+            .filter(|k| !k.is_empty())
+            .try_fold(self, |node, key| node.get(key))
+    }
+
+    pub fn entries(&self) -> &[(String, Node)] {
+        match self {
+            Node::Map(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Node::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The value as text, formatting numbers from binary files the way the text format would.
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Node::Str(s) => Some(s.clone()),
+            Node::Int(i) => Some(i.to_string()),
+            Node::Float(f) => Some(f.to_string()),
+            Node::Map(_) => None,
+        }
+    }
+
+    /// The tree as JSON, maps becoming objects with their keys in file order.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Node::Str(s) => Value::String(s.clone()),
+            Node::Int(i) => Value::Int(*i),
+            Node::Float(f) => Value::Float(*f),
+            Node::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, node)| (key.clone(), node.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses text KeyValues into a map node holding the top-level keys.
+pub fn parse_text(input: &str) -> Result<Node, ParseError> {
+    let mut parser = TextParser {
+        chars: input.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+    let entries = parser.parse_entries(false)?;
+    Ok(Node::Map(entries))
+}
+
+struct TextParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+impl TextParser {
+    fn parse_entries(&mut self, nested: bool) -> Result<Vec<(String, Node)>, ParseError> {
+        let mut entries = Vec::new();
+        loop {
+            let key = match self.next_token()? {
+                Some(Token::Str(key)) => key,
+                Some(Token::Close) if nested => return Ok(entries),
+                Some(Token::Close) => return Err(self.error("Unexpected '}'")),
+                Some(Token::Open) => return Err(self.error("Expected a key, found '{'")),
+                None if nested => return Err(self.error("Unexpected end of input, missing '}'")),
+                None => return Ok(entries),
+            };
+            let value = match self.next_token()? {
+                Some(Token::Str(value)) => Node::Str(value),
+                Some(Token::Open) => Node::Map(self.parse_entries(true)?),
+                Some(Token::Close) => {
+                    return Err(self.error(&format!("Expected a value for {:?}, found '}}'", key)));
+                }
+                None => {
+                    return Err(self.error(&format!("Unexpected end of input after {:?}", key)));
+                }
+            };
+            self.skip_conditional();
+            entries.push((key, value));
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        self.skip_trivia();
+        let Some(c) = self.peek() else {
+            return Ok(None);
+        };
+        match c {
+            '{' => {
+                self.bump();
+                Ok(Some(Token::Open))
+            }
+            '}' => {
+                self.bump();
+                Ok(Some(Token::Close))
+            }
+            '"' => {
+                self.bump();
+                let mut value = String::new();
+                loop {
+                    match self.bump() {
+                        Some('"') => return Ok(Some(Token::Str(value))),
+                        Some('\\') => match self.bump() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some('\\') => value.push('\\'),
+                            Some('"') => value.push('"'),
+                            // unknown escapes are kept verbatim, Windows paths rely on it
+                            Some(other) => {
+                                value.push('\\');
+                                value.push(other);
+                            }
+                            None => return Err(self.error("Unterminated string")),
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(self.error("Unterminated string")),
+                    }
+                }
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(c) = self.peek() {
+                    if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                        break;
+                    }
+                    value.push(c);
+                    self.bump();
+                }
+                Ok(Some(Token::Str(value)))
+            }
+        }
+    }
+
+    /// Skips whitespace and `//` comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') if self.chars.get(self.pos + 1) == Some(&'/') => {
+                    while let Some(c) = self.bump() {
+                        if c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Skips platform conditionals like `[$WIN32]` that may trail a value.
+    fn skip_conditional(&mut self) {
+        let start = (self.pos, self.line, self.column);
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.bump();
+        }
+        if self.peek() == Some('[') {
+            while let Some(c) = self.bump() {
+                if c == ']' || c == '\n' {
+                    return;
+                }
+            }
+        } else {
+            (self.pos, self.line, self.column) = start;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+const BIN_MAP: u8 = 0x00;
+const BIN_STRING: u8 = 0x01;
+const BIN_INT32: u8 = 0x02;
+const BIN_FLOAT32: u8 = 0x03;
+const BIN_POINTER: u8 = 0x04;
+const BIN_WIDE_STRING: u8 = 0x05;
+const BIN_COLOR: u8 = 0x06;
+const BIN_UINT64: u8 = 0x07;
+const BIN_END: u8 = 0x08;
+const BIN_INT64: u8 = 0x0A;
+const BIN_END_ALT: u8 = 0x0B;
+
+/// Parses binary KeyValues starting at `data`, up to the end marker of the outermost map.
+/// Newer appinfo files store keys as indices into a shared string table, passed as `keys`.
+/// Returns the map node and the number of bytes consumed.
+pub fn parse_binary(data: &[u8], keys: Option<&[String]>) -> Result<(Node, usize)> {
+    let mut reader = BinaryReader { data, pos: 0, keys };
+    let entries = reader.parse_entries(0)?;
+    Ok((Node::Map(entries), reader.pos))
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    keys: Option<&'a [String]>,
+}
+
+impl BinaryReader<'_> {
+    fn parse_entries(&mut self, depth: usize) -> Result<Vec<(String, Node)>> {
+        if depth > 64 {
+            bail!("Binary VDF nested too deeply at offset {}", self.pos);
+        }
+        let mut entries = Vec::new();
+        loop {
+            let kind = self.u8()?;
+            if kind == BIN_END || kind == BIN_END_ALT {
+                return Ok(entries);
+            }
+            let key = self.key()?;
+            let value = match kind {
+                BIN_MAP => Node::Map(self.parse_entries(depth + 1)?),
+                BIN_STRING => Node::Str(self.cstring()?),
+                BIN_INT32 | BIN_POINTER | BIN_COLOR => {
+                    Node::Int(i32::from_le_bytes(self.array()?) as i64)
+                }
+                BIN_FLOAT32 => Node::Float(f32::from_le_bytes(self.array()?) as f64),
+                BIN_UINT64 => Node::Int(u64::from_le_bytes(self.array()?) as i64),
+                BIN_INT64 => Node::Int(i64::from_le_bytes(self.array()?)),
+                BIN_WIDE_STRING => Node::Str(self.wide_string()?),
+                other => bail!(
+                    "Unknown binary VDF type 0x{:02x} at offset {}",
+                    other,
+                    self.pos - 1
+                ),
+            };
+            entries.push((key, value));
+        }
+    }
+
+    fn key(&mut self) -> Result<String> {
+        match self.keys {
+            Some(keys) => {
+                let index = u32::from_le_bytes(self.array()?) as usize;
+                match keys.get(index) {
+                    Some(key) => Ok(key.clone()),
+                    None => bail!("String table index {} out of range", index),
+                }
+            }
+            None => self.cstring(),
+        }
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        let [b] = self.array()?;
+        Ok(b)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        match self.data.get(self.pos..self.pos + N) {
+            Some(bytes) => {
+                self.pos += N;
+                Ok(bytes.try_into().unwrap())
+            }
+            None => bail!("Unexpected end of binary VDF at offset {}", self.pos),
+        }
+    }
+
+    fn cstring(&mut self) -> Result<String> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        match rest.iter().position(|&b| b == 0) {
+            Some(len) => {
+                let s = String::from_utf8_lossy(&rest[..len]).into_owned();
+                self.pos += len + 1;
+                Ok(s)
+            }
+            None => bail!("Unterminated string at offset {}", self.pos),
+        }
+    }
+
+    fn wide_string(&mut self) -> Result<String> {
+        let mut units = Vec::new();
+        loop {
+            let unit = u16::from_le_bytes(self.array()?);
+            if unit == 0 {
+                return Ok(String::from_utf16_lossy(&units));
+            }
+            units.push(unit);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn str_node(s: &str) -> Node {
+        Node::Str(s.to_string())
+    }
+
+    #[test]
+    fn escapes() {
+        let node =
+            parse_text(r#""a" "line\nbreak\ttab \"quoted\" back\\slash" "path" "C:\Games\Portal""#)
+                .unwrap();
+        assert_eq!(
+            node.get("a"),
+            Some(&str_node("line\nbreak\ttab \"quoted\" back\\slash"))
+        );
+        // unknown escapes stay as written
+        assert_eq!(node.get("path"), Some(&str_node(r"C:\Games\Portal")));
+    }
+
+    #[test]
+    fn nested_blocks() {
+        let text = r#"
+"AppState"
+{
+	"appid"		"620"
+	"UserConfig"
+	{
+		"language"		"english"
+		"Empty" {}
+	}
+	"name"		"Portal 2"
+}
+"#;
+        let node = parse_text(text).unwrap();
+        assert_eq!(node.path("AppState/appid"), Some(&str_node("620")));
+        assert_eq!(
+            node.path("appstate/userconfig/LANGUAGE"),
+            Some(&str_node("english"))
+        );
+        assert_eq!(
+            node.path("AppState/UserConfig/Empty"),
+            Some(&Node::Map(Vec::new()))
+        );
+        let keys: Vec<&str> = node
+            .path("AppState")
+            .unwrap()
+            .entries()
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["appid", "UserConfig", "name"]);
+    }
+
+    #[test]
+    fn unquoted_tokens_and_conditionals() {
+        let node = parse_text("key value\n\"os\" \"linux\" [$LINUX]\nnext{inner 1}").unwrap();
+        assert_eq!(node.get("key"), Some(&str_node("value")));
+        assert_eq!(node.get("os"), Some(&str_node("linux")));
+        assert_eq!(node.path("next/inner"), Some(&str_node("1")));
+    }
+
+    #[test]
+    fn comments() {
+        let text = "// written by Steam\n\"a\" // the first key\n{\n\t// nothing here\n\t\"b\" \"c\" // trailing\n}\n// end";
+        let node = parse_text(text).unwrap();
+        assert_eq!(node.path("a/b"), Some(&str_node("c")));
+        // a slash inside a string isn't a comment
+        let node = parse_text(r#""url" "https://store.steampowered.com""#).unwrap();
+        assert_eq!(
+            node.get("url"),
+            Some(&str_node("https://store.steampowered.com"))
+        );
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        let error = parse_text("\"AppState\"\n{\n\t\"name\" \"Portal").unwrap_err();
+        assert_eq!(error.message, "Unterminated string");
+        assert_eq!((error.line, error.column), (3, 16));
+        assert_eq!(
+            parse_text("\"a\" \"b\\").unwrap_err().message,
+            "Unterminated string"
+        );
+    }
+
+    #[test]
+    fn unbalanced_braces() {
+        assert_eq!(
+            parse_text("\"a\" { \"b\" \"c\"").unwrap_err().message,
+            "Unexpected end of input, missing '}'"
+        );
+        assert_eq!(parse_text("}").unwrap_err().message, "Unexpected '}'");
+        assert_eq!(
+            parse_text("\"a\" }").unwrap_err().message,
+            "Expected a value for \"a\", found '}'"
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! `steamer watch`: puts entries back soon after another tool deletes or changes them. The
+//! applications directory and its `--bucket-by-letter` subdirectories are watched with inotify,
+//! and once they have been quiet for `--settle` seconds after an entry steamer recorded was
+//! touched, a `--repair` sync writes the damaged ones again. The sync's own writes leave the
+//! entries intact, so they set nothing off. When the kernel drops changes because too many came
+//! at once, there's no telling which entries were touched, and the repair runs regardless.
+
+use crate::cli::{Args, WatchArgs};
+use crate::desktop::is_bucket;
+use crate::paths::Paths;
+use crate::report::Reporter;
+use crate::signals;
+use crate::state::{Condition, EntryState};
+use anyhow::{Context, Result, bail};
+use std::collections::{BTreeSet, HashMap};
+use std::ffi::{CString, OsStr};
+use std::fs;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Changes to the entries in a letter bucket.
+const BUCKET_MASK: u32 =
+    libc::IN_CLOSE_WRITE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO;
+
+/// Changes to entries, new letter buckets, and the directory itself going away.
+const MASK: u32 = BUCKET_MASK | libc::IN_CREATE | libc::IN_DELETE_SELF | libc::IN_MOVE_SELF;
+
+/// Watches until interrupted, calling `repair` each time recorded entries were damaged.
+pub fn watch(args: &Args, watch: &WatchArgs, repair: impl Fn() -> Result<()>) -> Result<()> {
+    let reporter = Reporter::new(args.log_format, false);
+    let paths = Paths::resolve(args, false, &reporter)?;
+    let mut inotify = Inotify::new(&paths.app_dir)
+        .with_context(|| format!("Could not watch {:?}", paths.app_dir))?;
+    signals::install();
+    eprintln!(
+        "Watching {:?} for entries that are deleted or changed",
+        paths.app_dir
+    );
+
+    // entries damaged while steamer wasn't watching are restored right away
+    restore(&repair, &reporter);
+    loop {
+        let mut changes = inotify.wait(None)?;
+        // a tool removing entries one by one is let finish first
+        loop {
+            let more = inotify.wait(Some(Duration::from_secs(watch.settle)))?;
+            if more.is_empty() {
+                break;
+            }
+            changes.extend(more);
+        }
+
+        let mut damaged = changes.overflowed;
+        if changes.overflowed {
+            eprintln!("Too many changes to follow at once, restoring every damaged entry");
+        }
+        let state = EntryState::load(&paths.cache_dir);
+        for path in &changes.entries {
+            if let Condition::Damaged(damage) = state.condition(path) {
+                eprintln!("{:?} was {}, restoring it", path, damage);
+                damaged = true;
+            }
+        }
+        if damaged {
+            restore(&repair, &reporter);
+        }
+    }
+}
+
+/// Runs a repair, a failed one is tried again with the next change.
+fn restore(repair: &impl Fn() -> Result<()>, reporter: &Reporter) {
+    if let Err(e) = repair() {
+        reporter.warn(format!("Could not restore the entries: {:#}", e));
+    }
+}
+
+/// What [`Inotify::wait`] saw.
+#[derive(Debug, Default)]
+struct Changes {
+    /// The entries changed.
+    entries: BTreeSet<PathBuf>,
+    /// Whether the kernel dropped changes, so any entry may have been.
+    overflowed: bool,
+}
+
+impl Changes {
+    fn is_empty(&self) -> bool {
+        self.entries.is_empty() && !self.overflowed
+    }
+
+    fn extend(&mut self, other: Changes) {
+        self.entries.extend(other.entries);
+        self.overflowed |= other.overflowed;
+    }
+}
+
+struct Inotify {
+    fd: OwnedFd,
+    app_dir: PathBuf,
+    /// The watch on `app_dir`.
+    root: libc::c_int,
+    /// The watches on its letter buckets, with the bucket each is on.
+    buckets: HashMap<libc::c_int, PathBuf>,
+}
+
+impl Inotify {
+    fn new(app_dir: &Path) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let root = add_watch(&fd, app_dir, MASK)?;
+        let mut inotify = Self {
+            fd,
+            app_dir: app_dir.to_path_buf(),
+            root,
+            buckets: HashMap::new(),
+        };
+        inotify.watch_buckets()?;
+        Ok(inotify)
+    }
+
+    /// Watches every letter bucket in the applications directory. Watching one twice is fine,
+    /// inotify hands back the watch it already has.
+    fn watch_buckets(&mut self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.app_dir)? {
+            let entry = entry?;
+            if is_bucket(&entry.file_name().to_string_lossy()) && entry.file_type()?.is_dir() {
+                self.watch_bucket(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Watches a letter bucket, unless it's already gone again.
+    fn watch_bucket(&mut self, bucket: PathBuf) -> io::Result<()> {
+        match add_watch(&self.fd, &bucket, BUCKET_MASK) {
+            Ok(wd) => {
+                self.buckets.insert(wd, bucket);
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The entries changed within `timeout`, or until the first change without one. Empty
+    /// changes mean none were. Stops the process on Ctrl-C or SIGTERM.
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<Changes> {
+        let timeout = timeout.map_or(-1, |timeout| {
+            libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX)
+        });
+        let mut poll = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        loop {
+            if unsafe { libc::poll(&mut poll, 1, timeout) } >= 0 {
+                break;
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error).context("Could not wait for changes");
+            }
+            if signals::interrupted() {
+                std::process::exit(signals::EXIT_INTERRUPTED);
+            }
+        }
+        if poll.revents & libc::POLLIN == 0 {
+            return Ok(Changes::default());
+        }
+
+        let mut buffer = [0u8; 4096];
+        let read = unsafe {
+            libc::read(
+                self.fd.as_raw_fd(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            )
+        };
+        if read < 0 {
+            return Err(io::Error::last_os_error()).context("Could not read the changes");
+        }
+        self.changes(parse_events(&buffer[..read as usize]))
+    }
+
+    /// The entries `events` touched. New letter buckets get watched, and after an overflow
+    /// every bucket is looked for again, as the ones created meanwhile went unseen.
+    fn changes(&mut self, events: Vec<Event>) -> Result<Changes> {
+        let mut changes = Changes::default();
+        for event in events {
+            let dir = if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                changes.overflowed = true;
+                continue;
+            } else if event.wd == self.root {
+                if event.mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF | libc::IN_IGNORED) != 0
+                {
+                    bail!("{:?} was removed or moved away, stopping", self.app_dir);
+                }
+                if event.mask & libc::IN_ISDIR != 0 {
+                    if event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0
+                        && is_bucket(&event.name.to_string_lossy())
+                    {
+                        let bucket = self.app_dir.join(event.name);
+                        self.watch_bucket(bucket.clone())
+                            .with_context(|| format!("Could not watch {:?}", bucket))?;
+                    }
+                    continue;
+                }
+                &self.app_dir
+            } else if let Some(bucket) = self.buckets.get(&event.wd) {
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.buckets.remove(&event.wd);
+                    continue;
+                }
+                bucket
+            } else {
+                continue;
+            };
+            if event.mask & libc::IN_ISDIR == 0 && event.name.as_bytes().ends_with(b".desktop") {
+                changes.entries.insert(dir.join(event.name));
+            }
+        }
+        if changes.overflowed {
+            self.watch_buckets()
+                .with_context(|| format!("Could not watch {:?}", self.app_dir))?;
+        }
+        Ok(changes)
+    }
+}
+
+fn add_watch(fd: &OwnedFd, dir: &Path, mask: u32) -> io::Result<libc::c_int> {
+    let dir = CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), mask) };
+    if wd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(wd)
+}
+
+/// One `inotify_event`, with an empty name for events on the watched directory itself.
+#[derive(Debug, PartialEq)]
+struct Event<'a> {
+    wd: libc::c_int,
+    mask: u32,
+    name: &'a OsStr,
+}
+
+/// The events in a buffer of `inotify_event`s.
+fn parse_events(mut buffer: &[u8]) -> Vec<Event<'_>> {
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+    let field = |bytes: &[u8], at: usize| u32::from_ne_bytes(bytes[at..at + 4].try_into().unwrap());
+    let mut events = Vec::new();
+    while buffer.len() >= HEADER {
+        let wd = field(buffer, 0) as libc::c_int;
+        let mask = field(buffer, 4);
+        let len = field(buffer, 12) as usize;
+        let Some(name) = buffer.get(HEADER..HEADER + len) else {
+            break;
+        };
+        // the name is padded with NULs
+        let end = name.iter().position(|&byte| byte == 0).unwrap_or(len);
+        events.push(Event {
+            wd,
+            mask,
+            name: OsStr::from_bytes(&name[..end]),
+        });
+        buffer = &buffer[HEADER + len..];
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// A fresh directory for one test, tests run in parallel.
+    fn temp_dir() -> PathBuf {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-watch-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn event(wd: libc::c_int, mask: u32, name: &str) -> Vec<u8> {
+        let padded = (name.len() + 1).next_multiple_of(4);
+        let mut bytes = Vec::new();
+        bytes.extend(wd.to_ne_bytes());
+        bytes.extend(mask.to_ne_bytes());
+        bytes.extend(0u32.to_ne_bytes());
+        bytes.extend((padded as u32).to_ne_bytes());
+        bytes.extend(name.as_bytes());
+        bytes.resize(bytes.len() + padded - name.len(), 0);
+        bytes
+    }
+
+    fn changed(inotify: &mut Inotify) -> BTreeSet<PathBuf> {
+        let changes = inotify.wait(Some(Duration::from_secs(5))).unwrap();
+        assert!(!changes.overflowed);
+        changes.entries
+    }
+
+    #[test]
+    fn reads_events() {
+        let mut buffer = event(1, libc::IN_DELETE, "steam-620.desktop");
+        buffer.extend(event(2, libc::IN_CLOSE_WRITE, "steam-400.desktop"));
+        buffer.extend(event(1, libc::IN_DELETE_SELF, ""));
+        assert_eq!(
+            parse_events(&buffer),
+            [
+                Event {
+                    wd: 1,
+                    mask: libc::IN_DELETE,
+                    name: OsStr::new("steam-620.desktop")
+                },
+                Event {
+                    wd: 2,
+                    mask: libc::IN_CLOSE_WRITE,
+                    name: OsStr::new("steam-400.desktop")
+                },
+                Event {
+                    wd: 1,
+                    mask: libc::IN_DELETE_SELF,
+                    name: OsStr::new("")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn notices_the_directory_going_away() {
+        let dir = temp_dir();
+        let mut inotify = Inotify::new(&dir).unwrap();
+        let mut buffer = event(inotify.root, libc::IN_DELETE, "steam-620.desktop");
+        buffer.extend(event(inotify.root, libc::IN_DELETE_SELF, ""));
+        let error = inotify.changes(parse_events(&buffer)).unwrap_err();
+        assert!(error.to_string().contains("was removed or moved away"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sees_entries_deleted_in_a_directory() {
+        let dir = temp_dir();
+        let entry = dir.join("steam-620.desktop");
+        fs::write(&entry, "[Desktop Entry]\n").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let mut inotify = Inotify::new(&dir).unwrap();
+        fs::remove_file(&entry).unwrap();
+        fs::remove_file(dir.join("notes.txt")).unwrap();
+        assert_eq!(changed(&mut inotify), BTreeSet::from([entry]));
+        assert!(
+            inotify
+                .wait(Some(Duration::from_millis(10)))
+                .unwrap()
+                .is_empty()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sees_entries_in_letter_buckets() {
+        let dir = temp_dir();
+        let bucketed = dir.join("P/steam-620.desktop");
+        fs::create_dir_all(dir.join("P")).unwrap();
+        fs::write(&bucketed, "[Desktop Entry]\n").unwrap();
+        fs::create_dir_all(dir.join("icons")).unwrap();
+
+        let mut inotify = Inotify::new(&dir).unwrap();
+        fs::remove_file(&bucketed).unwrap();
+        fs::write(dir.join("icons/steam-620.desktop"), "").unwrap();
+        assert_eq!(changed(&mut inotify), BTreeSet::from([bucketed]));
+
+        // a bucket the sync creates later is watched as soon as it shows up
+        fs::create_dir(dir.join("H")).unwrap();
+        assert!(changed(&mut inotify).is_empty());
+        let created = dir.join("H/steam-400.desktop");
+        fs::write(&created, "[Desktop Entry]\n").unwrap();
+        assert_eq!(changed(&mut inotify), BTreeSet::from([created]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn an_overflow_repairs_everything_and_finds_new_buckets() {
+        let dir = temp_dir();
+        let mut inotify = Inotify::new(&dir).unwrap();
+        fs::create_dir(dir.join("Q")).unwrap();
+        // drop the creation of Q, like an overflowing queue would
+        inotify.wait(Some(Duration::from_secs(5))).unwrap();
+        inotify.buckets.clear();
+
+        let changes = inotify
+            .changes(parse_events(&event(-1, libc::IN_Q_OVERFLOW, "")))
+            .unwrap();
+        assert!(changes.overflowed);
+        assert!(!changes.is_empty());
+
+        let entry = dir.join("Q/steam-620.desktop");
+        fs::write(&entry, "[Desktop Entry]\n").unwrap();
+        assert_eq!(changed(&mut inotify), BTreeSet::from([entry]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
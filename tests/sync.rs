@@ -0,0 +1,900 @@
+//! Whole runs of the binary against a made-up Steam install, for behavior that ends the process
+//! or only shows in what a run leaves behind.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A home directory, a Steam root with one library and an applications directory, all in a
+/// fresh temporary directory removed on drop.
+struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "steamer-sync-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(dir.join("home")).unwrap();
+        fs::create_dir_all(dir.join("apps")).unwrap();
+        fs::create_dir_all(dir.join("steam/steamapps")).unwrap();
+        let fixture = Self { dir };
+        fixture.write_libraries(&[]);
+        fixture
+    }
+
+    fn steamapps(&self) -> PathBuf {
+        self.dir.join("steam/steamapps")
+    }
+
+    fn apps(&self) -> PathBuf {
+        self.dir.join("apps")
+    }
+
+    /// Lists `appids` in the library's libraryfolders.vdf.
+    fn write_libraries(&self, appids: &[&str]) {
+        let apps: String = appids
+            .iter()
+            .map(|appid| format!("\t\t\t\"{}\"\t\"1\"\n", appid))
+            .collect();
+        let vdf = format!(
+            "\"libraryfolders\"\n{{\n\t\"0\"\n\t{{\n\t\t\"path\"\t\"{}\"\n\t\t\"apps\"\n\t\t{{\n{}\t\t}}\n\t}}\n}}\n",
+            self.dir.join("steam").display(),
+            apps
+        );
+        fs::write(self.steamapps().join("libraryfolders.vdf"), vdf).unwrap();
+    }
+
+    /// Installs a game with a complete appmanifest.
+    fn install(&self, appid: &str, name: &str) {
+        let manifest = format!(
+            "\"AppState\"\n{{\n\t\"appid\"\t\"{}\"\n\t\"name\"\t\"{}\"\n\t\"installdir\"\t\"{}\"\n\t\"StateFlags\"\t\"4\"\n}}\n",
+            appid, name, name
+        );
+        self.write_manifest(appid, &manifest);
+    }
+
+    fn write_manifest(&self, appid: &str, manifest: &str) {
+        let path = self.steamapps().join(format!("appmanifest_{}.acf", appid));
+        fs::write(path, manifest).unwrap();
+    }
+
+    /// Runs steamer on this install with `args`, writing to [`Fixture::apps`].
+    fn run(&self, args: &[&str]) -> Output {
+        self.command()
+            .args(["--app-dir", path_str(&self.apps())])
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// steamer on this install, with every XDG directory inside the fixture.
+    fn command(&self) -> Command {
+        let home = self.dir.join("home");
+        let mut command = Command::new(env!("CARGO_BIN_EXE_steamer"));
+        command
+            .args(["--steam-path", path_str(&self.dir.join("steam"))])
+            .args(["--exec-mode", "xdg-open"])
+            .env("HOME", &home)
+            .env("XDG_DATA_HOME", home.join(".local/share"))
+            .env("XDG_CACHE_HOME", home.join(".cache"))
+            .env("XDG_CONFIG_HOME", home.join(".config"))
+            .env_remove("XDG_CURRENT_DESKTOP");
+        command
+    }
+
+    fn entries(&self) -> Vec<String> {
+        let mut entries: Vec<String> = fs::read_dir(self.apps())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".desktop"))
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+fn path_str(path: &Path) -> &str {
+    path.to_str().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn fail_fast_stops_at_a_broken_manifest() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["400", "620"]);
+    fixture.write_manifest("400", "\"AppState\"\n{\n\t\"appid\"\t\"400\"\n");
+    fixture.install("620", "Portal 2");
+
+    let output = fixture.run(&["--fail-fast"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("stopped by --fail-fast after writing 0 entries"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(fixture.entries().is_empty());
+
+    // without it the broken manifest is a warning and the rest still gets its entry
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Could not parse"));
+    assert!(fixture.entries().contains(&"steam-620.desktop".to_string()));
+}
+
+#[test]
+fn one_pass_feeds_every_emitted_format() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "1145360"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("1145360", "Hades");
+
+    let output = fixture.run(&["--emit", "desktop,json,html"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-1145360.desktop", "steam-620.desktop"]
+    );
+    let index_dir = fixture.dir.join("home/.local/share/steamer");
+    let json = fs::read_to_string(index_dir.join("steamer-index.json")).unwrap();
+    let html = fs::read_to_string(index_dir.join("steamer-index.html")).unwrap();
+    for (appid, name) in [("620", "Portal 2"), ("1145360", "Hades")] {
+        assert!(
+            json.contains(&format!("\"appid\": \"{}\"", appid)),
+            "{}",
+            json
+        );
+        assert!(html.contains(&format!(">{}</a>", name)), "{}", html);
+    }
+
+    // without desktop the applications directory is left alone
+    fs::remove_file(fixture.apps().join("steam-620.desktop")).unwrap();
+    let output = fixture.run(&["--emit", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-1145360.desktop"]);
+}
+
+#[test]
+fn count_only_totals_without_writing() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "1145360", "400"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("1145360", "Hades");
+    fixture.install("400", "Portal Demo");
+
+    let output = fixture.run(&["--count-only", "--skip-demos"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("  Games:   2\n  Skipped: 1\n"), "{}", out);
+    assert!(
+        out.ends_with("Total: 2 games, 1 skipped, in 1 libraries\n"),
+        "{}",
+        out
+    );
+    assert!(fixture.entries().is_empty());
+}
+
+#[test]
+fn dry_run_write_prints_every_entry_sorted() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "1145360"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("1145360", "Hades");
+
+    let output = fixture.run(&["--dry-run-write"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let headers: Vec<&str> = out
+        .lines()
+        .filter(|line| line.starts_with("# ----"))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            format!(
+                "# ---- {}/steam-1145360.desktop ----",
+                fixture.apps().display()
+            ),
+            format!("# ---- {}/steam-620.desktop ----", fixture.apps().display()),
+        ]
+    );
+    assert_eq!(out.matches("[Desktop Entry]\n").count(), 2);
+    assert!(out.contains("\nName=Portal 2\n"), "{}", out);
+    assert!(fixture.entries().is_empty());
+}
+
+#[test]
+fn render_shows_one_manifest_and_its_entry() {
+    let fixture = Fixture::new();
+    fixture.install("620", "Portal 2");
+    let manifest = fixture.steamapps().join("appmanifest_620.acf");
+
+    let output = fixture.run(&["render", path_str(&manifest)]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    for line in [
+        "  AppID:       620\n",
+        "  Name:        Portal 2\n",
+        "  Install dir: Portal 2\n",
+        "Skipped: no\n",
+        "# steam-620.desktop\n[Desktop Entry]\n",
+        "\nExec=xdg-open steam://rungameid/620\n",
+    ] {
+        assert!(out.contains(line), "{:?} missing from\n{}", line, out);
+    }
+    assert!(fixture.entries().is_empty());
+
+    let output = fixture.run(&["--ignored-app-ids", "6*", "render", path_str(&manifest)]);
+    assert!(
+        stdout(&output).contains("Skipped: ignored app ID 6*\n"),
+        "{}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn a_held_lock_stops_writing_runs_only() {
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let lock_path = fixture.dir.join("home/.cache/steamer/lock");
+    fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+    let mut lock = fs::File::create(&lock_path).unwrap();
+    writeln!(lock, "4242").unwrap();
+    assert_eq!(
+        unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) },
+        0
+    );
+
+    let output = fixture.run(&[]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Another steamer run (PID 4242) is updating entries"),
+        "{}",
+        stderr(&output)
+    );
+    assert!(fixture.entries().is_empty());
+
+    let output = fixture.run(&["--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    drop(lock);
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+}
+
+#[test]
+fn extra_libraries_are_scanned_once() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let extra = fixture.dir.join("usb");
+    fs::create_dir_all(extra.join("steamapps")).unwrap();
+    fs::write(
+        extra.join("steamapps/appmanifest_1145360.acf"),
+        "\"AppState\"\n{\n\t\"appid\"\t\"1145360\"\n\t\"name\"\t\"Hades\"\n}\n",
+    )
+    .unwrap();
+    // the Steam library again, through a symlink
+    let link = fixture.dir.join("link");
+    std::os::unix::fs::symlink(fixture.dir.join("steam"), &link).unwrap();
+
+    let output = fixture.run(&[
+        "--extra-library",
+        path_str(&extra),
+        "--extra-library",
+        path_str(&link),
+        "--extra-library",
+        path_str(&fixture.dir.join("unmounted")),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-1145360.desktop", "steam-620.desktop"]
+    );
+    assert!(stderr(&output).contains("has no steamapps folder"));
+    let out = stdout(&output);
+    assert_eq!(out.matches("Checking Library").count(), 2, "{}", out);
+    assert!(out.contains(&format!("{:?} (extra)", extra)), "{}", out);
+}
+
+#[test]
+fn the_steam_entry_is_managed_like_the_games() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+
+    let output = fixture.run(&["--with-steam-entry"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-620.desktop", "steam-client.desktop"]
+    );
+    let entry = fs::read_to_string(fixture.apps().join("steam-client.desktop")).unwrap();
+    assert!(entry.contains("\nName=Steam\n"), "{}", entry);
+
+    // dropping the flag cleans it up with the other stale entries
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+}
+
+#[test]
+fn tracked_launches_show_up_in_the_history() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+
+    let output = fixture.run(&["--track-launches"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let entry = fs::read_to_string(fixture.apps().join("steam-620.desktop")).unwrap();
+    let exec = entry
+        .lines()
+        .find(|line| line.starts_with("Exec="))
+        .unwrap();
+    assert!(
+        exec.ends_with(" launch-wrapper 620 -- xdg-open steam://rungameid/620"),
+        "{}",
+        exec
+    );
+
+    let output = fixture.run(&["history"]);
+    assert!(stdout(&output).starts_with("No launches recorded yet"));
+
+    // the wrapper records the launch, then becomes the command it was given
+    for appid in ["620", "620", "1145360"] {
+        let output = fixture.run(&["launch-wrapper", appid, "--", "sh", "-c", "exit 3"]);
+        assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    }
+    let output = fixture.run(&["history"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.starts_with("3 launches of 2 games since "), "{}", out);
+    assert!(out.contains("     2  Portal 2 (AppID: 620)\n"), "{}", out);
+    assert!(
+        out.contains("     1  Unknown Game (1145360) (AppID: 1145360)\n"),
+        "{}",
+        out
+    );
+
+    let output = fixture.run(&["history", "--weekly"]);
+    let out = stdout(&output);
+    assert!(out.starts_with("Week of "), "{}", out);
+    assert!(out.contains(": 3 launches\n"), "{}", out);
+}
+
+#[test]
+fn diff_output_shows_what_would_change_without_writing() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "400"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("400", "Portal");
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let written = fs::read_to_string(fixture.apps().join("steam-620.desktop")).unwrap();
+
+    fixture.install("620", "Portal Two");
+    fs::remove_file(fixture.steamapps().join("appmanifest_400.acf")).unwrap();
+    fixture.write_libraries(&["620", "1145360"]);
+    fixture.install("1145360", "Hades");
+    let output = fixture.run(&["--diff-output"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let out = stdout(&output);
+    let entry = |appid: &str| format!("{}/steam-{}.desktop", fixture.apps().display(), appid);
+    for part in [
+        format!("--- /dev/null\n+++ {}\n", entry("1145360")),
+        "+Name=Hades\n".to_string(),
+        format!("--- {}\n+++ /dev/null\n", entry("400")),
+        "-Name=Portal\n".to_string(),
+        format!("--- {0}\n+++ {0}\n", entry("620")),
+        "-Name=Portal 2\n+Name=Portal Two\n".to_string(),
+    ] {
+        assert!(out.contains(&part), "{:?} missing from\n{}", part, out);
+    }
+    assert!(out.find(&entry("1145360")) < out.find(&entry("400")));
+    assert!(out.find(&entry("400")) < out.find(&entry("620")));
+    assert_eq!(
+        fs::read_to_string(fixture.apps().join("steam-620.desktop")).unwrap(),
+        written
+    );
+    assert_eq!(
+        fixture.entries(),
+        ["steam-400.desktop", "steam-620.desktop"]
+    );
+}
+
+#[test]
+fn debug_bundles_never_include_credentials() {
+    use std::io::Read;
+
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let steam = fixture.dir.join("steam");
+    fs::create_dir_all(steam.join("config")).unwrap();
+    fs::create_dir_all(steam.join("userdata/123456789/config")).unwrap();
+    for (path, content) in [
+        (
+            "config/config.vdf",
+            "\"ConnectCache\" { \"1\" \"SECRET-CONNECT\" }",
+        ),
+        ("config/loginusers.vdf", "\"users\" { \"SECRET-LOGIN\" }"),
+        ("ssfn1234567890", "SECRET-SENTRY"),
+        (
+            "userdata/123456789/config/localconfig.vdf",
+            "SECRET-USERDATA",
+        ),
+    ] {
+        fs::write(steam.join(path), content).unwrap();
+    }
+
+    let bundle = fixture.dir.join("bundle.tar.gz");
+    let output = fixture.run(&[
+        "--api-key",
+        "SECRET-API-KEY",
+        "debug-bundle",
+        "--output",
+        path_str(&bundle),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let file = fs::File::open(&bundle).unwrap();
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(file));
+    let mut names = Vec::new();
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().into_owned();
+        let mut content = String::new();
+        entry.read_to_string(&mut content).unwrap();
+        assert!(!content.contains("SECRET"), "{}:\n{}", name, content);
+        if name.ends_with("/steamer.txt") {
+            assert!(content.contains("\"<redacted>\""), "{}", content);
+        }
+        names.push(name);
+    }
+    for name in [
+        "steamer-debug/steamer.txt",
+        "steamer-debug/libraryfolders.vdf",
+        "steamer-debug/manifests/appmanifest_620.acf",
+        "steamer-debug/userdata.txt",
+    ] {
+        assert!(
+            names.iter().any(|n| n == name),
+            "{} not in {:?}",
+            name,
+            names
+        );
+    }
+}
+
+#[test]
+fn desktops_can_have_their_own_applications_directory() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let home = fixture.dir.join("home");
+    fs::create_dir_all(home.join(".config/steamer")).unwrap();
+    fs::write(
+        home.join(".config/steamer/config.toml"),
+        "[app_dirs]\nPantheon = \"~/pantheon-apps\"\n",
+    )
+    .unwrap();
+    let entry = |dir: &str| home.join(dir).join("steam-620.desktop");
+
+    let output = fixture
+        .command()
+        .env("XDG_CURRENT_DESKTOP", "Pantheon:GNOME")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(entry("pantheon-apps").is_file());
+
+    // the generic tuning is for every desktop, so it gets the XDG directory
+    let output = fixture
+        .command()
+        .env("XDG_CURRENT_DESKTOP", "Pantheon:GNOME")
+        .args(["--desktop-environment", "generic"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(entry(".local/share/applications").is_file());
+
+    let output = fixture
+        .command()
+        .env("XDG_CURRENT_DESKTOP", "Pantheon:GNOME")
+        .args(["--app-dir", path_str(&fixture.apps())])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+}
+
+#[test]
+fn servers_from_the_config_get_launchers() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let config = fixture.dir.join("home/.config/steamer/config.toml");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(
+        &config,
+        "[[servers]]\nname = \"Portal 2 Server\"\nexec = \"srcds_run -game portal2\"\nterminal = true\n",
+    )
+    .unwrap();
+
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-620.desktop", "steam-server-portal-2-server.desktop"]
+    );
+    let server =
+        fs::read_to_string(fixture.apps().join("steam-server-portal-2-server.desktop")).unwrap();
+    assert!(server.contains("\nExec=srcds_run -game portal2\nIcon=steam\nTerminal=true\n"));
+
+    // a server taken out of the config is cleaned up like an uninstalled game
+    fs::write(&config, "").unwrap();
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+}
+
+#[test]
+fn purge_removes_everything_steamer_wrote() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let home = fixture.dir.join("home");
+    let config = home.join(".config/steamer/config.toml");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(&config, "raw_names = true\n").unwrap();
+    fs::write(fixture.apps().join("firefox.desktop"), "[Desktop Entry]\n").unwrap();
+    let output = fixture.run(&["--emit", "desktop,json,html"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let index_dir = home.join(".local/share/steamer");
+    let cache_dir = home.join(".cache/steamer");
+    assert!(index_dir.join("steamer-index.json").is_file());
+    assert!(index_dir.join("steamer-index.html").is_file());
+    assert!(cache_dir.is_dir());
+
+    let output = fixture.run(&["purge", "--dry-run"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let listed = stdout(&output);
+    assert!(listed.contains("Would remove") && listed.contains("steam-620.desktop"));
+    assert!(listed.contains("steamer-index.json") && listed.contains("steamer-index.html"));
+    assert!(listed.contains("and everything in it"));
+    assert!(!listed.contains("firefox"));
+    assert!(cache_dir.is_dir());
+
+    let output = fixture.run(&["purge"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["firefox.desktop"]);
+    assert!(!index_dir.exists());
+    assert!(!cache_dir.exists());
+    // the config is the user's, not something steamer wrote
+    assert!(config.is_file());
+
+    let output = fixture.run(&["purge"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Nothing to remove.\n");
+}
+
+#[test]
+fn entries_move_in_and_out_of_letter_buckets() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "251570"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("251570", "7 Days to Die");
+
+    let output = fixture.run(&["--bucket-by-letter"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(fixture.apps().join("P/steam-620.desktop").is_file());
+    assert!(fixture.apps().join("0-9/steam-251570.desktop").is_file());
+    assert!(fixture.entries().is_empty());
+
+    // turned off, the buckets are cleaned up like any other entry
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-251570.desktop", "steam-620.desktop"]
+    );
+    assert!(!fixture.apps().join("P/steam-620.desktop").exists());
+}
+
+#[test]
+fn shortcuts_are_tagged_apart_from_steam_games() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    // one shortcut, appid 3456789012, in the binary format Steam writes
+    let mut vdf = b"\x00shortcuts\x00\x000\x00\x02appid\x00".to_vec();
+    vdf.extend(3_456_789_012u32.to_le_bytes());
+    vdf.extend(b"\x01AppName\x00Heroic\x00\x01Exe\x00\"/opt/heroic/heroic\"\x00\x08\x08\x08");
+    let config = fixture.dir.join("steam/userdata/1001/config");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("shortcuts.vdf"), vdf).unwrap();
+
+    let output = fixture.run(&["--tag-source"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+
+    let output = fixture.run(&["--include-shortcuts", "--tag-source"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-3456789012.desktop", "steam-620.desktop"]
+    );
+    let read = |name: &str| fs::read_to_string(fixture.apps().join(name)).unwrap();
+    let portal = read("steam-620.desktop");
+    assert!(portal.contains("\nExec=xdg-open steam://rungameid/620\n"));
+    assert!(portal.contains("\nX-Steamer-Source=steam\n"));
+    let heroic = read("steam-3456789012.desktop");
+    assert!(heroic.contains("Name=Heroic\n"));
+    assert!(heroic.contains("\nExec=xdg-open steam://rungameid/14846795755745705984\n"));
+    assert!(heroic.contains("\nX-Steamer-Source=shortcut\n"));
+}
+
+#[test]
+fn new_since_writes_only_the_games_installed_since() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let recent = fixture.dir.join("recent");
+
+    // with no earlier sync saved, every game is new
+    let output = fixture.run(&["--new-since", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("every game counts as new"));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+
+    fixture.write_libraries(&["620", "400"]);
+    fixture.install("400", "Portal");
+    let output = fixture
+        .command()
+        .args(["--app-dir", path_str(&recent), "--new-since", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let mut written: Vec<String> = fs::read_dir(&recent)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    written.sort();
+    assert_eq!(written, ["steam-400.desktop"]);
+
+    // Portal counted as installed, so it's no longer new at the next sync
+    let output = fixture.run(&["--new-since", "1"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(fixture.entries().is_empty());
+}
+
+#[test]
+fn migrate_from_renames_the_entries_of_an_older_scheme() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    fs::write(
+        fixture.apps().join("steam_portal2.desktop"),
+        "[Desktop Entry]\nName=Portal 2\nExec=steam steam://rungameid/620\n",
+    )
+    .unwrap();
+    fs::write(
+        fixture.apps().join("steam_400.desktop"),
+        "[Desktop Entry]\nName=Portal\n",
+    )
+    .unwrap();
+
+    let output = fixture.run(&["--migrate-from", "steam_"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("app 400 isn't installed"));
+    let mut left: Vec<String> = fs::read_dir(fixture.apps())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    left.sort();
+    assert_eq!(left, ["steam-620.desktop", "steam_400.desktop"]);
+    // renamed, then written over by the run
+    let portal = fs::read_to_string(fixture.apps().join("steam-620.desktop")).unwrap();
+    assert!(portal.contains("\nExec=xdg-open steam://rungameid/620\n"));
+}
+
+#[test]
+fn profiles_change_the_settings_of_a_run() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let profiles = fixture.dir.join("home/.config/steamer/profiles");
+    fs::create_dir_all(&profiles).unwrap();
+    fs::write(
+        profiles.join("couch.toml"),
+        "[[servers]]\nname = \"Portal 2 Server\"\nexec = \"srcds_run\"\n",
+    )
+    .unwrap();
+
+    let output = fixture.run(&["--profile", "desk"]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("There is no profile \"desk\", the profiles are: couch"),
+        "{}",
+        stderr(&output)
+    );
+
+    let output = fixture.run(&["--profile", "couch"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-620.desktop", "steam-server-portal-2-server.desktop"]
+    );
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+}
+
+#[test]
+fn an_empty_library_list_leaves_the_steam_root() {
+    let fixture = Fixture::new();
+    fixture.install("620", "Portal 2");
+    fs::write(
+        fixture.steamapps().join("libraryfolders.vdf"),
+        "\"libraryfolders\"\n{\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        fixture.apps().join("steam-400.desktop"),
+        "[Desktop Entry]\nName=Portal\n",
+    )
+    .unwrap();
+
+    let output = fixture.run(&[]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stderr(&output)
+            .contains("lists no libraries (tried 3 times), only scanning the Steam root")
+    );
+    // the list may be missing a drive, so entries it doesn't find stay
+    assert_eq!(
+        fixture.entries(),
+        ["steam-400.desktop", "steam-620.desktop"]
+    );
+}
+
+#[test]
+fn summary_format_replaces_the_last_line() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    for appid in ["620", "400"] {
+        fs::write(
+            fixture.apps().join(format!("steam-{}.desktop", appid)),
+            "[Desktop Entry]\n",
+        )
+        .unwrap();
+    }
+
+    let output = fixture.run(&["--summary-format", "{created} created, {removed} removed"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout(&output).ends_with("\n1 created, 1 removed\n"),
+        "{}",
+        stdout(&output)
+    );
+    assert!(!stdout(&output).contains("Done!"));
+
+    let output = fixture.run(&["--summary-format", "{created} in {seconds}"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("seconds"), "{}", stderr(&output));
+}
+
+#[test]
+fn grid_art_is_only_used_for_shortcuts() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620"]);
+    fixture.install("620", "Portal 2");
+    let mut vdf = b"\x00shortcuts\x00\x000\x00\x02appid\x00".to_vec();
+    vdf.extend(3_456_789_012u32.to_le_bytes());
+    vdf.extend(b"\x01AppName\x00Heroic\x00\x08\x08\x08");
+    let config = fixture.dir.join("steam/userdata/1001/config");
+    fs::create_dir_all(config.join("grid")).unwrap();
+    fs::write(config.join("shortcuts.vdf"), vdf).unwrap();
+    let png = include_bytes!("../assets/proton-badge.png");
+    for name in ["620_icon.png", "3456789012.png", "3456789012_icon.png"] {
+        fs::write(config.join("grid").join(name), png).unwrap();
+    }
+
+    let output = fixture.run(&["--include-shortcuts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let read = |name: &str| fs::read_to_string(fixture.apps().join(name)).unwrap();
+    assert!(read("steam-620.desktop").contains("\nIcon=steam\n"));
+    let icon = config.join("grid/3456789012_icon.png");
+    assert!(
+        read("steam-3456789012.desktop").contains(&format!("\nIcon={}\n", icon.display())),
+        "{}",
+        read("steam-3456789012.desktop")
+    );
+}
+
+#[test]
+fn tools_get_entries_named_after_the_built_in_list() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "250820"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("250820", "SteamVR");
+
+    // the default skip keywords leave it out
+    let output = fixture.run(&["--no-appinfo"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(fixture.entries(), ["steam-620.desktop"]);
+
+    // a manifest Steam left without a name
+    fixture.write_manifest(
+        "250820",
+        "\"AppState\"\n{\n\t\"appid\"\t\"250820\"\n\t\"StateFlags\"\t\"4\"\n}\n",
+    );
+    let output = fixture.run(&["--no-appinfo", "--tools"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        fixture.entries(),
+        ["steam-250820.desktop", "steam-620.desktop"]
+    );
+    let steamvr = fs::read_to_string(fixture.apps().join("steam-250820.desktop")).unwrap();
+    assert!(
+        steamvr.starts_with("[Desktop Entry]\nName=SteamVR\n"),
+        "{}",
+        steamvr
+    );
+    assert!(steamvr.contains("\nExec=xdg-open steam://rungameid/250820\n"));
+}
+
+#[test]
+fn icon_changes_only_rewrites_the_entries_whose_icon_changed() {
+    let fixture = Fixture::new();
+    fixture.write_libraries(&["620", "400"]);
+    fixture.install("620", "Portal 2");
+    fixture.install("400", "Portal");
+    let cache = fixture.dir.join("steam/appcache/librarycache");
+    let png = include_bytes!("../assets/proton-badge.png");
+    for appid in ["620", "400"] {
+        fs::create_dir_all(cache.join(appid)).unwrap();
+        fs::write(
+            cache.join(appid).join(format!("{}.jpg", "b".repeat(40))),
+            png,
+        )
+        .unwrap();
+    }
+    let output = fixture.run(&["--no-appinfo"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let read = |name: &str| fs::read_to_string(fixture.apps().join(name)).unwrap();
+    let edited = format!("{}X-Edited=true\n", read("steam-400.desktop"));
+    fs::write(fixture.apps().join("steam-400.desktop"), &edited).unwrap();
+    let icon = cache.join("620").join(format!("{}.jpg", "a".repeat(40)));
+    fs::write(&icon, png).unwrap();
+
+    let output = fixture.run(&["--no-appinfo", "--icon-changes-only"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(read("steam-620.desktop").contains(&format!("\nIcon={}\n", icon.display())));
+    assert_eq!(read("steam-400.desktop"), edited);
+}